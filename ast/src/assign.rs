@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::{formatter::Formatter, RcLocal, SideEffects, Traverse};
 
 use super::{LValue, LocalRw, RValue};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Assign {
     pub left: Vec<LValue>,
     pub right: Vec<RValue>,
@@ -75,11 +76,6 @@ impl LocalRw for Assign {
 
 impl fmt::Display for Assign {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_assign(self)
+        Formatter::new(f, Default::default(), Default::default()).format_assign(self)
     }
 }
@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::{Literal, LocalRw, RValue, RcLocal, Reduce, SideEffects, Traverse};
 
 use super::{Unary, UnaryOperation};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum BinaryOperation {
     Add,
     Sub,
@@ -22,6 +23,11 @@ pub enum BinaryOperation {
     And,
     Or,
     IDiv,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
 }
 
 impl BinaryOperation {
@@ -60,12 +66,17 @@ impl fmt::Display for BinaryOperation {
                 BinaryOperation::And => "and",
                 BinaryOperation::Or => "or",
                 BinaryOperation::IDiv => "//",
+                BinaryOperation::BitAnd => "&",
+                BinaryOperation::BitOr => "|",
+                BinaryOperation::BitXor => "~",
+                BinaryOperation::ShiftLeft => "<<",
+                BinaryOperation::ShiftRight => ">>",
             }
         )
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Binary {
     pub left: Box<RValue>,
     pub right: Box<RValue>,
@@ -277,13 +288,17 @@ impl Binary {
 
     pub fn precedence(&self) -> usize {
         match self.operation {
-            BinaryOperation::Pow => 8,
+            BinaryOperation::Pow => 12,
             BinaryOperation::Mul
             | BinaryOperation::Div
             | BinaryOperation::Mod
-            | BinaryOperation::IDiv => 6,
-            BinaryOperation::Add | BinaryOperation::Sub => 5,
-            BinaryOperation::Concat => 4,
+            | BinaryOperation::IDiv => 10,
+            BinaryOperation::Add | BinaryOperation::Sub => 9,
+            BinaryOperation::Concat => 8,
+            BinaryOperation::ShiftLeft | BinaryOperation::ShiftRight => 7,
+            BinaryOperation::BitAnd => 6,
+            BinaryOperation::BitXor => 5,
+            BinaryOperation::BitOr => 4,
             BinaryOperation::LessThan
             | BinaryOperation::GreaterThan
             | BinaryOperation::LessThanOrEqual
@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use crate::{formatter::Formatter, has_side_effects, LocalRw, RcLocal, Traverse};
 use super::RValue;
 use crate::Literal;
 use std::ops::Deref;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Call {
     pub value: Box<RValue>,
     pub arguments: Vec<RValue>,
@@ -93,16 +94,11 @@ impl LocalRw for Call {
 
 impl fmt::Display for Call {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_call(self)
+        Formatter::new(f, Default::default(), Default::default()).format_call(self)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MethodCall {
     // TODO: STYLE: rename to object?
     pub value: Box<RValue>,
@@ -157,11 +153,6 @@ impl LocalRw for MethodCall {
 
 impl fmt::Display for MethodCall {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_method_call(self)
+        Formatter::new(f, Default::default(), Default::default()).format_method_call(self)
     }
 }
@@ -2,6 +2,7 @@ use std::fmt;
 
 use by_address::ByAddress;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use triomphe::Arc;
 
 use crate::{
@@ -10,13 +11,13 @@ use crate::{
     Block, Literal, LocalRw, RcLocal, Reduce, SideEffects, Traverse, Type,
 };
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Upvalue {
     Copy(RcLocal),
     Ref(RcLocal),
 }
 
-#[derive(Default, Debug, PartialEq, Clone)]
+#[derive(Default, Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Function {
     pub name: Option<String>,
     pub parameters: Vec<RcLocal>,
@@ -56,12 +57,7 @@ impl Infer for Closure {
 
 impl fmt::Display for Closure {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_closure(self)
+        Formatter::new(f, Default::default(), Default::default()).format_closure(self)
     }
 }
 
@@ -1,17 +1,21 @@
 use std::fmt::Write;
 use std::iter;
+use std::str::FromStr;
 use std::{
     borrow::Cow,
     fmt::{self},
 };
 
 use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
-    Assign, Binary, BinaryOperation, Block, Call, Closure, GenericFor, If, Index, LValue, Literal,
-    MethodCall, NumericFor, RValue, Repeat, Return, Select, Statement, Table, Unary, While,
+    Assign, Binary, Block, Call, Closure, GenericFor, If, Index, LValue, Literal,
+    MethodCall, NumericFor, RValue, RcLocal, Repeat, Return, Select, Statement, Table, Unary,
+    While,
 };
 
+#[derive(Debug, Clone, Copy)]
 pub enum IndentationMode {
     Spaces(u8),
     Tab,
@@ -42,6 +46,270 @@ impl Default for IndentationMode {
     }
 }
 
+/// The Lua dialect that the output is meant to be read by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Luau,
+    Lua51,
+    /// Lua 5.2, which allows `goto` and `::label::`.
+    Lua52,
+    /// Lua 5.3, which adds integers, bitwise operators and floor division on top of Lua 5.2.
+    Lua53,
+    /// Lua 5.4, which adds local attributes on top of Lua 5.3.
+    Lua54,
+}
+
+impl Dialect {
+    pub fn supports_goto(self) -> bool {
+        matches!(self, Self::Lua52 | Self::Lua53 | Self::Lua54)
+    }
+
+    /// Whether strings may have `\u{XXXX}` escapes.
+    pub fn supports_unicode_escapes(self) -> bool {
+        matches!(self, Self::Luau | Self::Lua53 | Self::Lua54)
+    }
+
+    /// Whether numbers are either integers or floats, so integral floats need a `.0`.
+    pub fn has_integers(self) -> bool {
+        matches!(self, Self::Lua53 | Self::Lua54)
+    }
+
+    /// Whether `&`, `|`, `~`, `<<` and `>>` are operators rather than library calls.
+    pub fn has_bitwise_operators(self) -> bool {
+        matches!(self, Self::Lua53 | Self::Lua54)
+    }
+
+    /// Whether locals may be declared with `<const>` or `<close>`.
+    pub fn supports_attributes(self) -> bool {
+        matches!(self, Self::Lua54)
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::Luau
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "luau" => Ok(Self::Luau),
+            "lua51" | "5.1" => Ok(Self::Lua51),
+            "lua52" | "5.2" => Ok(Self::Lua52),
+            "lua53" | "5.3" => Ok(Self::Lua53),
+            "lua54" | "5.4" => Ok(Self::Lua54),
+            _ => Err(format!("unknown dialect `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Luau => write!(f, "luau"),
+            Self::Lua51 => write!(f, "lua51"),
+            Self::Lua52 => write!(f, "lua52"),
+            Self::Lua53 => write!(f, "lua53"),
+            Self::Lua54 => write!(f, "lua54"),
+        }
+    }
+}
+
+/// How string literals are escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringEscaping {
+    /// Every byte that isn't printable ASCII is escaped as `\ddd`.
+    #[default]
+    Ascii,
+    /// Valid UTF-8 is kept as is, only invisible characters are escaped, as `\u{XXXX}` in
+    /// dialects that have it. `\ddd` is left for bytes that aren't valid UTF-8.
+    Utf8,
+}
+
+impl FromStr for StringEscaping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ascii" => Ok(Self::Ascii),
+            "utf8" | "utf-8" => Ok(Self::Utf8),
+            _ => Err(format!("unknown string escaping `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for StringEscaping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ascii => write!(f, "ascii"),
+            Self::Utf8 => write!(f, "utf8"),
+        }
+    }
+}
+
+/// Which quotes strings are written between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    #[default]
+    Double,
+    Single,
+    /// Double quotes, unless the string has double quotes and no single quotes.
+    AutoPreferDouble,
+    /// Single quotes, unless the string has single quotes and no double quotes.
+    AutoPreferSingle,
+}
+
+impl QuoteStyle {
+    fn quote(self, string: &[u8]) -> u8 {
+        let (preferred, other) = match self {
+            Self::Double => return b'"',
+            Self::Single => return b'\'',
+            Self::AutoPreferDouble => (b'"', b'\''),
+            Self::AutoPreferSingle => (b'\'', b'"'),
+        };
+        if string.contains(&preferred) && !string.contains(&other) {
+            other
+        } else {
+            preferred
+        }
+    }
+}
+
+impl FromStr for QuoteStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "double" => Ok(Self::Double),
+            "single" => Ok(Self::Single),
+            "auto-prefer-double" => Ok(Self::AutoPreferDouble),
+            "auto-prefer-single" => Ok(Self::AutoPreferSingle),
+            _ => Err(format!("unknown quote style `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for QuoteStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Double => write!(f, "double"),
+            Self::Single => write!(f, "single"),
+            Self::AutoPreferDouble => write!(f, "auto-prefer-double"),
+            Self::AutoPreferSingle => write!(f, "auto-prefer-single"),
+        }
+    }
+}
+
+/// When table constructors are written over several lines, one field per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableLayout {
+    /// Tables with keys that aren't a sequence, more than 3 fields or nested tables.
+    #[default]
+    Auto,
+    /// Tables with more fields than this.
+    Threshold(usize),
+}
+
+/// Where statements are followed by `;`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Semicolons {
+    /// Only where the next statement could otherwise be read as part of it, ex. before
+    /// `(f or g)()`.
+    #[default]
+    Ambiguous,
+    /// After every statement that isn't a block or a function declaration.
+    Always,
+}
+
+impl FromStr for Semicolons {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ambiguous" => Ok(Self::Ambiguous),
+            "always" => Ok(Self::Always),
+            _ => Err(format!("unknown semicolon mode `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Semicolons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ambiguous => write!(f, "ambiguous"),
+            Self::Always => write!(f, "always"),
+        }
+    }
+}
+
+/// How the output is laid out.
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    pub indentation_mode: IndentationMode,
+    pub string_escaping: StringEscaping,
+    /// Write strings that span several lines as long strings, ex. `[[...]]`, when they have
+    /// nothing that would need escaping.
+    pub long_strings: bool,
+    /// The width lines are kept within where they can be, by breaking argument lists,
+    /// chains of binary operators and table constructors over several lines. Tabs count
+    /// as 4 columns. Lines are never broken if this is `None`.
+    pub line_width: Option<usize>,
+    pub table_layout: TableLayout,
+    pub quote_style: QuoteStyle,
+    /// Write a comma after the last field of tables written over several lines.
+    pub trailing_commas: bool,
+    pub semicolons: Semicolons,
+}
+
+const TAB_WIDTH: usize = 4;
+
+// the number of columns `string` takes up
+fn width(string: &str) -> usize {
+    string
+        .chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+// output that keeps track of the column it's at
+pub(crate) struct Output<'a, W: fmt::Write> {
+    inner: &'a mut W,
+    column: usize,
+}
+
+impl<W: fmt::Write> fmt::Write for Output<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match s.rfind('\n') {
+            Some(index) => self.column = width(&s[index + 1..]),
+            None => self.column += width(s),
+        }
+        self.inner.write_str(s)
+    }
+}
+
+fn collect_goto_targets(statement: &Statement, targets: &mut Vec<String>) {
+    let mut visit_block = |block: &Block, targets: &mut Vec<String>| {
+        for statement in &block.0 {
+            collect_goto_targets(statement, targets);
+        }
+    };
+    match statement {
+        Statement::Goto(goto) => targets.push(goto.0 .0.clone()),
+        Statement::If(r#if) => {
+            visit_block(&r#if.then_block.lock(), targets);
+            visit_block(&r#if.else_block.lock(), targets);
+        }
+        Statement::While(r#while) => visit_block(&r#while.block.lock(), targets),
+        Statement::Repeat(repeat) => visit_block(&repeat.block.lock(), targets),
+        Statement::NumericFor(numeric_for) => visit_block(&numeric_for.block.lock(), targets),
+        Statement::GenericFor(generic_for) => visit_block(&generic_for.block.lock(), targets),
+        _ => {}
+    }
+}
+
 pub(crate) fn format_arg_list(list: &[RValue]) -> String {
     let mut s = String::new();
     for (index, rvalue) in list.iter().enumerate() {
@@ -60,26 +328,53 @@ pub(crate) fn format_arg_list(list: &[RValue]) -> String {
 
 pub struct Formatter<'a, W: fmt::Write> {
     pub(crate) indentation_level: usize,
-    pub(crate) indentation_mode: IndentationMode,
-    pub(crate) output: &'a mut W,
+    pub(crate) options: FormatOptions,
+    pub(crate) dialect: Dialect,
+    pub(crate) output: Output<'a, W>,
 }
 
 impl<'a, W: fmt::Write> Formatter<'a, W> {
+    pub(crate) fn new(output: &'a mut W, options: FormatOptions, dialect: Dialect) -> Self {
+        Self {
+            indentation_level: 0,
+            options,
+            dialect,
+            output: Output {
+                inner: output,
+                column: 0,
+            },
+        }
+    }
+
     pub fn format(
         main: &Block,
         output: &'a mut W,
-        indentation_mode: IndentationMode,
+        options: FormatOptions,
+        dialect: Dialect,
     ) -> fmt::Result {
-        let mut formatter = Self {
-            indentation_level: 0,
-            indentation_mode,
-            output,
+        Self::new(output, options, dialect).format_block_no_indent(main)
+    }
+
+    // whether what `format` writes fits on the rest of the line when it isn't broken
+    fn fits(&self, format: impl FnOnce(&mut Formatter<String>) -> fmt::Result) -> bool {
+        let Some(line_width) = self.options.line_width else {
+            return true;
+        };
+        let mut output = String::new();
+        let options = FormatOptions {
+            line_width: None,
+            ..self.options.clone()
         };
-        formatter.format_block_no_indent(main)
+        let mut formatter = Formatter::new(&mut output, options, self.dialect);
+        formatter.indentation_level = self.indentation_level;
+        format(&mut formatter).unwrap();
+        let first_line = output.lines().next().unwrap_or_default();
+        self.output.column + width(first_line) <= line_width
     }
 
     fn indent(&mut self) -> fmt::Result {
-        self.indentation_mode
+        self.options
+            .indentation_mode
             .display(&mut self.output, self.indentation_level)
     }
 
@@ -102,13 +397,91 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         Ok(())
     }
 
+    // a goto may not jump into the scope of a local, so locals declared between a goto and
+    // the label it jumps forward to are declared before the goto instead.
+    // returns the locals to declare before each statement and the declarations to print
+    // as plain assignments.
+    fn goto_scope_fixups(block: &Block) -> (FxHashMap<usize, Vec<RcLocal>>, FxHashSet<usize>) {
+        let labels = block
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| s.as_label().map(|l| (l.0.as_str(), i)))
+            .collect::<FxHashMap<_, _>>();
+        if labels.is_empty() {
+            return Default::default();
+        }
+
+        let mut first_goto = FxHashMap::default();
+        for (i, statement) in block.iter().enumerate() {
+            let mut targets = Vec::new();
+            collect_goto_targets(statement, &mut targets);
+            for target in targets {
+                if let Some(&label_index) = labels.get(target.as_str())
+                    && i < label_index
+                {
+                    first_goto.entry(label_index).or_insert(i);
+                }
+            }
+        }
+
+        let mut hoist_to = FxHashMap::<usize, usize>::default();
+        for (label_index, goto_index) in first_goto {
+            // a label followed only by void statements is not in the scope of the block's locals
+            if block[label_index + 1..].iter().all(|s| {
+                matches!(
+                    s,
+                    Statement::Label(_) | Statement::Empty(_) | Statement::Comment(_)
+                )
+            }) {
+                continue;
+            }
+            for declaration_index in goto_index + 1..label_index {
+                if let Statement::Assign(assign) = &block[declaration_index]
+                    && assign.prefix
+                {
+                    let hoist_index = hoist_to.entry(declaration_index).or_insert(goto_index);
+                    *hoist_index = (*hoist_index).min(goto_index);
+                }
+            }
+        }
+
+        let mut hoisted = FxHashMap::<usize, Vec<RcLocal>>::default();
+        for (declaration_index, hoist_index) in hoist_to.iter().sorted() {
+            let assign = block[*declaration_index].as_assign().unwrap();
+            hoisted
+                .entry(*hoist_index)
+                .or_default()
+                .extend(assign.left.iter().filter_map(|l| l.as_local()).cloned());
+        }
+        (hoisted, hoist_to.into_keys().collect())
+    }
+
     fn format_block_no_indent(&mut self, block: &Block) -> fmt::Result {
+        let (hoisted, demoted) = if self.dialect.supports_goto() {
+            Self::goto_scope_fixups(block)
+        } else {
+            Default::default()
+        };
         for (i, statement) in block.iter().enumerate() {
             if i != 0 {
                 writeln!(self.output)?;
             }
+            if let Some(locals) = hoisted.get(&i) {
+                self.indent()?;
+                writeln!(self.output, "local {}", locals.iter().join(", "))?;
+            }
+            let statement = if demoted.contains(&i) {
+                let mut assign = statement.as_assign().unwrap().clone();
+                assign.prefix = false;
+                Cow::Owned(assign.into())
+            } else {
+                Cow::Borrowed(statement)
+            };
+            let statement = statement.as_ref();
             self.format_statement(statement)?;
-            if let Some(next_statement) =
+            if self.options.semicolons == Semicolons::Always && Self::takes_semicolon(statement) {
+                write!(self.output, ";")?;
+            } else if let Some(next_statement) =
                 block.iter().skip(i + 1).find(|s| s.as_comment().is_none())
             {
                 fn is_ambiguous(r: &RValue) -> bool {
@@ -166,6 +539,20 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         Ok(())
     }
 
+    // whether a statement is followed by `;` with `Semicolons::Always`
+    fn takes_semicolon(statement: &Statement) -> bool {
+        match statement {
+            Statement::Assign(assign) => Self::as_named_function(assign).is_none(),
+            Statement::Call(_)
+            | Statement::MethodCall(_)
+            | Statement::Return(_)
+            | Statement::Goto(_)
+            | Statement::Continue(_)
+            | Statement::Break(_) => true,
+            _ => false,
+        }
+    }
+
     fn format_lvalue(&mut self, lvalue: &LValue) -> fmt::Result {
         match lvalue {
             LValue::Index(index) => self.format_index(index),
@@ -188,8 +575,11 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
             false
         } else {
             keys_vec.iter().enumerate().all(|(i, k)| {
-                matches!(k, Some(RValue::Literal(Literal::Number(x)))
-                        if (x - 1f64) as usize == i)
+                match k {
+                    Some(RValue::Literal(Literal::Number(x))) => (x - 1f64) as usize == i,
+                    Some(RValue::Literal(Literal::Integer(x))) => x - 1 == i as i64,
+                    _ => false,
+                }
             })
         }
     }
@@ -201,8 +591,15 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
     pub(crate) fn format_table(&mut self, table: &Table, skip_keys: Option<&[&str]>) -> fmt::Result {
         let sequential_keys = Self::are_table_keys_sequential(table);
         let should_space = !table.0.is_empty();
-        let should_format = !table.0.is_empty() && (!sequential_keys || table.0.len() > 3)
-            || Self::contains_table(table);
+        let should_format = match self.options.table_layout {
+            TableLayout::Auto => {
+                !table.0.is_empty() && (!sequential_keys || table.0.len() > 3)
+                    || Self::contains_table(table)
+            }
+            TableLayout::Threshold(threshold) => table.0.len() > threshold,
+        };
+        let should_format =
+            should_format || !self.fits(|f| f.format_table(table, skip_keys));
         write!(self.output, "{{")?;
         if should_format {
             writeln!(self.output)?;
@@ -254,9 +651,9 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
                             {
                                 write!(self.output, "{} = ", key_str)?;
                             } else {
-                                write!(self.output, "[\"")?;
-                                write!(self.output, "{}", Self::escape_string(bytes))?;
-                                write!(self.output, "\"] = ")?;
+                                write!(self.output, "[")?;
+                                self.format_quoted_string(bytes)?;
+                                write!(self.output, "] = ")?;
                             }
                         } else {
                             write!(self.output, "[")?;
@@ -269,6 +666,9 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
             }
         }
         self.indentation_level -= 1;
+        if should_format && self.options.trailing_commas && !first {
+            write!(self.output, ",")?;
+        }
         if should_format {
             writeln!(self.output)?;
             self.indent()?;
@@ -291,21 +691,58 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         Ok(())
     }
 
+    fn format_operand(&mut self, operand: &RValue, wrap: bool) -> fmt::Result {
+        if wrap {
+            write!(self.output, "(")?;
+        }
+        self.format_rvalue(operand)?;
+        if wrap {
+            write!(self.output, ")")?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn format_binary(&mut self, binary: &Binary) -> fmt::Result {
-        let parentheses = |f: &mut Self, wrap: bool, rvalue: &RValue| -> fmt::Result {
-            if wrap {
-                write!(f.output, "(")?;
-            }
-            f.format_rvalue(rvalue)?;
-            if wrap {
-                write!(f.output, ")")?;
+        if !self.fits(|f| f.format_binary(binary)) {
+            return self.format_binary_chain(binary);
+        }
+        self.format_operand(&binary.left, binary.left_group())?;
+        write!(self.output, " {} ", binary.operation)?;
+        self.format_operand(&binary.right, binary.right_group())
+    }
+
+    // the operands of a chain of the same operator, ex. `a`, `b` and `c` for `a + b + c`,
+    // and whether each needs parentheses
+    fn binary_chain<'b>(binary: &'b Binary, operands: &mut Vec<(&'b RValue, bool)>) {
+        for (operand, group) in [
+            (binary.left.as_ref(), binary.left_group()),
+            (binary.right.as_ref(), binary.right_group()),
+        ] {
+            match operand {
+                RValue::Binary(inner) if !group && inner.operation == binary.operation => {
+                    Self::binary_chain(inner, operands)
+                }
+                _ => operands.push((operand, group)),
             }
-            Ok(())
-        };
+        }
+    }
 
-        parentheses(self, binary.left_group(), &binary.left)?;
-        write!(self.output, " {} ", binary.operation)?;
-        parentheses(self, binary.right_group(), &binary.right)
+    // writes a chain of the same operator with every operator starting an indented line
+    fn format_binary_chain(&mut self, binary: &Binary) -> fmt::Result {
+        let mut operands = Vec::new();
+        Self::binary_chain(binary, &mut operands);
+        let mut operands = operands.into_iter();
+        let (first, wrap) = operands.next().unwrap();
+        self.format_operand(first, wrap)?;
+        self.indentation_level += 1;
+        for (operand, wrap) in operands {
+            writeln!(self.output)?;
+            self.indent()?;
+            write!(self.output, "{} ", binary.operation)?;
+            self.format_operand(operand, wrap)?;
+        }
+        self.indentation_level -= 1;
+        Ok(())
     }
 
     fn format_closure_parameters(&mut self, closure: &Closure) -> fmt::Result {
@@ -395,30 +832,40 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
             RValue::Unary(unary) => self.format_unary(unary),
             RValue::Binary(binary) => self.format_binary(binary),
             RValue::Closure(closure) => self.format_closure(closure),
-            RValue::Literal(Literal::Number(n)) if n.is_infinite() => {
-                // TODO: only insert parentheses when necessary
-                write!(self.output, "(")?;
-                self.format_binary(&Binary::new(
-                    Literal::Number(if n.is_sign_positive() { 1.0 } else { -1.0 }).into(),
-                    Literal::Number(0.0).into(),
-                    BinaryOperation::Div,
-                ))?;
-                write!(self.output, ")")
-            }
-            RValue::Literal(Literal::Number(n)) if n.is_nan() => {
-                 if n.is_sign_positive() {
-                    write!(self.output, "math.huge")?;
-                }
-                else {
-                    write!(self.output, "-math.huge")?;
-                }
-
-                Ok(())
+            RValue::Literal(Literal::String(string)) => self.format_string(string),
+            RValue::Literal(Literal::Number(n)) if self.dialect.has_integers() => {
+                // keep the ".0" so the literal stays a float
+                write!(self.output, "{}", crate::literal::format_number(*n, true))
             }
             _ => write!(self.output, "{}", rvalue),
         }
     }
 
+    // writes arguments between parentheses, one per line if they don't fit on the line
+    fn format_arguments(&mut self, arguments: &[RValue]) -> fmt::Result {
+        // a single table or function breaks over lines itself
+        let hug = matches!(arguments, [RValue::Table(_) | RValue::Closure(_)]);
+        if arguments.is_empty() || hug || self.fits(|f| f.format_arguments(arguments)) {
+            write!(self.output, "(")?;
+            self.format_arg_list(arguments)?;
+            return write!(self.output, ")");
+        }
+        writeln!(self.output, "(")?;
+        self.indentation_level += 1;
+        for (index, argument) in arguments.iter().enumerate() {
+            if index != 0 {
+                writeln!(self.output, ",")?;
+            }
+            self.indent()?;
+            let is_last = index + 1 == arguments.len();
+            self.format_operand(argument, is_last && matches!(argument, RValue::Select(_)))?;
+        }
+        self.indentation_level -= 1;
+        writeln!(self.output)?;
+        self.indent()?;
+        write!(self.output, ")")
+    }
+
     fn format_arg_list(&mut self, list: &[RValue]) -> fmt::Result {
         for (index, rvalue) in list.iter().enumerate() {
             if index + 1 == list.len() {
@@ -445,10 +892,10 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         {
             return false;
         }
-        // TODO: Consider adding "goto" to reserved keywords
+        // "goto" is only reserved in Lua 5.2+, but quoting it is valid in every dialect
         const RESERVED_KEYWORDS: &[&str] = &[
-            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
-            "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if",
+            "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
         ];
 
         let name_str = std::str::from_utf8(name).unwrap_or("");
@@ -459,11 +906,11 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
     }
 
     // TODO: PERF: Cow like from_utf8_lossy
-    pub(crate) fn escape_string(string: &[u8]) -> Cow<str> {
+    pub(crate) fn escape_string(string: &[u8], quote: u8) -> Cow<str> {
         let mut owned: Option<String> = None;
         let mut iter = string.iter().enumerate().peekable();
         while let Some((i, &c)) = iter.next() {
-            if c == b' ' || (c.is_ascii_graphic() && c != b'\\' && c != b'\'' && c != b'\"') {
+            if Self::is_plain(c, quote) {
                 if let Some(owned) = &mut owned {
                     owned.push(c as char);
                 }
@@ -475,28 +922,8 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
                     // TODO: PERF: String::with_capacity + push_str to avoid an allocation
                     owned.as_mut().unwrap().reserve((string.len() - i) * 2);
                 }
-                let owned = owned.as_mut().unwrap();
-                match c {
-                    b'\n' => owned.push_str(r"\n"),
-                    b'\r' => owned.push_str(r"\r"),
-                    b'\t' => owned.push_str(r"\t"),
-                    b'\"' => owned.push_str(r#"\""#),
-                    b'\'' => owned.push_str(r"\'"),
-                    b'\\' => owned.push_str(r"\\"),
-                    12 => owned.push_str(r"\f"),
-                    _ => {
-                        let mut buffer = itoa::Buffer::new();
-                        let printed = buffer.format(c);
-                        owned.push('\\');
-                        if printed.len() != 3
-                            && let Some((_, next)) = iter.peek()
-                            && next.is_ascii_digit()
-                        {
-                            owned.extend(iter::repeat('0').take(3 - printed.len()));
-                        }
-                        owned.push_str(printed);
-                    }
-                };
+                let next_is_digit = iter.peek().is_some_and(|(_, next)| next.is_ascii_digit());
+                Self::escape_byte(owned.as_mut().unwrap(), c, next_is_digit);
             }
         }
         if let Some(owned) = owned {
@@ -507,6 +934,146 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         }
     }
 
+    // whether a byte can be written as is in a string between `quote`s
+    fn is_plain(c: u8, quote: u8) -> bool {
+        c == b' ' || (c.is_ascii_graphic() && c != b'\\' && c != quote)
+    }
+
+    // escapes a byte that can't be written as is, `next_is_digit` tells whether the byte
+    // after it is a digit that would be read as part of a decimal escape
+    fn escape_byte(output: &mut String, c: u8, next_is_digit: bool) {
+        match c {
+            b'\n' => output.push_str(r"\n"),
+            b'\r' => output.push_str(r"\r"),
+            b'\t' => output.push_str(r"\t"),
+            b'\"' => output.push_str(r#"\""#),
+            b'\'' => output.push_str(r"\'"),
+            b'\\' => output.push_str(r"\\"),
+            12 => output.push_str(r"\f"),
+            _ => {
+                let mut buffer = itoa::Buffer::new();
+                let printed = buffer.format(c);
+                output.push('\\');
+                if printed.len() != 3 && next_is_digit {
+                    output.extend(iter::repeat('0').take(3 - printed.len()));
+                }
+                output.push_str(printed);
+            }
+        };
+    }
+
+    // characters that can't be seen, or told apart from others, when written as is
+    fn is_invisible(c: char) -> bool {
+        c.is_control()
+            || (c.is_whitespace() && c != ' ')
+            || matches!(
+                c,
+                '\u{200B}'..='\u{200C}'
+                    | '\u{200E}'..='\u{200F}'
+                    | '\u{202A}'..='\u{202E}'
+                    | '\u{2060}'..='\u{2064}'
+                    | '\u{2066}'..='\u{2069}'
+                    | '\u{FEFF}'
+            )
+    }
+
+    // escapes `string` to be written between `quote`s, keeping valid UTF-8 as is
+    pub(crate) fn escape_string_utf8(string: &[u8], quote: u8, unicode_escapes: bool) -> Cow<str> {
+        if string.iter().all(|&c| Self::is_plain(c, quote)) {
+            return std::str::from_utf8(string).unwrap().into();
+        }
+        let mut output = String::with_capacity(string.len());
+        let next_is_digit = |end: usize| string.get(end).is_some_and(u8::is_ascii_digit);
+        let mut offset = 0;
+        for chunk in string.utf8_chunks() {
+            for (index, c) in chunk.valid().char_indices() {
+                let end = offset + index + c.len_utf8();
+                if c.is_ascii() {
+                    if Self::is_plain(c as u8, quote) {
+                        output.push(c);
+                    } else {
+                        Self::escape_byte(&mut output, c as u8, next_is_digit(end));
+                    }
+                } else if !Self::is_invisible(c) {
+                    output.push(c);
+                } else if unicode_escapes {
+                    write!(output, "\\u{{{:X}}}", c as u32).unwrap();
+                } else {
+                    for (i, &byte) in string[end - c.len_utf8()..end].iter().enumerate() {
+                        let next = end - c.len_utf8() + i + 1;
+                        Self::escape_byte(&mut output, byte, next_is_digit(next));
+                    }
+                }
+            }
+            offset += chunk.valid().len();
+            for &byte in chunk.invalid() {
+                offset += 1;
+                Self::escape_byte(&mut output, byte, next_is_digit(offset));
+            }
+        }
+        output.into()
+    }
+
+    // the number of `=` in the brackets of a long string holding `string`, if it should
+    // be written as one
+    fn long_string_level(&self, string: &[u8]) -> Option<usize> {
+        if !self.options.long_strings || !string.contains(&b'\n') {
+            return None;
+        }
+        // long strings can't have escapes, and turn `\r` into a newline
+        let string = std::str::from_utf8(string).ok()?;
+        let writable = |c: char| {
+            c == '\n'
+                || c == '\t'
+                || match self.options.string_escaping {
+                    StringEscaping::Ascii => c == ' ' || c.is_ascii_graphic(),
+                    StringEscaping::Utf8 => !Self::is_invisible(c),
+                }
+        };
+        if !string.chars().all(writable) {
+            return None;
+        }
+        // Lua 5.1 rejects `[[` inside a level 0 long string as deprecated nesting
+        let lowest = usize::from(self.dialect == Dialect::Lua51 && string.contains("[["));
+        // the closing bracket must not appear earlier, including by its start ending the string
+        (lowest..).find(|&level| {
+            let close = format!("]{}]", "=".repeat(level));
+            (string.to_string() + &close).find(&close) == Some(string.len())
+        })
+    }
+
+    // writes a string between quotes
+    fn format_quoted_string(&mut self, string: &[u8]) -> fmt::Result {
+        let quote = self.options.quote_style.quote(string);
+        let escaped = match self.options.string_escaping {
+            StringEscaping::Ascii => Self::escape_string(string, quote),
+            StringEscaping::Utf8 => {
+                Self::escape_string_utf8(string, quote, self.dialect.supports_unicode_escapes())
+            }
+        };
+        let quote = quote as char;
+        write!(self.output, "{}{}{}", quote, escaped, quote)
+    }
+
+    fn format_string(&mut self, string: &[u8]) -> fmt::Result {
+        match self.long_string_level(string) {
+            Some(level) => {
+                let equals = "=".repeat(level);
+                // a newline right after the opening bracket isn't part of the string
+                let newline = if string.starts_with(b"\n") { "\n" } else { "" };
+                write!(
+                    self.output,
+                    "[{}[{}{}]{}]",
+                    equals,
+                    newline,
+                    std::str::from_utf8(string).unwrap(),
+                    equals
+                )
+            }
+            None => self.format_quoted_string(string),
+        }
+    }
+
     pub(crate) fn format_index(&mut self, index: &Index) -> fmt::Result {
         let wrap = Self::should_wrap_left_rvalue(&index.left);
         if wrap {
@@ -521,6 +1088,11 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
             RValue::Literal(super::Literal::String(field)) if Self::is_valid_name(field) => {
                 write!(self.output, ".{}", std::str::from_utf8(field).unwrap())
             }
+            RValue::Literal(super::Literal::String(field)) => {
+                write!(self.output, "[")?;
+                self.format_quoted_string(field)?;
+                write!(self.output, "]")
+            }
             _ => {
                 write!(self.output, "[")?;
                 self.format_rvalue(&index.right)?;
@@ -539,9 +1111,7 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
             write!(self.output, ")")?;
         }
 
-        write!(self.output, "(")?;
-        self.format_arg_list(&call.arguments)?;
-        write!(self.output, ")")
+        self.format_arguments(&call.arguments)
     }
 
     pub(crate) fn format_method_call(&mut self, method_call: &MethodCall) -> fmt::Result {
@@ -555,10 +1125,7 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         }
 
         write!(self.output, ":{}", method_call.method)?;
-
-        write!(self.output, "(")?;
-        self.format_arg_list(&method_call.arguments)?;
-        write!(self.output, ")")
+        self.format_arguments(&method_call.arguments)
     }
 
     pub(crate) fn format_if(&mut self, r#if: &If) -> fmt::Result {
@@ -609,6 +1176,45 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         Ok(())
     }
 
+    // the name and function of an assignment written as `function name() end`
+    fn as_named_function(assign: &Assign) -> Option<(&LValue, &Closure)> {
+        if assign.left.len() == 1
+            && assign.right.len() == 1
+            && let RValue::Closure(closure) = &assign.right[0]
+        {
+            let left = &assign.left[0];
+            if assign.prefix || left.as_global().is_some() || {
+                if let LValue::Index(index) = left {
+                    let mut index = index;
+                    let mut valid = true;
+                    loop {
+                        if let box RValue::Literal(Literal::String(key)) = &index.right
+                            && Self::is_valid_name(key)
+                        {
+                            match index.left {
+                                box RValue::Index(ref i) => {
+                                    index = i;
+                                    continue;
+                                }
+                                box RValue::Global(_) | box RValue::Local(_) => {}
+                                _ => valid = false,
+                            }
+                        } else {
+                            valid = false;
+                        }
+                        break;
+                    }
+                    valid
+                } else {
+                    false
+                }
+            } {
+                return Some((left, closure));
+            }
+        }
+        None
+    }
+
     pub(crate) fn format_assign(&mut self, assign: &Assign) -> fmt::Result {
         if assign.prefix {
             write!(self.output, "local ")?;
@@ -653,50 +1259,22 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
                 self.format_lvalue(var_lvalue)?;
                 write!(self.output, " = ")?;
                 self.format_table(table, Some(&skip_keys))?;
-                writeln!(self.output)?;
                 for (key_str, value) in moved_fields {
+                    if self.options.semicolons == Semicolons::Always {
+                        write!(self.output, ";")?;
+                    }
+                    writeln!(self.output)?;
+                    self.indent()?;
                     self.format_lvalue(var_lvalue)?;
                     write!(self.output, ".{} = ", key_str)?;
                     self.format_rvalue(value)?;
-                    writeln!(self.output)?;
                 }
                 return Ok(());
             }
         }
 
-        if assign.left.len() == 1
-            && assign.right.len() == 1
-            && let RValue::Closure(closure) = &assign.right[0]
-        {
-            let left = &assign.left[0];
-            if assign.prefix || left.as_global().is_some() || {
-                if let LValue::Index(index) = left {
-                    let mut index = index;
-                    let mut valid = true;
-                    loop {
-                        if let box RValue::Literal(Literal::String(key)) = &index.right
-                            && Self::is_valid_name(key)
-                        {
-                            match index.left {
-                                box RValue::Index(ref i) => {
-                                    index = i;
-                                    continue;
-                                }
-                                box RValue::Global(_) | box RValue::Local(_) => {}
-                                _ => valid = false,
-                            }
-                        } else {
-                            valid = false;
-                        }
-                        break;
-                    }
-                    valid
-                } else {
-                    false
-                }
-            } {
-                return self.format_named_function(left, closure);
-            }
+        if let Some((left, closure)) = Self::as_named_function(assign) {
+            return self.format_named_function(left, closure);
         }
 
         for (i, lvalue) in assign.left.iter().enumerate() {
@@ -704,6 +1282,13 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
                 write!(self.output, ", ")?;
             }
             self.format_lvalue(lvalue)?;
+            if assign.prefix
+                && self.dialect.supports_attributes()
+                && let LValue::Local(local) = lvalue
+                && let Some(attribute) = local.0.lock().1
+            {
+                write!(self.output, " <{}>", attribute)?;
+            }
         }
 
         if !assign.right.is_empty() {
@@ -756,10 +1341,11 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
         self.format_rvalue(&numeric_for.initial)?;
         write!(self.output, ", ")?;
         self.format_rvalue(&numeric_for.limit)?;
-        let skip_step = if let RValue::Literal(Literal::Number(n)) = numeric_for.step {
-            n == 1.0
-        } else {
-            false
+        let skip_step = match numeric_for.step {
+            // a float step makes a Lua 5.4 loop count in floats
+            RValue::Literal(Literal::Number(n)) => n == 1.0 && !self.dialect.has_integers(),
+            RValue::Literal(Literal::Integer(n)) => n == 1,
+            _ => false,
         };
         if !skip_step {
             write!(self.output, ", ")?;
@@ -810,7 +1396,15 @@ impl<'a, W: fmt::Write> Formatter<'a, W> {
             } else {
                 write!(self.output, ", ")?;
             }
+            // a call or vararg adjusted to one value
+            let wrap = i + 1 == r#return.values.len() && matches!(rvalue, RValue::Select(_));
+            if wrap {
+                write!(self.output, "(")?;
+            }
             self.format_rvalue(rvalue)?;
+            if wrap {
+                write!(self.output, ")")?;
+            }
         }
 
         Ok(())
@@ -1,10 +1,11 @@
 use derive_more::From;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::{formatter::Formatter, LocalRw, SideEffects, Traverse};
 
-#[derive(Debug, From, PartialEq, Eq, PartialOrd, Clone)]
-pub struct Global(pub Vec<u8>);
+#[derive(Debug, From, PartialEq, Eq, PartialOrd, Clone, Serialize, Deserialize)]
+pub struct Global(#[serde(with = "crate::serialize::bytes")] pub Vec<u8>);
 
 impl Global {
     pub fn new(name: Vec<u8>) -> Self {
@@ -36,7 +37,7 @@ impl fmt::Display for Global {
             write!(
                 f,
                 "__FENV[\"{}\"]",
-                Formatter::<fmt::Formatter>::escape_string(&self.0)
+                Formatter::<fmt::Formatter>::escape_string(&self.0, b'"')
             )
         }
     }
@@ -1,4 +1,5 @@
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use triomphe::Arc;
 
 use crate::{formatter::Formatter, LocalRw, RcLocal, SideEffects, Traverse};
@@ -7,10 +8,12 @@ use super::{Block, RValue};
 
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct If {
     pub condition: RValue,
+    #[serde(with = "crate::serialize::shared")]
     pub then_block: Arc<Mutex<Block>>,
+    #[serde(with = "crate::serialize::shared")]
     pub else_block: Arc<Mutex<Block>>,
 }
 
@@ -60,11 +63,6 @@ impl LocalRw for If {
 
 impl fmt::Display for If {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_if(self)
+        Formatter::new(f, Default::default(), Default::default()).format_if(self)
     }
 }
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use crate::{formatter::Formatter, has_side_effects, LocalRw, RcLocal, Traverse};
 
 use super::RValue;
@@ -6,7 +7,7 @@ use std::fmt;
 use crate::Literal;
 use std::ops::Deref;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Index {
     pub left: Box<RValue>,
     pub right: Box<RValue>,
@@ -74,11 +75,6 @@ impl Traverse for Index {
 
 impl fmt::Display for Index {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_index(self)
+        Formatter::new(f, Default::default(), Default::default()).format_index(self)
     }
 }
@@ -6,6 +6,7 @@ use enum_as_inner::EnumAsInner;
 use enum_dispatch::enum_dispatch;
 use formatter::Formatter;
 use itertools::Either;
+use serde::{Deserialize, Serialize};
 
 use std::{
     fmt,
@@ -27,12 +28,16 @@ mod r#if;
 mod index;
 mod literal;
 mod local;
-//mod name_gen;
+pub mod lower_dialect;
 pub mod local_declarations;
+pub mod name_gen;
 pub mod name_locals;
+pub mod naming_rules;
+pub mod parser;
 mod repeat;
 pub mod replace_locals;
 mod r#return;
+pub mod serialize;
 mod set_list;
 mod side_effects;
 mod table;
@@ -74,7 +79,7 @@ pub trait Reduce {
 }
 
 #[enum_dispatch(LocalRw, SideEffects, Traverse)]
-#[derive(Debug, Clone, PartialEq, EnumAsInner)]
+#[derive(Debug, Clone, PartialEq, EnumAsInner, Serialize, Deserialize)]
 pub enum Select {
     VarArg(VarArg),
     Call(Call),
@@ -92,7 +97,7 @@ impl fmt::Display for Select {
 }
 
 #[enum_dispatch(LocalRw, SideEffects, Traverse)]
-#[derive(Debug, Clone, PartialEq, EnumAsInner)]
+#[derive(Debug, Clone, PartialEq, EnumAsInner, Serialize, Deserialize)]
 pub enum RValue {
     Local(RcLocal),
     Global(Global),
@@ -154,10 +159,11 @@ impl RValue {
         match self {
             Self::Binary(binary) => binary.precedence(),
             Self::Unary(unary) => unary.precedence(),
-            RValue::Literal(Literal::Number(n)) if n.is_finite() && n.is_sign_negative() => {
-                return 7;
-            }
-            _ => 9,
+            // `0/0`
+            RValue::Literal(Literal::Number(n)) if n.is_nan() => 10,
+            RValue::Literal(Literal::Number(n)) if n.is_sign_negative() => 11,
+            RValue::Literal(Literal::Integer(n)) if *n < 0 && *n != i64::MIN => 11,
+            _ => 13,
         }
     }
 
@@ -191,7 +197,7 @@ impl fmt::Display for RValue {
 }
 
 #[enum_dispatch(SideEffects, Traverse)]
-#[derive(Debug, Clone, PartialEq, EnumAsInner)]
+#[derive(Debug, Clone, PartialEq, EnumAsInner, Serialize, Deserialize)]
 pub enum LValue {
     Local(RcLocal),
     Global(Global),
@@ -242,7 +248,7 @@ impl fmt::Display for LValue {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Comment {
     pub text: String,
 }
@@ -260,7 +266,7 @@ impl SideEffects for Comment {}
 impl LocalRw for Comment {}
 
 #[enum_dispatch(LocalRw, SideEffects, Traverse)]
-#[derive(Debug, Clone, PartialEq, EnumAsInner)]
+#[derive(Debug, Clone, PartialEq, EnumAsInner, Serialize, Deserialize)]
 pub enum Statement {
     Empty(Empty),
     Call(Call),
@@ -292,7 +298,7 @@ impl Statement {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Empty {}
 
 impl SideEffects for Empty {}
@@ -343,7 +349,7 @@ impl fmt::Display for Statement {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Default, From)]
+#[derive(Debug, PartialEq, Clone, Default, From, Serialize, Deserialize)]
 pub struct Block(pub Vec<Statement>);
 
 // rust-analyzer doesnt like derive_more :/
@@ -363,6 +369,6 @@ impl DerefMut for Block {
 
 impl fmt::Display for Block {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter::format(self, f, Default::default())
+        Formatter::format(self, f, Default::default(), Default::default())
     }
 }
@@ -1,5 +1,6 @@
 use derive_more::From;
 use enum_as_inner::EnumAsInner;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::{
@@ -7,12 +8,13 @@ use crate::{
     TypeSystem,
 };
 
-#[derive(Debug, From, Clone, PartialEq, PartialOrd, EnumAsInner)]
+#[derive(Debug, From, Clone, PartialEq, PartialOrd, EnumAsInner, Serialize, Deserialize)]
 pub enum Literal {
     Nil,
     Boolean(bool),
-    Number(f64),
-    String(Vec<u8>),
+    Number(#[serde(with = "crate::serialize::number")] f64),
+    Integer(i64),
+    String(#[serde(with = "crate::serialize::bytes")] Vec<u8>),
     Vector(f32, f32, f32),
 }
 
@@ -26,6 +28,7 @@ impl Reduce for Literal {
             Literal::Boolean(false) | Literal::Nil => false,
             Literal::Boolean(true)
             | Literal::Number(_)
+            | Literal::Integer(_)
             | Literal::String(_)
             | Literal::Vector(..) => true,
         })
@@ -38,7 +41,7 @@ impl Infer for Literal {
         match self {
             Literal::Nil => Type::Nil,
             Literal::Boolean(_) => Type::Boolean,
-            Literal::Number(_) => Type::Number,
+            Literal::Number(_) | Literal::Integer(_) => Type::Number,
             Literal::String(_) => Type::String,
             Literal::Vector(..) => Type::Vector,
         }
@@ -62,23 +65,47 @@ impl fmt::Display for Literal {
         match self {
             Literal::Nil => write!(f, "nil"),
             Literal::Boolean(value) => write!(f, "{}", value),
-            &Literal::Number(value) => {
-                // TODO: this is a bit messy, just use `buffer.format` here and format_finite
-                // in formatter.rs
-                debug_assert!(value.is_finite());
-                // TODO: fork ryu to remove ".0"
-                let mut buffer = ryu::Buffer::new();
-                let printed = buffer.format_finite(value);
-                write!(f, "{}", printed.strip_suffix(".0").unwrap_or(printed))
-            }
+            &Literal::Number(value) => write!(f, "{}", format_number(value, false)),
+            // `-9223372036854775808` would be read as the negation of a float
+            Literal::Integer(i64::MIN) => write!(f, "math.mininteger"),
+            Literal::Integer(value) => write!(f, "{}", value),
             Literal::String(value) => {
                 write!(
                     f,
                     "\"{}\"",
-                    Formatter::<fmt::Formatter>::escape_string(value)
+                    Formatter::<fmt::Formatter>::escape_string(value, b'"')
                 )
             }
             Literal::Vector(x, y, z) => write!(f, "Vector3.new({}, {}, {})", x, y, z),
         }
     }
 }
+
+/// Formats a number so that it reads back as the same value. Integral numbers are written
+/// without an exponent when they are exact, ex. `1000000000000000` instead of `1e15`, and
+/// keep a `.0` if `float_suffix` is set, for dialects where they would otherwise be
+/// integers. Anything else is written in the shortest form that reads back the same.
+pub(crate) fn format_number(value: f64, float_suffix: bool) -> String {
+    if value.is_nan() {
+        "0/0".to_string()
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            "math.huge".to_string()
+        } else {
+            "-math.huge".to_string()
+        }
+    } else if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        // `value as i64` loses the sign of -0
+        let sign = if value == 0.0 && value.is_sign_negative() {
+            "-"
+        } else {
+            ""
+        };
+        let suffix = if float_suffix { ".0" } else { "" };
+        format!("{}{}{}", sign, value as i64, suffix)
+    } else {
+        let mut buffer = ryu::Buffer::new();
+        let printed = buffer.format_finite(value);
+        printed.strip_suffix(".0").unwrap_or(printed).to_string()
+    }
+}
@@ -2,20 +2,47 @@ use crate::{type_system::Infer, SideEffects, Traverse, Type, TypeSystem};
 use by_address::ByAddress;
 use derive_more::From;
 use enum_dispatch::enum_dispatch;
-use nohash_hasher::NoHashHasher;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::{
+    cell::Cell,
+    cmp::Ordering,
     fmt::{self, Display},
     hash::{Hash, Hasher},
 };
 use triomphe::Arc;
 
+/// The attribute a Lua 5.4 local is declared with, as in `local x <close> = ...`. Only
+/// `<close>` survives compilation, so only the parser declares locals `<const>`. Attributes
+/// are dropped when formatting for dialects without them.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Attribute {
+    Const,
+    Close,
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Const => write!(f, "const"),
+            Self::Close => write!(f, "close"),
+        }
+    }
+}
+
 #[derive(Debug, Default, From, Clone, PartialEq, PartialOrd, Ord, Eq, Hash)]
-pub struct Local(pub Option<String>);
+pub struct Local(pub Option<String>, pub Option<Attribute>);
 
 impl Local {
     pub fn new(name: Option<String>) -> Self {
-        Self(name)
+        Self(name, None)
+    }
+
+    /// Whether the value of the local is closed when it goes out of scope. These locals
+    /// have to stay declared, so they are never inlined or propagated away.
+    pub fn is_to_be_closed(&self) -> bool {
+        self.1 == Some(Attribute::Close)
     }
 }
 
@@ -28,8 +55,61 @@ impl fmt::Display for Local {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct RcLocal(pub ByAddress<Arc<Mutex<Local>>>);
+thread_local! {
+    static NEXT_LOCAL_ID: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Restarts the ids given to new locals on this thread. Ids only decide how locals are
+/// hashed, ordered and printed when unnamed, so resetting them before each decompilation
+/// makes the output independent of anything decompiled before it.
+pub fn reset_local_ids() {
+    NEXT_LOCAL_ID.with(|id| id.set(0));
+}
+
+fn next_local_id() -> usize {
+    NEXT_LOCAL_ID.with(|id| {
+        let next = id.get();
+        id.set(next + 1);
+        next
+    })
+}
+
+// locals are compared by address, but hashed and ordered by the order they were created in
+// so that iterating over a set of locals doesn't depend on where they were allocated.
+#[derive(Debug, Clone)]
+pub struct RcLocal(pub ByAddress<Arc<Mutex<Local>>>, usize);
+
+impl Default for RcLocal {
+    fn default() -> Self {
+        Self::new(Local::default())
+    }
+}
+
+impl PartialEq for RcLocal {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for RcLocal {}
+
+impl Hash for RcLocal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.1.hash(state);
+    }
+}
+
+impl PartialOrd for RcLocal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RcLocal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.1.cmp(&other.1).then_with(|| self.0.cmp(&other.0))
+    }
+}
 
 impl Infer for RcLocal {
     fn infer<'a: 'b, 'b>(&'a mut self, system: &mut TypeSystem<'b>) -> Type {
@@ -41,11 +121,7 @@ impl Display for RcLocal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.0 .0.lock().0 {
             Some(name) => write!(f, "{}", name),
-            None => {
-                let mut hasher = NoHashHasher::<u8>::default();
-                self.hash(&mut hasher);
-                write!(f, "UNNAMED_{}", hasher.finish())
-            }
+            None => write!(f, "UNNAMED_{}", self.1),
         }
     }
 }
@@ -56,7 +132,7 @@ impl Traverse for RcLocal {}
 
 impl RcLocal {
     pub fn new(local: Local) -> Self {
-        Self(ByAddress(Arc::new(Mutex::new(local))))
+        Self(ByAddress(Arc::new(Mutex::new(local))), next_local_id())
     }
 }
 
@@ -0,0 +1,250 @@
+use itertools::Itertools;
+
+use crate::{
+    formatter::Dialect, Assign, Binary, BinaryOperation, Block, Break, Call, Global, If, Index,
+    LValue, Literal, LocalRw, RValue, RcLocal, Repeat, Statement, Traverse, Unary,
+    UnaryOperation,
+};
+
+/// Rewrites constructs `dialect` has no syntax for so the output runs on stock interpreters.
+/// Luau's `continue` becomes a `repeat ... until true` wrapper around the loop body,
+/// `a // b` becomes `math.floor(a / b)` before Lua 5.3 and vector literals become
+/// `Vector3.new` calls. Lua 5.3's bitwise operators become calls to the `bit32` library, or
+/// to LuaJIT's `bit` library for Lua 5.1.
+pub fn lower_dialect(block: &mut Block, dialect: Dialect) {
+    lower_block(block, dialect);
+}
+
+fn lower_block(block: &mut Block, dialect: Dialect) {
+    for statement in &mut block.0 {
+        statement.traverse_rvalues(&mut |rvalue| lower_rvalue(rvalue, dialect));
+        match statement {
+            Statement::If(r#if) => {
+                lower_block(&mut r#if.then_block.lock(), dialect);
+                lower_block(&mut r#if.else_block.lock(), dialect);
+            }
+            Statement::While(r#while) => lower_loop(&mut r#while.block.lock(), None, dialect),
+            Statement::Repeat(repeat) => {
+                lower_loop(&mut repeat.block.lock(), Some(&repeat.condition), dialect)
+            }
+            Statement::NumericFor(numeric_for) => {
+                lower_loop(&mut numeric_for.block.lock(), None, dialect)
+            }
+            Statement::GenericFor(generic_for) => {
+                lower_loop(&mut generic_for.block.lock(), None, dialect)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn lower_loop(body: &mut Block, until: Option<&RValue>, dialect: Dialect) {
+    lower_block(body, dialect);
+    if dialect != Dialect::Luau {
+        lower_continue(body, until);
+    }
+}
+
+// the library bitwise operators are called from in `dialect`
+fn bit_library(dialect: Dialect) -> &'static str {
+    match dialect {
+        Dialect::Lua51 => "bit",
+        _ => "bit32",
+    }
+}
+
+fn bit_function(library: &str, name: &str, arguments: Vec<RValue>) -> RValue {
+    Call::new(
+        Index::new(Global::from(library).into(), Literal::from(name).into()).into(),
+        arguments,
+    )
+    .into()
+}
+
+fn lower_rvalue(rvalue: &mut RValue, dialect: Dialect) {
+    match rvalue {
+        RValue::Binary(binary)
+            if !dialect.has_bitwise_operators()
+                && matches!(
+                    binary.operation,
+                    BinaryOperation::BitAnd
+                        | BinaryOperation::BitOr
+                        | BinaryOperation::BitXor
+                        | BinaryOperation::ShiftLeft
+                        | BinaryOperation::ShiftRight
+                ) =>
+        {
+            let name = match binary.operation {
+                BinaryOperation::BitAnd => "band",
+                BinaryOperation::BitOr => "bor",
+                BinaryOperation::BitXor => "bxor",
+                BinaryOperation::ShiftLeft => "lshift",
+                BinaryOperation::ShiftRight => "rshift",
+                _ => unreachable!(),
+            };
+            *rvalue = bit_function(
+                bit_library(dialect),
+                name,
+                vec![
+                    std::mem::replace(binary.left.as_mut(), Literal::Nil.into()),
+                    std::mem::replace(binary.right.as_mut(), Literal::Nil.into()),
+                ],
+            );
+        }
+        RValue::Unary(Unary {
+            value,
+            operation: UnaryOperation::BitNot,
+        }) if !dialect.has_bitwise_operators() => {
+            *rvalue = bit_function(
+                bit_library(dialect),
+                "bnot",
+                vec![std::mem::replace(value.as_mut(), Literal::Nil.into())],
+            );
+        }
+        // luau and lua 5.3+ have floor division, only luau has vectors
+        RValue::Binary(binary)
+            if binary.operation == BinaryOperation::IDiv
+                && !matches!(dialect, Dialect::Luau | Dialect::Lua53 | Dialect::Lua54) =>
+        {
+            let division = Binary::new(
+                std::mem::replace(binary.left.as_mut(), Literal::Nil.into()),
+                std::mem::replace(binary.right.as_mut(), Literal::Nil.into()),
+                BinaryOperation::Div,
+            );
+            *rvalue = Call::new(
+                Index::new(Global::from("math").into(), Literal::from("floor").into()).into(),
+                vec![division.into()],
+            )
+            .into();
+        }
+        &mut RValue::Literal(Literal::Vector(x, y, z)) if dialect != Dialect::Luau => {
+            *rvalue = Call::new(
+                Index::new(Global::from("Vector3").into(), Literal::from("new").into()).into(),
+                vec![
+                    Literal::Number(x.into()).into(),
+                    Literal::Number(y.into()).into(),
+                    Literal::Number(z.into()).into(),
+                ],
+            )
+            .into();
+        }
+        RValue::Closure(closure) => lower_block(&mut closure.function.lock().body, dialect),
+        _ => {}
+    }
+}
+
+// returns whether the loop body contains a `continue` and a `break` for the loop itself,
+// jumps inside nested loops belong to those loops.
+fn find_loop_jumps(block: &Block) -> (bool, bool) {
+    let (mut has_continue, mut has_break) = (false, false);
+    for statement in &block.0 {
+        match statement {
+            Statement::Continue(_) => has_continue = true,
+            Statement::Break(_) => has_break = true,
+            Statement::If(r#if) => {
+                for block in [&r#if.then_block, &r#if.else_block] {
+                    let (continues, breaks) = find_loop_jumps(&block.lock());
+                    has_continue |= continues;
+                    has_break |= breaks;
+                }
+            }
+            _ => {}
+        }
+    }
+    (has_continue, has_break)
+}
+
+fn replace_loop_jumps(block: &mut Block, broke: Option<&RcLocal>) {
+    let mut index = 0;
+    while index < block.len() {
+        match &mut block[index] {
+            Statement::Continue(_) => block[index] = Break {}.into(),
+            Statement::Break(_) => {
+                let broke = broke.unwrap();
+                block.insert(
+                    index,
+                    Assign::new(
+                        vec![broke.clone().into()],
+                        vec![Literal::Boolean(true).into()],
+                    )
+                    .into(),
+                );
+                index += 1;
+            }
+            Statement::If(r#if) => {
+                replace_loop_jumps(&mut r#if.then_block.lock(), broke);
+                replace_loop_jumps(&mut r#if.else_block.lock(), broke);
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+// loop
+//   if a then continue end
+//   if b then break end
+// end
+// ->
+// loop
+//   local broke = false
+//   repeat
+//     if a then break end
+//     if b then broke = true break end
+//   until true
+//   if broke then break end
+// end
+fn lower_continue(body: &mut Block, until: Option<&RValue>) {
+    let (has_continue, has_break) = find_loop_jumps(body);
+    if !has_continue {
+        return;
+    }
+
+    let broke = has_break.then(RcLocal::default);
+    replace_loop_jumps(body, broke.as_ref());
+
+    let mut new_body = Block::default();
+    // the `until` condition can read locals declared in the body, which would otherwise be
+    // out of scope once the body is wrapped.
+    if let Some(until) = until {
+        let read = until.values_read();
+        let mut hoisted = Vec::new();
+        let mut index = 0;
+        while index < body.len() {
+            if let Statement::Assign(assign) = &mut body[index]
+                && assign.prefix
+                && assign
+                    .left
+                    .iter()
+                    .any(|l| l.as_local().is_some_and(|l| read.contains(&l)))
+            {
+                hoisted.extend(assign.left.iter().filter_map(LValue::as_local).cloned());
+                if assign.right.is_empty() {
+                    body.remove(index);
+                    continue;
+                }
+                assign.prefix = false;
+            }
+            index += 1;
+        }
+        if !hoisted.is_empty() {
+            let mut declaration =
+                Assign::new(hoisted.into_iter().map(|l| l.into()).collect_vec(), vec![]);
+            declaration.prefix = true;
+            new_body.push(declaration.into());
+        }
+    }
+    if let Some(broke) = &broke {
+        let mut declaration = Assign::new(
+            vec![broke.clone().into()],
+            vec![Literal::Boolean(false).into()],
+        );
+        declaration.prefix = true;
+        new_body.push(declaration.into());
+    }
+    new_body.push(Repeat::new(Literal::Boolean(true).into(), std::mem::take(body)).into());
+    if let Some(broke) = broke {
+        new_body.push(If::new(broke.into(), vec![Break {}.into()].into(), Block::default()).into());
+    }
+    *body = new_body;
+}
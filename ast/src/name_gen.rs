@@ -1,88 +1,270 @@
-use super::{Literal, RValue};
-
-/// Your global values. Update as needed based on your actual `Global` definition.
-#[derive(Debug)]
-pub enum Global {
-    Game,
-    Script,
-    Workspace,
-    Players,
-    ReplicatedStorage,
-    StarterGui,
-    StarterPlayer,
-    TweenService,
-    Debris,
-    // Add others if needed
+//! Strategies for naming locals. A [`NameGenerator`] suggests a name for a local from what
+//! it holds and how it is used, strategies are combined with [`NameGenerator::chain`] and
+//! [`name_locals`](crate::name_locals::name_locals) names every local with one.
+
+use triomphe::Arc;
+
+use crate::{
+    formatter::Formatter, naming_rules::NamingRules, BinaryOperation, Literal, RValue, RcLocal,
+    Select,
+};
+
+/// A name suggested for a local.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Name {
+    /// Used as is, unless it would shadow another local in scope.
+    Exact(String),
+    /// Numbered to tell apart the locals given it, ex. `var`, `var_1`.
+    Numbered(String),
+}
+
+/// How a local is declared.
+#[derive(Debug, Clone, Copy)]
+pub enum Role<'a> {
+    Local,
+    Parameter,
+    /// The counter of a numeric for loop inside `depth` other loops of its function.
+    NumericForCounter {
+        depth: usize,
+    },
+    /// The variable at `index` of a generic for loop over `iterator`, inside `depth` other
+    /// loops of its function.
+    GenericForVariable {
+        index: usize,
+        iterator: &'a [RValue],
+        depth: usize,
+    },
+}
+
+/// What a name is generated from.
+#[derive(Debug, Clone, Copy)]
+pub struct NameContext<'a> {
+    pub local: &'a RcLocal,
+    /// The name the local already has, ex. from debug info.
+    pub existing: Option<&'a str>,
+    pub role: Role<'a>,
+    /// The value the local is declared with.
+    pub value: Option<&'a RValue>,
 }
 
 pub trait NameGenerator {
-    fn generate_name(&self, rvalue: &RValue, identifier: usize) -> Option<String>;
+    /// Suggests a name for a local, or `None` to leave it to the next strategy.
+    fn generate_name(&self, context: &NameContext) -> Option<Name>;
+
+    /// Falls back to `next` when this strategy has no name.
+    fn chain<G: NameGenerator>(self, next: G) -> Chain<Self, G>
+    where
+        Self: Sized,
+    {
+        Chain(self, next)
+    }
 }
 
-pub struct DefaultNameGenerator {}
+impl<G: NameGenerator + ?Sized> NameGenerator for &G {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        (**self).generate_name(context)
+    }
+}
 
-impl NameGenerator for DefaultNameGenerator {
-    fn generate_name(&self, rvalue: &RValue, identifier: usize) -> Option<String> {
-        let hint = match rvalue {
-            // Global values like `game`, `script`
-            RValue::Global(global) => {
-                let name = match global {
-                    Global::Game => "game",
-                    Global::Script => "script",
-                    Global::Workspace => "workspace",
-                    Global::Players => "players",
-                    Global::ReplicatedStorage => "replicatedStorage",
-                    Global::StarterGui => "starterGui",
-                    Global::StarterPlayer => "starterPlayer",
-                    Global::TweenService => "tweenService",
-                    Global::Debris => "debris",
-                    // Add other variants or return None
-                    _ => return None,
-                };
-                Some(name.to_string())
-            }
+impl<G: NameGenerator + ?Sized> NameGenerator for Box<G> {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        (**self).generate_name(context)
+    }
+}
+
+/// A strategy that can be turned off.
+impl<G: NameGenerator> NameGenerator for Option<G> {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        self.as_ref()?.generate_name(context)
+    }
+}
+
+/// Two strategies, the second only used when the first has no name.
+#[derive(Debug, Clone, Copy)]
+pub struct Chain<A, B>(pub A, pub B);
+
+impl<A: NameGenerator, B: NameGenerator> NameGenerator for Chain<A, B> {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        self.0
+            .generate_name(context)
+            .or_else(|| self.1.generate_name(context))
+    }
+}
+
+/// Keeps names locals already have, ex. from debug info.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DebugNames;
+
+impl NameGenerator for DebugNames {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        context
+            .existing
+            .filter(|name| {
+                !name.is_empty() && Formatter::<std::fmt::Formatter>::is_valid_name(name.as_bytes())
+            })
+            .map(|name| Name::Exact(name.to_string()))
+    }
+}
+
+/// Names locals that are never referenced `_`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnusedNames;
+
+impl NameGenerator for UnusedNames {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        // the only reference is the declaration
+        (Arc::count(&context.local.0 .0) == 1).then(|| Name::Exact("_".to_string()))
+    }
+}
 
-            // Index expressions like game["RunService"]
-            RValue::Index(index) => match &*index.right {
-                RValue::Literal(Literal::String(string)) => Some(string.clone()),
-                _ => None,
+/// Names locals after the calls, globals and paths they are declared with, by a set of
+/// [`NamingRules`], ex. `Players` for `game:GetService("Players")` with the default rules.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleNames<'a>(pub &'a NamingRules);
+
+impl NameGenerator for RuleNames<'_> {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        let name = match context.role {
+            // `for _, child in ipairs(parent:GetChildren()) do`
+            Role::GenericForVariable {
+                index: 1,
+                iterator: [RValue::Call(call)],
+                ..
+            } => match call.arguments.first()? {
+                RValue::MethodCall(method_call)
+                | RValue::Select(Select::MethodCall(method_call)) => {
+                    self.0.iterator_name(&method_call.method)?.to_string()
+                }
+                _ => return None,
             },
+            _ => self.0.name(context.value?)?,
+        };
+        Some(Name::Numbered(name))
+    }
+}
+
+/// Names loop variables after their role, ex. `i` for counters and `k, v` for `pairs`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoopNames;
 
-            // Method calls like game:GetService("RunService") or parent:WaitForChild("Gui")
-            RValue::Call { function, args } => {
-                if let RValue::Field { field, .. } = &**function {
-                    match field.as_str() {
-                        "GetService" | "WaitForChild" | "FindFirstChild" => {
-                            if let Some(RValue::Literal(Literal::String(name))) = args.get(0) {
-                                Some(name.clone())
-                            } else {
-                                None
-                            }
-                        }
-                        _ => None,
+const COUNTERS: &[&str] = &["i", "j", "k"];
+
+impl NameGenerator for LoopNames {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        match context.role {
+            Role::NumericForCounter { depth } => Some(match COUNTERS.get(depth) {
+                Some(counter) => Name::Exact(counter.to_string()),
+                None => Name::Numbered("i".to_string()),
+            }),
+            Role::GenericForVariable {
+                index,
+                iterator,
+                depth,
+            } => {
+                let iterator = match iterator.first()? {
+                    RValue::Call(call) | RValue::Select(Select::Call(call)) => {
+                        call.get_method_name()?
                     }
-                } else {
-                    None
-                }
+                    RValue::Global(global) => String::from_utf8(global.0.clone()).ok()?,
+                    _ => return None,
+                };
+                let name = match (iterator.as_str(), index) {
+                    ("ipairs", 0) => COUNTERS.get(depth).copied().unwrap_or("i"),
+                    ("pairs" | "next", 0) => "k",
+                    ("ipairs" | "pairs" | "next", 1) => "v",
+                    _ => return None,
+                };
+                Some(Name::Exact(name.to_string()))
             }
+            Role::Local | Role::Parameter => None,
+        }
+    }
+}
+
+/// Names locals after the type of the value they are declared with, ex. `func` for closures.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypeNames;
+
+impl NameGenerator for TypeNames {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        let name = match context.value? {
+            RValue::Closure(_) => "func",
+            RValue::Table(_) => "tbl",
+            RValue::Literal(Literal::String(_)) => "str",
+            RValue::Literal(Literal::Number(_) | Literal::Integer(_)) => "num",
+            RValue::Literal(Literal::Boolean(_)) => "bool",
+            RValue::Literal(Literal::Vector(..)) => "vec",
+            RValue::Binary(binary) if binary.operation == BinaryOperation::Concat => "str",
+            _ => return None,
+        };
+        Some(Name::Numbered(name.to_string()))
+    }
+}
+
+/// Numbered names for locals no other strategy could name.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fallback;
 
-            _ => None,
+impl NameGenerator for Fallback {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        let name = match context.role {
+            Role::Local => "var",
+            Role::Parameter => "arg",
+            Role::NumericForCounter { .. } => "i",
+            Role::GenericForVariable { .. } => "iter",
         };
+        Some(Name::Numbered(name.to_string()))
+    }
+}
+
+/// The strategies used when none are given: debug names, unused locals, naming rules,
+/// loop roles, types and then numbered fallbacks.
+#[derive(Debug, Clone)]
+pub struct DefaultNameGenerator {
+    /// Whether names locals already have are kept.
+    pub debug_names: bool,
+    pub rules: NamingRules,
+}
 
-        hint.map(|hint| format!("{}_{}", sanitize_name(&hint), identifier))
+impl Default for DefaultNameGenerator {
+    fn default() -> Self {
+        Self {
+            debug_names: true,
+            rules: NamingRules::default(),
+        }
     }
 }
 
-/// Replaces non-alphanumeric characters and avoids identifiers starting with digits
-fn sanitize_name(name: &str) -> String {
+impl NameGenerator for DefaultNameGenerator {
+    fn generate_name(&self, context: &NameContext) -> Option<Name> {
+        self.debug_names
+            .then_some(DebugNames)
+            .chain(UnusedNames)
+            .chain(RuleNames(&self.rules))
+            .chain(LoopNames)
+            .chain(TypeNames)
+            .chain(Fallback)
+            .generate_name(context)
+    }
+}
+
+/// Makes `name` a valid identifier, dropping spaces and replacing other characters that
+/// can't be in one. Returns `None` if nothing is left of it.
+pub fn sanitize_name(name: &str) -> Option<String> {
     let mut result = name
         .chars()
-        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .filter(|&c| c != ' ')
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
         .collect::<String>();
-
-    if result.chars().next().map_or(false, |c| c.is_ascii_digit()) {
-        result = format!("_{}", result);
+    if result.is_empty() {
+        return None;
     }
-
-    result
+    if result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    // keywords aren't valid names
+    if !Formatter::<std::fmt::Formatter>::is_valid_name(result.as_bytes()) {
+        result.push('_');
+    }
+    Some(result)
 }
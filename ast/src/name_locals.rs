@@ -1,44 +1,26 @@
-use rustc_hash::FxHashSet;
-use triomphe::Arc;
-use std::collections::HashMap;
+use rustc_hash::FxHashMap;
 
-use crate::{Block, RValue, RcLocal, Statement, Traverse, Upvalue};
+use crate::{
+    name_gen::{sanitize_name, Name, NameContext, NameGenerator, Role},
+    Block, RValue, RcLocal, Statement, Traverse,
+};
 
-#[derive(Debug)]
-struct NamingContext {
-    method_name: String,
-    argument: String,
-    kind: String,
+pub struct Namer<'a, G: NameGenerator + ?Sized> {
+    generator: &'a G,
+    used_names: FxHashMap<String, usize>,
+    // the names declared in each enclosing scope
+    scopes: Vec<FxHashMap<String, RcLocal>>,
+    // the number of loops the statements being named are in, within their function
+    loop_depth: usize,
 }
 
-pub struct Namer {
-    rename: bool,
-    counter: usize,
-    upvalues: FxHashSet<RcLocal>,
-    naming_patterns: HashMap<String, Vec<String>>,
-    context_stack: Vec<NamingContext>,
-    used_names: HashMap<String, usize>,
-}
-
-impl Namer {
-    fn new(rename: bool) -> Self {
-        let mut naming_patterns = HashMap::new();
-        
-        // Roblox-specific patterns without suffixes
-        naming_patterns.insert("WaitForChild".to_string(), vec!["".to_string()]);
-        naming_patterns.insert("FindFirstChild".to_string(), vec!["".to_string()]);
-        naming_patterns.insert("GetService".to_string(), vec!["".to_string()]);
-        naming_patterns.insert("Create".to_string(), vec!["".to_string()]);
-        naming_patterns.insert("Clone".to_string(), vec!["Clone".to_string()]);
-        naming_patterns.insert("new".to_string(), vec!["".to_string()]);
-
+impl<'a, G: NameGenerator + ?Sized> Namer<'a, G> {
+    fn new(generator: &'a G) -> Self {
         Self {
-            rename,
-            counter: 1,
-            upvalues: FxHashSet::default(),
-            naming_patterns,
-            context_stack: Vec::new(), // ✅ This is correct
-            used_names: HashMap::new(),
+            generator,
+            used_names: FxHashMap::default(),
+            scopes: Vec::new(),
+            loop_depth: 0,
         }
     }
 
@@ -53,184 +35,58 @@ impl Namer {
         name
     }
 
-    fn generate_meaningful_name(&self, value: &RValue) -> Option<String> {
-        // Handle chained MethodCall/Call for WaitForChild/FindFirstChild/GetService
-        let mut current = value;
-        let mut last_name: Option<String> = None;
-        loop {
-            match current {
-                RValue::Call(call) => {
-                    if let Some(method_name) = call.get_method_name() {
-                        if ["GetService", "WaitForChild", "FindFirstChild"].contains(&method_name.as_str()) {
-                            if let Some(arg) = call.get_first_argument() {
-                                let clean_arg = arg.trim_matches(|c| c == '\'' || c == '"').replace(" ", "");
-                                let suffix = self.naming_patterns.get(&method_name).map_or("".to_string(), |v| v.join(""));
-                                if !clean_arg.is_empty() {
-                                    last_name = Some(format!("{}{}", clean_arg, suffix));
-                                }
-                            }
-                        }
-                    }
-                    // For chaining, go deeper if the call's value is another call/methodcall
-                    current = call.value.as_ref();
-                }
-                RValue::MethodCall(method_call) => {
-                    let method_name = &method_call.method;
-                    if ["GetService", "WaitForChild", "FindFirstChild"].contains(&method_name.as_str()) {
-                        if let Some(arg) = method_call.arguments.get(0) {
-                            match arg {
-                                RValue::Literal(crate::Literal::String(s)) => {
-                                    if let Ok(arg_str) = String::from_utf8(s.clone()) {
-                                        let clean_arg = arg_str.trim_matches(|c| c == '\'' || c == '"').replace(" ", "");
-                                        let suffix = self.naming_patterns.get(method_name).map_or("".to_string(), |v| v.join(""));
-                                        if !clean_arg.is_empty() {
-                                            last_name = Some(format!("{}{}", clean_arg, suffix));
-                                        }
-                                    }
-                                }
-                                RValue::Local(local) => {
-                                    let base_name = match method_call.value.as_ref() {
-                                        RValue::Index(index) => index.get_key_name(),
-                                        RValue::Global(global) => String::from_utf8(global.0.clone()).ok(),
-                                        _ => None,
-                                    };
-                                    if let Some(base) = base_name {
-                                        let mut chars = base.chars();
-                                        let capitalized = match chars.next() {
-                                            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-                                            None => base,
-                                        };
-                                        last_name = Some(format!("Some{}", capitalized));
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                    // For chaining, go deeper if the method_call's value is another call/methodcall
-                    current = method_call.value.as_ref();
-                }
-                _ => break,
-            }
-        }
-        if last_name.is_some() {
-            return last_name;
-        }
-        // Handle require(...)
-        if let RValue::Call(call) = value {
-            if let Some(method_name) = call.get_method_name() {
-                if method_name == "require" {
-                    if let Some(arg) = call.arguments.get(0) {
-                        let mut current = arg;
-                        let mut last_key = None;
-                        while let RValue::Index(index) = current {
-                            if let Some(key) = index.get_key_name() {
-                                last_key = Some(key);
-                            }
-                            current = &index.left;
-                        }
-                        if let Some(key) = last_key {
-                            return Some(key);
-                        }
-                    }
-                }
-            }
-        }
-        // Handle GetService/WaitForChild/FindFirstChild
-        if let RValue::Call(call) = value {
-            if let Some(method_name) = call.get_method_name() {
-                if ["GetService", "WaitForChild", "FindFirstChild"].contains(&method_name.as_str()) {
-                    if let Some(arg) = call.get_first_argument() {
-                        let clean_arg = arg.trim_matches(|c| c == '\'' || c == '"').replace(" ", "");
-                        let suffix = self.naming_patterns.get(&method_name).map_or("".to_string(), |v| v.join(""));
-                        if !clean_arg.is_empty() {
-                            return Some(format!("{}{}", clean_arg, suffix));
-                        }
-                    }
-                }
-            }
-        }
-        if let RValue::MethodCall(method_call) = value {
-            let method_name = &method_call.method;
-            // Handle GetService/WaitForChild/FindFirstChild for MethodCall
-            if ["GetService", "WaitForChild", "FindFirstChild"].contains(&method_name.as_str()) {
-                if let Some(arg) = method_call.arguments.get(0) {
-                    match arg {
-                        RValue::Literal(crate::Literal::String(s)) => {
-                            if let Ok(arg_str) = String::from_utf8(s.clone()) {
-                                let clean_arg = arg_str.trim_matches(|c| c == '\'' || c == '"').replace(" ", "");
-                                let suffix = self.naming_patterns.get(method_name).map_or("".to_string(), |v| v.join(""));
-                                if !clean_arg.is_empty() {
-                                    return Some(format!("{}{}", clean_arg, suffix));
-                                }
-                            }
-                        }
-                        RValue::Local(local) => {
-                            let base_name = match method_call.value.as_ref() {
-                                RValue::Index(index) => index.get_key_name(),
-                                RValue::Global(global) => String::from_utf8(global.0.clone()).ok(),
-                                _ => None,
-                            };
-                            if let Some(base) = base_name {
-                                let mut chars = base.chars();
-                                let capitalized = match chars.next() {
-                                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-                                    None => base,
-                                };
-                                return Some(format!("Some{}", capitalized));
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
-        if let RValue::Index(index) = value {
-            if let Some(key_name) = index.get_key_name() {
-                return Some(key_name);
-            }
-        }
-        None
+    // whether declaring `local` as `name` would shadow a different local that is in scope
+    fn shadows(&self, name: &str, local: &RcLocal) -> bool {
+        self.scopes
+            .iter()
+            .any(|scope| scope.get(name).is_some_and(|other| other != local))
     }
 
-    fn name_local(&mut self, prefix: &str, local: &RcLocal, value: Option<&RValue>) {
-        let mut lock = local.0.0.lock();
-        if self.rename || lock.0.is_none() {
-            // Single reference locals get underscore
-            if Arc::count(&local.0.0) == 1 {
-                lock.0 = Some("_".to_string());
+    fn name_local(&mut self, local: &RcLocal, role: Role, value: Option<&RValue>) {
+        let existing = local.0 .0.lock().0.clone();
+        let context = NameContext {
+            local,
+            existing: existing.as_deref(),
+            role,
+            value,
+        };
+        let name = self.generator.generate_name(&context);
+        let mut name = match name {
+            Some(Name::Exact(name)) if name == "_" => {
+                local.0 .0.lock().0 = Some(name);
                 return;
             }
-            // Try meaningful name first
-            if let Some(value) = value {
-                if let Some(meaningful_name) = self.generate_meaningful_name(value) {
-                    lock.0 = Some(self.unique_name(&meaningful_name));
-                    return;
-                }
-            }
-            // Better fallback names
-            let base_name = match prefix {
-                "param" => "arg",
-                "iter" => "iter",
-                "index" => "i",
-                _ => "var"
-            };
-            lock.0 = Some(self.unique_name(base_name));
+            Some(Name::Exact(name)) => sanitize_name(&name).inspect(|name| {
+                // so a local renamed to not shadow this one is numbered from `name_1`
+                self.used_names.entry(name.clone()).or_insert(1);
+            }),
+            Some(Name::Numbered(name)) => sanitize_name(&name).map(|name| self.unique_name(&name)),
+            None => None,
         }
+        .unwrap_or_else(|| self.unique_name("var"));
+        let base = name.clone();
+        while self.shadows(&name, local) {
+            name = self.unique_name(&base);
+        }
+        local.0 .0.lock().0 = Some(name.clone());
+        self.scopes.last_mut().unwrap().insert(name, local.clone());
     }
 
     fn name_locals(&mut self, block: &mut Block) {
+        self.scopes.push(FxHashMap::default());
         for statement in &mut block.0 {
             statement.post_traverse_values(&mut |value| -> Option<()> {
                 if let itertools::Either::Right(RValue::Closure(closure)) = value {
                     let mut function = closure.function.lock();
-                    
-                    // Name parameters
+                    self.scopes.push(FxHashMap::default());
+                    // loops outside of the closure don't nest the ones in it
+                    let loop_depth = std::mem::take(&mut self.loop_depth);
                     for param in &function.parameters {
-                        self.name_local("param", param, None);
+                        self.name_local(param, Role::Parameter, None);
                     }
-                    
                     self.name_locals(&mut function.body);
+                    self.loop_depth = loop_depth;
+                    self.scopes.pop();
                 };
                 None
             });
@@ -240,7 +96,7 @@ impl Namer {
                     for (idx, lvalue) in assign.left.iter().enumerate() {
                         if let Some(local) = lvalue.as_local() {
                             let value = assign.right.get(idx);
-                            self.name_local("var", local, value);
+                            self.name_local(local, Role::Local, value);
                         }
                     }
                 }
@@ -249,70 +105,49 @@ impl Namer {
                     self.name_locals(&mut r#if.else_block.lock());
                 }
                 Statement::While(r#while) => {
+                    self.loop_depth += 1;
                     self.name_locals(&mut r#while.block.lock());
+                    self.loop_depth -= 1;
                 }
                 Statement::Repeat(repeat) => {
+                    self.loop_depth += 1;
                     self.name_locals(&mut repeat.block.lock());
+                    self.loop_depth -= 1;
                 }
                 Statement::NumericFor(numeric_for) => {
-                    self.name_local("index", &numeric_for.counter, None);
+                    self.scopes.push(FxHashMap::default());
+                    let role = Role::NumericForCounter {
+                        depth: self.loop_depth,
+                    };
+                    self.name_local(&numeric_for.counter, role, None);
+                    self.loop_depth += 1;
                     self.name_locals(&mut numeric_for.block.lock());
+                    self.loop_depth -= 1;
+                    self.scopes.pop();
                 }
                 Statement::GenericFor(generic_for) => {
-                    for res_local in &generic_for.res_locals {
-                        self.name_local("iter", res_local, None);
+                    self.scopes.push(FxHashMap::default());
+                    for (index, res_local) in generic_for.res_locals.iter().enumerate() {
+                        let role = Role::GenericForVariable {
+                            index,
+                            iterator: &generic_for.right,
+                            depth: self.loop_depth,
+                        };
+                        self.name_local(res_local, role, None);
                     }
+                    self.loop_depth += 1;
                     self.name_locals(&mut generic_for.block.lock());
+                    self.loop_depth -= 1;
+                    self.scopes.pop();
                 }
                 _ => {}
             }
         }
-    }
-
-    fn find_upvalues(&mut self, block: &mut Block) {
-        for statement in &mut block.0 {
-            statement.post_traverse_values(&mut |value| -> Option<()> {
-                if let itertools::Either::Right(RValue::Closure(closure)) = value {
-                    self.upvalues.extend(
-                        closure
-                            .upvalues
-                            .iter()
-                            .map(|u| match u {
-                                Upvalue::Copy(l) | Upvalue::Ref(l) => l,
-                            })
-                            .cloned(),
-                    );
-                    self.find_upvalues(&mut closure.function.lock().body);
-                };
-                None
-            });
-
-            match statement {
-                Statement::If(r#if) => {
-                    self.find_upvalues(&mut r#if.then_block.lock());
-                    self.find_upvalues(&mut r#if.else_block.lock());
-                }
-                Statement::While(r#while) => {
-                    self.find_upvalues(&mut r#while.block.lock());
-                }
-                Statement::Repeat(repeat) => {
-                    self.find_upvalues(&mut repeat.block.lock());
-                }
-                Statement::NumericFor(numeric_for) => {
-                    self.find_upvalues(&mut numeric_for.block.lock());
-                }
-                Statement::GenericFor(generic_for) => {
-                    self.find_upvalues(&mut generic_for.block.lock());
-                }
-                _ => {}
-            }
-        }
+        self.scopes.pop();
     }
 }
 
-// Public interface
-pub fn name_locals(block: &mut Block, rename: bool) {
-    let mut namer = Namer::new(rename);
-    namer.find_upvalues(block);
-    namer.name_locals(block);
-}
\ No newline at end of file
+/// Names every local in `block` and the functions in it with `generator`.
+pub fn name_locals(block: &mut Block, generator: &(impl NameGenerator + ?Sized)) {
+    Namer::new(generator).name_locals(block);
+}
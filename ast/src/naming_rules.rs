@@ -0,0 +1,260 @@
+//! Rules for naming locals after the calls, globals and paths they are declared with, loaded
+//! from TOML or JSON. See `rules/roblox.toml` for the format and the rules used by default.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{Call, Literal, MethodCall, RValue, Select};
+
+const DEFAULT_RULES: &str = include_str!("../rules/roblox.toml");
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid TOML naming rules: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid JSON naming rules: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unknown placeholder {{{placeholder}}} in template {template:?}")]
+    Placeholder {
+        template: String,
+        placeholder: String,
+    },
+}
+
+/// Names a local declared with a call to `method`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodRule {
+    pub method: String,
+    /// Only applies to calls on a global or key of this name.
+    #[serde(default)]
+    pub object: Option<String>,
+    /// The argument `{arg}` and `{path}` are taken from, counting from 0.
+    #[serde(default)]
+    pub argument: usize,
+    /// The name, with `{arg}`, `{path}`, `{object}` and `{Object}` placeholders.
+    pub template: String,
+}
+
+impl MethodRule {
+    // the template with its placeholders filled in, if they all have a value
+    fn expand(&self, object: Option<&str>, argument: Option<&RValue>) -> Option<String> {
+        let mut result = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}')?;
+            result.push_str(&rest[..start]);
+            match &rest[start + 1..end] {
+                "arg" => match argument? {
+                    RValue::Literal(Literal::String(string)) => {
+                        result.push_str(std::str::from_utf8(string).ok()?)
+                    }
+                    _ => return None,
+                },
+                "path" => result.push_str(path(argument?)?.last()?),
+                "object" => result.push_str(object?),
+                "Object" => {
+                    let mut chars = object?.chars();
+                    result.extend(chars.next()?.to_uppercase());
+                    result.push_str(chars.as_str());
+                }
+                _ => return None,
+            }
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+        Some(result)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map_or(rest.len(), |end| start + end);
+            let placeholder = &rest[start + 1..end];
+            if !matches!(placeholder, "arg" | "path" | "object" | "Object") {
+                return Err(Error::Placeholder {
+                    template: self.template.clone(),
+                    placeholder: placeholder.to_string(),
+                });
+            }
+            rest = &rest[(end + 1).min(rest.len())..];
+        }
+        Ok(())
+    }
+}
+
+/// The rules a [`RuleNames`](crate::name_gen::RuleNames) strategy names locals by.
+/// Defaults to the rules for Roblox scripts.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamingRules {
+    /// Tried in order, the first that applies names the local.
+    #[serde(default)]
+    pub methods: Vec<MethodRule>,
+    /// Names for the values of `for _, value in ipairs(object:method())` loops, by method.
+    #[serde(default)]
+    pub iterators: BTreeMap<String, String>,
+    /// Names for locals holding a global, by global.
+    #[serde(default)]
+    pub globals: BTreeMap<String, String>,
+    /// Names for modules and instances by the end of their path, ex. `Packages.Knit`.
+    #[serde(default)]
+    pub imports: BTreeMap<String, String>,
+}
+
+impl Default for NamingRules {
+    fn default() -> Self {
+        Self::from_toml(DEFAULT_RULES).unwrap()
+    }
+}
+
+impl NamingRules {
+    pub fn from_toml(input: &str) -> Result<Self, Error> {
+        let rules = toml::from_str::<Self>(input)?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, Error> {
+        let rules = serde_json::from_str::<Self>(input)?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        self.methods.iter().try_for_each(MethodRule::validate)
+    }
+
+    /// The name for a local declared with `value`.
+    pub fn name(&self, value: &RValue) -> Option<String> {
+        match value {
+            RValue::Call(call) | RValue::Select(Select::Call(call)) => self.call_name(call),
+            RValue::MethodCall(method_call) | RValue::Select(Select::MethodCall(method_call)) => {
+                self.method_call_name(method_call)
+            }
+            RValue::Global(global) => self.import_name(path(value)).or_else(|| {
+                self.globals
+                    .get(std::str::from_utf8(&global.0).ok()?)
+                    .cloned()
+            }),
+            RValue::Index(index) => self
+                .import_name(path(value))
+                .or_else(|| string_key(&index.right)),
+            _ => None,
+        }
+    }
+
+    /// The name for the values of a loop over what `method` returns.
+    pub fn iterator_name(&self, method: &str) -> Option<&str> {
+        self.iterators.get(method).map(String::as_str)
+    }
+
+    fn call_name(&self, call: &Call) -> Option<String> {
+        let (method, object) = match call.value.as_ref() {
+            RValue::Global(global) => (std::str::from_utf8(&global.0).ok()?, None),
+            RValue::Index(index) => (string_key_str(&index.right)?, object_name(&index.left)),
+            _ => return None,
+        };
+        // modules are named by their path before the rules for `require`
+        let import = match (method, &object) {
+            ("require", None) => self.import_name(call.arguments.first().and_then(path)),
+            _ => None,
+        };
+        import.or_else(|| self.method_name(method, object, &call.arguments))
+    }
+
+    fn method_call_name(&self, method_call: &MethodCall) -> Option<String> {
+        self.import_name(method_call_path(method_call)).or_else(|| {
+            self.method_name(
+                &method_call.method,
+                object_name(&method_call.value),
+                &method_call.arguments,
+            )
+        })
+    }
+
+    fn method_name(
+        &self,
+        method: &str,
+        object: Option<&str>,
+        arguments: &[RValue],
+    ) -> Option<String> {
+        self.methods
+            .iter()
+            .filter(|rule| {
+                rule.method == method
+                    && rule
+                        .object
+                        .as_ref()
+                        .is_none_or(|rule_object| Some(rule_object.as_str()) == object)
+            })
+            .find_map(|rule| rule.expand(object, arguments.get(rule.argument)))
+    }
+
+    // the import that `path` ends with, the longest if there are several
+    fn import_name(&self, path: Option<Vec<&str>>) -> Option<String> {
+        let path = path?;
+        self.imports
+            .iter()
+            .filter(|(import, _)| {
+                let import = import.split('.').collect::<Vec<_>>();
+                path.ends_with(&import)
+            })
+            .max_by_key(|(import, _)| import.split('.').count())
+            .map(|(_, name)| name.clone())
+    }
+}
+
+fn string_key_str(key: &RValue) -> Option<&str> {
+    match key {
+        RValue::Literal(Literal::String(key)) => std::str::from_utf8(key).ok(),
+        _ => None,
+    }
+}
+
+fn string_key(key: &RValue) -> Option<String> {
+    string_key_str(key).map(str::to_string)
+}
+
+// the name of a global, or the key of an index, that a method is called on
+fn object_name(value: &RValue) -> Option<&str> {
+    match value {
+        RValue::Global(global) => std::str::from_utf8(&global.0).ok(),
+        RValue::Index(index) => string_key_str(&index.right),
+        _ => None,
+    }
+}
+
+// the keys `value` is looked up by, starting from a global, ex. `game`, `ReplicatedStorage`
+// and `Packages` for `game:GetService("ReplicatedStorage").Packages`. Calls with a single
+// string argument are treated like indexing, and paths starting from a local leave it out.
+fn path(value: &RValue) -> Option<Vec<&str>> {
+    match value {
+        RValue::Local(_) => Some(Vec::new()),
+        RValue::Global(global) => Some(vec![std::str::from_utf8(&global.0).ok()?]),
+        RValue::Index(index) => {
+            let mut path = path(&index.left)?;
+            path.push(string_key_str(&index.right)?);
+            Some(path)
+        }
+        RValue::MethodCall(method_call) | RValue::Select(Select::MethodCall(method_call)) => {
+            method_call_path(method_call)
+        }
+        _ => None,
+    }
+}
+
+fn method_call_path(method_call: &MethodCall) -> Option<Vec<&str>> {
+    match method_call.arguments.as_slice() {
+        [key] => {
+            let mut path = path(&method_call.value)?;
+            path.push(string_key_str(key)?);
+            Some(path)
+        }
+        _ => None,
+    }
+}
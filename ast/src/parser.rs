@@ -0,0 +1,944 @@
+//! Parses Lua and Luau source into the same ast the lifters produce, so code can be written
+//! by hand to test the formatter and the passes over the ast.
+//!
+//! Every name a local is declared with becomes its own [`RcLocal`], and the upvalues of
+//! closures are the locals of enclosing functions they use, in the order they are first
+//! used. In Luau they are captured by reference only if they are assigned to after being
+//! declared, in Lua they always are. Lua 5.4 functions that use globals capture `_ENV` like
+//! `luac` does.
+//!
+//! `do` blocks are flattened into the block around them, locals in them keep their identity
+//! but may need renaming when formatted. Luau's type annotations, if-expressions and string
+//! interpolation are not supported.
+
+mod lexer;
+
+use by_address::ByAddress;
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use thiserror::Error;
+use triomphe::Arc;
+
+use crate::{
+    formatter::Dialect, Assign, Attribute, Binary, BinaryOperation, Block, Break, Call, Closure,
+    Continue, Function, GenericFor, Global, Goto, If, Index, LValue, Label, Literal, Local,
+    MethodCall, NumericFor, RValue, RcLocal, Repeat, Return, Select, Statement, Table, Traverse,
+    Unary, UnaryOperation, Upvalue, VarArg, While,
+};
+use lexer::{Lexer, Token};
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("line {line}: {kind}")]
+pub struct Error {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ErrorKind {
+    #[error("unexpected character {0:?}")]
+    UnexpectedCharacter(char),
+    #[error("unfinished string")]
+    UnfinishedString,
+    #[error("unfinished long string or comment")]
+    UnfinishedLongString,
+    #[error("invalid escape sequence `\\{0}`")]
+    InvalidEscape(String),
+    #[error("malformed number `{0}`")]
+    MalformedNumber(String),
+    #[error("expected {expected}, found {found}")]
+    Expected { expected: String, found: String },
+    #[error("cannot assign to this expression")]
+    InvalidAssignment,
+    #[error("cannot use `...` outside a variadic function")]
+    VarArgOutsideVariadic,
+    #[error("unknown attribute `{0}`")]
+    UnknownAttribute(String),
+    #[error("unsupported syntax: {0}")]
+    Unsupported(&'static str),
+}
+
+/// Parses `source` written in `dialect`.
+pub fn parse(source: &str, dialect: Dialect) -> Result<Block, Error> {
+    let tokens = Lexer::new(source, dialect).tokens()?;
+    let mut parser = Parser::new(tokens, dialect);
+    let mut block = parser.chunk()?;
+    capture_kinds(&mut block, dialect, &parser.reassigned);
+    Ok(block)
+}
+
+struct FunctionState {
+    is_variadic: bool,
+    // the locals of enclosing functions used by this one
+    upvalues: Vec<RcLocal>,
+    // the index of the first scope of this function
+    scope_start: usize,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    dialect: Dialect,
+    scopes: Vec<FxHashMap<String, RcLocal>>,
+    functions: Vec<FunctionState>,
+    // `_ENV` of the main function in Lua 5.4
+    environment: Option<RcLocal>,
+    // locals that are assigned to after being declared
+    reassigned: FxHashSet<RcLocal>,
+}
+
+// binary operators and their left and right precedence, like in `lparser.c`
+fn binary_operation(token: &Token) -> Option<(BinaryOperation, usize, usize)> {
+    let (Token::Symbol(symbol) | Token::Keyword(symbol)) = token else {
+        return None;
+    };
+    Some(match *symbol {
+        "or" => (BinaryOperation::Or, 1, 1),
+        "and" => (BinaryOperation::And, 2, 2),
+        "<" => (BinaryOperation::LessThan, 3, 3),
+        ">" => (BinaryOperation::GreaterThan, 3, 3),
+        "<=" => (BinaryOperation::LessThanOrEqual, 3, 3),
+        ">=" => (BinaryOperation::GreaterThanOrEqual, 3, 3),
+        "~=" => (BinaryOperation::NotEqual, 3, 3),
+        "==" => (BinaryOperation::Equal, 3, 3),
+        "|" => (BinaryOperation::BitOr, 4, 4),
+        "~" => (BinaryOperation::BitXor, 5, 5),
+        "&" => (BinaryOperation::BitAnd, 6, 6),
+        "<<" => (BinaryOperation::ShiftLeft, 7, 7),
+        ">>" => (BinaryOperation::ShiftRight, 7, 7),
+        ".." => (BinaryOperation::Concat, 9, 8),
+        "+" => (BinaryOperation::Add, 10, 10),
+        "-" => (BinaryOperation::Sub, 10, 10),
+        "*" => (BinaryOperation::Mul, 11, 11),
+        "/" => (BinaryOperation::Div, 11, 11),
+        "//" => (BinaryOperation::IDiv, 11, 11),
+        "%" => (BinaryOperation::Mod, 11, 11),
+        "^" => (BinaryOperation::Pow, 14, 13),
+        _ => return None,
+    })
+}
+
+const UNARY_PRECEDENCE: usize = 12;
+
+// Luau's compound assignments and the operation they stand for
+fn compound_operation(token: &Token) -> Option<BinaryOperation> {
+    let Token::Symbol(symbol) = token else {
+        return None;
+    };
+    Some(match *symbol {
+        "+=" => BinaryOperation::Add,
+        "-=" => BinaryOperation::Sub,
+        "*=" => BinaryOperation::Mul,
+        "/=" => BinaryOperation::Div,
+        "//=" => BinaryOperation::IDiv,
+        "%=" => BinaryOperation::Mod,
+        "^=" => BinaryOperation::Pow,
+        "..=" => BinaryOperation::Concat,
+        _ => return None,
+    })
+}
+
+// a call or vararg, which has as many values as are used
+fn is_multiple(value: &RValue) -> bool {
+    matches!(
+        value,
+        RValue::Call(_) | RValue::MethodCall(_) | RValue::VarArg(_)
+    )
+}
+
+// a call or vararg in parentheses, which is adjusted to one value
+fn into_select(value: RValue) -> RValue {
+    match value {
+        RValue::Call(call) => RValue::Select(call.into()),
+        RValue::MethodCall(method_call) => RValue::Select(method_call.into()),
+        RValue::VarArg(var_arg) => RValue::Select(var_arg.into()),
+        value => value,
+    }
+}
+
+// the value a parenthesized call or vararg is where only one value is used anyway
+fn single(value: RValue) -> RValue {
+    match value {
+        RValue::Select(Select::Call(call)) => call.into(),
+        RValue::Select(Select::MethodCall(method_call)) => method_call.into(),
+        RValue::Select(Select::VarArg(var_arg)) => var_arg.into(),
+        value => value,
+    }
+}
+
+// adjusts the values assigned to `targets` lvalues. Like in the lifters, a call or vararg
+// that sets several of them is a `Select`, and one in parentheses is followed by nils.
+fn adjust(mut values: Vec<RValue>, targets: usize) -> Vec<RValue> {
+    let Some(last) = values.pop() else {
+        return values;
+    };
+    if values.len() + 1 < targets && is_multiple(&last) {
+        values.push(into_select(last));
+    } else if values.len() + 1 < targets && matches!(last, RValue::Select(_)) {
+        values.push(single(last));
+        values.resize(targets, Literal::Nil.into());
+    } else {
+        values.push(single(last));
+    }
+    values
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>, dialect: Dialect) -> Self {
+        let mut scopes = vec![FxHashMap::default()];
+        let environment = matches!(dialect, Dialect::Lua53 | Dialect::Lua54).then(|| {
+            let environment = RcLocal::new(Local::new(Some("_ENV".into())));
+            scopes[0].insert("_ENV".into(), environment.clone());
+            environment
+        });
+        Self {
+            tokens,
+            position: 0,
+            dialect,
+            scopes,
+            functions: Vec::new(),
+            environment,
+            reassigned: FxHashSet::default(),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let index = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[index].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token != Token::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error {
+            line: self.line(),
+            kind,
+        }
+    }
+
+    fn expected(&self, expected: impl Into<String>) -> Error {
+        self.error(ErrorKind::Expected {
+            expected: expected.into(),
+            found: self.peek().to_string(),
+        })
+    }
+
+    fn check_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    fn check_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Keyword(k) if *k == keyword)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let matched = self.check_symbol(symbol);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matched = self.check_keyword(keyword);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.expected(format!("`{}`", symbol)))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.expected(format!("`{}`", keyword)))
+        }
+    }
+
+    fn expect_name(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Token::Name(name) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => Err(self.expected("name")),
+        }
+    }
+
+    // Luau's type annotations start with `:` where Lua has none
+    fn reject_type_annotation(&self) -> Result<(), Error> {
+        if self.dialect == Dialect::Luau && self.check_symbol(":") {
+            Err(self.error(ErrorKind::Unsupported("type annotations")))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn declare(&mut self, name: String) -> RcLocal {
+        let local = RcLocal::new(Local::new(Some(name.clone())));
+        self.scopes.last_mut().unwrap().insert(name, local.clone());
+        local
+    }
+
+    // the local `name` refers to, which functions between it and here capture
+    fn resolve(&mut self, name: &str) -> Option<RcLocal> {
+        let (depth, local) = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| scope.get(name).map(|local| (depth, local.clone())))?;
+        for function in self.functions.iter_mut().rev() {
+            if function.scope_start <= depth {
+                break;
+            }
+            if !function.upvalues.contains(&local) {
+                function.upvalues.push(local.clone());
+            }
+        }
+        Some(local)
+    }
+
+    fn name(&mut self, name: String) -> RValue {
+        if let Some(local) = self.resolve(&name) {
+            return local.into();
+        }
+        if self.environment.is_some() {
+            let environment = self.resolve("_ENV").unwrap();
+            // a global in a different `_ENV`
+            if Some(&environment) != self.environment.as_ref() {
+                return Index::new(
+                    environment.into(),
+                    Literal::String(name.into_bytes()).into(),
+                )
+                .into();
+            }
+        }
+        Global::new(name.into_bytes()).into()
+    }
+
+    fn chunk(&mut self) -> Result<Block, Error> {
+        self.functions.push(FunctionState {
+            is_variadic: true,
+            upvalues: Vec::new(),
+            scope_start: 0,
+        });
+        self.scopes.push(FxHashMap::default());
+        let block = self.statements()?;
+        if *self.peek() != Token::Eof {
+            return Err(self.expected("statement"));
+        }
+        Ok(block)
+    }
+
+    fn block(&mut self) -> Result<Block, Error> {
+        self.scopes.push(FxHashMap::default());
+        let block = self.statements();
+        self.scopes.pop();
+        block
+    }
+
+    fn is_block_end(&self) -> bool {
+        match self.peek() {
+            Token::Eof => true,
+            Token::Keyword(keyword) => matches!(*keyword, "end" | "else" | "elseif" | "until"),
+            _ => false,
+        }
+    }
+
+    // the statements until the end of the block, in the current scope
+    fn statements(&mut self) -> Result<Block, Error> {
+        let mut block = Block::default();
+        while !self.is_block_end() {
+            if self.eat_symbol(";") {
+                continue;
+            }
+            if self.eat_keyword("return") {
+                block.push(self.r#return()?.into());
+                break;
+            }
+            self.statement(&mut block)?;
+        }
+        Ok(block)
+    }
+
+    fn statement(&mut self, block: &mut Block) -> Result<(), Error> {
+        match self.next() {
+            Token::Keyword("if") => block.push(self.r#if()?.into()),
+            Token::Keyword("while") => {
+                let condition = single(self.expression()?);
+                self.expect_keyword("do")?;
+                let body = self.block()?;
+                self.expect_keyword("end")?;
+                block.push(While::new(condition, body).into());
+            }
+            Token::Keyword("do") => {
+                let body = self.block()?;
+                self.expect_keyword("end")?;
+                block.extend(body.0);
+            }
+            Token::Keyword("for") => block.push(self.r#for()?),
+            Token::Keyword("repeat") => {
+                // the condition can use the locals of the body
+                self.scopes.push(FxHashMap::default());
+                let body = self.statements();
+                let condition = body.and_then(|body| {
+                    self.expect_keyword("until")?;
+                    Ok((body, single(self.expression()?)))
+                });
+                self.scopes.pop();
+                let (body, condition) = condition?;
+                block.push(Repeat::new(condition, body).into());
+            }
+            Token::Keyword("function") => block.push(self.function_statement()?.into()),
+            Token::Keyword("local") => {
+                if self.eat_keyword("function") {
+                    let name = self.expect_name()?;
+                    let local = self.declare(name.clone());
+                    let closure = self.function_body(Some(name), false)?;
+                    let mut assign = Assign::new(vec![local.into()], vec![closure.into()]);
+                    assign.prefix = true;
+                    block.push(assign.into());
+                } else {
+                    block.push(self.local()?.into());
+                }
+            }
+            Token::Keyword("break") => block.push(Break {}.into()),
+            Token::Keyword("goto") => {
+                let label = self.expect_name()?;
+                block.push(Goto::new(Label(label)).into());
+            }
+            Token::Symbol("::") if self.dialect.supports_goto() => {
+                let label = self.expect_name()?;
+                self.expect_symbol("::")?;
+                block.push(Label(label).into());
+            }
+            Token::Name(name)
+                if self.dialect == Dialect::Luau && name == "continue" && {
+                    // `continue` is only a keyword where it can't start an expression
+                    !matches!(
+                        self.peek(),
+                        Token::Symbol("(" | "." | "[" | ":" | "=" | "," | "{") | Token::String(_)
+                    ) && compound_operation(self.peek()).is_none()
+                } =>
+            {
+                block.push(Continue {}.into())
+            }
+            Token::Name(name)
+                if self.dialect == Dialect::Luau
+                    && (name == "type" || name == "export")
+                    && matches!(self.peek(), Token::Name(_)) =>
+            {
+                return Err(self.error(ErrorKind::Unsupported("type aliases")));
+            }
+            _ => {
+                self.position -= 1;
+                block.push(self.expression_statement()?);
+            }
+        }
+        Ok(())
+    }
+
+    fn r#return(&mut self) -> Result<Return, Error> {
+        let values = if self.is_block_end() || self.check_symbol(";") {
+            Vec::new()
+        } else {
+            self.expression_list()?
+        };
+        self.eat_symbol(";");
+        if !self.is_block_end() {
+            return Err(self.expected("end of block after `return`"));
+        }
+        Ok(Return::new(values))
+    }
+
+    fn r#if(&mut self) -> Result<If, Error> {
+        let condition = single(self.expression()?);
+        self.expect_keyword("then")?;
+        let then_block = self.block()?;
+        let else_block = if self.eat_keyword("elseif") {
+            Block(vec![self.r#if()?.into()])
+        } else if self.eat_keyword("else") {
+            let else_block = self.block()?;
+            self.expect_keyword("end")?;
+            else_block
+        } else {
+            self.expect_keyword("end")?;
+            Block::default()
+        };
+        Ok(If::new(condition, then_block, else_block))
+    }
+
+    fn r#for(&mut self) -> Result<Statement, Error> {
+        let first = self.expect_name()?;
+        self.reject_type_annotation()?;
+        if self.eat_symbol("=") {
+            let initial = single(self.expression()?);
+            self.expect_symbol(",")?;
+            let limit = single(self.expression()?);
+            let step = if self.eat_symbol(",") {
+                single(self.expression()?)
+            } else if self.dialect.has_integers() {
+                Literal::Integer(1).into()
+            } else {
+                Literal::Number(1.0).into()
+            };
+            self.expect_keyword("do")?;
+            self.scopes.push(FxHashMap::default());
+            let counter = self.declare(first);
+            let body = self.block();
+            self.scopes.pop();
+            self.expect_keyword("end")?;
+            return Ok(NumericFor::new(initial, limit, step, counter, body?).into());
+        }
+
+        let mut names = vec![first];
+        while self.eat_symbol(",") {
+            names.push(self.expect_name()?);
+            self.reject_type_annotation()?;
+        }
+        self.expect_keyword("in")?;
+        let mut right = self.expression_list()?;
+        // the generator, state and control are adjusted to three values
+        if let Some(RValue::Select(_)) = right.last() {
+            let last = single(right.pop().unwrap());
+            right.push(last);
+            right.resize(3, Literal::Nil.into());
+        }
+        self.expect_keyword("do")?;
+        self.scopes.push(FxHashMap::default());
+        let locals = names.into_iter().map(|name| self.declare(name)).collect();
+        let body = self.block();
+        self.scopes.pop();
+        self.expect_keyword("end")?;
+        Ok(GenericFor::new(locals, right, body?).into())
+    }
+
+    // `function a.b:c() end`, which assigns a closure to `a.b.c`
+    fn function_statement(&mut self) -> Result<Assign, Error> {
+        let mut name = self.expect_name()?;
+        let mut target = self.name(name.clone());
+        let mut is_method = false;
+        while self.check_symbol(".") || self.check_symbol(":") {
+            is_method = self.eat_symbol(":");
+            if !is_method {
+                self.position += 1;
+            }
+            name = self.expect_name()?;
+            let key = Literal::String(name.clone().into_bytes());
+            target = Index::new(target, key.into()).into();
+            if is_method {
+                break;
+            }
+        }
+        let closure = self.function_body(Some(name), is_method)?;
+        let target = target.into_lvalue().unwrap();
+        if let LValue::Local(local) = &target {
+            self.reassigned.insert(local.clone());
+        }
+        Ok(Assign::new(vec![target], vec![closure.into()]))
+    }
+
+    fn local(&mut self) -> Result<Assign, Error> {
+        let mut names = Vec::new();
+        loop {
+            let name = self.expect_name()?;
+            self.reject_type_annotation()?;
+            let attribute = if self.dialect.supports_attributes() && self.eat_symbol("<") {
+                let attribute = match self.expect_name()?.as_str() {
+                    "const" => Attribute::Const,
+                    "close" => Attribute::Close,
+                    attribute => {
+                        return Err(self.error(ErrorKind::UnknownAttribute(attribute.into())));
+                    }
+                };
+                self.expect_symbol(">")?;
+                Some(attribute)
+            } else {
+                None
+            };
+            names.push((name, attribute));
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        // the values are evaluated before the locals are in scope
+        let right = if self.eat_symbol("=") {
+            adjust(self.expression_list()?, names.len())
+        } else {
+            Vec::new()
+        };
+        let left = names
+            .into_iter()
+            .map(|(name, attribute)| {
+                let local = self.declare(name);
+                local.0.lock().1 = attribute;
+                local.into()
+            })
+            .collect();
+        let mut assign = Assign::new(left, right);
+        assign.prefix = true;
+        Ok(assign)
+    }
+
+    fn expression_statement(&mut self) -> Result<Statement, Error> {
+        let value = self.suffixed_expression()?;
+        if self.check_symbol("=") || self.check_symbol(",") {
+            let mut left = vec![self.assignment_target(value)?];
+            while self.eat_symbol(",") {
+                let value = self.suffixed_expression()?;
+                left.push(self.assignment_target(value)?);
+            }
+            self.expect_symbol("=")?;
+            let right = adjust(self.expression_list()?, left.len());
+            return Ok(Assign::new(left, right).into());
+        }
+        if self.dialect == Dialect::Luau
+            && let Some(operation) = compound_operation(self.peek())
+        {
+            self.position += 1;
+            let target = self.assignment_target(value.clone())?;
+            let right = single(self.expression()?);
+            let value = Binary::new(value, right, operation);
+            return Ok(Assign::new(vec![target], vec![value.into()]).into());
+        }
+        match value {
+            RValue::Call(call) => Ok(call.into()),
+            RValue::MethodCall(method_call) => Ok(method_call.into()),
+            _ => Err(self.expected("`=` or arguments")),
+        }
+    }
+
+    fn assignment_target(&mut self, value: RValue) -> Result<LValue, Error> {
+        let target = value
+            .into_lvalue()
+            .ok_or_else(|| self.error(ErrorKind::InvalidAssignment))?;
+        if let LValue::Local(local) = &target {
+            self.reassigned.insert(local.clone());
+        }
+        Ok(target)
+    }
+
+    // the parameters and body of a function, after its name
+    fn function_body(&mut self, name: Option<String>, is_method: bool) -> Result<Closure, Error> {
+        if self.dialect == Dialect::Luau && self.check_symbol("<") {
+            return Err(self.error(ErrorKind::Unsupported("generic functions")));
+        }
+        self.expect_symbol("(")?;
+        self.functions.push(FunctionState {
+            is_variadic: false,
+            upvalues: Vec::new(),
+            scope_start: self.scopes.len(),
+        });
+        self.scopes.push(FxHashMap::default());
+        let function = self.function_contents(name, is_method);
+        self.scopes.pop();
+        let state = self.functions.pop().unwrap();
+        Ok(Closure {
+            function: ByAddress(Arc::new(Mutex::new(function?))),
+            upvalues: state.upvalues.into_iter().map(Upvalue::Copy).collect(),
+        })
+    }
+
+    fn function_contents(
+        &mut self,
+        name: Option<String>,
+        is_method: bool,
+    ) -> Result<Function, Error> {
+        let mut parameters = Vec::new();
+        if is_method {
+            parameters.push(self.declare("self".into()));
+        }
+        let mut is_variadic = false;
+        if !self.check_symbol(")") {
+            loop {
+                if self.eat_symbol("...") {
+                    is_variadic = true;
+                    self.reject_type_annotation()?;
+                    break;
+                }
+                let name = self.expect_name()?;
+                self.reject_type_annotation()?;
+                parameters.push(self.declare(name));
+                if !self.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        self.expect_symbol(")")?;
+        self.reject_type_annotation()?;
+        self.functions.last_mut().unwrap().is_variadic = is_variadic;
+        let body = self.statements()?;
+        self.expect_keyword("end")?;
+        Ok(Function {
+            name,
+            parameters,
+            is_variadic,
+            body,
+        })
+    }
+
+    // expressions separated by commas. Only the last can be a `Select`.
+    fn expression_list(&mut self) -> Result<Vec<RValue>, Error> {
+        let mut values = vec![self.expression()?];
+        while self.eat_symbol(",") {
+            let last = values.pop().unwrap();
+            values.push(single(last));
+            values.push(self.expression()?);
+        }
+        Ok(values)
+    }
+
+    fn expression(&mut self) -> Result<RValue, Error> {
+        self.subexpression(0)
+    }
+
+    // an expression of binary operators with a left precedence greater than `limit`
+    fn subexpression(&mut self, limit: usize) -> Result<RValue, Error> {
+        let unary = match self.peek() {
+            Token::Keyword("not") => Some(UnaryOperation::Not),
+            Token::Symbol("-") => Some(UnaryOperation::Negate),
+            Token::Symbol("#") => Some(UnaryOperation::Length),
+            Token::Symbol("~") if self.dialect.has_bitwise_operators() => {
+                Some(UnaryOperation::BitNot)
+            }
+            _ => None,
+        };
+        let mut left = if let Some(operation) = unary {
+            self.position += 1;
+            let value = single(self.subexpression(UNARY_PRECEDENCE)?);
+            match (operation, value) {
+                (UnaryOperation::Negate, RValue::Literal(Literal::Number(value))) => {
+                    Literal::Number(-value).into()
+                }
+                (UnaryOperation::Negate, RValue::Literal(Literal::Integer(value))) => {
+                    Literal::Integer(value.wrapping_neg()).into()
+                }
+                (operation, value) => Unary::new(value, operation).into(),
+            }
+        } else {
+            self.simple_expression()?
+        };
+        while let Some((operation, left_precedence, right_precedence)) =
+            binary_operation(self.peek())
+            && left_precedence > limit
+        {
+            self.check_operation(operation)?;
+            self.position += 1;
+            let right = single(self.subexpression(right_precedence)?);
+            left = Binary::new(single(left), right, operation).into();
+        }
+        Ok(left)
+    }
+
+    fn check_operation(&self, operation: BinaryOperation) -> Result<(), Error> {
+        match operation {
+            BinaryOperation::IDiv if matches!(self.dialect, Dialect::Lua51 | Dialect::Lua52) => {
+                Err(self.error(ErrorKind::Unsupported("floor division")))
+            }
+            BinaryOperation::BitAnd
+            | BinaryOperation::BitOr
+            | BinaryOperation::BitXor
+            | BinaryOperation::ShiftLeft
+            | BinaryOperation::ShiftRight
+                if !self.dialect.has_bitwise_operators() =>
+            {
+                Err(self.error(ErrorKind::Unsupported("bitwise operators")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn simple_expression(&mut self) -> Result<RValue, Error> {
+        let value = match self.peek() {
+            Token::Number { value, integer } => match integer {
+                Some(integer) if self.dialect.has_integers() => Literal::Integer(*integer),
+                _ => Literal::Number(*value),
+            }
+            .into(),
+            Token::String(string) => Literal::String(string.clone()).into(),
+            Token::Keyword("nil") => Literal::Nil.into(),
+            Token::Keyword("true") => Literal::Boolean(true).into(),
+            Token::Keyword("false") => Literal::Boolean(false).into(),
+            Token::Symbol("...") => {
+                if !self.functions.last().unwrap().is_variadic {
+                    return Err(self.error(ErrorKind::VarArgOutsideVariadic));
+                }
+                VarArg.into()
+            }
+            Token::Symbol("{") => return self.table().map(RValue::from),
+            Token::Keyword("function") => {
+                self.position += 1;
+                return self.function_body(None, false).map(RValue::from);
+            }
+            Token::Keyword("if") if self.dialect == Dialect::Luau => {
+                return Err(self.error(ErrorKind::Unsupported("if-expressions")));
+            }
+            _ => return self.suffixed_expression(),
+        };
+        self.position += 1;
+        Ok(value)
+    }
+
+    fn primary_expression(&mut self) -> Result<RValue, Error> {
+        match self.peek() {
+            Token::Name(name) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(self.name(name))
+            }
+            Token::Symbol("(") => {
+                self.position += 1;
+                let value = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(into_select(single(value)))
+            }
+            _ => Err(self.expected("expression")),
+        }
+    }
+
+    // a name or parenthesized expression followed by indexes and calls
+    fn suffixed_expression(&mut self) -> Result<RValue, Error> {
+        let mut value = self.primary_expression()?;
+        loop {
+            value = match self.peek() {
+                Token::Symbol(".") => {
+                    self.position += 1;
+                    let key = Literal::String(self.expect_name()?.into_bytes());
+                    Index::new(single(value), key.into()).into()
+                }
+                Token::Symbol("[") => {
+                    self.position += 1;
+                    let key = single(self.expression()?);
+                    self.expect_symbol("]")?;
+                    Index::new(single(value), key).into()
+                }
+                Token::Symbol(":") => {
+                    self.position += 1;
+                    let method = self.expect_name()?;
+                    let arguments = self.arguments()?;
+                    MethodCall::new(single(value), method, arguments).into()
+                }
+                Token::Symbol("(" | "{") | Token::String(_) => {
+                    let arguments = self.arguments()?;
+                    Call::new(single(value), arguments).into()
+                }
+                _ => return Ok(value),
+            };
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<RValue>, Error> {
+        match self.peek() {
+            Token::String(string) => {
+                let string = Literal::String(string.clone());
+                self.position += 1;
+                Ok(vec![string.into()])
+            }
+            Token::Symbol("{") => Ok(vec![self.table()?.into()]),
+            Token::Symbol("(") => {
+                self.position += 1;
+                if self.eat_symbol(")") {
+                    return Ok(Vec::new());
+                }
+                let arguments = self.expression_list()?;
+                self.expect_symbol(")")?;
+                Ok(arguments)
+            }
+            _ => Err(self.expected("arguments")),
+        }
+    }
+
+    fn table(&mut self) -> Result<Table, Error> {
+        self.expect_symbol("{")?;
+        let mut fields: Vec<(Option<RValue>, RValue)> = Vec::new();
+        while !self.check_symbol("}") {
+            // only the last value can be a `Select`
+            if let Some((None, last)) = fields.last_mut() {
+                *last = single(std::mem::replace(last, Literal::Nil.into()));
+            }
+            let field = match (self.peek(), self.peek_at(1)) {
+                (Token::Symbol("["), _) => {
+                    self.position += 1;
+                    let key = single(self.expression()?);
+                    self.expect_symbol("]")?;
+                    self.expect_symbol("=")?;
+                    (Some(key), single(self.expression()?))
+                }
+                (Token::Name(name), Token::Symbol("=")) => {
+                    let key = Literal::String(name.clone().into_bytes());
+                    self.position += 2;
+                    (Some(key.into()), single(self.expression()?))
+                }
+                _ => (None, self.expression()?),
+            };
+            fields.push(field);
+            if !self.eat_symbol(",") && !self.eat_symbol(";") {
+                break;
+            }
+        }
+        self.expect_symbol("}")?;
+        Ok(Table(fields))
+    }
+}
+
+// captures the upvalues of closures by reference where they need to be
+fn capture_kinds(block: &mut Block, dialect: Dialect, reassigned: &FxHashSet<RcLocal>) {
+    for statement in &mut block.0 {
+        statement.post_traverse_values(&mut |value| -> Option<()> {
+            if let itertools::Either::Right(RValue::Closure(closure)) = value {
+                capture_kinds(&mut closure.function.lock().body, dialect, reassigned);
+                for upvalue in &mut closure.upvalues {
+                    if let Upvalue::Copy(local) = upvalue
+                        && (dialect != Dialect::Luau || reassigned.contains(local))
+                    {
+                        *upvalue = Upvalue::Ref(local.clone());
+                    }
+                }
+            }
+            None
+        });
+        match statement {
+            Statement::If(r#if) => {
+                capture_kinds(&mut r#if.then_block.lock(), dialect, reassigned);
+                capture_kinds(&mut r#if.else_block.lock(), dialect, reassigned);
+            }
+            Statement::While(r#while) => {
+                capture_kinds(&mut r#while.block.lock(), dialect, reassigned)
+            }
+            Statement::Repeat(repeat) => {
+                capture_kinds(&mut repeat.block.lock(), dialect, reassigned)
+            }
+            Statement::NumericFor(numeric_for) => {
+                capture_kinds(&mut numeric_for.block.lock(), dialect, reassigned)
+            }
+            Statement::GenericFor(generic_for) => {
+                capture_kinds(&mut generic_for.block.lock(), dialect, reassigned)
+            }
+            _ => {}
+        }
+    }
+}
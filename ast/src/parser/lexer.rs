@@ -0,0 +1,408 @@
+use std::fmt;
+
+use super::{Error, ErrorKind};
+use crate::formatter::Dialect;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Name(String),
+    Keyword(&'static str),
+    Symbol(&'static str),
+    /// `integer` is set for numerals without a fraction or exponent that are integers in
+    /// Lua 5.3 and 5.4.
+    Number {
+        value: f64,
+        integer: Option<i64>,
+    },
+    String(Vec<u8>),
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "`{}`", name),
+            Self::Keyword(keyword) => write!(f, "`{}`", keyword),
+            Self::Symbol(symbol) => write!(f, "`{}`", symbol),
+            Self::Number { .. } => write!(f, "number"),
+            Self::String(_) => write!(f, "string"),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// longest first, so that ex. `..` isn't read as two `.`
+const SYMBOLS: &[&str] = &[
+    "...", "..=", "//=", "==", "~=", "<=", ">=", "<<", ">>", "//", "::", "..", "+=", "-=", "*=",
+    "/=", "%=", "^=", "+", "-", "*", "/", "%", "^", "#", "&", "~", "|", "<", ">", "=", "(", ")",
+    "{", "}", "[", "]", ";", ":", ",", ".",
+];
+
+pub(super) struct Lexer<'a> {
+    source: &'a [u8],
+    position: usize,
+    line: usize,
+    dialect: Dialect,
+}
+
+impl<'a> Lexer<'a> {
+    pub(super) fn new(source: &'a str, dialect: Dialect) -> Self {
+        Self {
+            source: source.as_bytes(),
+            position: 0,
+            line: 1,
+            dialect,
+        }
+    }
+
+    /// Every token in the source and the line it starts on, ending with [`Token::Eof`].
+    pub(super) fn tokens(mut self) -> Result<Vec<(Token, usize)>, Error> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments()?;
+            let line = self.line;
+            let token = self.token()?;
+            let eof = token == Token::Eof;
+            tokens.push((token, line));
+            if eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error {
+            line: self.line,
+            kind,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.source.get(self.position + offset).copied()
+    }
+
+    // skips a `\n`, `\r`, `\r\n` or `\n\r` line break
+    fn skip_newline(&mut self) {
+        let first = self.source[self.position];
+        self.position += 1;
+        if let Some(second @ (b'\n' | b'\r')) = self.peek()
+            && second != first
+        {
+            self.position += 1;
+        }
+        self.line += 1;
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), Error> {
+        while let Some(c) = self.peek() {
+            match c {
+                b'\n' | b'\r' => self.skip_newline(),
+                b' ' | b'\t' | 11 | 12 => self.position += 1,
+                b'-' if self.peek_at(1) == Some(b'-') => {
+                    self.position += 2;
+                    if let Some(level) = self.long_bracket_level() {
+                        self.long_string(level)?;
+                    } else {
+                        while self.peek().is_some_and(|c| c != b'\n' && c != b'\r') {
+                            self.position += 1;
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn token(&mut self) -> Result<Token, Error> {
+        let Some(c) = self.peek() else {
+            return Ok(Token::Eof);
+        };
+        match c {
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                let start = self.position;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+                {
+                    self.position += 1;
+                }
+                let name = std::str::from_utf8(&self.source[start..self.position]).unwrap();
+                Ok(match KEYWORDS.iter().find(|&&keyword| keyword == name) {
+                    // `goto` is a name before Lua 5.2
+                    Some(&"goto") if !self.dialect.supports_goto() => Token::Name(name.into()),
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Name(name.into()),
+                })
+            }
+            b'0'..=b'9' => self.number(),
+            b'.' if self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) => self.number(),
+            b'"' | b'\'' => self.string(c),
+            b'[' if self.long_bracket_level().is_some() => {
+                let level = self.long_bracket_level().unwrap();
+                self.long_string(level).map(Token::String)
+            }
+            b'`' => Err(self.error(ErrorKind::Unsupported("string interpolation"))),
+            _ => {
+                let rest = &self.source[self.position..];
+                match SYMBOLS
+                    .iter()
+                    .find(|symbol| rest.starts_with(symbol.as_bytes()))
+                {
+                    Some(symbol) => {
+                        self.position += symbol.len();
+                        Ok(Token::Symbol(symbol))
+                    }
+                    None => {
+                        let c = std::str::from_utf8(rest)
+                            .ok()
+                            .and_then(|rest| rest.chars().next())
+                            .unwrap_or(char::REPLACEMENT_CHARACTER);
+                        Err(self.error(ErrorKind::UnexpectedCharacter(c)))
+                    }
+                }
+            }
+        }
+    }
+
+    // the number of `=` in a long bracket that starts here, ex. 1 for `[=[`
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.peek() != Some(b'[') {
+            return None;
+        }
+        let level = self.source[self.position + 1..]
+            .iter()
+            .take_while(|&&c| c == b'=')
+            .count();
+        (self.peek_at(level + 1) == Some(b'[')).then_some(level)
+    }
+
+    fn long_string(&mut self, level: usize) -> Result<Vec<u8>, Error> {
+        self.position += level + 2;
+        // a line break right after the opening bracket isn't part of the string
+        if matches!(self.peek(), Some(b'\n' | b'\r')) {
+            self.skip_newline();
+        }
+        let mut string = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ErrorKind::UnfinishedLongString)),
+                Some(b']')
+                    if self.source[self.position + 1..]
+                        .iter()
+                        .take_while(|&&c| c == b'=')
+                        .count()
+                        == level
+                        && self.peek_at(level + 1) == Some(b']') =>
+                {
+                    self.position += level + 2;
+                    return Ok(string);
+                }
+                Some(b'\n' | b'\r') => {
+                    self.skip_newline();
+                    string.push(b'\n');
+                }
+                Some(c) => {
+                    self.position += 1;
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    fn string(&mut self, quote: u8) -> Result<Token, Error> {
+        self.position += 1;
+        let mut string = Vec::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error(ErrorKind::UnfinishedString));
+            };
+            match c {
+                b'\n' | b'\r' => return Err(self.error(ErrorKind::UnfinishedString)),
+                b'\\' => {
+                    self.position += 1;
+                    self.escape(&mut string)?;
+                }
+                _ => {
+                    self.position += 1;
+                    if c == quote {
+                        return Ok(Token::String(string));
+                    }
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    // reads the escape sequence after a `\`
+    fn escape(&mut self, string: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(c) = self.peek() else {
+            return Err(self.error(ErrorKind::UnfinishedString));
+        };
+        let simple = match c {
+            b'a' => Some(7),
+            b'b' => Some(8),
+            b'f' => Some(12),
+            b'n' => Some(b'\n'),
+            b'r' => Some(b'\r'),
+            b't' => Some(b'\t'),
+            b'v' => Some(11),
+            b'\\' | b'"' | b'\'' => Some(c),
+            _ => None,
+        };
+        if let Some(byte) = simple {
+            self.position += 1;
+            string.push(byte);
+            return Ok(());
+        }
+        match c {
+            b'\n' | b'\r' => {
+                self.skip_newline();
+                string.push(b'\n');
+            }
+            b'z' => {
+                self.position += 1;
+                while let Some(c) = self.peek() {
+                    match c {
+                        b'\n' | b'\r' => self.skip_newline(),
+                        b' ' | b'\t' | 11 | 12 => self.position += 1,
+                        _ => break,
+                    }
+                }
+            }
+            b'x' => {
+                let digits = self.source.get(self.position + 1..self.position + 3);
+                let byte = digits
+                    .and_then(|digits| std::str::from_utf8(digits).ok())
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .ok_or_else(|| self.invalid_escape(3))?;
+                self.position += 3;
+                string.push(byte);
+            }
+            b'0'..=b'9' => {
+                let length = self.source[self.position..]
+                    .iter()
+                    .take(3)
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let digits = &self.source[self.position..self.position + length];
+                let byte = std::str::from_utf8(digits)
+                    .unwrap()
+                    .parse::<u8>()
+                    .map_err(|_| self.invalid_escape(length))?;
+                self.position += length;
+                string.push(byte);
+            }
+            b'u' => {
+                let end = self.source[self.position..]
+                    .iter()
+                    .position(|&c| c == b'}')
+                    .map(|end| self.position + end);
+                let code_point = end
+                    .filter(|_| self.peek_at(1) == Some(b'{'))
+                    .and_then(|end| std::str::from_utf8(&self.source[self.position + 2..end]).ok())
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.invalid_escape(1))?;
+                self.position = end.unwrap() + 1;
+                string.extend(code_point.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            _ => return Err(self.invalid_escape(1)),
+        }
+        Ok(())
+    }
+
+    fn invalid_escape(&self, length: usize) -> Error {
+        let end = (self.position + length).min(self.source.len());
+        let sequence = String::from_utf8_lossy(&self.source[self.position..end]).into_owned();
+        self.error(ErrorKind::InvalidEscape(sequence))
+    }
+
+    fn number(&mut self) -> Result<Token, Error> {
+        let start = self.position;
+        let hex = self.peek() == Some(b'0') && matches!(self.peek_at(1), Some(b'x' | b'X'));
+        let exponent = if hex { b"pP" } else { b"eE" };
+        while let Some(c) = self.peek() {
+            if exponent.contains(&c) && matches!(self.peek_at(1), Some(b'+' | b'-')) {
+                self.position += 2;
+            } else if c.is_ascii_alphanumeric() || c == b'_' || c == b'.' {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        let numeral = std::str::from_utf8(&self.source[start..self.position]).unwrap();
+        parse_number(numeral).ok_or_else(|| self.error(ErrorKind::MalformedNumber(numeral.into())))
+    }
+}
+
+fn parse_number(numeral: &str) -> Option<Token> {
+    // Luau allows `_` between digits
+    let digits = numeral.replace('_', "");
+    let (value, integer) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        parse_hex(hex)?
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        let value = u64::from_str_radix(binary, 2).ok()?;
+        (value as f64, Some(value as i64))
+    } else {
+        if !digits
+            .bytes()
+            .all(|c| c.is_ascii_digit() || matches!(c, b'.' | b'e' | b'E' | b'+' | b'-'))
+        {
+            return None;
+        }
+        let value = digits.parse::<f64>().ok()?;
+        // decimal integers that don't fit are floats
+        let integer = digits
+            .bytes()
+            .all(|c| c.is_ascii_digit())
+            .then(|| digits.parse::<i64>().ok())
+            .flatten();
+        (value, integer)
+    };
+    Some(Token::Number { value, integer })
+}
+
+// a hexadecimal numeral after its `0x`. Integers wrap around, like in Lua 5.3 and 5.4.
+fn parse_hex(hex: &str) -> Option<(f64, Option<i64>)> {
+    let (mantissa, exponent) = match hex.find(['p', 'P']) {
+        Some(index) => (&hex[..index], Some(hex[index + 1..].parse::<i32>().ok()?)),
+        None => (hex, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let mut value = 0.0;
+    let mut integer = 0i64;
+    for c in whole.chars() {
+        let digit = c.to_digit(16)?;
+        value = value * 16.0 + digit as f64;
+        integer = integer.wrapping_mul(16).wrapping_add(digit as i64);
+    }
+    let mut scale = 1.0 / 16.0;
+    for c in fraction.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+    if let Some(exponent) = exponent {
+        value *= 2f64.powi(exponent);
+    }
+    let is_integer = exponent.is_none() && !mantissa.contains('.');
+    Some((value, is_integer.then_some(integer)))
+}
@@ -1,13 +1,15 @@
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use triomphe::Arc;
 
 use crate::{formatter::Formatter, has_side_effects, Block, LocalRw, RValue, RcLocal, Traverse};
 use std::fmt;
 
 // TODO: move condition after block
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repeat {
     pub condition: RValue,
+    #[serde(with = "crate::serialize::shared")]
     pub block: Arc<Mutex<Block>>,
 }
 
@@ -51,11 +53,6 @@ impl LocalRw for Repeat {
 
 impl fmt::Display for Repeat {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_repeat(self)
+        Formatter::new(f, Default::default(), Default::default()).format_repeat(self)
     }
 }
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use itertools::Either;
 
-use crate::{Block, LocalRw, RValue, RcLocal, Statement, Traverse};
+use crate::{formatter::Dialect, Block, Label, LocalRw, RValue, RcLocal, Statement, Traverse};
 
 pub fn replace_locals<H: std::hash::BuildHasher>(
     block: &mut Block,
@@ -48,30 +48,92 @@ pub fn replace_locals<H: std::hash::BuildHasher>(
     }
 }
 
-/// Panics if any goto or label is found in the AST block (for enforcing structured output).
-pub fn fail_on_goto(block: &crate::Block) {
-    for stmt in &block.0 {
-        match stmt {
-            crate::Statement::Goto(_) | crate::Statement::Label(_) => {
-                panic!("Unstructured control flow (goto/label) found in output! Please improve structuring.");
-            }
-            crate::Statement::If(r#if) => {
-                fail_on_goto(&r#if.then_block.lock());
-                fail_on_goto(&r#if.else_block.lock());
-            }
-            crate::Statement::While(r#while) => {
-                fail_on_goto(&r#while.block.lock());
-            }
-            crate::Statement::Repeat(repeat) => {
-                fail_on_goto(&repeat.block.lock());
-            }
-            crate::Statement::NumericFor(numeric_for) => {
-                fail_on_goto(&numeric_for.block.lock());
-            }
-            crate::Statement::GenericFor(generic_for) => {
-                fail_on_goto(&generic_for.block.lock());
-            }
-            _ => {}
+/// Fails if the block contains control flow that can't be expressed in `dialect`.
+/// Dialects without `goto` reject any goto or label, goto-capable dialects require every
+/// goto to jump to a unique label that is visible from it.
+pub fn fail_on_goto(block: &Block, dialect: Dialect) -> Result<(), String> {
+    if dialect.supports_goto() {
+        check_goto_targets(block, &mut Vec::new())
+    } else {
+        reject_gotos(block)
+    }
+}
+
+fn for_each_child_block(
+    statement: &Statement,
+    mut callback: impl FnMut(&Block) -> Result<(), String>,
+) -> Result<(), String> {
+    match statement {
+        Statement::If(r#if) => {
+            callback(&r#if.then_block.lock())?;
+            callback(&r#if.else_block.lock())
+        }
+        Statement::While(r#while) => callback(&r#while.block.lock()),
+        Statement::Repeat(repeat) => callback(&repeat.block.lock()),
+        Statement::NumericFor(numeric_for) => callback(&numeric_for.block.lock()),
+        Statement::GenericFor(generic_for) => callback(&generic_for.block.lock()),
+        _ => Ok(()),
+    }
+}
+
+fn for_each_closure_body(
+    statement: &Statement,
+    callback: &mut impl FnMut(&Block) -> Result<(), String>,
+) -> Result<(), String> {
+    fn visit(
+        rvalue: &RValue,
+        callback: &mut impl FnMut(&Block) -> Result<(), String>,
+    ) -> Result<(), String> {
+        if let RValue::Closure(closure) = rvalue {
+            callback(&closure.function.lock().body)?;
+        }
+        for rvalue in rvalue.rvalues() {
+            visit(rvalue, callback)?;
+        }
+        Ok(())
+    }
+
+    for rvalue in statement.rvalues() {
+        visit(rvalue, callback)?;
+    }
+    Ok(())
+}
+
+fn reject_gotos(block: &Block) -> Result<(), String> {
+    for statement in &block.0 {
+        if matches!(statement, Statement::Goto(_) | Statement::Label(_)) {
+            return Err("Unstructured control flow (goto/label) found in output! Please improve structuring.".into());
+        }
+        for_each_child_block(statement, reject_gotos)?;
+        for_each_closure_body(statement, &mut reject_gotos)?;
+    }
+    Ok(())
+}
+
+// labels are visible in the block they are defined in and all nested blocks,
+// but not in nested functions.
+fn check_goto_targets(block: &Block, visible: &mut Vec<Label>) -> Result<(), String> {
+    let visible_len = visible.len();
+    for label in block.iter().filter_map(|s| s.as_label()) {
+        if visible.contains(label) {
+            return Err(format!("label {} is defined more than once", label));
+        }
+        visible.push(label.clone());
+    }
+    for statement in &block.0 {
+        if let Statement::Goto(goto) = statement
+            && !visible.contains(&goto.0)
+        {
+            return Err(format!(
+                "goto {} jumps to a label that is not visible",
+                goto.0 .0
+            ));
         }
+        for_each_child_block(statement, |block| check_goto_targets(block, visible))?;
+        for_each_closure_body(statement, &mut |body| {
+            check_goto_targets(body, &mut Vec::new())
+        })?;
     }
+    visible.truncate(visible_len);
+    Ok(())
 }
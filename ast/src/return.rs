@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::{formatter::Formatter, has_side_effects, LocalRw, RcLocal, Traverse};
 
 use super::RValue;
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct Return {
     pub values: Vec<RValue>,
 }
@@ -42,11 +43,6 @@ impl LocalRw for Return {
 
 impl fmt::Display for Return {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_return(self)
+        Formatter::new(f, Default::default(), Default::default()).format_return(self)
     }
 }
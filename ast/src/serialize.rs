@@ -0,0 +1,240 @@
+//! Serde support for the ast, so decompiled code can be read by tools that aren't written
+//! in Rust. Enums are externally tagged, ex. `{"Global": "print"}`.
+//!
+//! Locals are written as `{"id", "name", "attribute"}` wherever they appear, and closures
+//! with the `id` of their function, numbered in the order they are first written. When
+//! deserialized, locals with the same id are the same local and closures with the same id
+//! share one function. Ids are only unique within one call to [`serialize`], and
+//! [`deserialize`] keeps the ids of different documents apart.
+//!
+//! Strings that are valid UTF-8 are written as JSON strings, others as arrays of bytes, and
+//! numbers that aren't finite as `"nan"`, `"inf"` and `"-inf"`.
+
+use std::cell::RefCell;
+
+use by_address::ByAddress;
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use triomphe::Arc;
+
+use crate::{Attribute, Block, Closure, Function, Local, RcLocal, Upvalue};
+
+#[derive(Default)]
+struct Context {
+    // the ids given to locals and functions while serializing, by address
+    local_ids: FxHashMap<usize, usize>,
+    function_ids: FxHashMap<usize, usize>,
+    // what ids stand for while deserializing
+    locals: FxHashMap<usize, RcLocal>,
+    functions: FxHashMap<usize, Arc<Mutex<Function>>>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::new(Context::default());
+}
+
+fn id(ids: &mut FxHashMap<usize, usize>, address: usize) -> usize {
+    let next = ids.len();
+    *ids.entry(address).or_insert(next)
+}
+
+/// Serializes `value` with ids numbered from 0.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + ?Sized,
+    S: Serializer,
+{
+    CONTEXT.take();
+    let result = value.serialize(serializer);
+    CONTEXT.take();
+    result
+}
+
+/// Deserializes `value`, linking up only the locals and functions within it.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    CONTEXT.take();
+    let result = T::deserialize(deserializer);
+    CONTEXT.take();
+    result
+}
+
+pub fn to_json(block: &Block) -> serde_json::Result<String> {
+    let mut output = Vec::new();
+    serialize(block, &mut serde_json::Serializer::pretty(&mut output))?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+pub fn from_json(input: &str) -> serde_json::Result<Block> {
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let block = deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(block)
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedLocal {
+    id: usize,
+    name: Option<String>,
+    attribute: Option<Attribute>,
+}
+
+impl Serialize for RcLocal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let address = Arc::as_ptr(&self.0) as usize;
+        let id = CONTEXT.with_borrow_mut(|context| id(&mut context.local_ids, address));
+        let Local(name, attribute) = self.0.lock().clone();
+        SerializedLocal {
+            id,
+            name,
+            attribute,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RcLocal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let local = SerializedLocal::deserialize(deserializer)?;
+        Ok(CONTEXT.with_borrow_mut(|context| {
+            context
+                .locals
+                .entry(local.id)
+                .or_insert_with(|| RcLocal::new(Local(local.name, local.attribute)))
+                .clone()
+        }))
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedClosure<'a> {
+    id: usize,
+    function: &'a Function,
+    upvalues: &'a [Upvalue],
+}
+
+#[derive(Deserialize)]
+struct DeserializedClosure {
+    id: usize,
+    function: Function,
+    upvalues: Vec<Upvalue>,
+}
+
+impl Serialize for Closure {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let address = Arc::as_ptr(&self.function) as usize;
+        let id = CONTEXT.with_borrow_mut(|context| id(&mut context.function_ids, address));
+        let function = self.function.lock();
+        SerializedClosure {
+            id,
+            function: &function,
+            upvalues: &self.upvalues,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Closure {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let closure = DeserializedClosure::deserialize(deserializer)?;
+        // closures sharing a function all have it written out, the first is kept
+        let function = CONTEXT.with_borrow_mut(|context| {
+            context
+                .functions
+                .entry(closure.id)
+                .or_insert_with(|| Arc::new(Mutex::new(closure.function)))
+                .clone()
+        });
+        Ok(Self {
+            function: ByAddress(function),
+            upvalues: closure.upvalues,
+        })
+    }
+}
+
+// blocks of statements, which are behind a lock
+pub(crate) mod shared {
+    use parking_lot::Mutex;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use triomphe::Arc;
+
+    pub fn serialize<T, S>(value: &Arc<Mutex<T>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        value.lock().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Arc<Mutex<T>>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(|value| Arc::new(Mutex::new(value)))
+    }
+}
+
+// strings and names, which may not be valid UTF-8
+pub(crate) mod bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bytes {
+        String(String),
+        Bytes(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(bytes) {
+            Ok(string) => serializer.serialize_str(string),
+            Err(_) => serializer.collect_seq(bytes),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Ok(match Bytes::deserialize(deserializer)? {
+            Bytes::String(string) => string.into_bytes(),
+            Bytes::Bytes(bytes) => bytes,
+        })
+    }
+}
+
+// numbers, which JSON has no infinities or NaN for
+pub(crate) mod number {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Finite(f64),
+        NonFinite(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_nan() {
+            serializer.serialize_str("nan")
+        } else if value.is_infinite() {
+            serializer.serialize_str(if *value > 0.0 { "inf" } else { "-inf" })
+        } else {
+            serializer.serialize_f64(*value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Number::deserialize(deserializer)? {
+            Number::Finite(value) => Ok(value),
+            Number::NonFinite(value) => match value.as_str() {
+                "nan" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::custom(format!("invalid number `{}`", value))),
+            },
+        }
+    }
+}
@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use crate::{formatter, LocalRw, RValue, RcLocal, SideEffects, Traverse};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SetList {
     pub object_local: RcLocal,
     pub index: usize,
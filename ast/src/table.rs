@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use crate::{
     formatter::Formatter, Literal, LocalRw, RValue, RcLocal, Reduce, SideEffects, Traverse,
 };
 
 use std::{fmt, iter};
 
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Table(pub Vec<(Option<RValue>, RValue)>);
 
 impl Reduce for Table {
@@ -117,11 +118,6 @@ impl SideEffects for Table {
 
 impl fmt::Display for Table {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_table(self, None)
+        Formatter::new(f, Default::default(), Default::default()).format_table(self, None)
     }
 }
@@ -1,14 +1,16 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::{Literal, LocalRw, RValue, RcLocal, Reduce, SideEffects, Traverse};
 
 use super::{Binary, BinaryOperation};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum UnaryOperation {
     Not,
     Negate,
     Length,
+    BitNot,
 }
 
 impl fmt::Display for UnaryOperation {
@@ -17,11 +19,12 @@ impl fmt::Display for UnaryOperation {
             Self::Not => write!(f, "not "),
             Self::Negate => write!(f, "-"),
             Self::Length => write!(f, "#"),
+            Self::BitNot => write!(f, "~"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Unary {
     pub value: Box<RValue>,
     pub operation: UnaryOperation,
@@ -32,7 +35,7 @@ impl SideEffects for Unary {
         // TODO: do this properly
         matches!(
             self.operation,
-            UnaryOperation::Negate | UnaryOperation::Length
+            UnaryOperation::Negate | UnaryOperation::Length | UnaryOperation::BitNot
         ) || self.value.has_side_effects()
     }
 }
@@ -93,9 +96,15 @@ impl Reduce for Unary {
             (RValue::Literal(Literal::Number(value)), UnaryOperation::Negate) => {
                 RValue::Literal(Literal::Number(-value))
             }
+            (RValue::Literal(Literal::Integer(value)), UnaryOperation::Negate) => {
+                RValue::Literal(Literal::Integer(value.wrapping_neg()))
+            }
+            (RValue::Literal(Literal::Integer(value)), UnaryOperation::BitNot) => {
+                RValue::Literal(Literal::Integer(!value))
+            }
             (RValue::Literal(Literal::String(value)), UnaryOperation::Length) => {
                 // TODO: is this accurate w/ unicode in Luau?
-                RValue::Literal(Literal::Number(value.len() as f64))
+                RValue::Literal(Literal::Integer(value.len() as i64))
             }
             (
                 RValue::Binary(Binary {
@@ -243,6 +252,9 @@ impl Reduce for Unary {
             (RValue::Literal(Literal::Number(value)), UnaryOperation::Negate) => {
                 RValue::Literal(Literal::Number(-value))
             }
+            (RValue::Literal(Literal::Integer(value)), UnaryOperation::Negate) => {
+                RValue::Literal(Literal::Integer(value.wrapping_neg()))
+            }
             // __len has to return number, numbers are always truthy
             (_, UnaryOperation::Length) => RValue::Literal(Literal::Boolean(true)),
             (
@@ -381,7 +393,7 @@ impl Unary {
     }
 
     pub fn precedence(&self) -> usize {
-        7
+        11
     }
 
     pub fn group(&self) -> bool {
@@ -395,8 +407,10 @@ impl Unary {
                     })
                 ) || matches!(
                     *self.value,
-                    RValue::Literal(Literal::Number(value))
-                        if value.is_finite() && value.is_sign_negative()
+                    RValue::Literal(Literal::Number(value)) if value.is_sign_negative()
+                ) || matches!(
+                    *self.value,
+                    RValue::Literal(Literal::Integer(value)) if value < 0 && value != i64::MIN
                 )))
     }
 }
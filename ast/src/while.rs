@@ -1,12 +1,14 @@
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use triomphe::Arc;
 
 use crate::{formatter::Formatter, has_side_effects, Block, LocalRw, RValue, RcLocal, Traverse};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct While {
     pub condition: RValue,
+    #[serde(with = "crate::serialize::shared")]
     pub block: Arc<Mutex<Block>>,
 }
 
@@ -50,11 +52,6 @@ impl LocalRw for While {
 
 impl fmt::Display for While {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Formatter {
-            indentation_level: 0,
-            indentation_mode: Default::default(),
-            output: f,
-        }
-        .format_while(self)
+        Formatter::new(f, Default::default(), Default::default()).format_while(self)
     }
 }
@@ -0,0 +1,153 @@
+//! Lays out statements with line widths, table layouts, quote styles, trailing commas and
+//! semicolons.
+
+use ast::{
+    formatter::{Dialect, FormatOptions, Formatter, QuoteStyle, Semicolons, TableLayout},
+    Assign, Binary, BinaryOperation, Block, Call, Global, Literal, RValue, Return, Statement,
+    Table,
+};
+
+fn format(statements: Vec<Statement>, options: FormatOptions) -> String {
+    let mut output = String::new();
+    Formatter::format(&Block(statements), &mut output, options, Dialect::Luau).unwrap();
+    output
+}
+
+fn global(name: &str) -> RValue {
+    Global::new(name.into()).into()
+}
+
+fn string(value: &str) -> RValue {
+    Literal::String(value.into()).into()
+}
+
+fn call(name: &str, arguments: Vec<RValue>) -> Call {
+    Call::new(global(name), arguments)
+}
+
+fn width(line_width: usize) -> FormatOptions {
+    FormatOptions {
+        line_width: Some(line_width),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn wrap_arguments() {
+    let statement = call(
+        "print",
+        vec![string("first argument"), string("second argument")],
+    );
+    assert_eq!(
+        format(vec![statement.clone().into()], width(40)),
+        "print(\n\t\"first argument\",\n\t\"second argument\"\n)"
+    );
+    assert_eq!(
+        format(vec![statement.into()], width(80)),
+        "print(\"first argument\", \"second argument\")"
+    );
+}
+
+#[test]
+fn wrap_binary_chain() {
+    let sum = ["alpha", "beta", "gamma", "delta"]
+        .into_iter()
+        .map(global)
+        .reduce(|left, right| Binary::new(left, right, BinaryOperation::Add).into())
+        .unwrap();
+    assert_eq!(
+        format(vec![Return::new(vec![sum]).into()], width(20)),
+        "return alpha\n\t+ beta\n\t+ gamma\n\t+ delta"
+    );
+}
+
+#[test]
+fn wrap_table() {
+    let table = Table(vec![(None, string("first")), (None, string("second"))]);
+    let assign = Assign::new(vec![Global::new("t".into()).into()], vec![table.into()]);
+    assert_eq!(
+        format(vec![assign.clone().into()], width(20)),
+        "t = {\n\t\"first\",\n\t\"second\"\n}"
+    );
+    assert_eq!(
+        format(
+            vec![assign.into()],
+            FormatOptions {
+                trailing_commas: true,
+                ..width(20)
+            }
+        ),
+        "t = {\n\t\"first\",\n\t\"second\",\n}"
+    );
+}
+
+#[test]
+fn table_threshold() {
+    let table = |length: i64| -> Statement {
+        let fields = (1..=length)
+            .map(|i| (None, Literal::Integer(i).into()))
+            .collect();
+        Return::new(vec![Table(fields).into()]).into()
+    };
+    let options = FormatOptions {
+        table_layout: TableLayout::Threshold(5),
+        ..Default::default()
+    };
+    assert_eq!(
+        format(vec![table(5)], options.clone()),
+        "return { 1, 2, 3, 4, 5 }"
+    );
+    assert_eq!(
+        format(vec![table(6)], options),
+        "return {\n\t1,\n\t2,\n\t3,\n\t4,\n\t5,\n\t6\n}"
+    );
+}
+
+#[test]
+fn quote_styles() {
+    let quote = |value: &str, quote_style| {
+        let options = FormatOptions {
+            quote_style,
+            ..Default::default()
+        };
+        format(vec![Return::new(vec![string(value)]).into()], options)
+    };
+    assert_eq!(quote("it's", QuoteStyle::Double), r#"return "it's""#);
+    assert_eq!(quote("it's", QuoteStyle::Single), r"return 'it\'s'");
+    assert_eq!(quote("a", QuoteStyle::Single), "return 'a'");
+    assert_eq!(
+        quote(r#"say "hi""#, QuoteStyle::AutoPreferDouble),
+        r#"return 'say "hi"'"#
+    );
+    assert_eq!(
+        quote("it's", QuoteStyle::AutoPreferSingle),
+        r#"return "it's""#
+    );
+    assert_eq!(
+        quote(r#"it's "hi""#, QuoteStyle::AutoPreferSingle),
+        r#"return 'it\'s "hi"'"#
+    );
+}
+
+#[test]
+fn semicolons() {
+    let statements = || -> Vec<Statement> {
+        vec![
+            call("print", vec![string("a")]).into(),
+            call("print", vec![string("b")]).into(),
+            Return::new(Vec::new()).into(),
+        ]
+    };
+    assert_eq!(
+        format(statements(), Default::default()),
+        "print(\"a\")\nprint(\"b\")\nreturn"
+    );
+    let options = FormatOptions {
+        semicolons: Semicolons::Always,
+        ..Default::default()
+    };
+    assert_eq!(
+        format(statements(), options),
+        "print(\"a\");\nprint(\"b\");\nreturn;"
+    );
+}
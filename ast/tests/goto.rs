@@ -0,0 +1,111 @@
+//! Checks that gotos only reach the output of dialects that have them, and that a goto
+//! never jumps into the scope of a local.
+
+use ast::{
+    formatter::{Dialect, Formatter},
+    parser::parse,
+    replace_locals::fail_on_goto,
+    Block,
+};
+
+const CONTINUE: &str = "while x do\n\
+                        \tif y then\n\
+                        \t\tgoto continue\n\
+                        \tend\n\
+                        \tf()\n\
+                        \t::continue::\n\
+                        end";
+
+fn format(block: &Block, dialect: Dialect) -> String {
+    let mut output = String::new();
+    Formatter::format(block, &mut output, Default::default(), dialect).unwrap();
+    output
+}
+
+#[test]
+fn accepted() {
+    for dialect in [Dialect::Lua52, Dialect::Lua54] {
+        let block = parse(CONTINUE, dialect).unwrap();
+        assert_eq!(fail_on_goto(&block, dialect), Ok(()));
+        assert_eq!(format(&block, dialect), CONTINUE);
+    }
+}
+
+#[test]
+fn rejected_without_goto() {
+    let block = parse(CONTINUE, Dialect::Lua52).unwrap();
+    for dialect in [Dialect::Lua51, Dialect::Luau] {
+        let error = fail_on_goto(&block, dialect).unwrap_err();
+        assert!(error.starts_with("Unstructured control flow"), "{}", error);
+    }
+}
+
+#[test]
+fn label_in_nested_block() {
+    let source = "goto inner\nif x then\n\t::inner::\nend";
+    assert_eq!(
+        fail_on_goto(&parse(source, Dialect::Lua52).unwrap(), Dialect::Lua52),
+        Err("goto inner jumps to a label that is not visible".into())
+    );
+}
+
+#[test]
+fn duplicate_label() {
+    let source = "::done::\nif x then\n\t::done::\nend";
+    assert_eq!(
+        fail_on_goto(&parse(source, Dialect::Lua52).unwrap(), Dialect::Lua52),
+        Err("label ::done:: is defined more than once".into())
+    );
+}
+
+#[test]
+fn label_in_closure() {
+    // a function can't jump to the labels of the function it is defined in
+    let source = "::done::\nf(function()\n\tgoto done\nend)";
+    assert_eq!(
+        fail_on_goto(&parse(source, Dialect::Lua52).unwrap(), Dialect::Lua52),
+        Err("goto done jumps to a label that is not visible".into())
+    );
+}
+
+#[test]
+fn jump_into_local_scope() {
+    // the local is declared before the goto, so the goto doesn't jump into its scope
+    let block = parse(
+        "if x then\n\
+         \tgoto skip\n\
+         end\n\
+         local a = f()\n\
+         print(a)\n\
+         ::skip::\n\
+         print(2)",
+        Dialect::Lua52,
+    )
+    .unwrap();
+    assert_eq!(fail_on_goto(&block, Dialect::Lua52), Ok(()));
+    assert_eq!(
+        format(&block, Dialect::Lua52),
+        "local a\n\
+         if x then\n\
+         \tgoto skip\n\
+         end\n\
+         a = f()\n\
+         print(a)\n\
+         ::skip::\n\
+         print(2)"
+    );
+}
+
+#[test]
+fn jump_to_end_of_block() {
+    // nothing follows the label, so it isn't in the scope of the local
+    let source = "if x then\n\
+                  \tgoto skip\n\
+                  end\n\
+                  local a = f()\n\
+                  print(a)\n\
+                  ::skip::";
+    let block = parse(source, Dialect::Lua54).unwrap();
+    assert_eq!(fail_on_goto(&block, Dialect::Lua54), Ok(()));
+    assert_eq!(format(&block, Dialect::Lua54), source);
+}
@@ -0,0 +1,219 @@
+//! Lowers constructs the output dialect has no syntax for.
+
+use ast::{
+    formatter::{Dialect, Formatter},
+    lower_dialect::lower_dialect,
+    parser::parse,
+    reset_local_ids, Block, Literal, RValue, Return,
+};
+
+fn format(mut block: Block, dialect: Dialect) -> String {
+    lower_dialect(&mut block, dialect);
+    let mut output = String::new();
+    Formatter::format(&block, &mut output, Default::default(), dialect).unwrap();
+    output
+}
+
+// locals are numbered from 0 so the ones lowering adds print the same every time
+fn lower(source: &str, from: Dialect, to: Dialect) -> String {
+    reset_local_ids();
+    format(parse(source, from).unwrap(), to)
+}
+
+#[test]
+fn bitwise_operators() {
+    let source = "return a & b, a | b, a ~ b, a << 1, a >> 1, ~a";
+    for dialect in [Dialect::Lua53, Dialect::Lua54] {
+        assert_eq!(lower(source, Dialect::Lua54, dialect), source);
+    }
+    for dialect in [Dialect::Lua52, Dialect::Luau] {
+        assert_eq!(
+            lower(source, Dialect::Lua54, dialect),
+            "return bit32.band(a, b), bit32.bor(a, b), bit32.bxor(a, b), bit32.lshift(a, 1), \
+             bit32.rshift(a, 1), bit32.bnot(a)"
+        );
+    }
+    assert_eq!(
+        lower(source, Dialect::Lua54, Dialect::Lua51),
+        "return bit.band(a, b), bit.bor(a, b), bit.bxor(a, b), bit.lshift(a, 1), \
+         bit.rshift(a, 1), bit.bnot(a)"
+    );
+}
+
+#[test]
+fn nested_bitwise_operators() {
+    assert_eq!(
+        lower(
+            "local x = ~(a & 0xff) | b << 8\nreturn function() return x ~ 1 end",
+            Dialect::Lua54,
+            Dialect::Lua52
+        ),
+        "local x = bit32.bor(bit32.bnot(bit32.band(a, 255)), bit32.lshift(b, 8))\n\
+         return function()\n\
+         \t-- upvalues: (ref) x\n\
+         \treturn bit32.bxor(x, 1)\n\
+         end"
+    );
+}
+
+#[test]
+fn continue_in_nested_loops() {
+    let source = "while x do\n\
+                  \twhile y do\n\
+                  \t\tif a then\n\
+                  \t\t\tcontinue\n\
+                  \t\tend\n\
+                  \t\tf()\n\
+                  \tend\n\
+                  \tif b then\n\
+                  \t\tcontinue\n\
+                  \tend\n\
+                  \tg()\n\
+                  end";
+    assert_eq!(lower(source, Dialect::Luau, Dialect::Luau), source);
+    // each `continue` breaks out of the wrapper of its own loop
+    assert_eq!(
+        lower(source, Dialect::Luau, Dialect::Lua51),
+        "while x do\n\
+         \trepeat\n\
+         \t\twhile y do\n\
+         \t\t\trepeat\n\
+         \t\t\t\tif a then\n\
+         \t\t\t\t\tbreak\n\
+         \t\t\t\tend\n\
+         \t\t\t\tf()\n\
+         \t\t\tuntil true\n\
+         \t\tend\n\
+         \t\tif b then\n\
+         \t\t\tbreak\n\
+         \t\tend\n\
+         \t\tg()\n\
+         \tuntil true\n\
+         end"
+    );
+}
+
+#[test]
+fn continue_in_lua54() {
+    assert_eq!(
+        lower(
+            "while x do\n\
+             \tif a then\n\
+             \t\tcontinue\n\
+             \tend\n\
+             \tf(a // b)\n\
+             end",
+            Dialect::Luau,
+            Dialect::Lua54
+        ),
+        "while x do\n\
+         \trepeat\n\
+         \t\tif a then\n\
+         \t\t\tbreak\n\
+         \t\tend\n\
+         \t\tf(a // b)\n\
+         \tuntil true\n\
+         end"
+    );
+}
+
+#[test]
+fn continue_next_to_break() {
+    // the flag is only named later, along with the other locals
+    assert_eq!(
+        lower(
+            "while x do\n\
+             \tif a then\n\
+             \t\tcontinue\n\
+             \tend\n\
+             \tif b then\n\
+             \t\tbreak\n\
+             \tend\n\
+             \tf()\n\
+             end",
+            Dialect::Luau,
+            Dialect::Lua52
+        ),
+        "while x do\n\
+         \tlocal UNNAMED_0 = false\n\
+         \trepeat\n\
+         \t\tif a then\n\
+         \t\t\tbreak\n\
+         \t\tend\n\
+         \t\tif b then\n\
+         \t\t\tUNNAMED_0 = true\n\
+         \t\t\tbreak\n\
+         \t\tend\n\
+         \t\tf()\n\
+         \tuntil true\n\
+         \tif UNNAMED_0 then\n\
+         \t\tbreak\n\
+         \tend\n\
+         end"
+    );
+}
+
+#[test]
+fn continue_before_until() {
+    // locals the condition reads are declared outside of the wrapper
+    assert_eq!(
+        lower(
+            "repeat\n\
+             \tlocal done = f()\n\
+             \tif a then\n\
+             \t\tcontinue\n\
+             \tend\n\
+             \tg()\n\
+             until done",
+            Dialect::Luau,
+            Dialect::Lua51
+        ),
+        "repeat\n\
+         \tlocal done\n\
+         \trepeat\n\
+         \t\tdone = f()\n\
+         \t\tif a then\n\
+         \t\t\tbreak\n\
+         \t\tend\n\
+         \t\tg()\n\
+         \tuntil true\n\
+         until done"
+    );
+}
+
+#[test]
+fn floor_division() {
+    let source = "return a // b";
+    assert_eq!(lower(source, Dialect::Luau, Dialect::Luau), source);
+    for dialect in [Dialect::Lua53, Dialect::Lua54] {
+        assert_eq!(lower(source, Dialect::Lua54, dialect), source);
+    }
+    for dialect in [Dialect::Lua51, Dialect::Lua52] {
+        assert_eq!(
+            lower(source, Dialect::Luau, dialect),
+            "return math.floor(a / b)"
+        );
+    }
+}
+
+#[test]
+fn vector() {
+    // lua 5.3 and 5.4 have integers, so their float components keep their `.0`
+    for (dialect, expected) in [
+        (Dialect::Lua51, "return Vector3.new(1, 2.5, -3)"),
+        (Dialect::Lua52, "return Vector3.new(1, 2.5, -3)"),
+        (Dialect::Lua53, "return Vector3.new(1.0, 2.5, -3.0)"),
+        (Dialect::Lua54, "return Vector3.new(1.0, 2.5, -3.0)"),
+    ] {
+        let mut block = Block(vec![Return::new(vec![
+            Literal::Vector(1.0, 2.5, -3.0).into()
+        ])
+        .into()]);
+        lower_dialect(&mut block, dialect);
+        assert!(matches!(
+            block[0].as_return().unwrap().values[0],
+            RValue::Call(_)
+        ));
+        assert_eq!(format(block, dialect), expected);
+    }
+}
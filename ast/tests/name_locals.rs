@@ -0,0 +1,64 @@
+//! Names the locals of parsed source, renaming the ones that would shadow another local.
+
+use ast::{
+    formatter::{Dialect, Formatter},
+    name_gen::DebugNames,
+    name_locals::name_locals,
+    parser::parse,
+};
+
+fn name(source: &str) -> String {
+    let mut block = parse(source, Dialect::Lua51).unwrap();
+    name_locals(&mut block, &DebugNames);
+    let mut output = String::new();
+    Formatter::format(&block, &mut output, Default::default(), Dialect::Lua51).unwrap();
+    output
+}
+
+#[test]
+fn shadowed_exact_names() {
+    let source = "local x = 1\n\
+                  if a then\n\
+                  \tlocal x = 2\n\
+                  \tif b then\n\
+                  \t\tlocal x = 3\n\
+                  \t\tprint(x)\n\
+                  \tend\n\
+                  \tprint(x)\n\
+                  end\n\
+                  print(x)";
+    assert_eq!(
+        name(source),
+        "local x = 1\n\
+         if a then\n\
+         \tlocal x_1 = 2\n\
+         \tif b then\n\
+         \t\tlocal x_2 = 3\n\
+         \t\tprint(x_2)\n\
+         \tend\n\
+         \tprint(x_1)\n\
+         end\n\
+         print(x)"
+    );
+}
+
+#[test]
+fn renamed_past_taken_names() {
+    let source = "local x = 1\n\
+                  local x_1 = 2\n\
+                  if a then\n\
+                  \tlocal x = 3\n\
+                  \tprint(x)\n\
+                  end\n\
+                  print(x, x_1)";
+    assert_eq!(
+        name(source),
+        "local x = 1\n\
+         local x_1 = 2\n\
+         if a then\n\
+         \tlocal x_2 = 3\n\
+         \tprint(x_2)\n\
+         end\n\
+         print(x, x_1)"
+    );
+}
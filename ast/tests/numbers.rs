@@ -0,0 +1,88 @@
+//! Formats number literals that have no plain decimal form, or are written with an exponent
+//! by default.
+
+use ast::{
+    formatter::{Dialect, Formatter},
+    Binary, BinaryOperation, Block, Global, Literal, RValue, Return, Unary, UnaryOperation,
+};
+
+fn format(value: RValue, dialect: Dialect) -> String {
+    let block = Block(vec![Return::new(vec![value]).into()]);
+    let mut output = String::new();
+    Formatter::format(&block, &mut output, Default::default(), dialect).unwrap();
+    output.strip_prefix("return ").unwrap().to_string()
+}
+
+fn number(value: f64) -> RValue {
+    Literal::Number(value).into()
+}
+
+#[test]
+fn non_finite() {
+    assert_eq!(format(number(f64::INFINITY), Dialect::Lua51), "math.huge");
+    assert_eq!(
+        format(number(f64::NEG_INFINITY), Dialect::Lua51),
+        "-math.huge"
+    );
+    assert_eq!(format(number(f64::NAN), Dialect::Lua51), "0/0");
+    assert_eq!(format(number(-0.0), Dialect::Lua51), "-0");
+    assert_eq!(format(number(-0.0), Dialect::Lua54), "-0.0");
+}
+
+#[test]
+fn non_finite_operands() {
+    let x = || RValue::Global(Global::from("x"));
+    let binary = |left, right, operation| Binary::new(left, right, operation).into();
+    assert_eq!(
+        format(
+            binary(x(), number(f64::NAN), BinaryOperation::Mul),
+            Dialect::Luau
+        ),
+        "x * (0/0)"
+    );
+    assert_eq!(
+        format(
+            binary(number(f64::NEG_INFINITY), x(), BinaryOperation::Pow),
+            Dialect::Luau
+        ),
+        "(-math.huge) ^ x"
+    );
+    assert_eq!(
+        format(
+            binary(x(), number(f64::INFINITY), BinaryOperation::Pow),
+            Dialect::Luau
+        ),
+        "x ^ math.huge"
+    );
+    assert_eq!(
+        format(
+            Unary::new(number(f64::NEG_INFINITY), UnaryOperation::Negate).into(),
+            Dialect::Luau
+        ),
+        "-(-math.huge)"
+    );
+}
+
+#[test]
+fn extreme() {
+    assert_eq!(format(number(1e15), Dialect::Lua51), "1000000000000000");
+    assert_eq!(
+        format(number(2f64.powi(60)), Dialect::Lua51),
+        "1152921504606846976"
+    );
+    assert_eq!(
+        format(number(2f64.powi(60)), Dialect::Lua54),
+        "1152921504606846976.0"
+    );
+    assert_eq!(format(number(1e300), Dialect::Lua51), "1e300");
+    assert_eq!(format(number(-2.5e-8), Dialect::Lua51), "-2.5e-8");
+    assert_eq!(format(number(0.1), Dialect::Lua54), "0.1");
+    assert_eq!(
+        format(Literal::Integer(i64::MIN).into(), Dialect::Lua54),
+        "math.mininteger"
+    );
+    assert_eq!(
+        format(Literal::Integer(i64::MAX).into(), Dialect::Lua54),
+        "9223372036854775807"
+    );
+}
@@ -0,0 +1,213 @@
+//! Parses source into the ast and formats it back.
+
+use std::{fs, path::Path};
+
+use ast::{
+    formatter::{Dialect, Formatter},
+    parser::{parse, ErrorKind},
+    Block, Upvalue,
+};
+
+fn format(block: &Block, dialect: Dialect) -> String {
+    let mut output = String::new();
+    Formatter::format(block, &mut output, Default::default(), dialect).unwrap();
+    output
+}
+
+fn round_trip(source: &str, dialect: Dialect) -> String {
+    format(&parse(source, dialect).unwrap(), dialect)
+}
+
+// the expected output of the lifters' golden tests formats the same after being parsed
+#[test]
+fn fixtures() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut failures = Vec::new();
+    for (directory, dialect) in [
+        ("luau-lifter", Dialect::Luau),
+        ("lua51-lifter", Dialect::Lua51),
+        ("luajit-lifter", Dialect::Lua52),
+        ("lua54-lifter", Dialect::Lua54),
+    ] {
+        let fixtures = root.join(directory).join("tests/fixtures");
+        let mut paths = fs::read_dir(&fixtures)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "lua"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty(), "no fixtures in {}", fixtures.display());
+        for path in paths {
+            let source = fs::read_to_string(&path).unwrap();
+            match parse(&source, dialect) {
+                Ok(block) if format(&block, dialect) == source => {}
+                Ok(block) => failures.push(format!(
+                    "{} differs\n--- expected\n{}\n--- actual\n{}",
+                    path.display(),
+                    source,
+                    format(&block, dialect)
+                )),
+                Err(error) => failures.push(format!("{}: {}", path.display(), error)),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn precedence() {
+    assert_eq!(
+        round_trip(
+            "return (1 + 2) * 3, 1 + 2 * 3, -2 ^ 2, not a == b",
+            Dialect::Luau
+        ),
+        "return (1 + 2) * 3, 1 + 2 * 3, -2 ^ 2, not a == b"
+    );
+    assert_eq!(
+        round_trip(
+            "return a .. (b .. c), (a .. b) .. c, 2 ^ 3 ^ 2",
+            Dialect::Luau
+        ),
+        "return a .. b .. c, (a .. b) .. c, 2 ^ 3 ^ 2"
+    );
+    for dialect in [Dialect::Lua53, Dialect::Lua54] {
+        assert_eq!(
+            round_trip("return 1 | 2 & 3 << 4, 7 // 2", dialect),
+            "return 1 | 2 & 3 << 4, 7 // 2"
+        );
+    }
+}
+
+#[test]
+fn statements() {
+    let source = "\
+local t = {}
+function t.a:b(x, ...)
+\t-- upvalues: (ref) _ENV
+\tlocal y <const> = ...
+\tfor i = 1, 10, 2 do
+\t\tif i > x then
+\t\t\tbreak
+\t\telseif i == y then
+\t\t\tgoto done
+\t\telse
+\t\t\tprint(i)
+\t\tend
+\tend
+\t::done::
+\treturn (select(2, ...))
+end";
+    assert_eq!(
+        round_trip(source, Dialect::Lua54),
+        source.replace("t.a:b(x", "t.a.b(self, x")
+    );
+}
+
+#[test]
+fn luau_syntax() {
+    assert_eq!(
+        round_trip(
+            "local n = 0b1010 + 1_000\nn += 1\nfor i = 1, n do\n\tcontinue\nend",
+            Dialect::Luau
+        ),
+        "local n = 10 + 1000\nn = n + 1\nfor i = 1, n do\n\tcontinue\nend"
+    );
+    let unsupported = |source| parse(source, Dialect::Luau).unwrap_err().kind;
+    assert_eq!(
+        unsupported("local x: number = 1"),
+        ErrorKind::Unsupported("type annotations")
+    );
+    assert_eq!(
+        unsupported("local x = if a then b else c"),
+        ErrorKind::Unsupported("if-expressions")
+    );
+}
+
+#[test]
+fn multiple_values() {
+    assert_eq!(
+        round_trip(
+            "local a, b = f()\nlocal c, d = (f())\nreturn g(...), (...)",
+            Dialect::Lua51
+        ),
+        "local a, b = f()\nlocal c, d = f(), nil\nreturn g(...), (...)"
+    );
+}
+
+#[test]
+fn upvalues() {
+    let block = parse(
+        "local a, b = 1, 2\nb = 3\nreturn function() return a + b end",
+        Dialect::Luau,
+    )
+    .unwrap();
+    let closure = block[2].as_return().unwrap().values[0]
+        .as_closure()
+        .unwrap()
+        .clone();
+    let declared = &block[0].as_assign().unwrap().left;
+    assert_eq!(
+        closure.upvalues,
+        vec![
+            Upvalue::Copy(declared[0].as_local().unwrap().clone()),
+            Upvalue::Ref(declared[1].as_local().unwrap().clone()),
+        ]
+    );
+}
+
+#[test]
+fn errors() {
+    let error = |source| parse(source, Dialect::Lua51).unwrap_err().to_string();
+    assert_eq!(
+        error("x = 1 +"),
+        "line 1: expected expression, found end of input"
+    );
+    assert_eq!(error("\nlocal s = \"abc"), "line 2: unfinished string");
+    assert_eq!(
+        error("function f() return ... end"),
+        "line 1: cannot use `...` outside a variadic function"
+    );
+    assert_eq!(error("f() = 1"), "line 1: cannot assign to this expression");
+    assert_eq!(
+        error("return 1 & 2"),
+        "line 1: unsupported syntax: bitwise operators"
+    );
+}
+
+#[test]
+fn attributes() {
+    let block = parse("local a <const>, b <close> = 1, f()", Dialect::Lua54).unwrap();
+    assert_eq!(
+        format(&block, Dialect::Lua54),
+        "local a <const>, b <close> = 1, f()"
+    );
+    for dialect in [
+        Dialect::Lua51,
+        Dialect::Lua52,
+        Dialect::Lua53,
+        Dialect::Luau,
+    ] {
+        assert_eq!(format(&block, dialect), "local a, b = 1, f()");
+    }
+    // attributes are new in lua 5.4
+    assert!(parse("local a <const> = 1", Dialect::Lua53).is_err());
+}
+
+#[test]
+fn dialect_names() {
+    for (name, dialect) in [
+        ("luau", Dialect::Luau),
+        ("lua51", Dialect::Lua51),
+        ("5.2", Dialect::Lua52),
+        ("lua53", Dialect::Lua53),
+        ("5.3", Dialect::Lua53),
+        ("LUA54", Dialect::Lua54),
+    ] {
+        assert_eq!(name.parse::<Dialect>(), Ok(dialect));
+    }
+    assert_eq!(
+        Dialect::Lua53.to_string().parse::<Dialect>(),
+        Ok(Dialect::Lua53)
+    );
+    assert!("lua55".parse::<Dialect>().is_err());
+}
@@ -0,0 +1,71 @@
+//! Writes the ast as JSON and reads it back.
+
+use by_address::ByAddress;
+use parking_lot::Mutex;
+use triomphe::Arc;
+
+use ast::{
+    serialize::{from_json, to_json},
+    Assign, Block, Closure, Function, Global, Literal, Local, RValue, RcLocal, Return,
+};
+
+#[test]
+fn locals_keep_identity() {
+    let local = RcLocal::new(Local::new(Some("x".to_string())));
+    let mut declaration = Assign::new(vec![local.clone().into()], vec![Literal::Integer(1).into()]);
+    declaration.prefix = true;
+    let block = Block(vec![
+        declaration.into(),
+        Return::new(vec![local.into()]).into(),
+    ]);
+
+    let json = to_json(&block).unwrap();
+    let read = from_json(&json).unwrap();
+    assert_eq!(read.to_string(), "local x = 1\nreturn x");
+    let declared = &read[0].as_assign().unwrap().left[0];
+    let returned = &read[1].as_return().unwrap().values[0];
+    assert_eq!(declared.as_local(), returned.as_local());
+    assert_eq!(to_json(&read).unwrap(), json);
+}
+
+#[test]
+fn closures_share_functions() {
+    let function = Arc::new(Mutex::new(Function::default()));
+    let closure = || -> RValue {
+        Closure {
+            function: ByAddress(function.clone()),
+            upvalues: Vec::new(),
+        }
+        .into()
+    };
+    let block = Block(vec![Return::new(vec![closure(), closure()]).into()]);
+
+    let read = from_json(&to_json(&block).unwrap()).unwrap();
+    let values = &read[0].as_return().unwrap().values;
+    assert_eq!(
+        values[0].as_closure().unwrap().function,
+        values[1].as_closure().unwrap().function
+    );
+}
+
+#[test]
+fn literals() {
+    let values = vec![
+        Literal::Number(f64::NAN).into(),
+        Literal::Number(f64::NEG_INFINITY).into(),
+        Literal::Number(-0.0).into(),
+        Literal::String(b"\xff\x00".to_vec()).into(),
+        Global::new("print".into()).into(),
+    ];
+    let block = Block(vec![Return::new(values).into()]);
+
+    let json = to_json(&block).unwrap();
+    assert!(json.contains(r#""Number": "nan""#));
+    assert!(json.contains(r#""Number": "-inf""#));
+    assert!(json.contains(r#""Global": "print""#));
+    let read = from_json(&json).unwrap();
+    assert_eq!(
+        read.to_string(),
+        r#"return 0/0, -math.huge, -0, "\255\0", print"#
+    );
+}
@@ -0,0 +1,140 @@
+//! Formats string literals with each escaping mode.
+
+use ast::{
+    formatter::{Dialect, FormatOptions, Formatter, StringEscaping},
+    Block, Literal, Return,
+};
+
+fn format(string: &[u8], options: FormatOptions, dialect: Dialect) -> String {
+    let block = Block(vec![Return::new(vec![
+        Literal::String(string.to_vec()).into()
+    ])
+    .into()]);
+    let mut output = String::new();
+    Formatter::format(&block, &mut output, options, dialect).unwrap();
+    output.strip_prefix("return ").unwrap().to_string()
+}
+
+fn utf8() -> FormatOptions {
+    FormatOptions {
+        string_escaping: StringEscaping::Utf8,
+        ..Default::default()
+    }
+}
+
+fn long_strings(string_escaping: StringEscaping) -> FormatOptions {
+    FormatOptions {
+        string_escaping,
+        long_strings: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn ascii() {
+    let string = "“héllo”\t\x01".as_bytes();
+    assert_eq!(
+        format(string, Default::default(), Dialect::Luau),
+        r#""\226\128\156h\195\169llo\226\128\157\t\1""#
+    );
+}
+
+#[test]
+fn utf8_kept() {
+    for dialect in [Dialect::Lua51, Dialect::Luau] {
+        assert_eq!(
+            format("你好, 👋 “wörld”\n".as_bytes(), utf8(), dialect),
+            r#""你好, 👋 “wörld”\n""#
+        );
+    }
+}
+
+#[test]
+fn utf8_invisible() {
+    let string = "a\u{200B}b\u{A0}1".as_bytes();
+    assert_eq!(
+        format(string, utf8(), Dialect::Luau),
+        r#""a\u{200B}b\u{A0}1""#
+    );
+    // without unicode escapes, the bytes are escaped
+    assert_eq!(
+        format(string, utf8(), Dialect::Lua51),
+        r#""a\226\128\139b\194\1601""#
+    );
+}
+
+#[test]
+fn utf8_invalid_bytes() {
+    assert_eq!(
+        format(b"caf\xC3\xA9 \xFF\xFE1", utf8(), Dialect::Luau),
+        r#""café \255\2541""#
+    );
+}
+
+#[test]
+fn long() {
+    assert_eq!(
+        format(
+            b"line 1\nline 2",
+            long_strings(StringEscaping::Ascii),
+            Dialect::Luau
+        ),
+        "[[line 1\nline 2]]"
+    );
+    assert_eq!(
+        format(
+            "\nx = t[a[i]]\n".as_bytes(),
+            long_strings(StringEscaping::Ascii),
+            Dialect::Luau
+        ),
+        "[=[\n\nx = t[a[i]]\n]=]"
+    );
+    assert_eq!(
+        format(
+            b"ends with ]\n]",
+            long_strings(StringEscaping::Ascii),
+            Dialect::Luau
+        ),
+        "[=[ends with ]\n]]=]"
+    );
+    assert_eq!(
+        format(
+            "日本\n語".as_bytes(),
+            long_strings(StringEscaping::Utf8),
+            Dialect::Luau
+        ),
+        "[[日本\n語]]"
+    );
+}
+
+#[test]
+fn long_nested_lua51() {
+    // Lua 5.1 rejects `[[` in a level 0 long string as deprecated nesting
+    let options = long_strings(StringEscaping::Ascii);
+    assert_eq!(
+        format(b"s = [[\nx", options.clone(), Dialect::Lua51),
+        "[=[s = [[\nx]=]"
+    );
+    assert_eq!(
+        format(b"s = [[\nx", options, Dialect::Lua54),
+        "[[s = [[\nx]]"
+    );
+}
+
+#[test]
+fn long_needs_escapes() {
+    // single line strings, and strings with characters that must be escaped, stay quoted
+    let options = long_strings(StringEscaping::Ascii);
+    assert_eq!(
+        format(b"one line", options.clone(), Dialect::Luau),
+        r#""one line""#
+    );
+    assert_eq!(
+        format(b"a\r\nb", options.clone(), Dialect::Luau),
+        r#""a\r\nb""#
+    );
+    assert_eq!(
+        format("é\n".as_bytes(), options, Dialect::Luau),
+        r#""\195\169\n""#
+    );
+}
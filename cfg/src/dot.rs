@@ -1,10 +1,5 @@
-use std::{
-    borrow::{Borrow, Cow},
-    cell::RefCell,
-    io::Write,
-};
+use std::{borrow::Cow, io::Write};
 
-use ast::LocalRw;
 use dot::{GraphWalk, LabelText, Labeller};
 
 use itertools::Itertools;
@@ -29,7 +24,6 @@ fn arguments(args: &Vec<(ast::RcLocal, ast::RValue)>) -> String {
 
 struct FunctionLabeller<'a> {
     function: &'a Function,
-    counter: RefCell<usize>,
 }
 
 impl<'a> Labeller<'a, NodeIndex, EdgeIndex> for FunctionLabeller<'a> {
@@ -44,26 +38,10 @@ impl<'a> Labeller<'a, NodeIndex, EdgeIndex> for FunctionLabeller<'a> {
         } else {
             ""
         };
-        dot::LabelText::LabelStr(
-            block
-                .iter()
-                .map(|s| {
-                    for local in s.values() {
-                        let name = &mut local.0 .0.lock().0;
-                        if name.is_none() {
-                            // TODO: ugly
-                            *name = Some(format!("v{}", self.counter.borrow()));
-                            *self.counter.borrow_mut() += 1;
-                        }
-                    }
-                    s
-                })
-                .join("\n")
-                .into(),
+        // unnamed locals are left as they are, rendering mustn't change the function
+        dot::LabelText::LabelStr(block.iter().join("\n").into()).prefix_line(
+            dot::LabelText::LabelStr(format!("{} {}", n.index(), prefix).into()),
         )
-        .prefix_line(dot::LabelText::LabelStr(
-            format!("{} {}", n.index(), prefix).into(),
-        ))
     }
 
     fn edge_label<'b>(&'b self, e: &EdgeIndex) -> dot::LabelText<'b> {
@@ -123,11 +101,5 @@ impl<'a> GraphWalk<'a, NodeIndex, EdgeIndex> for FunctionLabeller<'a> {
 }
 
 pub fn render_to<W: Write>(function: &Function, output: &mut W) -> std::io::Result<()> {
-    dot::render(
-        &FunctionLabeller {
-            function,
-            counter: RefCell::new(1),
-        },
-        output,
-    )
+    dot::render(&FunctionLabeller { function }, output)
 }
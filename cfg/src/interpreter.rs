@@ -0,0 +1,941 @@
+//! A small evaluator for `Function` graphs and the structured statements passes leave in
+//! them. Running a function before and after a pass and comparing the results catches
+//! passes that change semantics, e.g. bad inlining or SSA destruction.
+//!
+//! Only the core language is modelled: there are no metatables and the standard library
+//! is limited to what `builtins` installs. The values, tables and builtins are shared with
+//! anything else that runs programs, like a bytecode VM, so their results can be compared.
+
+pub mod builtins;
+mod table;
+mod value;
+
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use ast::{BinaryOperation, LValue, RValue, RcLocal, Statement, UnaryOperation, Upvalue};
+use by_address::ByAddress;
+use parking_lot::Mutex;
+use petgraph::{stable_graph::NodeIndex, visit::EdgeRef};
+use rustc_hash::FxHashMap;
+use thiserror::Error;
+use triomphe::Arc;
+
+use crate::function::Function;
+
+pub use table::Table;
+pub use value::{Cell, Host, LuaClosure, NativeFunction, PrototypeClosure, Value};
+
+const MAX_DEPTH: usize = 200;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("attempt to {operation} a {kind} value")]
+    Type {
+        operation: &'static str,
+        kind: &'static str,
+    },
+    #[error("{0}")]
+    Runtime(String),
+    #[error("unsupported: {0}")]
+    Unsupported(&'static str),
+    #[error("malformed bytecode: {0}")]
+    Malformed(&'static str),
+    #[error("step limit exceeded")]
+    StepLimit,
+    #[error("stack overflow")]
+    StackOverflow,
+}
+
+/// The error of an `operation` that isn't defined for the type of `value`.
+pub fn type_error(operation: &'static str, value: &Value) -> Error {
+    Error::Type {
+        operation,
+        kind: value.type_name(),
+    }
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Vec<Value>),
+}
+
+fn returned(flow: Flow) -> Result<Vec<Value>, Error> {
+    match flow {
+        Flow::Normal => Ok(Vec::new()),
+        Flow::Return(values) => Ok(values),
+        Flow::Break | Flow::Continue => Err(Error::Unsupported("break outside of a loop")),
+    }
+}
+
+enum Place {
+    Local(RcLocal),
+    Global(Vec<u8>),
+    Index(Value, Value),
+}
+
+#[derive(Default)]
+struct Frame {
+    cells: FxHashMap<RcLocal, Cell>,
+    varargs: Vec<Value>,
+    // the hidden control variable of generic for loops that are still running
+    generic_for_controls: FxHashMap<NodeIndex, Value>,
+    pending_control: Option<Value>,
+}
+
+type Registered<'a> = (&'a Function, &'a [RcLocal]);
+
+pub struct Interpreter<'a> {
+    functions: FxHashMap<ByAddress<Arc<Mutex<ast::Function>>>, Registered<'a>>,
+    groups: FxHashMap<RcLocal, RcLocal>,
+    pub globals: Rc<RefCell<Table>>,
+    /// Lines written by `print`.
+    pub output: Vec<String>,
+    steps: usize,
+    step_limit: usize,
+    depth: usize,
+}
+
+impl Default for Interpreter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Host for Interpreter<'_> {
+    fn print(&mut self, line: String) {
+        self.output.push(line);
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new() -> Self {
+        let mut globals = Table::default();
+        builtins::install(&mut globals).unwrap();
+        Self {
+            functions: FxHashMap::default(),
+            groups: FxHashMap::default(),
+            globals: Rc::new(globals.into()),
+            output: Vec::new(),
+            steps: 0,
+            step_limit: 1_000_000,
+            depth: 0,
+        }
+    }
+
+    /// Limits the number of blocks and loop iterations executed, so that a pass that
+    /// introduces an infinite loop makes the run fail instead of hang.
+    pub fn with_step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = step_limit;
+        self
+    }
+
+    /// Makes closures of `ast_function` run `function`, binding the captured upvalues to
+    /// `upvalues` in order. Closures of unregistered functions run their AST body instead.
+    pub fn register(
+        &mut self,
+        ast_function: &Arc<Mutex<ast::Function>>,
+        function: &'a Function,
+        upvalues: &'a [RcLocal],
+    ) {
+        self.functions
+            .insert(ByAddress(ast_function.clone()), (function, upvalues));
+    }
+
+    /// Makes every local in a group share one variable, which is how SSA form represents a
+    /// local captured by reference. Takes `local -> group` pairs like the upvalue groups
+    /// returned by `ssa::construct`.
+    pub fn share_variables(&mut self, groups: impl IntoIterator<Item = (RcLocal, RcLocal)>) {
+        self.groups.extend(groups);
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().set(name.into(), value).unwrap();
+    }
+
+    pub fn execute(
+        &mut self,
+        function: &'a Function,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, Error> {
+        self.execute_function(function, Vec::new(), arguments)
+    }
+
+    /// Runs the body of a main function, e.g. a lifter's output, with `arguments` as its
+    /// varargs.
+    pub fn execute_chunk(
+        &mut self,
+        body: &ast::Block,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, Error> {
+        let mut frame = Frame {
+            varargs: arguments,
+            ..Default::default()
+        };
+        let flow = self.execute_block(&mut frame, body)?;
+        returned(flow)
+    }
+
+    pub fn call(&mut self, function: &Value, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+        match function {
+            Value::Native(_, native) => native(self, arguments),
+            Value::Function(closure) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(Error::StackOverflow);
+                }
+                self.depth += 1;
+                let result = self.call_closure(closure, arguments);
+                self.depth -= 1;
+                result
+            }
+            Value::Prototype(_) => Err(Error::Unsupported("closure of a bytecode function")),
+            other => Err(type_error("call", other)),
+        }
+    }
+
+    fn call_closure(
+        &mut self,
+        closure: &LuaClosure,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, Error> {
+        if let Some(&(function, upvalues)) = self.functions.get(&closure.function) {
+            let upvalues = upvalues
+                .iter()
+                .cloned()
+                .zip(closure.upvalues.iter().map(|(_, cell)| cell.clone()))
+                .collect();
+            return self.execute_function(function, upvalues, arguments);
+        }
+
+        // the body is cloned since a recursive call would lock the function again
+        let function = closure.function.lock().clone();
+        let mut frame = Frame::default();
+        for (local, cell) in &closure.upvalues {
+            frame.cells.insert(self.group(local), cell.clone());
+        }
+        self.bind_arguments(
+            &mut frame,
+            &function.parameters,
+            function.is_variadic,
+            arguments,
+        );
+        let flow = self.execute_block(&mut frame, &function.body)?;
+        returned(flow)
+    }
+
+    fn execute_function(
+        &mut self,
+        function: &'a Function,
+        upvalues: Vec<(RcLocal, Cell)>,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, Error> {
+        let mut frame = Frame::default();
+        for (local, cell) in upvalues {
+            frame.cells.insert(self.group(&local), cell);
+        }
+        self.bind_arguments(
+            &mut frame,
+            &function.parameters,
+            function.is_variadic,
+            arguments,
+        );
+
+        let mut node = function
+            .entry()
+            .ok_or(Error::Unsupported("function without an entry block"))?;
+        loop {
+            self.step()?;
+            let block = function.block(node).unwrap();
+            let branch = if let Some((then_edge, else_edge)) = function.conditional_edges(node)
+            {
+                let (terminator, body) = block
+                    .split_last()
+                    .ok_or(Error::Unsupported("conditional block without a terminator"))?;
+                if let Flow::Return(values) = self.execute_statements(&mut frame, body)? {
+                    return Ok(values);
+                }
+                if self.execute_terminator(&mut frame, node, terminator)? {
+                    then_edge
+                } else {
+                    else_edge
+                }
+            } else {
+                match self.execute_statements(&mut frame, block)? {
+                    Flow::Normal => {}
+                    Flow::Return(values) => return Ok(values),
+                    Flow::Break | Flow::Continue => {
+                        return Err(Error::Unsupported("break outside of a loop"))
+                    }
+                }
+                match function.unconditional_edge(node) {
+                    Some(edge) => edge,
+                    None => return Ok(Vec::new()),
+                }
+            };
+
+            let arguments = branch
+                .weight()
+                .arguments
+                .iter()
+                .map(|(_, argument)| self.evaluate(&mut frame, argument))
+                .collect::<Result<Vec<_>, _>>()?;
+            for ((parameter, _), value) in branch.weight().arguments.iter().zip(arguments) {
+                self.assign_local(&mut frame, parameter, value);
+            }
+            node = branch.target();
+        }
+    }
+
+    // calls the generator of a generic for loop, or traverses the table itself for Luau's
+    // generalized iteration
+    fn iterate(
+        &mut self,
+        generator: &Value,
+        state: Value,
+        control: Value,
+    ) -> Result<Vec<Value>, Error> {
+        match generator {
+            Value::Table(table) => Ok(match table.borrow().next(&control)? {
+                Some((key, value)) => vec![key, value],
+                None => vec![Value::Nil],
+            }),
+            generator => self.call(generator, vec![state, control]),
+        }
+    }
+
+    fn bind_arguments(
+        &self,
+        frame: &mut Frame,
+        parameters: &[RcLocal],
+        is_variadic: bool,
+        mut arguments: Vec<Value>,
+    ) {
+        if arguments.len() < parameters.len() {
+            arguments.resize(parameters.len(), Value::Nil);
+        }
+        let varargs = arguments.split_off(parameters.len());
+        for (parameter, value) in parameters.iter().zip(arguments) {
+            self.declare_local(frame, parameter, value);
+        }
+        if is_variadic {
+            frame.varargs = varargs;
+        }
+    }
+
+    fn step(&mut self) -> Result<(), Error> {
+        self.steps += 1;
+        if self.steps > self.step_limit {
+            Err(Error::StepLimit)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn group(&self, local: &RcLocal) -> RcLocal {
+        self.groups.get(local).unwrap_or(local).clone()
+    }
+
+    fn cell(&self, frame: &mut Frame, local: &RcLocal) -> Cell {
+        frame.cells.entry(self.group(local)).or_default().clone()
+    }
+
+    fn read_local(&self, frame: &Frame, local: &RcLocal) -> Value {
+        frame
+            .cells
+            .get(&self.group(local))
+            .map(|cell| cell.borrow().clone())
+            .unwrap_or(Value::Nil)
+    }
+
+    fn assign_local(&self, frame: &mut Frame, local: &RcLocal, value: Value) {
+        *self.cell(frame, local).borrow_mut() = value;
+    }
+
+    // declarations get a new variable so closures created in earlier loop iterations
+    // keep the one they captured
+    fn declare_local(&self, frame: &mut Frame, local: &RcLocal, value: Value) {
+        frame
+            .cells
+            .insert(self.group(local), Rc::new(RefCell::new(value)));
+    }
+
+    // returns whether the `Then` edge is taken
+    fn execute_terminator(
+        &mut self,
+        frame: &mut Frame,
+        node: NodeIndex,
+        terminator: &Statement,
+    ) -> Result<bool, Error> {
+        match terminator {
+            Statement::If(r#if) => Ok(self.evaluate(frame, &r#if.condition)?.is_truthy()),
+            Statement::NumForNext(num_for_next) => {
+                let counter = self.evaluate(frame, &num_for_next.counter.1)?;
+                let limit = self.evaluate(frame, &num_for_next.limit)?;
+                let step = self.evaluate(frame, &num_for_next.step)?;
+                let (counter, limit, step) = (
+                    for_number(&counter, "initial")?,
+                    for_number(&limit, "limit")?,
+                    for_number(&step, "step")?,
+                );
+                let counter = counter + step;
+                self.assign(frame, &num_for_next.counter.0, Value::Number(counter))?;
+                Ok(if step > 0.0 {
+                    counter <= limit
+                } else {
+                    counter >= limit
+                })
+            }
+            Statement::GenericForNext(generic_for_next) => {
+                let control = match frame.generic_for_controls.remove(&node) {
+                    Some(control) => control,
+                    None => frame
+                        .pending_control
+                        .take()
+                        .ok_or(Error::Unsupported("generic for loop without an init"))?,
+                };
+                let generator = self.evaluate(frame, &generic_for_next.generator)?;
+                let state = self.evaluate(frame, &generic_for_next.state)?;
+                let mut values = self.iterate(&generator, state, control)?;
+                let control = values.first().cloned().unwrap_or(Value::Nil);
+                if control == Value::Nil {
+                    return Ok(false);
+                }
+                frame.generic_for_controls.insert(node, control);
+                values.resize(generic_for_next.res_locals.len(), Value::Nil);
+                for (lvalue, value) in generic_for_next.res_locals.iter().zip(values) {
+                    self.assign(frame, lvalue, value)?;
+                }
+                Ok(true)
+            }
+            _ => Err(Error::Unsupported("conditional block without a terminator")),
+        }
+    }
+
+    fn execute_block(&mut self, frame: &mut Frame, block: &ast::Block) -> Result<Flow, Error> {
+        self.execute_statements(frame, block)
+    }
+
+    fn execute_statements(
+        &mut self,
+        frame: &mut Frame,
+        statements: &[Statement],
+    ) -> Result<Flow, Error> {
+        for statement in statements {
+            match self.execute_statement(frame, statement)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn execute_loop_body(
+        &mut self,
+        frame: &mut Frame,
+        block: &ast::Block,
+    ) -> Result<Option<Flow>, Error> {
+        self.step()?;
+        Ok(match self.execute_block(frame, block)? {
+            Flow::Normal | Flow::Continue => None,
+            Flow::Break => Some(Flow::Normal),
+            flow @ Flow::Return(_) => Some(flow),
+        })
+    }
+
+    fn execute_statement(&mut self, frame: &mut Frame, statement: &Statement) -> Result<Flow, Error> {
+        match statement {
+            Statement::Empty(_) | Statement::Comment(_) => {}
+            Statement::Call(call) => {
+                self.evaluate_call(frame, call)?;
+            }
+            Statement::MethodCall(method_call) => {
+                self.evaluate_method_call(frame, method_call)?;
+            }
+            Statement::Assign(assign) => {
+                let places = assign
+                    .left
+                    .iter()
+                    .map(|lvalue| self.place(frame, lvalue))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut values = self.evaluate_assigned(frame, &assign.right)?;
+                values.resize(places.len(), Value::Nil);
+                // a local function that is never reassigned captures itself by value, which
+                // is the closure being assigned, like in Luau. the cycle is never freed.
+                if let ([LValue::Local(local)], [RValue::Closure(_)], [Value::Function(closure)]) =
+                    (&assign.left[..], &assign.right[..], &values[..])
+                {
+                    for (captured, cell) in &closure.upvalues {
+                        if captured == local {
+                            *cell.borrow_mut() = values[0].clone();
+                        }
+                    }
+                }
+                for (place, value) in places.into_iter().zip(values) {
+                    match place {
+                        Place::Local(local) if assign.prefix => {
+                            self.declare_local(frame, &local, value)
+                        }
+                        place => self.store(frame, place, value)?,
+                    }
+                }
+            }
+            Statement::If(r#if) => {
+                let block = if self.evaluate(frame, &r#if.condition)?.is_truthy() {
+                    &r#if.then_block
+                } else {
+                    &r#if.else_block
+                };
+                let block = block.lock().clone();
+                return self.execute_block(frame, &block);
+            }
+            Statement::While(r#while) => {
+                let block = r#while.block.lock().clone();
+                while self.evaluate(frame, &r#while.condition)?.is_truthy() {
+                    if let Some(flow) = self.execute_loop_body(frame, &block)? {
+                        return Ok(flow);
+                    }
+                }
+            }
+            Statement::Repeat(repeat) => {
+                let block = repeat.block.lock().clone();
+                loop {
+                    if let Some(flow) = self.execute_loop_body(frame, &block)? {
+                        return Ok(flow);
+                    }
+                    if self.evaluate(frame, &repeat.condition)?.is_truthy() {
+                        break;
+                    }
+                }
+            }
+            Statement::NumericFor(numeric_for) => {
+                let initial = self.evaluate(frame, &numeric_for.initial)?;
+                let limit = self.evaluate(frame, &numeric_for.limit)?;
+                let step = self.evaluate(frame, &numeric_for.step)?;
+                let (mut counter, limit, step) = (
+                    for_number(&initial, "initial")?,
+                    for_number(&limit, "limit")?,
+                    for_number(&step, "step")?,
+                );
+                let block = numeric_for.block.lock().clone();
+                while if step > 0.0 {
+                    counter <= limit
+                } else {
+                    counter >= limit
+                } {
+                    self.declare_local(frame, &numeric_for.counter, Value::Number(counter));
+                    if let Some(flow) = self.execute_loop_body(frame, &block)? {
+                        return Ok(flow);
+                    }
+                    counter += step;
+                }
+            }
+            Statement::GenericFor(generic_for) => {
+                let mut values = self.evaluate_assigned(frame, &generic_for.right)?;
+                values.resize(3, Value::Nil);
+                let mut control = values.pop().unwrap();
+                let state = values.pop().unwrap();
+                let generator = values.pop().unwrap();
+                let block = generic_for.block.lock().clone();
+                loop {
+                    let mut values = self.iterate(&generator, state.clone(), control)?;
+                    control = values.first().cloned().unwrap_or(Value::Nil);
+                    if control == Value::Nil {
+                        break;
+                    }
+                    values.resize(generic_for.res_locals.len(), Value::Nil);
+                    for (local, value) in generic_for.res_locals.iter().zip(values) {
+                        self.declare_local(frame, local, value);
+                    }
+                    if let Some(flow) = self.execute_loop_body(frame, &block)? {
+                        return Ok(flow);
+                    }
+                }
+            }
+            Statement::NumForInit(num_for_init) => {
+                let counter = self.evaluate(frame, &num_for_init.counter.1)?;
+                let limit = self.evaluate(frame, &num_for_init.limit.1)?;
+                let step = self.evaluate(frame, &num_for_init.step.1)?;
+                let (counter, limit, step) = (
+                    for_number(&counter, "initial")?,
+                    for_number(&limit, "limit")?,
+                    for_number(&step, "step")?,
+                );
+                // the first NumForNext adds the step back, yielding the initial value
+                self.assign(frame, &num_for_init.counter.0, Value::Number(counter - step))?;
+                self.assign(frame, &num_for_init.limit.0, Value::Number(limit))?;
+                self.assign(frame, &num_for_init.step.0, Value::Number(step))?;
+            }
+            Statement::GenericForInit(generic_for_init) => {
+                let mut values = self.evaluate_assigned(frame, &generic_for_init.0.right)?;
+                values.resize(generic_for_init.0.left.len(), Value::Nil);
+                frame.pending_control = values.last().cloned();
+                for (lvalue, value) in generic_for_init.0.left.iter().zip(values) {
+                    self.assign(frame, lvalue, value)?;
+                }
+            }
+            Statement::NumForNext(_) | Statement::GenericForNext(_) => {
+                return Err(Error::Unsupported("for loop step outside of a terminator"))
+            }
+            Statement::Return(r#return) => {
+                return Ok(Flow::Return(self.evaluate_list(frame, &r#return.values)?))
+            }
+            Statement::Break(_) => return Ok(Flow::Break),
+            Statement::Continue(_) => return Ok(Flow::Continue),
+            Statement::Goto(_) | Statement::Label(_) => return Err(Error::Unsupported("goto")),
+            Statement::Close(close) => {
+                // later writes must not be visible to closures that captured these locals
+                for local in &close.locals {
+                    let value = self.read_local(frame, local);
+                    self.declare_local(frame, local, value);
+                }
+            }
+            Statement::SetList(set_list) => {
+                let table = self.read_local(frame, &set_list.object_local);
+                let Value::Table(table) = table else {
+                    return Err(type_error("index", &table));
+                };
+                let mut values = set_list
+                    .values
+                    .iter()
+                    .map(|value| self.evaluate(frame, value))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(tail) = &set_list.tail {
+                    values.extend(self.evaluate_multiple(frame, tail)?);
+                }
+                let mut table = table.borrow_mut();
+                for (offset, value) in values.into_iter().enumerate() {
+                    table.set(Value::Number((set_list.index + offset) as f64), value)?;
+                }
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn place(&mut self, frame: &mut Frame, lvalue: &LValue) -> Result<Place, Error> {
+        Ok(match lvalue {
+            LValue::Local(local) => Place::Local(local.clone()),
+            LValue::Global(global) => Place::Global(global.0.clone()),
+            LValue::Index(index) => Place::Index(
+                self.evaluate(frame, &index.left)?,
+                self.evaluate(frame, &index.right)?,
+            ),
+        })
+    }
+
+    fn store(&mut self, frame: &mut Frame, place: Place, value: Value) -> Result<(), Error> {
+        match place {
+            Place::Local(local) => self.assign_local(frame, &local, value),
+            Place::Global(name) => self.globals.borrow_mut().set(Value::String(name), value)?,
+            Place::Index(Value::Table(table), key) => table.borrow_mut().set(key, value)?,
+            Place::Index(object, _) => return Err(type_error("index", &object)),
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, frame: &mut Frame, lvalue: &LValue, value: Value) -> Result<(), Error> {
+        let place = self.place(frame, lvalue)?;
+        self.store(frame, place, value)
+    }
+
+    fn index(&self, object: &Value, key: &Value) -> Result<Value, Error> {
+        match object {
+            Value::Table(table) => Ok(table.borrow().get(key)),
+            Value::String(_) => match self.globals.borrow().get(&"string".into()) {
+                Value::Table(library) => Ok(library.borrow().get(key)),
+                _ => Err(type_error("index", object)),
+            },
+            _ => Err(type_error("index", object)),
+        }
+    }
+
+    fn evaluate_list(&mut self, frame: &mut Frame, rvalues: &[RValue]) -> Result<Vec<Value>, Error> {
+        let mut values = Vec::with_capacity(rvalues.len());
+        if let Some((last, rest)) = rvalues.split_last() {
+            for rvalue in rest {
+                values.push(self.evaluate(frame, rvalue)?);
+            }
+            values.extend(self.evaluate_multiple(frame, last)?);
+        }
+        Ok(values)
+    }
+
+    // evaluates the values of an assignment or generic for, where a call or vararg in a
+    // `Select` sets every target the other values don't
+    fn evaluate_assigned(
+        &mut self,
+        frame: &mut Frame,
+        rvalues: &[RValue],
+    ) -> Result<Vec<Value>, Error> {
+        match rvalues.split_last() {
+            Some((RValue::Select(select), rest)) => {
+                let mut values = rest
+                    .iter()
+                    .map(|rvalue| self.evaluate(frame, rvalue))
+                    .collect::<Result<Vec<_>, _>>()?;
+                values.extend(self.evaluate_select(frame, select)?);
+                Ok(values)
+            }
+            _ => self.evaluate_list(frame, rvalues),
+        }
+    }
+
+    // evaluates an expression in a position where it can produce multiple values. A
+    // `Select` there is in parentheses, so it is adjusted to one value.
+    fn evaluate_multiple(&mut self, frame: &mut Frame, rvalue: &RValue) -> Result<Vec<Value>, Error> {
+        match rvalue {
+            RValue::Call(call) => self.evaluate_call(frame, call),
+            RValue::MethodCall(method_call) => self.evaluate_method_call(frame, method_call),
+            RValue::VarArg(_) => Ok(frame.varargs.clone()),
+            rvalue => Ok(vec![self.evaluate(frame, rvalue)?]),
+        }
+    }
+
+    fn evaluate_select(
+        &mut self,
+        frame: &mut Frame,
+        select: &ast::Select,
+    ) -> Result<Vec<Value>, Error> {
+        match select {
+            ast::Select::Call(call) => self.evaluate_call(frame, call),
+            ast::Select::MethodCall(method_call) => self.evaluate_method_call(frame, method_call),
+            ast::Select::VarArg(_) => Ok(frame.varargs.clone()),
+        }
+    }
+
+    fn evaluate_call(&mut self, frame: &mut Frame, call: &ast::Call) -> Result<Vec<Value>, Error> {
+        let function = self.evaluate(frame, &call.value)?;
+        let arguments = self.evaluate_list(frame, &call.arguments)?;
+        self.call(&function, arguments)
+    }
+
+    fn evaluate_method_call(
+        &mut self,
+        frame: &mut Frame,
+        method_call: &ast::MethodCall,
+    ) -> Result<Vec<Value>, Error> {
+        let object = self.evaluate(frame, &method_call.value)?;
+        let function = self.index(&object, &method_call.method.as_str().into())?;
+        let mut arguments = vec![object];
+        arguments.extend(self.evaluate_list(frame, &method_call.arguments)?);
+        self.call(&function, arguments)
+    }
+
+    fn evaluate(&mut self, frame: &mut Frame, rvalue: &RValue) -> Result<Value, Error> {
+        Ok(match rvalue {
+            RValue::Local(local) => self.read_local(frame, local),
+            RValue::Global(global) => self
+                .globals
+                .borrow()
+                .get(&Value::String(global.0.clone())),
+            RValue::Literal(literal) => literal.into(),
+            RValue::Call(_) | RValue::MethodCall(_) | RValue::VarArg(_) => self
+                .evaluate_multiple(frame, rvalue)?
+                .into_iter()
+                .next()
+                .unwrap_or(Value::Nil),
+            RValue::Select(select) => self
+                .evaluate_select(frame, select)?
+                .into_iter()
+                .next()
+                .unwrap_or(Value::Nil),
+            RValue::Table(table) => {
+                let mut result = Table::default();
+                let mut array_index = 1;
+                for (position, (key, value)) in table.0.iter().enumerate() {
+                    match key {
+                        Some(key) => {
+                            let key = self.evaluate(frame, key)?;
+                            let value = self.evaluate(frame, value)?;
+                            result.set(key, value)?;
+                        }
+                        None if position + 1 == table.0.len() => {
+                            for value in self.evaluate_multiple(frame, value)? {
+                                result.set(Value::Number(array_index as f64), value)?;
+                                array_index += 1;
+                            }
+                        }
+                        None => {
+                            let value = self.evaluate(frame, value)?;
+                            result.set(Value::Number(array_index as f64), value)?;
+                            array_index += 1;
+                        }
+                    }
+                }
+                Value::Table(Rc::new(result.into()))
+            }
+            RValue::Index(index) => {
+                let object = self.evaluate(frame, &index.left)?;
+                let key = self.evaluate(frame, &index.right)?;
+                self.index(&object, &key)?
+            }
+            RValue::Unary(unary) => {
+                let value = self.evaluate(frame, &unary.value)?;
+                match unary.operation {
+                    UnaryOperation::Not => Value::Boolean(!value.is_truthy()),
+                    UnaryOperation::Negate => Value::Number(
+                        -value
+                            .to_number()
+                            .ok_or_else(|| type_error("perform arithmetic on", &value))?,
+                    ),
+                    UnaryOperation::Length => match &value {
+                        Value::String(string) => Value::Number(string.len() as f64),
+                        Value::Table(table) => Value::Number(table.borrow().len() as f64),
+                        _ => return Err(type_error("get length of", &value)),
+                    },
+                    UnaryOperation::BitNot => Value::Number(!integer(&value)? as f64),
+                }
+            }
+            RValue::Binary(binary) => {
+                let left = self.evaluate(frame, &binary.left)?;
+                match binary.operation {
+                    BinaryOperation::And if !left.is_truthy() => return Ok(left),
+                    BinaryOperation::Or if left.is_truthy() => return Ok(left),
+                    BinaryOperation::And | BinaryOperation::Or => {
+                        return self.evaluate(frame, &binary.right)
+                    }
+                    _ => {}
+                }
+                let right = self.evaluate(frame, &binary.right)?;
+                binary_operation(binary.operation, left, right)?
+            }
+            RValue::Closure(closure) => {
+                let upvalues = closure
+                    .upvalues
+                    .iter()
+                    .map(|upvalue| match upvalue {
+                        Upvalue::Copy(local) => (
+                            local.clone(),
+                            Rc::new(RefCell::new(self.read_local(frame, local))),
+                        ),
+                        Upvalue::Ref(local) => (local.clone(), self.cell(frame, local)),
+                    })
+                    .collect();
+                Value::Function(Rc::new(LuaClosure {
+                    function: closure.function.clone(),
+                    upvalues,
+                }))
+            }
+        })
+    }
+}
+
+fn for_number(value: &Value, name: &str) -> Result<f64, Error> {
+    value
+        .to_number()
+        .ok_or_else(|| Error::Runtime(format!("'for' {} value must be a number", name)))
+}
+
+fn arithmetic(left: &Value, right: &Value) -> Result<(f64, f64), Error> {
+    match (left.to_number(), right.to_number()) {
+        (Some(left), Some(right)) => Ok((left, right)),
+        (None, _) => Err(type_error("perform arithmetic on", left)),
+        (_, None) => Err(type_error("perform arithmetic on", right)),
+    }
+}
+
+// numbers are floats, bitwise operators only take the ones with an integer representation
+fn integer(value: &Value) -> Result<i64, Error> {
+    let number = value
+        .to_number()
+        .ok_or_else(|| type_error("perform bitwise operation on", value))?;
+    if number.fract() == 0.0 && number >= i64::MIN as f64 && number < -(i64::MIN as f64) {
+        Ok(number as i64)
+    } else {
+        Err(Error::Runtime(
+            "number has no integer representation".into(),
+        ))
+    }
+}
+
+fn shift_left(value: i64, shift: i64) -> i64 {
+    if shift <= -64 || shift >= 64 {
+        0
+    } else if shift < 0 {
+        ((value as u64) >> -shift) as i64
+    } else {
+        ((value as u64) << shift) as i64
+    }
+}
+
+// `None` when either number is NaN, which is unordered
+fn compare(left: &Value, right: &Value) -> Result<Option<Ordering>, Error> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => Ok(a.partial_cmp(b)),
+        (Value::String(a), Value::String(b)) => Ok(Some(a.cmp(b))),
+        (Value::Number(_) | Value::String(_), other) | (other, _) => {
+            Err(type_error("compare", other))
+        }
+    }
+}
+
+fn concat_operand(value: &Value) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::String(string) => Ok(string.clone()),
+        Value::Number(_) => Ok(value.to_string().into_bytes()),
+        _ => Err(type_error("concatenate", value)),
+    }
+}
+
+fn binary_operation(operation: BinaryOperation, left: Value, right: Value) -> Result<Value, Error> {
+    Ok(match operation {
+        BinaryOperation::Add => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number(a + b)
+        }
+        BinaryOperation::Sub => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number(a - b)
+        }
+        BinaryOperation::Mul => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number(a * b)
+        }
+        BinaryOperation::Div => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number(a / b)
+        }
+        BinaryOperation::Mod => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number(a - (a / b).floor() * b)
+        }
+        BinaryOperation::Pow => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number(a.powf(b))
+        }
+        BinaryOperation::IDiv => {
+            let (a, b) = arithmetic(&left, &right)?;
+            Value::Number((a / b).floor())
+        }
+        BinaryOperation::BitAnd => Value::Number((integer(&left)? & integer(&right)?) as f64),
+        BinaryOperation::BitOr => Value::Number((integer(&left)? | integer(&right)?) as f64),
+        BinaryOperation::BitXor => Value::Number((integer(&left)? ^ integer(&right)?) as f64),
+        BinaryOperation::ShiftLeft => {
+            Value::Number(shift_left(integer(&left)?, integer(&right)?) as f64)
+        }
+        BinaryOperation::ShiftRight => Value::Number(
+            shift_left(integer(&left)?, integer(&right)?.wrapping_neg()) as f64,
+        ),
+        BinaryOperation::Concat => {
+            let mut string = concat_operand(&left)?;
+            string.extend(concat_operand(&right)?);
+            Value::String(string)
+        }
+        BinaryOperation::Equal => Value::Boolean(left == right),
+        BinaryOperation::NotEqual => Value::Boolean(left != right),
+        // every ordered comparison with NaN is false
+        BinaryOperation::LessThan => {
+            Value::Boolean(compare(&left, &right)?.is_some_and(Ordering::is_lt))
+        }
+        BinaryOperation::LessThanOrEqual => {
+            Value::Boolean(compare(&left, &right)?.is_some_and(Ordering::is_le))
+        }
+        BinaryOperation::GreaterThan => {
+            Value::Boolean(compare(&left, &right)?.is_some_and(Ordering::is_gt))
+        }
+        BinaryOperation::GreaterThanOrEqual => {
+            Value::Boolean(compare(&left, &right)?.is_some_and(Ordering::is_ge))
+        }
+        BinaryOperation::And | BinaryOperation::Or => unreachable!(),
+    })
+}
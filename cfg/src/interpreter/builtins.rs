@@ -0,0 +1,232 @@
+use itertools::Itertools;
+
+use super::{
+    value::{parse_number, NativeFunction},
+    Error, Host, Table, Value,
+};
+
+fn argument(arguments: &[Value], index: usize) -> Value {
+    arguments.get(index).cloned().unwrap_or(Value::Nil)
+}
+
+fn bad_argument(index: usize, function: &str, expected: &str) -> Error {
+    Error::Runtime(format!(
+        "bad argument #{} to '{}' ({} expected)",
+        index + 1,
+        function,
+        expected
+    ))
+}
+
+fn number_argument(arguments: &[Value], index: usize, function: &str) -> Result<f64, Error> {
+    argument(arguments, index)
+        .to_number()
+        .ok_or_else(|| bad_argument(index, function, "number"))
+}
+
+fn string_argument(arguments: &[Value], index: usize, function: &str) -> Result<Vec<u8>, Error> {
+    match argument(arguments, index) {
+        Value::String(string) => Ok(string),
+        Value::Number(n) => Ok(Value::Number(n).to_string().into_bytes()),
+        _ => Err(bad_argument(index, function, "string")),
+    }
+}
+
+fn print(host: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    host.print(arguments.iter().join("\t"));
+    Ok(Vec::new())
+}
+
+fn r#type(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    if arguments.is_empty() {
+        return Err(bad_argument(0, "type", "value"));
+    }
+    Ok(vec![arguments[0].type_name().into()])
+}
+
+fn tostring(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::String(
+        argument(&arguments, 0).to_string().into_bytes(),
+    )])
+}
+
+fn tonumber(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    Ok(vec![match argument(&arguments, 0) {
+        Value::Number(n) => Value::Number(n),
+        Value::String(string) => std::str::from_utf8(&string)
+            .ok()
+            .and_then(parse_number)
+            .map_or(Value::Nil, Value::Number),
+        _ => Value::Nil,
+    }])
+}
+
+fn select(_: &mut dyn Host, mut arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match argument(&arguments, 0) {
+        Value::String(string) if string == b"#" => {
+            Ok(vec![Value::Number((arguments.len() - 1) as f64)])
+        }
+        index => {
+            let index = index
+                .to_number()
+                .ok_or_else(|| bad_argument(0, "select", "number"))?;
+            if index < 1.0 {
+                return Err(Error::Runtime(
+                    "bad argument #1 to 'select' (index out of range)".into(),
+                ));
+            }
+            Ok(arguments.split_off((index as usize).min(arguments.len())))
+        }
+    }
+}
+
+fn next(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match argument(&arguments, 0) {
+        Value::Table(table) => Ok(match table.borrow().next(&argument(&arguments, 1))? {
+            Some((key, value)) => vec![key, value],
+            None => vec![Value::Nil],
+        }),
+        _ => Err(bad_argument(0, "next", "table")),
+    }
+}
+
+fn pairs(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match argument(&arguments, 0) {
+        table @ Value::Table(_) => Ok(vec![Value::Native("next", next), table, Value::Nil]),
+        _ => Err(bad_argument(0, "pairs", "table")),
+    }
+}
+
+fn ipairs_iterator(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let index = number_argument(&arguments, 1, "ipairs")? + 1.0;
+    match argument(&arguments, 0) {
+        Value::Table(table) => Ok(match table.borrow().get(&Value::Number(index)) {
+            Value::Nil => vec![Value::Nil],
+            value => vec![Value::Number(index), value],
+        }),
+        _ => Err(bad_argument(0, "ipairs", "table")),
+    }
+}
+
+fn ipairs(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    match argument(&arguments, 0) {
+        table @ Value::Table(_) => Ok(vec![
+            Value::Native("ipairs_iterator", ipairs_iterator),
+            table,
+            Value::Number(0.0),
+        ]),
+        _ => Err(bad_argument(0, "ipairs", "table")),
+    }
+}
+
+fn error(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    Err(Error::Runtime(argument(&arguments, 0).to_string()))
+}
+
+fn assert(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    if argument(&arguments, 0).is_truthy() {
+        Ok(arguments)
+    } else {
+        Err(Error::Runtime(match arguments.get(1) {
+            Some(message) => message.to_string(),
+            None => "assertion failed!".into(),
+        }))
+    }
+}
+
+fn string_len(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let string = string_argument(&arguments, 0, "len")?;
+    Ok(vec![Value::Number(string.len() as f64)])
+}
+
+fn string_sub(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let string = string_argument(&arguments, 0, "sub")?;
+    let len = string.len() as i64;
+    let relative = |position: f64| {
+        let position = position as i64;
+        if position < 0 {
+            (len + position + 1).max(0)
+        } else {
+            position
+        }
+    };
+    let start = relative(number_argument(&arguments, 1, "sub")?).max(1);
+    let end = match argument(&arguments, 2) {
+        Value::Nil => len,
+        _ => relative(number_argument(&arguments, 2, "sub")?).min(len),
+    };
+    Ok(vec![Value::String(if start > end {
+        Vec::new()
+    } else {
+        string[(start - 1) as usize..end as usize].to_vec()
+    })])
+}
+
+fn string_upper(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let string = string_argument(&arguments, 0, "upper")?;
+    Ok(vec![Value::String(string.to_ascii_uppercase())])
+}
+
+fn string_lower(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let string = string_argument(&arguments, 0, "lower")?;
+    Ok(vec![Value::String(string.to_ascii_lowercase())])
+}
+
+fn string_rep(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    let string = string_argument(&arguments, 0, "rep")?;
+    let count = number_argument(&arguments, 1, "rep")?.max(0.0);
+    Ok(vec![Value::String(string.repeat(count as usize))])
+}
+
+fn math_floor(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::Number(
+        number_argument(&arguments, 0, "floor")?.floor(),
+    )])
+}
+
+fn math_abs(_: &mut dyn Host, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+    Ok(vec![Value::Number(number_argument(&arguments, 0, "abs")?.abs())])
+}
+
+fn library(functions: &[(&'static str, NativeFunction)]) -> Result<Value, Error> {
+    let mut table = Table::default();
+    for &(name, function) in functions {
+        table.set(name.into(), Value::Native(name, function))?;
+    }
+    Ok(Value::Table(std::rc::Rc::new(table.into())))
+}
+
+/// Installs the globals `Interpreter::new` starts with. Embedders that need more can call
+/// this on their own table and add to it.
+pub fn install(globals: &mut Table) -> Result<(), Error> {
+    let functions: [(&'static str, NativeFunction); 10] = [
+        ("print", print),
+        ("type", r#type),
+        ("tostring", tostring),
+        ("tonumber", tonumber),
+        ("select", select),
+        ("next", next),
+        ("pairs", pairs),
+        ("ipairs", ipairs),
+        ("error", error),
+        ("assert", assert),
+    ];
+    for (name, function) in functions {
+        globals.set(name.into(), Value::Native(name, function))?;
+    }
+    globals.set(
+        "string".into(),
+        library(&[
+            ("len", string_len),
+            ("sub", string_sub),
+            ("upper", string_upper),
+            ("lower", string_lower),
+            ("rep", string_rep),
+        ])?,
+    )?;
+    globals.set(
+        "math".into(),
+        library(&[("floor", math_floor), ("abs", math_abs)])?,
+    )?;
+    Ok(())
+}
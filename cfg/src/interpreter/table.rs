@@ -0,0 +1,68 @@
+use super::{Error, Value};
+
+/// A Lua table without metatables. Entries are kept in insertion order so that `next` is
+/// deterministic, which keeps traversal order identical between two runs being compared.
+#[derive(Debug, Default)]
+pub struct Table {
+    entries: Vec<(Value, Value)>,
+}
+
+impl Table {
+    fn position(&self, key: &Value) -> Option<usize> {
+        self.entries.iter().position(|(k, _)| k == key)
+    }
+
+    pub fn get(&self, key: &Value) -> Value {
+        self.position(key)
+            .map(|i| self.entries[i].1.clone())
+            .unwrap_or(Value::Nil)
+    }
+
+    pub fn set(&mut self, key: Value, value: Value) -> Result<(), Error> {
+        match key {
+            Value::Nil => return Err(Error::Runtime("table index is nil".into())),
+            Value::Number(n) if n.is_nan() => {
+                return Err(Error::Runtime("table index is NaN".into()))
+            }
+            _ => {}
+        }
+        match (self.position(&key), value) {
+            (Some(i), Value::Nil) => {
+                self.entries.remove(i);
+            }
+            (Some(i), value) => self.entries[i].1 = value,
+            (None, Value::Nil) => {}
+            (None, value) => self.entries.push((key, value)),
+        }
+        Ok(())
+    }
+
+    /// Returns a border of the table, the same one Lua returns for tables without holes.
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        while self.position(&Value::Number((len + 1) as f64)).is_some() {
+            len += 1;
+        }
+        len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn next(&self, key: &Value) -> Result<Option<(Value, Value)>, Error> {
+        let index = match key {
+            Value::Nil => 0,
+            key => {
+                self.position(key)
+                    .ok_or_else(|| Error::Runtime("invalid key to 'next'".into()))?
+                    + 1
+            }
+        };
+        Ok(self.entries.get(index).cloned())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter()
+    }
+}
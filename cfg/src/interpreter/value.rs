@@ -0,0 +1,235 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use ast::RcLocal;
+use by_address::ByAddress;
+use itertools::Itertools;
+use parking_lot::Mutex;
+use triomphe::Arc;
+
+use super::{Error, Table};
+
+pub type Cell = Rc<RefCell<Value>>;
+
+/// What builtins can do to the program calling them, implemented by everything that runs
+/// programs over these values.
+pub trait Host {
+    /// Writes a line for `print`.
+    fn print(&mut self, line: String);
+}
+
+pub type NativeFunction = fn(&mut dyn Host, Vec<Value>) -> Result<Vec<Value>, Error>;
+
+pub struct LuaClosure {
+    pub function: ByAddress<Arc<Mutex<ast::Function>>>,
+    // the local each upvalue was captured from, and the variable it refers to
+    pub upvalues: Vec<(RcLocal, Cell)>,
+}
+
+/// A closure of the function at index `proto` of a bytecode chunk, which only something
+/// running that chunk can call.
+pub struct PrototypeClosure {
+    pub proto: usize,
+    pub upvalues: Vec<Cell>,
+}
+
+#[derive(Clone, Default)]
+pub enum Value {
+    #[default]
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(Vec<u8>),
+    Vector(f32, f32, f32),
+    Table(Rc<RefCell<Table>>),
+    Function(Rc<LuaClosure>),
+    Prototype(Rc<PrototypeClosure>),
+    Native(&'static str, NativeFunction),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Vector(..) => "vector",
+            Value::Table(_) => "table",
+            Value::Function(_) | Value::Prototype(_) | Value::Native(..) => "function",
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Boolean(false))
+    }
+
+    pub fn new_table() -> Self {
+        Value::Table(Default::default())
+    }
+
+    /// Converts the value to a number the way arithmetic does, strings are coerced.
+    pub fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::String(string) => parse_number(std::str::from_utf8(string).ok()?),
+            _ => None,
+        }
+    }
+
+    /// Renders the value including the contents of tables, so that two runs producing
+    /// structurally equal results can be compared even though their tables are distinct.
+    /// Functions are only named if the ast they were created from gives them a name.
+    pub fn dump(&self) -> String {
+        let mut visited = Vec::new();
+        self.dump_into(&mut visited)
+    }
+
+    fn dump_into(&self, visited: &mut Vec<*const RefCell<Table>>) -> String {
+        match self {
+            Value::String(string) => format!("{:?}", String::from_utf8_lossy(string)),
+            Value::Table(table) => {
+                if visited.contains(&Rc::as_ptr(table)) {
+                    return "<cycle>".into();
+                }
+                visited.push(Rc::as_ptr(table));
+                let entries = table
+                    .borrow()
+                    .entries()
+                    .map(|(k, v)| format!("[{}] = {}", k.dump_into(visited), v.dump_into(visited)))
+                    .join(", ");
+                visited.pop();
+                format!("{{{}}}", entries)
+            }
+            Value::Function(closure) => match &closure.function.lock().name {
+                Some(name) => format!("function: {}", name),
+                None => "function".into(),
+            },
+            Value::Prototype(_) => "function".into(),
+            Value::Native(name, _) => format!("builtin: {}", name),
+            other => other.to_string(),
+        }
+    }
+}
+
+pub(super) fn parse_number(string: &str) -> Option<f64> {
+    let string = string.trim();
+    let (negative, digits) = match string.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, string),
+    };
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()? as f64
+    } else if digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        digits.parse::<f64>().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+// mirrors the "%.14g" format Lua uses for `tostring`
+pub(super) fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        return "nan".into();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "inf" } else { "-inf" }.into();
+    }
+    if n == n.trunc() && n.abs() < 1e15 {
+        return format!("{}", n as i64);
+    }
+    let exponent = n.abs().log10().floor() as i32;
+    if (-5..14).contains(&exponent) {
+        let fixed = format!("{:.*}", (13 - exponent) as usize, n);
+        fixed.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        let scientific = format!("{:.13e}", n);
+        let (mantissa, exponent) = scientific.split_once('e').unwrap();
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        let exponent = exponent.parse::<i32>().unwrap();
+        format!(
+            "{}e{}{:02}",
+            mantissa,
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Vector(ax, ay, az), Value::Vector(bx, by, bz)) => {
+                ax == bx && ay == by && az == bz
+            }
+            (Value::Table(a), Value::Table(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Prototype(a), Value::Prototype(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a, _), Value::Native(b, _)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", format_number(*n)),
+            Value::String(string) => write!(f, "{}", String::from_utf8_lossy(string)),
+            Value::Vector(x, y, z) => write!(f, "{}, {}, {}", x, y, z),
+            Value::Table(table) => write!(f, "table: {:p}", Rc::as_ptr(table)),
+            Value::Function(closure) => write!(f, "function: {:p}", Rc::as_ptr(closure)),
+            Value::Prototype(closure) => write!(f, "function: {:p}", Rc::as_ptr(closure)),
+            Value::Native(name, _) => write!(f, "builtin: {}", name),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(string) => write!(f, "{:?}", String::from_utf8_lossy(string)),
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.into())
+    }
+}
+
+impl From<&ast::Literal> for Value {
+    fn from(literal: &ast::Literal) -> Self {
+        match literal {
+            ast::Literal::Nil => Value::Nil,
+            ast::Literal::Boolean(b) => Value::Boolean(*b),
+            ast::Literal::Number(n) => Value::Number(*n),
+            &ast::Literal::Integer(n) => Value::Number(n as f64),
+            ast::Literal::String(string) => Value::String(string.clone()),
+            &ast::Literal::Vector(x, y, z) => Value::Vector(x, y, z),
+        }
+    }
+}
@@ -6,5 +6,6 @@
 pub mod block;
 pub mod dot;
 pub mod function;
+pub mod interpreter;
 pub mod pattern;
 pub mod ssa;
@@ -1,10 +1,13 @@
 pub mod construct;
 mod destruct;
+pub mod gvn;
 pub mod inline;
 mod param_dependency_graph;
 pub mod structuring;
 pub mod upvalues;
+pub mod verify;
 //pub mod dataflow;
 
 pub use construct::construct;
 pub use destruct::Destructor;
+pub use verify::verify;
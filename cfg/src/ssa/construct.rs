@@ -351,6 +351,17 @@ impl<'a> SsaConstructor<'a> {
         same
     }
 
+    // versions keep the name of the local they are a version of
+    fn new_local(&mut self, local: &RcLocal) -> RcLocal {
+        let new_local = RcLocal::new(local.0.lock().clone());
+        self.old_locals.insert(new_local.clone(), local.clone());
+        if let Some(upvalues) = self.new_upvalues_in.get_mut(local) {
+            upvalues.insert(new_local.clone());
+        }
+        self.local_count += 1;
+        new_local
+    }
+
     fn find_local(&mut self, node: NodeIndex, local: &RcLocal) -> RcLocal {
         let res = if let Some(new_local) = self
             .current_definition
@@ -362,13 +373,7 @@ impl<'a> SsaConstructor<'a> {
         } else {
             // search globally
             if !self.sealed_blocks.contains(&node) {
-                // TODO: this code is repeated multiple times, create new_local function
-                let param_local = RcLocal::default();
-                self.old_locals.insert(param_local.clone(), local.clone());
-                if let Some(upvalues) = self.new_upvalues_in.get_mut(local) {
-                    upvalues.insert(param_local.clone());
-                }
-                self.local_count += 1;
+                let param_local = self.new_local(local);
                 self.incomplete_params
                     .entry(node)
                     .or_default()
@@ -377,12 +382,7 @@ impl<'a> SsaConstructor<'a> {
             } else if let Ok(pred) = self.function.predecessor_blocks(node).exactly_one() {
                 self.find_local(pred, local)
             } else {
-                let param_local = RcLocal::default();
-                self.old_locals.insert(param_local.clone(), local.clone());
-                if let Some(upvalues) = self.new_upvalues_in.get_mut(local) {
-                    upvalues.insert(param_local.clone());
-                }
-                self.local_count += 1;
+                let param_local = self.new_local(local);
                 self.write_local(node, local, &param_local);
 
                 self.add_param_args(node, local, param_local)
@@ -407,6 +407,7 @@ impl<'a> SsaConstructor<'a> {
                 if assign.left.len() == 1
                     && assign.right.len() == 1
                     && let Some(from) = assign.left[0].as_local()
+                    && !from.0.lock().is_to_be_closed()
                     && let from_old = &self.old_locals[from]
                     && !self.new_upvalues_in.contains_key(from_old)
                     && !self.upvalues_passed.contains_key(from_old)
@@ -523,12 +524,7 @@ impl<'a> SsaConstructor<'a> {
                     && let Some(local) = assign.left[0].as_local().cloned()
                     && assign.right[0].as_closure().is_some()
                 {
-                    let new_local = RcLocal::default();
-                    self.old_locals.insert(new_local.clone(), local.clone());
-                    if let Some(upvalues) = self.new_upvalues_in.get_mut(&local) {
-                        upvalues.insert(new_local.clone());
-                    }
-                    self.local_count += 1;
+                    let new_local = self.new_local(&local);
                     self.write_local(node, &local, &new_local);
                     let statement = self
                         .function
@@ -549,12 +545,7 @@ impl<'a> SsaConstructor<'a> {
                     self.read(node, stat_index);
                     // write
                     for (local_index, local) in written.iter().enumerate() {
-                        let new_local = RcLocal::default();
-                        self.old_locals.insert(new_local.clone(), local.clone());
-                        if let Some(upvalues) = self.new_upvalues_in.get_mut(local) {
-                            upvalues.insert(new_local.clone());
-                        }
-                        self.local_count += 1;
+                        let new_local = self.new_local(local);
                         self.write_local(node, local, &new_local);
                         let statement = self
                             .function
@@ -106,8 +106,6 @@ impl<'a> Destructor<'a> {
 
         super::construct::apply_local_map(self.function, self.build_local_map());
 
-        //crate::dot::render_to(self.function, &mut std::io::stdout()).unwrap();
-
         self.sequentialize();
     }
 
@@ -242,6 +240,15 @@ impl<'a> Destructor<'a> {
         for (local, con_class) in &self.congruence_classes {
             let con_class = con_class.borrow();
             let new_local = con_class.iter().next().unwrap().1;
+            // the coalesced local keeps a name and an attribute if any of its versions had one
+            if new_local.0.lock().0.is_none()
+                && let Some(name) = con_class.iter().find_map(|(_, l)| l.0.lock().0.clone())
+            {
+                new_local.0.lock().0 = Some(name);
+            }
+            if let Some(attribute) = con_class.iter().find_map(|(_, l)| l.0.lock().1) {
+                new_local.0.lock().1 = Some(attribute);
+            }
             // TODO: see apply_local_map TODO,
             // we dont want to handle this here
             if local != new_local {
@@ -0,0 +1,297 @@
+//! Global value numbering over ssa form. A local that is assigned a value an earlier local
+//! already holds is replaced by that local, so an expression computed twice is declared
+//! once and used twice instead of being inlined twice.
+//!
+//! Only expressions that can't run code are numbered: arithmetic on numbers, concatenation
+//! of strings and numbers, comparisons that can't reach a metamethod and indexing tables
+//! that are constructed in the function and only ever indexed. Anything else may call a
+//! metamethod, so indexing in general is treated as having side effects.
+
+use ast::{
+    BinaryOperation, LValue, Literal, LocalRw, RValue, RcLocal, Statement, Traverse, UnaryOperation,
+};
+use indexmap::IndexMap;
+use petgraph::{algo::dominators::simple_fast, stable_graph::NodeIndex};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::function::Function;
+
+use super::construct::apply_local_map;
+
+// the type of value an expression is known to evaluate to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Nil,
+    Boolean,
+    Number,
+    String,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Local(RcLocal),
+    Nil,
+    Boolean(bool),
+    // by bits, so that 0 and -0 are different values
+    Number(u64),
+    Integer(i64),
+    String(Vec<u8>),
+    Unary(UnaryOperation, Box<Key>),
+    Binary(BinaryOperation, Box<Key>, Box<Key>),
+    Index(Box<Key>, Box<Key>),
+}
+
+impl Key {
+    fn reads_local(&self) -> bool {
+        match self {
+            Self::Local(_) => true,
+            Self::Unary(_, value) => value.reads_local(),
+            Self::Binary(_, left, right) | Self::Index(left, right) => {
+                left.reads_local() || right.reads_local()
+            }
+            _ => false,
+        }
+    }
+}
+
+enum Visit {
+    Enter(NodeIndex),
+    // the values numbered in the block, which go out of scope with it
+    Leave(Vec<Key>),
+}
+
+struct ValueNumbering<'a> {
+    upvalue_to_group: &'a IndexMap<RcLocal, RcLocal>,
+    immutable_tables: FxHashSet<RcLocal>,
+    kinds: FxHashMap<RcLocal, Kind>,
+    values: FxHashMap<Key, RcLocal>,
+    local_map: FxHashMap<RcLocal, RcLocal>,
+}
+
+impl ValueNumbering<'_> {
+    // the key of `rvalue` and the kind of value it evaluates to, if evaluating it can't
+    // run code. the value of upvalues can change in any call, so they have no key.
+    fn key(&self, rvalue: &RValue) -> Option<(Key, Kind)> {
+        Some(match rvalue {
+            RValue::Local(local) => {
+                if self.upvalue_to_group.contains_key(local) {
+                    return None;
+                }
+                let local = self.local_map.get(local).unwrap_or(local);
+                let kind = self.kinds.get(local).copied().unwrap_or(Kind::Unknown);
+                (Key::Local(local.clone()), kind)
+            }
+            RValue::Literal(literal) => match *literal {
+                Literal::Nil => (Key::Nil, Kind::Nil),
+                Literal::Boolean(value) => (Key::Boolean(value), Kind::Boolean),
+                Literal::Number(value) => (Key::Number(value.to_bits()), Kind::Number),
+                Literal::Integer(value) => (Key::Integer(value), Kind::Number),
+                Literal::String(ref value) => (Key::String(value.clone()), Kind::String),
+                Literal::Vector(..) => return None,
+            },
+            RValue::Unary(unary) => {
+                let (value, kind) = self.key(&unary.value)?;
+                let kind = match (unary.operation, kind) {
+                    (UnaryOperation::Not, _) => Kind::Boolean,
+                    (UnaryOperation::Negate | UnaryOperation::BitNot, Kind::Number)
+                    | (UnaryOperation::Length, Kind::String) => Kind::Number,
+                    _ => return None,
+                };
+                (Key::Unary(unary.operation, Box::new(value)), kind)
+            }
+            RValue::Binary(binary) => {
+                let (left, left_kind) = self.key(&binary.left)?;
+                let (right, right_kind) = self.key(&binary.right)?;
+                let kind = match binary.operation {
+                    // evaluates to one of the operands
+                    BinaryOperation::And | BinaryOperation::Or if left_kind == right_kind => {
+                        left_kind
+                    }
+                    BinaryOperation::And | BinaryOperation::Or => Kind::Unknown,
+                    // __eq is only called when both operands are tables or userdata
+                    BinaryOperation::Equal | BinaryOperation::NotEqual
+                        if left_kind != Kind::Unknown || right_kind != Kind::Unknown =>
+                    {
+                        Kind::Boolean
+                    }
+                    BinaryOperation::LessThan
+                    | BinaryOperation::LessThanOrEqual
+                    | BinaryOperation::GreaterThan
+                    | BinaryOperation::GreaterThanOrEqual
+                        if left_kind == right_kind
+                            && matches!(left_kind, Kind::Number | Kind::String) =>
+                    {
+                        Kind::Boolean
+                    }
+                    BinaryOperation::Concat
+                        if matches!(left_kind, Kind::Number | Kind::String)
+                            && matches!(right_kind, Kind::Number | Kind::String) =>
+                    {
+                        Kind::String
+                    }
+                    BinaryOperation::Equal
+                    | BinaryOperation::NotEqual
+                    | BinaryOperation::LessThan
+                    | BinaryOperation::LessThanOrEqual
+                    | BinaryOperation::GreaterThan
+                    | BinaryOperation::GreaterThanOrEqual
+                    | BinaryOperation::Concat => return None,
+                    _ if left_kind == Kind::Number && right_kind == Kind::Number => Kind::Number,
+                    _ => return None,
+                };
+                (
+                    Key::Binary(binary.operation, Box::new(left), Box::new(right)),
+                    kind,
+                )
+            }
+            RValue::Index(index) => {
+                let (left, _) = self.key(&index.left)?;
+                if !matches!(&left, Key::Local(table) if self.immutable_tables.contains(table)) {
+                    return None;
+                }
+                let (right, _) = self.key(&index.right)?;
+                (Key::Index(Box::new(left), Box::new(right)), Kind::Unknown)
+            }
+            _ => return None,
+        })
+    }
+
+    // numbers the values assigned in `node`, removing the assignments of values an earlier
+    // local holds. returns the values numbered first in `node`.
+    fn number_block(&mut self, function: &mut Function, node: NodeIndex) -> Vec<Key> {
+        let mut numbered = Vec::new();
+        let block = function.block_mut(node).unwrap();
+        for statement in block.iter_mut() {
+            let Statement::Assign(assign) = statement else {
+                continue;
+            };
+            let (local, (key, kind)) = match (&assign.left[..], &assign.right[..]) {
+                ([LValue::Local(local)], [value])
+                    if !self.upvalue_to_group.contains_key(local)
+                        && !local.0.lock().is_to_be_closed() =>
+                {
+                    match self.key(value) {
+                        Some(key) => (local.clone(), key),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            self.kinds.insert(local.clone(), kind);
+            // literals, copies and constant expressions aren't worth a local of their own
+            if !matches!(key, Key::Unary(..) | Key::Binary(..) | Key::Index(..))
+                || !key.reads_local()
+            {
+                continue;
+            }
+            match self.values.get(&key) {
+                Some(leader) => {
+                    // two named locals are kept apart, the names say they mean different
+                    // things
+                    let name = local.0.lock().0.clone();
+                    let mut leader_local = leader.0.lock();
+                    if name.is_some() && leader_local.0.is_some() {
+                        continue;
+                    }
+                    if leader_local.0.is_none() {
+                        leader_local.0 = name;
+                    }
+                    drop(leader_local);
+                    self.local_map.insert(local, leader.clone());
+                    *statement = ast::Empty {}.into();
+                }
+                None => {
+                    numbered.push(key.clone());
+                    self.values.insert(key, local);
+                }
+            }
+        }
+        block.retain(|s| s.as_empty().is_none());
+        numbered
+    }
+}
+
+// tables constructed in the function that are only ever indexed, which means nothing can
+// write to them or give them a metatable
+fn immutable_tables(function: &mut Function) -> FxHashSet<RcLocal> {
+    let mut tables = FxHashSet::default();
+    let mut reads = FxHashMap::<RcLocal, usize>::default();
+    let mut indexed = FxHashMap::<RcLocal, usize>::default();
+    for node in function.graph().node_indices().collect::<Vec<_>>() {
+        for statement in function.block_mut(node).unwrap().iter_mut() {
+            if let Statement::Assign(assign) = statement
+                && let [LValue::Local(local)] = &assign.left[..]
+                && let [RValue::Table(_)] = &assign.right[..]
+            {
+                tables.insert(local.clone());
+            }
+            for local in statement.values_read() {
+                *reads.entry(local.clone()).or_default() += 1;
+            }
+            statement.traverse_rvalues(&mut |rvalue| {
+                if let RValue::Index(index) = rvalue
+                    && let RValue::Local(local) = &*index.left
+                {
+                    *indexed.entry(local.clone()).or_default() += 1;
+                }
+            });
+        }
+        for edge in function.edges(node) {
+            for (_, argument) in &edge.weight().arguments {
+                for local in argument.values_read() {
+                    *reads.entry(local.clone()).or_default() += 1;
+                }
+            }
+        }
+    }
+    tables.retain(|table| reads.get(table) == indexed.get(table));
+    tables
+}
+
+/// Replaces locals assigned a value that a local defined before them already holds with
+/// that local, visiting blocks in dominator tree order. A duplicate keeps its name by
+/// giving it to an unnamed local it's replaced with, and two named locals are never merged.
+/// Structured statements aren't looked into, so this runs before structuring. Returns
+/// whether anything changed.
+pub fn gvn(function: &mut Function, upvalue_to_group: &IndexMap<RcLocal, RcLocal>) -> bool {
+    let Some(entry) = *function.entry() else {
+        return false;
+    };
+    let dominators = simple_fast(function.graph(), entry);
+    let mut dominated = FxHashMap::<NodeIndex, Vec<NodeIndex>>::default();
+    for node in function.graph().node_indices() {
+        if let Some(dominator) = dominators.immediate_dominator(node) {
+            dominated.entry(dominator).or_default().push(node);
+        }
+    }
+
+    let mut numbering = ValueNumbering {
+        upvalue_to_group,
+        immutable_tables: immutable_tables(function),
+        kinds: FxHashMap::default(),
+        values: FxHashMap::default(),
+        local_map: FxHashMap::default(),
+    };
+    let mut stack = vec![Visit::Enter(entry)];
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Enter(node) => {
+                let numbered = numbering.number_block(function, node);
+                stack.push(Visit::Leave(numbered));
+                if let Some(dominated) = dominated.get(&node) {
+                    stack.extend(dominated.iter().map(|&node| Visit::Enter(node)));
+                }
+            }
+            Visit::Leave(numbered) => {
+                for key in numbered {
+                    numbering.values.remove(&key);
+                }
+            }
+        }
+    }
+
+    let changed = !numbering.local_map.is_empty();
+    apply_local_map(function, numbering.local_map);
+    changed
+}
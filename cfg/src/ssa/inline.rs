@@ -127,9 +127,7 @@ impl<'a> Inliner<'a> {
                 stat_to_values_read.push(
                     stat.values_read()
                         .into_iter()
-                        .filter(|&l| {
-                            self.local_usages[l] == 1 && !self.upvalue_to_group.contains_key(l)
-                        })
+                        .filter(|&l| is_candidate(l, self.local_usages, self.upvalue_to_group))
                         .cloned()
                         .map(Some)
                         .collect_vec(),
@@ -333,9 +331,7 @@ impl<'a> Inliner<'a> {
                     .map(|(_, a)| {
                         a.values_read()
                             .into_iter()
-                            .filter(|&l| {
-                                self.local_usages[l] == 1 && !self.upvalue_to_group.contains_key(l)
-                            })
+                            .filter(|&l| is_candidate(l, self.local_usages, self.upvalue_to_group))
                             .cloned()
                             .map(Some)
                             .collect_vec()
@@ -399,6 +395,7 @@ impl<'a> Inliner<'a> {
                                     .iter_mut()
                                     .find(|l| l.as_ref() == Some(local))
                             {
+                                let name = local.0.lock().0.clone();
                                 let mut new_rvalue = Some(
                                     block[stat_index]
                                         .as_assign_mut()
@@ -422,6 +419,16 @@ impl<'a> Inliner<'a> {
                                     new_rvalue_has_side_effects,
                                 ) {
                                     assert!(new_rvalue.is_none());
+                                    // the parameter takes the name of the inlined local
+                                    let param =
+                                        &self.function.graph().edge_weight(edge).unwrap().arguments
+                                            [index]
+                                            .0;
+                                    if let Some(name) = name
+                                        && param.0.lock().0.is_none()
+                                    {
+                                        param.0.lock().0 = Some(name);
+                                    }
                                     let block = self.function.block_mut(node).unwrap();
 
                                     // TODO: PERF: remove `local_usages[l] == 1` filter in stat_to_values_read
@@ -465,6 +472,16 @@ impl<'a> Inliner<'a> {
     }
 }
 
+fn is_candidate(
+    local: &ast::RcLocal,
+    local_usages: &FxHashMap<ast::RcLocal, usize>,
+    upvalue_to_group: &IndexMap<ast::RcLocal, ast::RcLocal>,
+) -> bool {
+    local_usages[local] == 1
+        && !upvalue_to_group.contains_key(local)
+        && !local.0.lock().is_to_be_closed()
+}
+
 pub fn inline(
     function: &mut Function,
     local_to_group: &FxHashMap<ast::RcLocal, usize>,
@@ -500,6 +517,7 @@ pub fn inline(
                     let has_side_effects = rvalue.has_side_effects();
                     // TODO: REFACTOR: is_some_and
                     if !upvalue_to_group.contains_key(local)
+                        && !local.0.lock().is_to_be_closed()
                         && local_usages.get(local).map_or(true, |&u| u == 0)
                     {
                         if has_side_effects {
@@ -0,0 +1,210 @@
+//! Checks the invariants of ssa form. A pass that breaks them usually only shows up much
+//! later, as wrong output or a panic during destruction, so the pipeline can verify the
+//! function after every pass that changes it.
+
+use std::fmt;
+
+use ast::{LocalRw, RcLocal};
+use itertools::Itertools;
+use petgraph::{algo::dominators::simple_fast, stable_graph::NodeIndex, visit::EdgeRef, Direction};
+use rustc_hash::{FxHashMap, FxHashSet};
+use thiserror::Error;
+
+use crate::function::Function;
+
+/// Where a local is defined or used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    /// The parameters of the function.
+    Parameter,
+    /// The parameters of a block, which every edge to it passes arguments for.
+    BlockParameter(NodeIndex),
+    /// A statement of a block, by its index.
+    Statement(NodeIndex, usize),
+    /// The arguments of the edge from a block to another.
+    Edge(NodeIndex, NodeIndex),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parameter => write!(f, "the function's parameters"),
+            Self::BlockParameter(block) => write!(f, "the parameters of block {}", block.index()),
+            Self::Statement(block, index) => {
+                write!(f, "statement {} of block {}", index, block.index())
+            }
+            Self::Edge(source, target) => write!(
+                f,
+                "the edge from block {} to block {}",
+                source.index(),
+                target.index()
+            ),
+        }
+    }
+}
+
+/// An invariant of ssa form that a function breaks.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{local} is defined by {first} and again by {second}")]
+    DefinedTwice {
+        local: RcLocal,
+        first: Location,
+        second: Location,
+    },
+    #[error("{local} is used by {location} but never defined")]
+    Undefined { local: RcLocal, location: Location },
+    #[error(
+        "{local} is used by {location}, which its definition by {definition} doesn't dominate"
+    )]
+    NotDominated {
+        local: RcLocal,
+        definition: Location,
+        location: Location,
+    },
+    #[error(
+        "the edge from block {} to block {} passes ({}), but the edge from block {} passes ({})",
+        .from.index(),
+        .target.index(),
+        .found.iter().join(", "),
+        .first.index(),
+        .expected.iter().join(", ")
+    )]
+    ParameterMismatch {
+        target: NodeIndex,
+        first: NodeIndex,
+        expected: Vec<RcLocal>,
+        from: NodeIndex,
+        found: Vec<RcLocal>,
+    },
+}
+
+// the block a location is in and its order within the block, none for the function's
+// parameters which come before every block
+fn position(location: Location) -> Option<(NodeIndex, usize)> {
+    match location {
+        Location::Parameter => None,
+        Location::BlockParameter(block) => Some((block, 0)),
+        Location::Statement(block, index) => Some((block, index + 1)),
+        Location::Edge(source, _) => Some((source, usize::MAX)),
+    }
+}
+
+/// Checks that every local of `function` is defined once and that its definition
+/// dominates its uses, and that every edge to a block passes arguments for the same
+/// parameters. Versions of `upvalues_in` may be used without being defined, their value
+/// comes from the closure.
+pub fn verify(function: &Function, upvalues_in: &FxHashSet<RcLocal>) -> Result<(), Error> {
+    let mut definitions = FxHashMap::default();
+    let mut define = |local: &RcLocal, location| {
+        if let Some(&first) = definitions.get(local) {
+            return Err(Error::DefinedTwice {
+                local: local.clone(),
+                first,
+                second: location,
+            });
+        }
+        definitions.insert(local.clone(), location);
+        Ok(())
+    };
+
+    for parameter in &function.parameters {
+        define(parameter, Location::Parameter)?;
+    }
+    for (node, block) in function.blocks() {
+        let mut edges = function.graph().edges_directed(node, Direction::Incoming);
+        if let Some(first) = edges.next() {
+            let parameters = first
+                .weight()
+                .arguments
+                .iter()
+                .map(|(p, _)| p)
+                .collect::<Vec<_>>();
+            for edge in edges {
+                if !edge
+                    .weight()
+                    .arguments
+                    .iter()
+                    .map(|(p, _)| p)
+                    .eq(parameters.iter().copied())
+                {
+                    return Err(Error::ParameterMismatch {
+                        target: node,
+                        first: first.source(),
+                        expected: parameters.into_iter().cloned().collect(),
+                        from: edge.source(),
+                        found: edge
+                            .weight()
+                            .arguments
+                            .iter()
+                            .map(|(p, _)| p.clone())
+                            .collect(),
+                    });
+                }
+            }
+            for parameter in parameters {
+                define(parameter, Location::BlockParameter(node))?;
+            }
+        }
+        for (index, statement) in block.iter().enumerate() {
+            for local in statement.values_written() {
+                define(local, Location::Statement(node, index))?;
+            }
+        }
+    }
+
+    let dominators = function
+        .entry()
+        .map(|entry| simple_fast(function.graph(), entry));
+    let dominates = |definition, location| {
+        let Some((definition_block, definition_index)) = position(definition) else {
+            return true;
+        };
+        let (block, index) = position(location).unwrap();
+        if definition_block == block {
+            return definition_index < index;
+        }
+        // nothing needs to dominate uses in unreachable blocks
+        match dominators.as_ref().and_then(|d| d.dominators(block)) {
+            Some(mut block_dominators) => block_dominators.any(|d| d == definition_block),
+            None => true,
+        }
+    };
+    let check = |local: &RcLocal, location| match definitions.get(local) {
+        Some(&definition) if dominates(definition, location) => Ok(()),
+        Some(&definition) => Err(Error::NotDominated {
+            local: local.clone(),
+            definition,
+            location,
+        }),
+        None if upvalues_in.contains(local) => Ok(()),
+        None => Err(Error::Undefined {
+            local: local.clone(),
+            location,
+        }),
+    };
+
+    for (node, block) in function.blocks() {
+        for (index, statement) in block.iter().enumerate() {
+            let location = Location::Statement(node, index);
+            // a closure can capture the local it's assigned to, see `construct`
+            let recursive = statement
+                .as_assign()
+                .is_some_and(|a| a.right.len() == 1 && a.right[0].as_closure().is_some());
+            for local in statement.values_read() {
+                if !(recursive && definitions.get(local) == Some(&location)) {
+                    check(local, location)?;
+                }
+            }
+        }
+        for edge in function.edges(node) {
+            let location = Location::Edge(node, edge.target());
+            for (_, argument) in &edge.weight().arguments {
+                for local in argument.values_read() {
+                    check(local, location)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
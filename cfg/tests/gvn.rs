@@ -0,0 +1,257 @@
+use ast::{
+    Assign, Binary, BinaryOperation, Call, Global, If, Index, Literal, Local, RValue, RcLocal,
+    Return, Statement, Table, Unary, UnaryOperation,
+};
+use cfg::{
+    block::{BlockEdge, BranchType},
+    function::Function,
+    ssa::gvn::gvn,
+};
+use indexmap::IndexMap;
+
+fn assign(local: &RcLocal, value: RValue) -> Statement {
+    Assign::new(vec![local.clone().into()], vec![value]).into()
+}
+
+fn number(n: f64) -> RValue {
+    Literal::Number(n).into()
+}
+
+fn double(local: &RcLocal) -> RValue {
+    Binary::new(local.clone().into(), number(2.0), BinaryOperation::Mul).into()
+}
+
+fn index(table: &RcLocal, key: &str) -> RValue {
+    Index::new(table.clone().into(), Literal::String(key.into()).into()).into()
+}
+
+fn print(local: &RcLocal) -> Statement {
+    Call::new(
+        Global::new(b"print".to_vec()).into(),
+        vec![local.clone().into()],
+    )
+    .into()
+}
+
+fn ret(locals: &[&RcLocal]) -> Statement {
+    Return::new(locals.iter().map(|&l| l.clone().into()).collect()).into()
+}
+
+// a function of one block holding `statements`
+fn function(parameters: Vec<RcLocal>, statements: Vec<Statement>) -> Function {
+    let mut function = Function::new(0);
+    function.parameters = parameters;
+    let entry = function.new_block();
+    function.set_entry(entry);
+    function.block_mut(entry).unwrap().extend(statements);
+    function
+}
+
+fn entry_block(function: &Function) -> &ast::Block {
+    function.block(function.entry().unwrap()).unwrap()
+}
+
+#[test]
+fn arithmetic_on_numbers_is_numbered() {
+    let x = RcLocal::default();
+    let first = RcLocal::default();
+    let second = RcLocal::default();
+    let mut function = function(
+        Vec::new(),
+        vec![
+            assign(&x, number(5.0)),
+            assign(&first, double(&x)),
+            print(&first),
+            assign(&second, double(&x)),
+            ret(&[&second]),
+        ],
+    );
+    assert!(gvn(&mut function, &IndexMap::new()));
+    let block = entry_block(&function);
+    assert_eq!(block.len(), 4);
+    assert_eq!(block[3], ret(&[&first]));
+}
+
+#[test]
+fn duplicate_keeps_its_name() {
+    let x = RcLocal::default();
+    let first = RcLocal::default();
+    let second = RcLocal::new(Local::new(Some("doubled".into())));
+    let mut function = function(
+        Vec::new(),
+        vec![
+            assign(&x, number(5.0)),
+            assign(&first, double(&x)),
+            assign(&second, double(&x)),
+            ret(&[&first, &second]),
+        ],
+    );
+    assert!(gvn(&mut function, &IndexMap::new()));
+    assert_eq!(first.0.lock().0.as_deref(), Some("doubled"));
+    assert_eq!(entry_block(&function)[2], ret(&[&first, &first]));
+
+    // two named locals are different variables in the source
+    let first = RcLocal::new(Local::new(Some("a".into())));
+    let second = RcLocal::new(Local::new(Some("b".into())));
+    let mut function = self::function(
+        Vec::new(),
+        vec![
+            assign(&x, number(5.0)),
+            assign(&first, double(&x)),
+            assign(&second, double(&x)),
+            ret(&[&first, &second]),
+        ],
+    );
+    assert!(!gvn(&mut function, &IndexMap::new()));
+}
+
+#[test]
+fn expressions_that_may_run_code_are_not_numbered() {
+    let parameter = RcLocal::default();
+    let locals = (0..6).map(|_| RcLocal::default()).collect::<Vec<_>>();
+    let mut function = function(
+        vec![parameter.clone()],
+        vec![
+            // __mul
+            assign(&locals[0], double(&parameter)),
+            assign(&locals[1], double(&parameter)),
+            // __index
+            assign(&locals[2], index(&parameter, "field")),
+            assign(&locals[3], index(&parameter, "field")),
+            // __eq
+            assign(
+                &locals[4],
+                Binary::new(
+                    parameter.clone().into(),
+                    locals[0].clone().into(),
+                    BinaryOperation::Equal,
+                )
+                .into(),
+            ),
+            assign(
+                &locals[5],
+                Binary::new(
+                    parameter.clone().into(),
+                    locals[0].clone().into(),
+                    BinaryOperation::Equal,
+                )
+                .into(),
+            ),
+            ret(&locals.iter().collect::<Vec<_>>()),
+        ],
+    );
+    assert!(!gvn(&mut function, &IndexMap::new()));
+}
+
+#[test]
+fn upvalues_are_not_numbered() {
+    let upvalue = RcLocal::default();
+    let first = RcLocal::default();
+    let second = RcLocal::default();
+    let not = || Unary::new(upvalue.clone().into(), UnaryOperation::Not).into();
+    let mut function = function(
+        Vec::new(),
+        vec![
+            assign(&first, not()),
+            print(&first),
+            assign(&second, not()),
+            ret(&[&second]),
+        ],
+    );
+    let upvalue_to_group = IndexMap::from_iter([(upvalue.clone(), upvalue.clone())]);
+    assert!(!gvn(&mut function, &upvalue_to_group));
+    assert!(gvn(&mut function, &IndexMap::new()));
+}
+
+#[test]
+fn tables_that_are_only_indexed_are_numbered() {
+    let table = RcLocal::default();
+    let first = RcLocal::default();
+    let second = RcLocal::default();
+    let statements = vec![
+        assign(
+            &table,
+            Table(vec![(
+                Some(Literal::String(b"field".to_vec()).into()),
+                number(1.0),
+            )])
+            .into(),
+        ),
+        assign(&first, index(&table, "field")),
+        assign(&second, index(&table, "field")),
+        ret(&[&first, &second]),
+    ];
+    let mut function = function(Vec::new(), statements.clone());
+    assert!(gvn(&mut function, &IndexMap::new()));
+    assert_eq!(entry_block(&function)[2], ret(&[&first, &first]));
+
+    // once the table escapes it could be given a metatable or written to
+    let mut statements = statements;
+    statements.insert(1, print(&table));
+    let mut function = self::function(Vec::new(), statements);
+    assert!(!gvn(&mut function, &IndexMap::new()));
+}
+
+#[test]
+fn values_are_only_reused_where_they_dominate() {
+    let x = RcLocal::default();
+    let condition = RcLocal::default();
+    let then_local = RcLocal::default();
+    let else_local = RcLocal::default();
+    let exit_local = RcLocal::default();
+
+    let mut function = function(
+        vec![condition.clone()],
+        vec![
+            assign(&x, number(5.0)),
+            If::new(
+                condition.clone().into(),
+                Default::default(),
+                Default::default(),
+            )
+            .into(),
+        ],
+    );
+    let entry = function.entry().unwrap();
+    let then_node = function.new_block();
+    let else_node = function.new_block();
+    let exit = function.new_block();
+    function
+        .block_mut(then_node)
+        .unwrap()
+        .extend([assign(&then_local, double(&x)), print(&then_local)]);
+    function
+        .block_mut(else_node)
+        .unwrap()
+        .extend([assign(&else_local, double(&x)), print(&else_local)]);
+    function
+        .block_mut(exit)
+        .unwrap()
+        .extend([assign(&exit_local, double(&x)), ret(&[&exit_local])]);
+    function.set_edges(
+        entry,
+        vec![
+            (then_node, BlockEdge::new(BranchType::Then)),
+            (else_node, BlockEdge::new(BranchType::Else)),
+        ],
+    );
+    for node in [then_node, else_node] {
+        function.set_edges(
+            node,
+            vec![(exit, BlockEdge::new(BranchType::Unconditional))],
+        );
+    }
+
+    assert!(!gvn(&mut function, &IndexMap::new()));
+
+    // once the entry computes it, every branch can use it
+    let first = RcLocal::default();
+    function
+        .block_mut(entry)
+        .unwrap()
+        .insert(1, assign(&first, double(&x)));
+    assert!(gvn(&mut function, &IndexMap::new()));
+    assert_eq!(function.block(then_node).unwrap()[0], print(&first));
+    assert_eq!(function.block(else_node).unwrap()[0], print(&first));
+    assert_eq!(function.block(exit).unwrap()[0], ret(&[&first]));
+}
@@ -0,0 +1,312 @@
+use ast::{
+    formatter::Dialect, parser::parse, Assign, Binary, BinaryOperation, Call, Global, If, Literal,
+    RValue, RcLocal, Return, Statement,
+};
+use cfg::{
+    block::{BlockEdge, BranchType},
+    function::Function,
+    interpreter::{Error, Interpreter, Value},
+    ssa,
+};
+use indexmap::IndexMap;
+use rustc_hash::FxHashMap;
+
+fn assign(local: &RcLocal, value: RValue) -> Statement {
+    Assign::new(vec![local.clone().into()], vec![value]).into()
+}
+
+fn number(n: f64) -> RValue {
+    Literal::Number(n).into()
+}
+
+fn binary(left: &RcLocal, right: RValue, operation: BinaryOperation) -> RValue {
+    Binary::new(left.clone().into(), right, operation).into()
+}
+
+// local total, i = 0, 1
+// while i <= n do
+//     total = total + i * i
+//     print(i, total)
+//     i = i + 1
+// end
+// return total, i
+fn sum_of_squares() -> Function {
+    let n = RcLocal::default();
+    let total = RcLocal::default();
+    let i = RcLocal::default();
+
+    let mut function = Function::new(0);
+    function.parameters.push(n.clone());
+    let entry = function.new_block();
+    let header = function.new_block();
+    let body = function.new_block();
+    let exit = function.new_block();
+    function.set_entry(entry);
+
+    function
+        .block_mut(entry)
+        .unwrap()
+        .extend([assign(&total, number(0.0)), assign(&i, number(1.0))]);
+    function.block_mut(header).unwrap().push(
+        If::new(
+            binary(&i, n.into(), BinaryOperation::LessThanOrEqual),
+            Default::default(),
+            Default::default(),
+        )
+        .into(),
+    );
+    function.block_mut(body).unwrap().extend([
+        assign(
+            &total,
+            Binary::new(
+                total.clone().into(),
+                binary(&i, i.clone().into(), BinaryOperation::Mul),
+                BinaryOperation::Add,
+            )
+            .into(),
+        ),
+        Call::new(
+            Global::new(b"print".to_vec()).into(),
+            vec![i.clone().into(), total.clone().into()],
+        )
+        .into(),
+        assign(&i, binary(&i, number(1.0), BinaryOperation::Add)),
+    ]);
+    function
+        .block_mut(exit)
+        .unwrap()
+        .push(Return::new(vec![total.into(), i.into()]).into());
+
+    function.set_edges(
+        entry,
+        vec![(header, BlockEdge::new(BranchType::Unconditional))],
+    );
+    function.set_edges(
+        header,
+        vec![
+            (body, BlockEdge::new(BranchType::Then)),
+            (exit, BlockEdge::new(BranchType::Else)),
+        ],
+    );
+    function.set_edges(
+        body,
+        vec![(header, BlockEdge::new(BranchType::Unconditional))],
+    );
+    function
+}
+
+fn run(function: &Function, n: f64) -> (Vec<String>, Vec<String>) {
+    let mut interpreter = Interpreter::new();
+    let results = interpreter
+        .execute(function, vec![Value::Number(n)])
+        .unwrap();
+    (
+        results.iter().map(Value::dump).collect(),
+        interpreter.output,
+    )
+}
+
+#[test]
+fn ssa_round_trip_preserves_semantics() {
+    let original = sum_of_squares();
+    let mut transformed = original.clone();
+
+    let (local_count, local_groups, _, _) = ssa::construct(&mut transformed, &Vec::new());
+    let local_to_group = local_groups
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, g)| g.into_iter().map(move |l| (l, i)))
+        .collect::<FxHashMap<_, _>>();
+    for n in [0.0, 1.0, 5.0] {
+        assert_eq!(run(&original, n), run(&transformed, n), "after construct");
+    }
+
+    ssa::inline::inline(&mut transformed, &local_to_group, &IndexMap::new());
+    for n in [0.0, 1.0, 5.0] {
+        assert_eq!(run(&original, n), run(&transformed, n), "after inline");
+    }
+
+    ssa::Destructor::new(
+        &mut transformed,
+        IndexMap::new(),
+        Default::default(),
+        local_count,
+    )
+    .destruct();
+    for n in [0.0, 1.0, 5.0] {
+        assert_eq!(run(&original, n), run(&transformed, n), "after destruct");
+    }
+
+    let (results, output) = run(&transformed, 3.0);
+    assert_eq!(results, ["14", "4"]);
+    assert_eq!(output, ["1\t1", "2\t5", "3\t14"]);
+}
+
+#[test]
+fn step_limit_stops_infinite_loops() {
+    let mut function = Function::new(0);
+    let entry = function.new_block();
+    function.set_entry(entry);
+    function.set_edges(
+        entry,
+        vec![(entry, BlockEdge::new(BranchType::Unconditional))],
+    );
+    let mut interpreter = Interpreter::new().with_step_limit(100);
+    assert!(matches!(
+        interpreter.execute(&function, Vec::new()),
+        Err(cfg::interpreter::Error::StepLimit)
+    ));
+}
+
+// runs `source` as a main function and returns its results, or the error it failed with,
+// followed by everything it printed
+fn execute(source: &str, arguments: Vec<Value>) -> (Result<Vec<String>, String>, Vec<String>) {
+    let body = parse(source, Dialect::Luau).unwrap();
+    let mut interpreter = Interpreter::new();
+    let results = interpreter
+        .execute_chunk(&body, arguments)
+        .map(|values| values.iter().map(Value::dump).collect())
+        .map_err(|error| error.to_string());
+    (results, interpreter.output)
+}
+
+fn results(source: &str) -> Vec<String> {
+    execute(source, Vec::new()).0.unwrap()
+}
+
+#[test]
+fn closures_and_upvalues() {
+    // each counter has its own upvalue, which both of its closures share
+    let source = "local function counter()\n\
+                  \tlocal count = 0\n\
+                  \treturn function() count = count + 1 return count end, function() return count end\n\
+                  end\n\
+                  local increment, get = counter()\n\
+                  local other = counter()\n\
+                  increment() increment() other()\n\
+                  return get(), other()";
+    assert_eq!(results(source), ["2", "2"]);
+
+    // every iteration of a loop has a fresh local
+    let source = "local functions = {}\n\
+                  for i = 1, 3 do\n\
+                  \tfunctions[i] = function() return i end\n\
+                  end\n\
+                  return functions[1](), functions[3]()";
+    assert_eq!(results(source), ["1", "3"]);
+
+    // a local function can call itself
+    let source = "local function fact(n) if n <= 1 then return 1 end return n * fact(n - 1) end\n\
+                  return fact(5)";
+    assert_eq!(results(source), ["120"]);
+}
+
+#[test]
+fn tables() {
+    let source = "local t = {1, 2, x = \"a\", [10] = true, {3}}\n\
+                  t[#t + 1] = 4\n\
+                  t.x = t.x .. \"b\"\n\
+                  local alias = t\n\
+                  alias.y = 5\n\
+                  return #t, t[3][1], t[4], t.x, t.y, t[10], t.missing";
+    assert_eq!(
+        results(source),
+        ["4", "3", "4", "\"ab\"", "5", "true", "nil"]
+    );
+}
+
+#[test]
+fn varargs() {
+    let source = "local function count(...) return select(\"#\", ...) end\n\
+                  local function pass(...) return ... end\n\
+                  local a, b, c = ...\n\
+                  return count(...), count(nil, nil), #{...}, c, (pass(...)), count((...))";
+    assert_eq!(
+        execute(source, vec![Value::Number(7.0), Value::Number(8.0)]).0,
+        Ok(vec![
+            "2".into(),
+            "2".into(),
+            "2".into(),
+            "nil".into(),
+            "7".into(),
+            "1".into()
+        ])
+    );
+}
+
+#[test]
+fn multiple_returns() {
+    // only the last expression of a list is expanded
+    let source = "local function three() return 1, 2, 3 end\n\
+                  local a, b = three()\n\
+                  local t = {three(), three()}\n\
+                  print(three(), (three()))\n\
+                  return a, b, #t, three(), three()";
+    let (results, output) = execute(source, Vec::new());
+    assert_eq!(
+        results,
+        Ok(vec![
+            "1".into(),
+            "2".into(),
+            "4".into(),
+            "1".into(),
+            "1".into(),
+            "2".into(),
+            "3".into()
+        ])
+    );
+    assert_eq!(output, ["1\t1"]);
+}
+
+#[test]
+fn runtime_errors() {
+    for (source, error) in [
+        ("local t = nil\nreturn t.x", "attempt to index a nil value"),
+        ("local t\nt.x = 1", "attempt to index a nil value"),
+        ("local f = 1\nf()", "attempt to call a number value"),
+        (
+            "return {} + 1",
+            "attempt to perform arithmetic on a table value",
+        ),
+        ("return {} < {}", "attempt to compare a table value"),
+        ("error(\"message\")", "message"),
+    ] {
+        assert_eq!(
+            execute(source, Vec::new()).0,
+            Err(error.into()),
+            "{}",
+            source
+        );
+    }
+    // printing happens until the error
+    let (results, output) = execute("print(1)\nlocal x = nil\nx()\nprint(2)", Vec::new());
+    assert!(results.is_err());
+    assert_eq!(output, ["1"]);
+}
+
+#[test]
+fn nan_comparisons() {
+    let source = "local nan = ...\n\
+                  return nan < 1, nan <= 1, nan > 1, nan >= 1, not (nan < nan), nan == nan";
+    assert_eq!(
+        execute(source, vec![Value::Number(f64::NAN)]).0,
+        Ok(vec![
+            "false".into(),
+            "false".into(),
+            "false".into(),
+            "false".into(),
+            "true".into(),
+            "false".into()
+        ])
+    );
+    assert!(matches!(
+        Interpreter::new().execute_chunk(
+            &parse("return 1 < \"x\"", Dialect::Luau).unwrap(),
+            Vec::new()
+        ),
+        Err(Error::Type {
+            operation: "compare",
+            ..
+        })
+    ));
+}
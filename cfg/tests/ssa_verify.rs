@@ -0,0 +1,211 @@
+use ast::{Assign, If, Literal, RValue, RcLocal, Return, Statement};
+use cfg::{
+    block::{BlockEdge, BranchType},
+    function::Function,
+    ssa::{self, verify::Error},
+};
+use petgraph::stable_graph::NodeIndex;
+use rustc_hash::FxHashSet;
+
+fn assign(local: &RcLocal, value: RValue) -> Statement {
+    Assign::new(vec![local.clone().into()], vec![value]).into()
+}
+
+fn number(n: f64) -> RValue {
+    Literal::Number(n).into()
+}
+
+fn edge(branch_type: BranchType, arguments: Vec<(&RcLocal, &RcLocal)>) -> BlockEdge {
+    BlockEdge {
+        branch_type,
+        arguments: arguments
+            .into_iter()
+            .map(|(parameter, argument)| (parameter.clone(), argument.clone().into()))
+            .collect(),
+    }
+}
+
+struct Diamond {
+    function: Function,
+    else_node: NodeIndex,
+    exit: NodeIndex,
+    condition: RcLocal,
+    then_local: RcLocal,
+    else_local: RcLocal,
+    merged: RcLocal,
+}
+
+// if condition then
+//     then_local = 1
+// else
+//     else_local = 2
+// end
+// merged = phi(then_local, else_local)
+// return merged
+fn diamond() -> Diamond {
+    let condition = RcLocal::default();
+    let then_local = RcLocal::default();
+    let else_local = RcLocal::default();
+    let merged = RcLocal::default();
+
+    let mut function = Function::new(0);
+    function.parameters.push(condition.clone());
+    let entry = function.new_block();
+    let then_node = function.new_block();
+    let else_node = function.new_block();
+    let exit = function.new_block();
+    function.set_entry(entry);
+
+    function.block_mut(entry).unwrap().push(
+        If::new(
+            condition.clone().into(),
+            Default::default(),
+            Default::default(),
+        )
+        .into(),
+    );
+    function
+        .block_mut(then_node)
+        .unwrap()
+        .push(assign(&then_local, number(1.0)));
+    function
+        .block_mut(else_node)
+        .unwrap()
+        .push(assign(&else_local, number(2.0)));
+    function
+        .block_mut(exit)
+        .unwrap()
+        .push(Return::new(vec![merged.clone().into()]).into());
+
+    function.set_edges(
+        entry,
+        vec![
+            (then_node, BlockEdge::new(BranchType::Then)),
+            (else_node, BlockEdge::new(BranchType::Else)),
+        ],
+    );
+    function.set_edges(
+        then_node,
+        vec![(
+            exit,
+            edge(BranchType::Unconditional, vec![(&merged, &then_local)]),
+        )],
+    );
+    function.set_edges(
+        else_node,
+        vec![(
+            exit,
+            edge(BranchType::Unconditional, vec![(&merged, &else_local)]),
+        )],
+    );
+
+    Diamond {
+        function,
+        else_node,
+        exit,
+        condition,
+        then_local,
+        else_local,
+        merged,
+    }
+}
+
+fn verify(function: &Function) -> Result<(), Error> {
+    ssa::verify(function, &FxHashSet::default())
+}
+
+#[test]
+fn accepts_ssa() {
+    assert!(verify(&diamond().function).is_ok());
+}
+
+#[test]
+fn local_defined_twice() {
+    let Diamond {
+        mut function,
+        else_node,
+        then_local,
+        ..
+    } = diamond();
+    function
+        .block_mut(else_node)
+        .unwrap()
+        .push(assign(&then_local, number(3.0)));
+    assert!(matches!(
+        verify(&function),
+        Err(Error::DefinedTwice { local, .. }) if local == then_local
+    ));
+}
+
+#[test]
+fn definition_does_not_dominate_use() {
+    let Diamond {
+        mut function,
+        exit,
+        then_local,
+        merged,
+        ..
+    } = diamond();
+    function.block_mut(exit).unwrap()[0] =
+        Return::new(vec![merged.into(), then_local.clone().into()]).into();
+    let error = verify(&function).unwrap_err();
+    assert!(matches!(&error, Error::NotDominated { local, .. } if local == &then_local));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "{} is used by statement 0 of block 3, which its definition by statement 0 of \
+            block 1 doesn't dominate",
+            then_local
+        )
+    );
+}
+
+#[test]
+fn use_of_removed_local() {
+    let Diamond {
+        mut function,
+        condition,
+        ..
+    } = diamond();
+    function.parameters.clear();
+    assert!(matches!(
+        verify(&function),
+        Err(Error::Undefined { local, .. }) if local == condition
+    ));
+    // unless it's an upvalue, which the closure defines
+    let upvalues_in = FxHashSet::from_iter([condition]);
+    assert!(ssa::verify(&function, &upvalues_in).is_ok());
+}
+
+#[test]
+fn edges_pass_different_parameters() {
+    let Diamond {
+        mut function,
+        else_node,
+        exit,
+        else_local,
+        merged,
+        ..
+    } = diamond();
+    let extra = RcLocal::default();
+    function.set_edges(
+        else_node,
+        vec![(
+            exit,
+            edge(
+                BranchType::Unconditional,
+                vec![(&merged, &else_local), (&extra, &else_local)],
+            ),
+        )],
+    );
+    let error = verify(&function).unwrap_err();
+    assert!(matches!(error, Error::ParameterMismatch { .. }));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "the edge from block 1 to block 3 passes ({}), but the edge from block 2 \
+            passes ({}, {})",
+            merged, merged, extra
+        )
+    );
+}
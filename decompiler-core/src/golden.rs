@@ -0,0 +1,67 @@
+//! The golden-file tests every frontend runs over its fixtures. Each bytecode file is
+//! decompiled and the output compared against the `.lua` file next to it. Setting `BLESS`
+//! rewrites the expected output instead. Output that doesn't parse is never accepted. Only
+//! built with the `golden` feature, which the frontends enable for their tests.
+
+use std::{env, fs, path::Path};
+
+use ast::{formatter::Dialect, parser::parse};
+
+/// Decompiles every file in `fixtures` with `extension` into source for `dialect` and panics
+/// with every fixture that fails to decompile, whose output doesn't parse or differs from
+/// its `.lua` file.
+pub fn check(
+    fixtures: &Path,
+    extension: &str,
+    dialect: Dialect,
+    decompile: impl Fn(&[u8]) -> Result<String, String>,
+) {
+    let bless = env::var_os("BLESS").is_some();
+    let mut paths = fs::read_dir(fixtures)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == extension))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", fixtures.display());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let bytecode = fs::read(&path).unwrap();
+        let output = match decompile(&bytecode) {
+            Ok(output) => output,
+            Err(error) => {
+                failures.push(format!("{} fails to decompile: {}", path.display(), error));
+                continue;
+            }
+        };
+        let expected_path = path.with_extension("lua");
+        if let Err(error) = parse(&output, dialect) {
+            failures.push(format!(
+                "{} doesn't parse: {}\n{}",
+                path.display(),
+                error,
+                output
+            ));
+            continue;
+        }
+        if bless {
+            fs::write(&expected_path, &output).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == output => {}
+            Ok(expected) => failures.push(format!(
+                "{} differs\n--- expected\n{}\n--- actual\n{}",
+                path.display(),
+                expected,
+                output
+            )),
+            Err(_) => failures.push(format!(
+                "{} has no expected output, run with BLESS=1",
+                path.display()
+            )),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,452 @@
+//! The decompilation pipeline shared by every bytecode format. A [`Frontend`] lifts a chunk
+//! into control flow graphs, which are then taken through ssa construction, structuring,
+//! ssa destruction and restructuring before the closures are linked back together.
+
+use std::{
+    backtrace::Backtrace,
+    cell::RefCell,
+    fmt::{self, Write},
+    panic,
+};
+
+use ast::{
+    formatter::{Dialect, FormatOptions, Formatter},
+    local_declarations::LocalDeclarer,
+    lower_dialect::lower_dialect,
+    name_gen::DefaultNameGenerator,
+    name_locals::name_locals,
+    naming_rules::NamingRules,
+    replace_locals::{fail_on_goto, replace_locals},
+    Traverse,
+};
+use by_address::ByAddress;
+use cfg::{
+    function::Function,
+    ssa::{
+        self,
+        structuring::{structure_conditionals, structure_jumps, structure_method_calls},
+    },
+};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use petgraph::algo::dominators::simple_fast;
+use rustc_hash::{FxHashMap, FxHashSet};
+use triomphe::Arc;
+
+#[cfg(feature = "golden")]
+pub mod golden;
+
+/// A lifted function: the ast function closures refer to it by, its control flow graph and
+/// the locals standing in for its upvalues.
+pub type LiftedFunction = (Arc<Mutex<ast::Function>>, Function, Vec<ast::RcLocal>);
+
+/// A bytecode format, which only has to lift its chunks into control flow graphs.
+pub trait Frontend {
+    /// Lifts every function in `bytecode`, the main function first. A closure refers to the
+    /// function it creates by that function's ast function, which is how the upvalues of
+    /// each closure are linked to the locals it captures. Returns an error if the bytecode
+    /// can't be parsed or holds the compiler's error message instead of a chunk.
+    fn lift(&self, bytecode: &[u8]) -> Result<Vec<LiftedFunction>, String>;
+
+    /// Whether `a.b(a, ...)` may be structured into `a:b(...)`, which isn't the same call
+    /// when the format has a dedicated method call instruction.
+    fn structure_method_calls(&self) -> bool {
+        true
+    }
+}
+
+/// A point in the pipeline at which a [`CfgHook`] is shown the control flow graph of each
+/// function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// As the frontend lifted it.
+    Lifted,
+    /// After ssa construction, before any structuring.
+    Constructed,
+    /// After global value numbering, if it is enabled.
+    ValueNumbered,
+    /// After an iteration of structuring and inlining, counting from 0.
+    Iteration(usize),
+    /// After ssa destruction, which is the graph restructuring starts from.
+    Destructed,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Lifted => write!(f, "lifted"),
+            Self::Constructed => write!(f, "constructed"),
+            Self::ValueNumbered => write!(f, "value-numbered"),
+            Self::Iteration(iteration) => write!(f, "iteration-{}", iteration),
+            Self::Destructed => write!(f, "destructed"),
+        }
+    }
+}
+
+/// Called with the id of a function, its debug name if it has one, a [`Stage`] and the
+/// function's control flow graph at that stage. Functions are numbered in the order the
+/// frontend lifted them, so the main function is 0.
+#[derive(Clone)]
+pub struct CfgHook(std::sync::Arc<CfgHookFn>);
+
+type CfgHookFn = dyn Fn(usize, Option<&str>, Stage, &Function) + Send + Sync;
+
+impl CfgHook {
+    pub fn new(
+        hook: impl Fn(usize, Option<&str>, Stage, &Function) + Send + Sync + 'static,
+    ) -> Self {
+        Self(std::sync::Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for CfgHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CfgHook")
+    }
+}
+
+/// How bytecode is decompiled.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub dialect: Dialect,
+    /// The rules locals are named by.
+    pub naming_rules: NamingRules,
+    pub format_options: FormatOptions,
+    /// Shown the control flow graph of every function at each stage of the pipeline, for
+    /// debugging structuring.
+    pub cfg_hook: Option<CfgHook>,
+    /// Whether functions are checked to still be in ssa form after every stage that
+    /// changes them, which debug builds always do. A function that isn't fails to
+    /// decompile with the invariant it broke.
+    pub verify_ssa: bool,
+    /// Whether pure expressions computed more than once are reused after ssa construction.
+    pub value_numbering: bool,
+}
+
+impl Options {
+    pub fn new(dialect: Dialect) -> Self {
+        Self {
+            dialect,
+            naming_rules: NamingRules::default(),
+            format_options: FormatOptions::default(),
+            cfg_hook: None,
+            verify_ssa: cfg!(debug_assertions),
+            value_numbering: true,
+        }
+    }
+}
+
+/// Decompiles `bytecode` into source for `dialect`. Goto-capable dialects keep
+/// unstructurable control flow as `goto`s instead of failing. Returns an error if the
+/// bytecode can't be parsed or holds the compiler's error message instead of a chunk.
+pub fn decompile_bytecode(
+    frontend: &impl Frontend,
+    bytecode: &[u8],
+    dialect: Dialect,
+) -> Result<String, String> {
+    decompile_bytecode_with_options(frontend, bytecode, &Options::new(dialect))
+}
+
+/// Decompiles `bytecode` with `options`, see [`decompile_bytecode`].
+pub fn decompile_bytecode_with_options(
+    frontend: &impl Frontend,
+    bytecode: &[u8],
+    options: &Options,
+) -> Result<String, String> {
+    let body = decompile_to_ast(frontend, bytecode, options)?;
+    let mut output = String::new();
+    Formatter::format(
+        &body,
+        &mut output,
+        options.format_options.clone(),
+        options.dialect,
+    )
+    .unwrap();
+    Ok(output)
+}
+
+/// Decompiles `bytecode` into the body of its main function, with its locals named and
+/// lowered to `options.dialect`, as it would be formatted. Fails like
+/// [`decompile_bytecode`].
+pub fn decompile_to_ast(
+    frontend: &impl Frontend,
+    bytecode: &[u8],
+    options: &Options,
+) -> Result<ast::Block, String> {
+    let mut body = lift(
+        frontend,
+        bytecode,
+        options.cfg_hook.as_ref(),
+        options.verify_ssa,
+        options.value_numbering,
+    )?;
+    fail_on_goto(&body, options.dialect)?;
+    lower_dialect(&mut body, options.dialect);
+    let generator = DefaultNameGenerator {
+        debug_names: true,
+        rules: options.naming_rules.clone(),
+    };
+    name_locals(&mut body, &generator);
+    Ok(body)
+}
+
+/// Lifts `bytecode` into the body of its main function, before locals are named and
+/// before any dialect specific lowering. Fails like [`decompile_bytecode`].
+pub fn lift_bytecode(frontend: &impl Frontend, bytecode: &[u8]) -> Result<ast::Block, String> {
+    lift(frontend, bytecode, None, cfg!(debug_assertions), true)
+}
+
+fn lift(
+    frontend: &impl Frontend,
+    bytecode: &[u8],
+    cfg_hook: Option<&CfgHook>,
+    verify_ssa: bool,
+    value_numbering: bool,
+) -> Result<ast::Block, String> {
+    ast::reset_local_ids();
+    let lifted = frontend.lift(bytecode)?;
+    let structure_method_calls = frontend.structure_method_calls();
+
+    let (main, ..) = lifted.first().unwrap().clone();
+    let mut upvalues = lifted
+        .into_iter()
+        .enumerate()
+        .map(|(id, (ast_function, function, upvalues_in))| {
+            thread_local! {
+                static BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+            }
+
+            let mut args =
+                panic::AssertUnwindSafe(Some((ast_function.clone(), function, upvalues_in)));
+            let cfg_hook = panic::AssertUnwindSafe(cfg_hook.map(|hook| (hook, id)));
+
+            let prev_hook = panic::take_hook();
+            panic::set_hook(Box::new(|_| {
+                let trace = Backtrace::capture();
+                BACKTRACE.with(move |b| b.borrow_mut().replace(trace));
+            }));
+            let result = panic::catch_unwind(move || {
+                let (ast_function, function, upvalues_in) = args.take().unwrap();
+                decompile_function(
+                    ast_function,
+                    function,
+                    upvalues_in,
+                    structure_method_calls,
+                    *cfg_hook,
+                    verify_ssa,
+                    value_numbering,
+                )
+            });
+            panic::set_hook(prev_hook);
+
+            match result {
+                Ok(r) => r,
+                Err(payload) => {
+                    let mut message = String::new();
+                    writeln!(message, "failed to decompile").unwrap();
+                    if let Some(InvalidSsa(stage, error)) = payload.downcast_ref() {
+                        writeln!(message, "ssa is invalid after stage {}: {}", stage, error)
+                            .unwrap();
+                    }
+                    // if let Some(backtrace) = BACKTRACE.with(|b| b.borrow_mut().take()) {
+                    //     write!(message, "stack backtrace:\n{}", backtrace).unwrap();
+                    // }
+
+                    ast_function.lock().body.extend(
+                        message
+                            .trim_end()
+                            .split('\n')
+                            .map(|s| ast::Comment::new(s.to_string()).into()),
+                    );
+                    (ByAddress(ast_function), Vec::new())
+                }
+            }
+        })
+        .collect::<FxHashMap<_, _>>();
+
+    let main = ByAddress(main);
+    upvalues.remove(&main);
+    let mut body = Arc::try_unwrap(main.0).unwrap().into_inner().body;
+    link_upvalues(&mut body, &upvalues);
+    Ok(body)
+}
+
+fn decompile_function(
+    ast_function: Arc<Mutex<ast::Function>>,
+    mut function: Function,
+    upvalues_in: Vec<ast::RcLocal>,
+    method_calls: bool,
+    cfg_hook: Option<(&CfgHook, usize)>,
+    verify_ssa: bool,
+    value_numbering: bool,
+) -> (ByAddress<Arc<Mutex<ast::Function>>>, Vec<ast::RcLocal>) {
+    let name = ast_function.lock().name.clone();
+    let show = |stage, function: &Function| {
+        if let Some((hook, id)) = cfg_hook {
+            (hook.0)(id, name.as_deref(), stage, function);
+        }
+    };
+    show(Stage::Lifted, &function);
+    let (local_count, local_groups, upvalue_in_groups, upvalue_passed_groups) =
+        cfg::ssa::construct(&mut function, &upvalues_in);
+    name_copies(&function);
+    show(Stage::Constructed, &function);
+    let upvalue_versions = upvalue_in_groups
+        .iter()
+        .flat_map(|(_, g)| g.iter().cloned())
+        .collect::<FxHashSet<_>>();
+    let verify = |stage, function: &Function| {
+        if verify_ssa && let Err(error) = ssa::verify(function, &upvalue_versions) {
+            panic::panic_any(InvalidSsa(stage, error.to_string()));
+        }
+    };
+    verify(Stage::Constructed, &function);
+    let upvalue_to_group = upvalue_in_groups
+        .into_iter()
+        .chain(
+            upvalue_passed_groups
+                .into_iter()
+                .map(|m| (ast::RcLocal::default(), m)),
+        )
+        .flat_map(|(i, g)| g.into_iter().map(move |u| (u, i.clone())))
+        .collect::<IndexMap<_, _>>();
+    // TODO: do we even need this?
+    let local_to_group = local_groups
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, g)| g.into_iter().map(move |l| (l, i)))
+        .collect::<FxHashMap<_, _>>();
+    if value_numbering {
+        ssa::gvn::gvn(&mut function, &upvalue_to_group);
+        show(Stage::ValueNumbered, &function);
+        verify(Stage::ValueNumbered, &function);
+    }
+    // TODO: REFACTOR: some way to write a macro that states
+    // if cfg::ssa::inline results in change then structure_jumps, structure_compound_conditionals,
+    // structure_for_loops and remove_unnecessary_params must run again.
+    // if structure_compound_conditionals results in change then dominators and post dominators
+    // must be recalculated.
+    // etc.
+    // the macro could also maybe generate an optimal ordering?
+    let mut changed = true;
+    let mut iteration = 0;
+    while changed {
+        changed = false;
+
+        let dominators = simple_fast(function.graph(), function.entry().unwrap());
+        changed |= structure_jumps(&mut function, &dominators);
+
+        ssa::inline::inline(&mut function, &local_to_group, &upvalue_to_group);
+
+        if structure_conditionals(&mut function)
+        // || {
+        //     let post_dominators = post_dominators(function.graph_mut());
+        //     structure_for_loops(&mut function, &dominators, &post_dominators)
+        // }
+            || (method_calls && structure_method_calls(&mut function))
+        {
+            changed = true;
+        }
+        let mut local_map = FxHashMap::default();
+        // TODO: loop until returns false?
+        if ssa::construct::remove_unnecessary_params(&mut function, &mut local_map) {
+            changed = true;
+        }
+        ssa::construct::apply_local_map(&mut function, local_map);
+        show(Stage::Iteration(iteration), &function);
+        verify(Stage::Iteration(iteration), &function);
+        iteration += 1;
+    }
+    ssa::Destructor::new(
+        &mut function,
+        upvalue_to_group,
+        upvalues_in.iter().cloned().collect(),
+        local_count,
+    )
+    .destruct();
+    show(Stage::Destructed, &function);
+
+    let params = std::mem::take(&mut function.parameters);
+    let is_variadic = function.is_variadic;
+    let block = Arc::new(restructure::lift(function).into());
+    LocalDeclarer::default().declare_locals(
+        // TODO: why does block.clone() not work?
+        Arc::clone(&block),
+        &upvalues_in.iter().chain(params.iter()).cloned().collect(),
+    );
+
+    {
+        let mut ast_function = ast_function.lock();
+        ast_function.body = Arc::try_unwrap(block).unwrap().into_inner();
+        ast_function.parameters = params;
+        ast_function.is_variadic = is_variadic;
+    }
+    (ByAddress(ast_function), upvalues_in)
+}
+
+// the panic of a function that broke an invariant of ssa form
+struct InvalidSsa(Stage, String);
+
+// copies of named locals into registers captured by a closure aren't propagated by ssa
+// construction, the register takes the name instead so it survives the copy being inlined
+fn name_copies(function: &Function) {
+    for (_, block) in function.blocks() {
+        for statement in block.iter() {
+            if let ast::Statement::Assign(assign) = statement
+                && let [ast::LValue::Local(to)] = &assign.left[..]
+                && let [ast::RValue::Local(from)] = &assign.right[..]
+                && to != from
+                && to.0.lock().0.is_none()
+            {
+                let name = from.0.lock().0.clone();
+                to.0.lock().0 = name;
+            }
+        }
+    }
+}
+
+// replaces the upvalues of every closure with the locals captured for them. a function
+// that failed to decompile has no upvalues left to replace.
+fn link_upvalues(
+    body: &mut ast::Block,
+    upvalues: &FxHashMap<ByAddress<Arc<Mutex<ast::Function>>>, Vec<ast::RcLocal>>,
+) {
+    for stat in &mut body.0 {
+        stat.traverse_rvalues(&mut |rvalue| {
+            if let ast::RValue::Closure(closure) = rvalue {
+                let old_upvalues = &upvalues[&closure.function];
+                let mut function = closure.function.lock();
+                // TODO: inefficient, try constructing a map of all up -> new up first
+                // and then call replace_locals on main body
+                let local_map = old_upvalues
+                    .iter()
+                    .cloned()
+                    .zip(closure.upvalues.iter().map(|u| match u {
+                        ast::Upvalue::Copy(l) | ast::Upvalue::Ref(l) => l.clone(),
+                    }))
+                    .collect::<FxHashMap<_, _>>();
+                link_upvalues(&mut function.body, upvalues);
+                replace_locals(&mut function.body, &local_map);
+            }
+        });
+        match stat {
+            ast::Statement::If(r#if) => {
+                link_upvalues(&mut r#if.then_block.lock(), upvalues);
+                link_upvalues(&mut r#if.else_block.lock(), upvalues);
+            }
+            ast::Statement::While(r#while) => {
+                link_upvalues(&mut r#while.block.lock(), upvalues);
+            }
+            ast::Statement::Repeat(repeat) => {
+                link_upvalues(&mut repeat.block.lock(), upvalues);
+            }
+            ast::Statement::NumericFor(numeric_for) => {
+                link_upvalues(&mut numeric_for.block.lock(), upvalues);
+            }
+            ast::Statement::GenericFor(generic_for) => {
+                link_upvalues(&mut generic_for.block.lock(), upvalues);
+            }
+            _ => {}
+        }
+    }
+}
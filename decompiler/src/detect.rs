@@ -0,0 +1,205 @@
+use std::{borrow::Cow, fmt};
+
+use base64::{
+    alphabet,
+    engine::{
+        general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+        DecodePaddingMode,
+    },
+    Engine,
+};
+use luau_lifter::Luau;
+use thiserror::Error;
+
+const LUA_SIGNATURE: &[u8] = b"\x1bLua";
+const LUAJIT_SIGNATURE: &[u8] = b"\x1bLJ";
+
+// base64 is often pasted without its padding
+const BASE64_CONFIG: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const BASE64_ENGINES: [GeneralPurpose; 2] = [
+    GeneralPurpose::new(&alphabet::STANDARD, BASE64_CONFIG),
+    GeneralPurpose::new(&alphabet::URL_SAFE, BASE64_CONFIG),
+];
+
+/// A bytecode format with a frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Lua51,
+    Lua54,
+    LuaJit,
+    /// Luau, read by the frontend it holds, which knows the key its opcodes are encoded
+    /// with. Detection can't tell the key, so detected bytecode is assumed not encoded.
+    Luau(Luau),
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Lua51 => "Lua 5.1",
+            Self::Lua54 => "Lua 5.4",
+            Self::LuaJit => "LuaJIT",
+            Self::Luau(_) => "Luau",
+        })
+    }
+}
+
+/// How the bytecode was written in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Binary,
+    Hex,
+    Base64,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "binary",
+            Self::Hex => "hex",
+            Self::Base64 => "base64",
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("could not identify the input as bytecode")]
+    Unidentified,
+    #[error("could not identify the input, it reads as both {0} and {1}")]
+    Ambiguous(String, String),
+    #[error("{0} bytecode is not supported")]
+    Unsupported(String),
+    #[error("{0}")]
+    Decompile(String),
+}
+
+/// The bytecode found in an input, decoded from its text form if it had one.
+#[derive(Debug)]
+pub struct Detected<'a> {
+    pub format: Format,
+    pub encoding: Encoding,
+    pub bytecode: Cow<'a, [u8]>,
+}
+
+/// Identifies the bytecode format of `input`, which may also be hex or base64 text.
+pub fn detect(input: &[u8]) -> Result<Detected<'_>, Error> {
+    if let Some(format) = identify(input)? {
+        return Ok(Detected {
+            format,
+            encoding: Encoding::Binary,
+            bytecode: Cow::Borrowed(input),
+        });
+    }
+
+    // text may be split over several lines
+    let text = input
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    let mut decoded = Vec::new();
+    if let Ok(bytecode) = hex::decode(&text) {
+        decoded.push((Encoding::Hex, bytecode));
+    }
+    if let Some(bytecode) = BASE64_ENGINES
+        .iter()
+        .find_map(|engine| engine.decode(&text).ok())
+    {
+        decoded.push((Encoding::Base64, bytecode));
+    }
+
+    let mut candidates = Vec::new();
+    for (encoding, bytecode) in decoded {
+        if let Some(format) = identify(&bytecode)? {
+            candidates.push(Detected {
+                format,
+                encoding,
+                bytecode: Cow::Owned(bytecode),
+            });
+        }
+    }
+    match <[_; 2]>::try_from(candidates) {
+        Ok([a, b]) => Err(Error::Ambiguous(
+            format!("{} {}", a.encoding, a.format),
+            format!("{} {}", b.encoding, b.format),
+        )),
+        Err(mut candidates) => candidates.pop().ok_or(Error::Unidentified),
+    }
+}
+
+// the format of binary bytecode, or an error for a recognized format without a frontend
+fn identify(bytecode: &[u8]) -> Result<Option<Format>, Error> {
+    if let Some(rest) = bytecode.strip_prefix(LUA_SIGNATURE) {
+        return match rest.first() {
+            Some(0x51) => Ok(Some(Format::Lua51)),
+            Some(0x54) => Ok(Some(Format::Lua54)),
+            Some(&version) => Err(Error::Unsupported(format!(
+                "Lua {}.{}",
+                version >> 4,
+                version & 0xF
+            ))),
+            None => Ok(None),
+        };
+    }
+    if let Some(rest) = bytecode.strip_prefix(LUAJIT_SIGNATURE) {
+        return match rest.first() {
+            Some(1 | 2) => Ok(Some(Format::LuaJit)),
+            Some(&version) => Err(Error::Unsupported(format!("LuaJIT version {}", version))),
+            None => Ok(None),
+        };
+    }
+    Ok(is_luau(bytecode).then(|| Format::Luau(Luau::default())))
+}
+
+// luau bytecode starts with its version, or 0 followed by the compiler's error message
+fn is_luau(bytecode: &[u8]) -> bool {
+    match bytecode.split_first() {
+        Some((0, message)) => std::str::from_utf8(message).is_ok_and(|message| {
+            !message.is_empty()
+                && message
+                    .chars()
+                    .all(|c| !c.is_control() || c.is_ascii_whitespace())
+        }),
+        Some((&version @ 3..=6, rest)) => has_string_table(rest, version),
+        _ => false,
+    }
+}
+
+// whether the string table at the start of a luau chunk fits in it, with the functions
+// following it
+fn has_string_table(mut input: &[u8], version: u8) -> bool {
+    if version >= 4 {
+        match input.split_first() {
+            Some((&types_version, rest)) if types_version <= 3 => input = rest,
+            _ => return false,
+        }
+    }
+    let Some(count) = read_uleb128(&mut input) else {
+        return false;
+    };
+    // every string takes at least the byte of its length
+    if count > input.len() {
+        return false;
+    }
+    for _ in 0..count {
+        match read_uleb128(&mut input) {
+            Some(length) if length <= input.len() => input = &input[length..],
+            _ => return false,
+        }
+    }
+    !input.is_empty()
+}
+
+fn read_uleb128(input: &mut &[u8]) -> Option<usize> {
+    let mut value = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        value |= ((byte & 0x7F) as usize).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
@@ -0,0 +1,94 @@
+//! A single entry point for every supported bytecode format, which is identified from the
+//! input itself.
+
+mod detect;
+
+use std::{fs, path::PathBuf};
+
+use ast::{formatter::Dialect, Block};
+pub use ast::formatter::{FormatOptions, QuoteStyle, Semicolons, StringEscaping, TableLayout};
+pub use ast::naming_rules::NamingRules;
+use decompiler_core::{decompile_bytecode_with_options, decompile_to_ast};
+pub use decompiler_core::{CfgHook, Options, Stage};
+pub use detect::{detect, Detected, Encoding, Error, Format};
+pub use luau_lifter::Luau;
+
+impl Format {
+    /// The dialect of the language this format is compiled from.
+    pub fn dialect(self) -> Dialect {
+        match self {
+            Self::Lua51 => Dialect::Lua51,
+            Self::Lua54 => Dialect::Lua54,
+            Self::LuaJit => Dialect::Lua52,
+            Self::Luau(_) => Dialect::Luau,
+        }
+    }
+
+    /// Decompiles `bytecode` of this format into source for the format's own dialect, or
+    /// the error it fails with.
+    pub fn decompile(self, bytecode: &[u8]) -> Result<String, String> {
+        self.decompile_with_options(bytecode, &Options::new(self.dialect()))
+    }
+
+    /// Decompiles `bytecode` of this format with `options`.
+    pub fn decompile_with_options(
+        self,
+        bytecode: &[u8],
+        options: &Options,
+    ) -> Result<String, String> {
+        match self {
+            Self::Lua51 => decompile_bytecode_with_options(&lua51_lifter::Lua51, bytecode, options),
+            Self::Lua54 => decompile_bytecode_with_options(&lua54_lifter::Lua54, bytecode, options),
+            Self::LuaJit => {
+                decompile_bytecode_with_options(&luajit_lifter::LuaJit, bytecode, options)
+            }
+            Self::Luau(luau) => decompile_bytecode_with_options(&luau, bytecode, options),
+        }
+    }
+
+    /// Decompiles `bytecode` of this format into the ast it would be formatted from, or
+    /// the compiler's error message if the bytecode holds one.
+    pub fn decompile_to_ast(self, bytecode: &[u8], options: &Options) -> Result<Block, String> {
+        match self {
+            Self::Lua51 => decompile_to_ast(&lua51_lifter::Lua51, bytecode, options),
+            Self::Lua54 => decompile_to_ast(&lua54_lifter::Lua54, bytecode, options),
+            Self::LuaJit => decompile_to_ast(&luajit_lifter::LuaJit, bytecode, options),
+            Self::Luau(luau) => decompile_to_ast(&luau, bytecode, options),
+        }
+    }
+}
+
+/// Identifies the format of `input`, which may be binary, hex or base64 bytecode, and
+/// decompiles it.
+pub fn decompile(input: &[u8]) -> Result<String, Error> {
+    let detected = detect(input)?;
+    detected
+        .format
+        .decompile(&detected.bytecode)
+        .map_err(Error::Decompile)
+}
+
+/// A [`CfgHook`] that writes the control flow graph of every function at each stage to
+/// `<directory>/<id>-<name>-<stage>.dot` in the DOT language, without the name for
+/// functions that have none. Files that can't be written are reported on stderr.
+pub fn dump_cfg(directory: impl Into<PathBuf>) -> CfgHook {
+    let directory = directory.into();
+    CfgHook::new(move |id, name, stage, function| {
+        let file_name = match name {
+            Some(name) => {
+                let name = name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect::<String>();
+                format!("{}-{}-{}.dot", id, name, stage)
+            }
+            None => format!("{}-{}.dot", id, stage),
+        };
+        let path = directory.join(file_name);
+        let result =
+            fs::File::create(&path).and_then(|mut file| cfg::dot::render_to(function, &mut file));
+        if let Err(error) = result {
+            eprintln!("failed to write {}: {}", path.display(), error);
+        }
+    })
+}
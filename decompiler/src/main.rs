@@ -0,0 +1,213 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Context;
+use ast::formatter::Dialect;
+use clap::{Parser, ValueEnum};
+use decompiler::{
+    Format, FormatOptions, Luau, NamingRules, Options, QuoteStyle, Semicolons, StringEscaping,
+    TableLayout,
+};
+
+/// What is written for the decompiled bytecode.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Emit {
+    /// Lua source, to `<file>.dec.lua`
+    Lua,
+    /// The decompiled ast as JSON, to `<file>.ast.json`
+    AstJson,
+}
+
+impl Emit {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Lua => "dec.lua",
+            Self::AstJson => "ast.json",
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// Lua 5.1, Lua 5.4, LuaJIT or Luau bytecode, as binary, hex or base64
+    #[clap(short, long)]
+    file: String,
+    /// What to write for the bytecode
+    #[clap(long, value_enum, default_value_t = Emit::Lua)]
+    emit: Emit,
+    /// Write the output to this file instead of next to the input, `-` for stdout
+    #[clap(short, long, conflicts_with = "out_dir")]
+    output: Option<PathBuf>,
+    /// Write the output to this directory instead of next to the input
+    #[clap(long)]
+    out_dir: Option<PathBuf>,
+    /// Comment written before Lua output, `{file}`, `{encoding}`, `{format}` and `{time}` are
+    /// replaced with the input, how its bytecode was written, its format and how long it
+    /// took to decompile
+    #[clap(
+        long,
+        default_value = "decompiled from {encoding} {format} by Sentinel (took {time})"
+    )]
+    header: String,
+    /// Don't write a header comment
+    #[clap(long, conflicts_with = "header")]
+    no_header: bool,
+    /// Dialect of the output (luau, lua51, lua52, lua53, lua54), the format's own by default
+    #[clap(short, long)]
+    dialect: Option<Dialect>,
+    /// A TOML or JSON file of rules to name locals by, instead of the rules for Roblox
+    #[clap(long)]
+    naming_rules: Option<PathBuf>,
+    /// How strings are escaped (ascii, utf8)
+    #[clap(long, default_value_t = StringEscaping::Ascii)]
+    string_escaping: StringEscaping,
+    /// Write strings spanning several lines as long strings, ex. `[[...]]`
+    #[clap(long)]
+    long_strings: bool,
+    /// Break argument lists, chains of operators and tables to keep lines within this width
+    #[clap(long)]
+    line_width: Option<usize>,
+    /// Write tables with more fields than this over several lines
+    #[clap(long)]
+    table_threshold: Option<usize>,
+    /// Which quotes strings are written between (double, single, auto-prefer-double,
+    /// auto-prefer-single)
+    #[clap(long, default_value_t = QuoteStyle::Double)]
+    quote_style: QuoteStyle,
+    /// Write a comma after the last field of tables written over several lines
+    #[clap(long)]
+    trailing_commas: bool,
+    /// Where statements are followed by `;` (ambiguous, always)
+    #[clap(long, default_value_t = Semicolons::Ambiguous)]
+    semicolons: Semicolons,
+    /// Write the control flow graph of every function at each stage of decompilation to this
+    /// directory, as `<id>-<name>-<stage>.dot`
+    #[clap(long)]
+    dump_cfg: Option<PathBuf>,
+    /// Check that every function is still in SSA form after each stage, as debug builds
+    /// always do
+    #[clap(long)]
+    verify_ssa: bool,
+    /// Don't reuse pure expressions that are computed more than once
+    #[clap(long)]
+    no_value_numbering: bool,
+    /// The key Luau opcodes are encoded with, 203 for Roblox client bytecode
+    #[clap(long, default_value_t = 1)]
+    luau_encode_key: u8,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let naming_rules = match &args.naming_rules {
+        Some(path) => load_naming_rules(path)?,
+        None => NamingRules::default(),
+    };
+    let path = Path::new(&args.file);
+    let output_path = output_path(&args, path)?;
+    let buffer = fs::read(path)?;
+    if let Some(directory) = &args.dump_cfg {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("failed to create {}", directory.display()))?;
+    }
+
+    let start = Instant::now();
+    let mut detected = decompiler::detect(&buffer)?;
+    if let Format::Luau(luau) = &mut detected.format {
+        *luau = Luau::new(args.luau_encode_key);
+    }
+    let options = Options {
+        naming_rules,
+        format_options: FormatOptions {
+            string_escaping: args.string_escaping,
+            long_strings: args.long_strings,
+            line_width: args.line_width,
+            table_layout: args
+                .table_threshold
+                .map_or(TableLayout::Auto, TableLayout::Threshold),
+            quote_style: args.quote_style,
+            trailing_commas: args.trailing_commas,
+            semicolons: args.semicolons,
+            ..Default::default()
+        },
+        cfg_hook: args.dump_cfg.map(decompiler::dump_cfg),
+        verify_ssa: args.verify_ssa || cfg!(debug_assertions),
+        value_numbering: !args.no_value_numbering,
+        ..Options::new(args.dialect.unwrap_or(detected.format.dialect()))
+    };
+    if let Emit::AstJson = args.emit {
+        let body = detected
+            .format
+            .decompile_to_ast(&detected.bytecode, &options)
+            .map_err(anyhow::Error::msg)?;
+        let json = ast::serialize::to_json(&body)?;
+        return write_output(output_path.as_deref(), &json);
+    }
+    let res = detected
+        .format
+        .decompile_with_options(&detected.bytecode, &options)
+        .map_err(anyhow::Error::msg)?;
+    let duration = start.elapsed();
+
+    let mut output = String::new();
+    if !args.no_header {
+        let header = args
+            .header
+            .replace("{file}", &path.display().to_string())
+            .replace("{encoding}", &detected.encoding.to_string())
+            .replace("{format}", &detected.format.to_string())
+            .replace("{time}", &format!("{:?}", duration));
+        for line in header.lines() {
+            output.push_str("-- ");
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    output.push_str(&res);
+    output.push('\n');
+    write_output(output_path.as_deref(), &output)
+}
+
+// where the output is written, `None` for stdout
+fn output_path(args: &Args, input: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let extension = args.emit.extension();
+    Ok(match (&args.output, &args.out_dir) {
+        (Some(path), _) if path.as_os_str() == "-" => None,
+        (Some(path), _) => Some(path.clone()),
+        (None, Some(out_dir)) => {
+            let file_name = input.file_name().context("input has no file name")?;
+            Some(out_dir.join(file_name).with_extension(extension))
+        }
+        (None, None) => Some(input.with_extension(extension)),
+    })
+}
+
+fn write_output(path: Option<&Path>, output: &str) -> anyhow::Result<()> {
+    let Some(path) = path else {
+        io::stdout().lock().write_all(output.as_bytes())?;
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, output).with_context(|| format!("failed to write {}", path.display()))
+}
+
+// rules files are TOML unless they have a `.json` extension
+fn load_naming_rules(path: &Path) -> anyhow::Result<NamingRules> {
+    let input =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let rules = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        NamingRules::from_json(&input)
+    } else {
+        NamingRules::from_toml(&input)
+    };
+    rules.with_context(|| format!("failed to load {}", path.display()))
+}
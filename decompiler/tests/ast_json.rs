@@ -0,0 +1,47 @@
+//! Writes decompiled bytecode as JSON and reads it back.
+
+use std::{fs, path::Path};
+
+use ast::{
+    formatter::Formatter,
+    serialize::{from_json, to_json},
+    Block,
+};
+use decompiler::{Format, Luau, Options};
+
+const LUAU: Format = Format::Luau(Luau::new(1));
+
+fn format(block: &Block) -> String {
+    let mut output = String::new();
+    Formatter::format(block, &mut output, Default::default(), LUAU.dialect()).unwrap();
+    output
+}
+
+fn round_trip(fixture: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../luau-lifter/tests/fixtures")
+        .join(fixture);
+    let bytecode = fs::read(path).unwrap();
+    let options = Options::new(LUAU.dialect());
+    let block = LUAU.decompile_to_ast(&bytecode, &options).unwrap();
+
+    let json = to_json(&block).unwrap();
+    let read = from_json(&json).unwrap();
+    assert_eq!(format(&read), format(&block));
+    assert_eq!(
+        format(&block),
+        LUAU.decompile_with_options(&bytecode, &options).unwrap()
+    );
+    // ids are numbered the same way every time
+    assert_eq!(to_json(&read).unwrap(), json);
+}
+
+#[test]
+fn closures() {
+    round_trip("closures_v4.luauc");
+}
+
+#[test]
+fn loops() {
+    round_trip("loops_v3.luauc");
+}
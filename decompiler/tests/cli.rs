@@ -0,0 +1,171 @@
+//! Runs the `decompiler` binary on the golden fixtures of the frontends.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn fixture(krate: &str, name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join(krate)
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn expected(krate: &str, fixture_name: &str) -> String {
+    fs::read_to_string(fixture(krate, fixture_name).with_extension("lua")).unwrap() + "\n"
+}
+
+// an empty directory only this test writes to
+fn temp_dir(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("decompiler-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn command(current_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_decompiler"))
+        .current_dir(current_dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run(current_dir: &Path, args: &[&str]) -> Output {
+    let output = command(current_dir, args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn next_to_input() {
+    let directory = temp_dir("next-to-input");
+    let input = directory.join("input/closures_v4.luauc");
+    fs::create_dir_all(input.parent().unwrap()).unwrap();
+    fs::copy(fixture("luau-lifter", "closures_v4.luauc"), &input).unwrap();
+    let current_dir = directory.join("current");
+    fs::create_dir_all(&current_dir).unwrap();
+
+    run(&current_dir, &["--file", input.to_str().unwrap()]);
+    let written = fs::read_to_string(directory.join("input/closures_v4.dec.lua")).unwrap();
+    let (header, output) = written.split_once('\n').unwrap();
+    assert!(
+        header.starts_with("-- decompiled from binary Luau by Sentinel (took "),
+        "{}",
+        header
+    );
+    assert_eq!(output, expected("luau-lifter", "closures_v4"));
+    // nothing is written to the current directory
+    assert_eq!(fs::read_dir(&current_dir).unwrap().count(), 0);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn stdout() {
+    let directory = temp_dir("stdout");
+    let input = fixture("luau-lifter", "closures_v4.luauc");
+    let output = run(
+        &directory,
+        &[
+            "--file",
+            input.to_str().unwrap(),
+            "--output",
+            "-",
+            "--header",
+            "{format} from {file}\nby the tests",
+        ],
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "-- Luau from {}\n-- by the tests\n{}",
+            input.display(),
+            expected("luau-lifter", "closures_v4")
+        )
+    );
+    assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn output_and_out_dir() {
+    let directory = temp_dir("output");
+    let input = fixture("lua51-lifter", "loops.luac");
+    let output = directory.join("nested/loops.lua");
+    run(
+        &directory,
+        &[
+            "--no-header",
+            "--file",
+            input.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        expected("lua51-lifter", "loops")
+    );
+
+    let out_dir = directory.join("out");
+    run(
+        &directory,
+        &[
+            "--emit",
+            "ast-json",
+            "--file",
+            input.to_str().unwrap(),
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+        ],
+    );
+    let json = fs::read_to_string(out_dir.join("loops.ast.json")).unwrap();
+    assert!(ast::serialize::from_json(&json).is_ok());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn dialect() {
+    let directory = temp_dir("dialect");
+    let input = fixture("lua54-lifter", "operators.luac");
+    let decompile = |dialect: &str| {
+        let output = run(
+            &directory,
+            &[
+                "--no-header",
+                "--file",
+                input.to_str().unwrap(),
+                "--output",
+                "-",
+                "--dialect",
+                dialect,
+            ],
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(decompile("lua53"), expected("lua54-lifter", "operators"));
+    let output = decompile("lua52");
+    assert!(output.contains("bit32.band(a, 15)"), "{}", output);
+    assert!(output.contains("math.floor(a / 3)"), "{}", output);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn unparsable_input() {
+    let directory = temp_dir("unparsable");
+    let input = directory.join("garbage.luac");
+    fs::write(&input, b"\x1bLua\x51\x00garbage").unwrap();
+    let output = command(&directory, &["--file", input.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("failed to parse bytecode"), "{}", stderr);
+    assert!(!directory.join("garbage.dec.lua").exists());
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,125 @@
+//! Identifies the golden fixtures of every frontend in their binary and text forms, and
+//! checks that each decompiles to the same output as through its own frontend, or to an
+//! error when it is cut short.
+
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use decompiler::{Encoding, Error, Format, Luau};
+
+const FRONTENDS: [(&str, &str, Format); 4] = [
+    ("lua51-lifter", "luac", Format::Lua51),
+    ("lua54-lifter", "luac", Format::Lua54),
+    ("luajit-lifter", "ljbc", Format::LuaJit),
+    ("luau-lifter", "luauc", Format::Luau(Luau::new(1))),
+];
+
+fn fixtures() -> Vec<(Vec<u8>, String, Format)> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut fixtures = Vec::new();
+    for (krate, extension, format) in FRONTENDS {
+        let directory = root.join(krate).join("tests/fixtures");
+        let mut paths = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|e| e == extension))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty(), "no fixtures in {}", directory.display());
+        for path in paths {
+            let expected = fs::read_to_string(path.with_extension("lua")).unwrap();
+            fixtures.push((fs::read(&path).unwrap(), expected, format));
+        }
+    }
+    fixtures
+}
+
+#[test]
+fn binary() {
+    for (bytecode, expected, format) in fixtures() {
+        let detected = decompiler::detect(&bytecode).unwrap();
+        assert_eq!(
+            (detected.format, detected.encoding),
+            (format, Encoding::Binary)
+        );
+        assert_eq!(decompiler::decompile(&bytecode).unwrap(), expected);
+    }
+}
+
+#[test]
+fn text() {
+    for (bytecode, expected, format) in fixtures() {
+        let hex = hex::encode(&bytecode);
+        // base64 as it is usually pasted, wrapped and without padding
+        let base64 = STANDARD
+            .encode(&bytecode)
+            .trim_end_matches('=')
+            .as_bytes()
+            .chunks(76)
+            .map(|line| String::from_utf8(line.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        for (text, encoding) in [(hex, Encoding::Hex), (base64, Encoding::Base64)] {
+            let detected = decompiler::detect(text.as_bytes()).unwrap();
+            assert_eq!((detected.format, detected.encoding), (format, encoding));
+            assert_eq!(detected.bytecode, bytecode);
+            assert_eq!(decompiler::decompile(text.as_bytes()).unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn luau_error() {
+    let detected =
+        decompiler::detect(b"\0:1: Expected identifier when parsing expression").unwrap();
+    assert_eq!(detected.format, Format::Luau(Luau::default()));
+}
+
+#[test]
+fn unsupported() {
+    let error = decompiler::detect(b"\x1bLuaR\0\x01\x04\x08\x04\x08\0").unwrap_err();
+    assert!(matches!(&error, Error::Unsupported(version) if version == "Lua 5.2"));
+}
+
+#[test]
+fn unidentified() {
+    for input in [&b"print('hello')"[..], b"", b"\x07\x01\x02", b"deadbeef"] {
+        let error = decompiler::detect(input).unwrap_err();
+        assert!(matches!(error, Error::Unidentified), "{:?}", error);
+        assert_eq!(
+            error.to_string(),
+            "could not identify the input as bytecode"
+        );
+    }
+}
+
+#[test]
+fn truncated() {
+    for (bytecode, _, format) in fixtures() {
+        // debug info is optional in some formats, so cut into the header or main function
+        let error = format.decompile(&bytecode[..16]).unwrap_err();
+        assert!(!error.is_empty(), "{:?}", format);
+        // the Luau deserializer reports its own errors
+        if !matches!(format, Format::Luau(_)) {
+            assert!(
+                error.starts_with("failed to parse bytecode: "),
+                "{:?}: {}",
+                format,
+                error
+            );
+        }
+    }
+}
+
+#[test]
+fn lua51_wrong_version() {
+    let (mut bytecode, _, _) = fixtures()
+        .into_iter()
+        .find(|(_, _, format)| *format == Format::Lua51)
+        .unwrap();
+    bytecode[4] = 0x52;
+    assert!(Format::Lua51
+        .decompile(&bytecode)
+        .unwrap_err()
+        .starts_with("failed to parse bytecode: "));
+}
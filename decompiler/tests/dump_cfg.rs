@@ -0,0 +1,80 @@
+//! Dumps the control flow graphs of decompiled functions at each stage.
+
+use std::{env, fs, path::Path};
+
+use decompiler::{dump_cfg, Format, Luau, Options};
+
+const LUAU: Format = Format::Luau(Luau::new(1));
+
+#[test]
+fn dump_cfg_writes_every_stage_without_changing_output() {
+    let bytecode = fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../luau-lifter/tests/fixtures/closures_v4.luauc"),
+    )
+    .unwrap();
+    let directory = env::temp_dir().join(format!("dump-cfg-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let options = Options::new(LUAU.dialect());
+    let expected = LUAU.decompile_with_options(&bytecode, &options).unwrap();
+    let options = Options {
+        cfg_hook: Some(dump_cfg(&directory)),
+        ..options
+    };
+    let actual = LUAU.decompile_with_options(&bytecode, &options).unwrap();
+
+    let mut files = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    let graph = fs::read_to_string(directory.join("0-lifted.dot")).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(actual, expected);
+    assert!(graph.starts_with("digraph cfg {"));
+    // the main function has no debug name, `make` and `increment` do
+    for file in [
+        "0-lifted.dot",
+        "0-constructed.dot",
+        "0-value-numbered.dot",
+        "0-iteration-0.dot",
+        "0-destructed.dot",
+        "1-make-lifted.dot",
+        "1-make-destructed.dot",
+        "3-increment-iteration-0.dot",
+    ] {
+        assert!(
+            files.iter().any(|f| f == file),
+            "{} not in {:?}",
+            file,
+            files
+        );
+    }
+}
+
+#[test]
+fn value_numbering_can_be_disabled() {
+    let bytecode = fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../luau-lifter/tests/fixtures/closures_v4.luauc"),
+    )
+    .unwrap();
+    let directory = env::temp_dir().join(format!("no-gvn-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let options = Options {
+        cfg_hook: Some(dump_cfg(&directory)),
+        value_numbering: false,
+        ..Options::new(LUAU.dialect())
+    };
+    LUAU.decompile_with_options(&bytecode, &options).unwrap();
+
+    let constructed = directory.join("0-constructed.dot").exists();
+    let value_numbered = directory.join("0-value-numbered.dot").exists();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert!(constructed);
+    assert!(!value_numbered);
+}
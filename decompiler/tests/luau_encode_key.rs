@@ -0,0 +1,19 @@
+//! Decompiles Luau bytecode whose opcodes are encoded with the key Roblox clients use.
+
+use std::{fs, path::Path};
+
+use decompiler::{Format, Luau};
+
+#[test]
+fn encode_key() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../luau-lifter/tests/fixtures");
+    let bytecode = fs::read(fixtures.join("encoded/closures_v4.luauc")).unwrap();
+    let expected = fs::read_to_string(fixtures.join("closures_v4.lua")).unwrap();
+
+    assert_eq!(
+        decompiler::detect(&bytecode).unwrap().format,
+        Format::Luau(Luau::default())
+    );
+    let format = Format::Luau(Luau::new(203));
+    assert_eq!(format.decompile(&bytecode).unwrap(), expected);
+}
@@ -0,0 +1,82 @@
+//! Decompiles with naming rules other than the default ones for Roblox.
+
+use std::{fs, path::Path};
+
+use decompiler::{Format, Luau, NamingRules, Options};
+
+const LUAU: Format = Format::Luau(Luau::new(1));
+
+fn services() -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../luau-lifter/tests/fixtures/services_v6.luauc");
+    fs::read(path).unwrap()
+}
+
+fn decompile(naming_rules: NamingRules) -> String {
+    let options = Options {
+        naming_rules,
+        ..Options::new(LUAU.dialect())
+    };
+    LUAU.decompile_with_options(&services(), &options).unwrap()
+}
+
+#[test]
+fn json() {
+    let naming_rules = NamingRules::from_json(
+        r#"{
+            "methods": [{ "method": "GetService", "template": "{arg}Service" }],
+            "imports": { "Packages.Knit": "Framework" }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        decompile(naming_rules),
+        r#"local PlayersService = game:GetService("Players")
+local ReplicatedStorageService = game:GetService("ReplicatedStorage")
+local Framework = require(ReplicatedStorageService.Packages.Knit)
+local var = PlayersService.LocalPlayer:WaitForChild("PlayerGui")
+var.Enabled = true
+Framework.Start(var, PlayersService)
+return Framework, ReplicatedStorageService"#
+    );
+}
+
+#[test]
+fn toml() {
+    let naming_rules = NamingRules::from_toml(
+        r#"
+        [[methods]]
+        method = "WaitForChild"
+        object = "LocalPlayer"
+        template = "{Object}{arg}"
+        "#,
+    )
+    .unwrap();
+    let output = decompile(naming_rules);
+    assert!(output.contains("local LocalPlayerPlayerGui = var.LocalPlayer:WaitForChild"));
+}
+
+#[test]
+fn default() {
+    let expected = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../luau-lifter/tests/fixtures/services_v6.lua"),
+    )
+    .unwrap();
+    assert_eq!(decompile(NamingRules::default()), expected);
+}
+
+#[test]
+fn unknown_placeholder() {
+    let error = NamingRules::from_toml(
+        r#"
+        [[methods]]
+        method = "GetService"
+        template = "{service}"
+        "#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        r#"unknown placeholder {service} in template "{service}""#
+    );
+}
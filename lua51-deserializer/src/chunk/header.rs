@@ -1,22 +1,24 @@
 use nom::{
-    bytes::complete::tag,
+    bytes::complete::{tag, take},
     error::{Error, ErrorKind, ParseError},
     number::complete::le_u8,
     Err, IResult,
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Endianness {
     Big,
     Little,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Format {
     Official,
 }
 
-#[derive(Debug)]
+/// The chunk header, describing the platform the chunk was dumped on. Every multi-byte
+/// field after it is read with the endianness and widths it declares.
+#[derive(Debug, Clone, Copy)]
 pub struct Header {
     pub(crate) version_number: u8,
     pub(crate) format: Format,
@@ -61,6 +63,24 @@ impl Header {
             ))),
         }?;
 
+        // instructions are at least 32 bits wide, floating point numbers are either a float
+        // or a double and everything else has to fit in 64 bits
+        let number_width_is_valid = if number_is_integral {
+            (1..=8).contains(&number_width)
+        } else {
+            matches!(number_width, 4 | 8)
+        };
+        if !(1..=8).contains(&int_width)
+            || !(1..=8).contains(&size_t_width)
+            || !(4..=8).contains(&instr_width)
+            || !number_width_is_valid
+        {
+            return Err(Err::Failure(Error::from_error_kind(
+                input,
+                ErrorKind::Verify,
+            )));
+        }
+
         Ok((
             input,
             Self {
@@ -75,4 +95,56 @@ impl Header {
             },
         ))
     }
+
+    fn parse_unsigned<'a>(&self, input: &'a [u8], width: u8) -> IResult<&'a [u8], u64> {
+        let (input, bytes) = take(width)(input)?;
+        let value = |acc: u64, &byte: &u8| acc << 8 | byte as u64;
+        let value = match self.endianness {
+            Endianness::Big => bytes.iter().fold(0, value),
+            Endianness::Little => bytes.iter().rev().fold(0, value),
+        };
+
+        Ok((input, value))
+    }
+
+    fn parse_signed<'a>(&self, input: &'a [u8], width: u8) -> IResult<&'a [u8], i64> {
+        let (input, value) = self.parse_unsigned(input, width)?;
+        let unused = 64 - width as u32 * 8;
+
+        Ok((input, ((value << unused) as i64) >> unused))
+    }
+
+    /// Parses a C `int`, used for counts, line numbers and local ranges.
+    pub fn parse_int<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], i64> {
+        self.parse_signed(input, self.int_width)
+    }
+
+    /// Parses a `size_t`, used for string lengths.
+    pub fn parse_size_t<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], u64> {
+        self.parse_unsigned(input, self.size_t_width)
+    }
+
+    /// Parses an instruction, only the low 32 bits of which are used by Lua 5.1.
+    pub fn parse_instruction<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], u32> {
+        let (input, instruction) = self.parse_unsigned(input, self.instr_width)?;
+
+        Ok((input, instruction as u32))
+    }
+
+    /// Parses a `lua_Number`, converting integral numbers to floating point.
+    pub fn parse_number<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], f64> {
+        if self.number_is_integral {
+            let (input, value) = self.parse_signed(input, self.number_width)?;
+
+            Ok((input, value as f64))
+        } else {
+            let (input, bits) = self.parse_unsigned(input, self.number_width)?;
+            let value = match self.number_width {
+                4 => f32::from_bits(bits as u32) as f64,
+                _ => f64::from_bits(bits),
+            };
+
+            Ok((input, value))
+        }
+    }
 }
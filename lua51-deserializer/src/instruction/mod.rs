@@ -2,12 +2,13 @@ use nom::{
     error::{Error, ErrorKind, ParseError},
     Err, IResult,
 };
-use num_traits::ToPrimitive;
 
 use argument::{Constant, Function, Register, RegisterOrConstant, Upvalue};
 use layout::Layout;
 use operation_code::OperationCode;
 
+use crate::chunk::Header;
+
 pub mod argument;
 mod layout;
 mod operation_code;
@@ -17,12 +18,12 @@ pub mod position;
 struct RawInstruction(OperationCode, Layout);
 
 impl RawInstruction {
-    pub fn parse(input: &[u8]) -> IResult<&[u8], Self> {
-        // TODO: we read the operation code and then the instruction including the operation code
-        // while parsing the layout.
-        // we should instead read the instruction here and pass it to Layout::parse
-        let operation_code = OperationCode::parse(input).map(|r| r.1)?;
-        let (input, layout) = Layout::parse(input, operation_code.to_u8().unwrap())?;
+    pub fn parse<'a>(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Self> {
+        let (input, instruction) = header.parse_instruction(input)?;
+        let operation_code = OperationCode::from_instruction(instruction).ok_or_else(|| {
+            Err::Failure(Error::from_error_kind(input, ErrorKind::Switch))
+        })?;
+        let layout = Layout::new(instruction, operation_code.instruction_layout());
 
         Ok((input, Self(operation_code, layout)))
     }
@@ -190,7 +191,7 @@ pub enum Instruction {
     SetList {
         table: Register,
         number_of_elements: u8,
-        block_number: u8,
+        block_number: u32,
     },
     Close(Register),
     Closure {
@@ -198,11 +199,50 @@ pub enum Instruction {
         function: Function,
     },
     VarArg(Register, u8),
+    // the word following a `SetList` whose block number doesn't fit in C,
+    // it holds the block number and is never executed
+    Data(u32),
 }
 
 impl Instruction {
-    pub fn parse(input: &[u8]) -> IResult<&[u8], Self> {
-        let (input, instruction) = RawInstruction::parse(input)?;
+    pub fn parse_list<'a>(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Vec<Self>> {
+        let (mut input, length) = header.parse_int(input)?;
+        let Ok(length) = usize::try_from(length) else {
+            return Err(Err::Failure(Error::from_error_kind(input, ErrorKind::Verify)));
+        };
+
+        // the length is read from the file, every instruction takes at least 4 bytes
+        let mut code = Vec::with_capacity(length.min(input.len() / 4));
+        while code.len() < length {
+            let instruction;
+            (input, instruction) = Self::parse(input, header)?;
+            match instruction {
+                Self::SetList {
+                    table,
+                    number_of_elements,
+                    block_number: 0,
+                } => {
+                    if code.len() + 1 == length {
+                        return Err(Err::Failure(Error::from_error_kind(input, ErrorKind::Eof)));
+                    }
+                    let block_number;
+                    (input, block_number) = header.parse_instruction(input)?;
+                    code.push(Self::SetList {
+                        table,
+                        number_of_elements,
+                        block_number,
+                    });
+                    code.push(Self::Data(block_number));
+                }
+                instruction => code.push(instruction),
+            }
+        }
+
+        Ok((input, code))
+    }
+
+    pub fn parse<'a>(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Self> {
+        let (input, instruction) = RawInstruction::parse(input, header)?;
         let instruction = match instruction {
             RawInstruction(OperationCode::Move, Layout::BC { a, b, .. }) => Self::Move {
                 destination: Register(a),
@@ -383,7 +423,7 @@ impl Instruction {
             RawInstruction(OperationCode::SetList, Layout::BC { a, b, c }) => Self::SetList {
                 table: Register(a),
                 number_of_elements: b as u8,
-                block_number: c as u8,
+                block_number: c as u32,
             },
             RawInstruction(OperationCode::Close, Layout::BC { a, .. }) => Self::Close(Register(a)),
             RawInstruction(OperationCode::Closure, Layout::BX { a, b_x }) => Self::Closure {
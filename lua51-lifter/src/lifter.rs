@@ -6,8 +6,9 @@ use itertools::Itertools;
 use parking_lot::Mutex;
 use rustc_hash::FxHashMap;
 
-use ast::{RcLocal, Statement};
+use ast::{Local, RcLocal, Statement};
 use cfg::function::Function;
+use decompiler_core::LiftedFunction;
 
 use lua51_deserializer::{
     argument::{Constant, Register, RegisterOrConstant},
@@ -21,33 +22,137 @@ use triomphe::Arc;
 pub struct Lifter<'a, 'b> {
     bytecode: &'a BytecodeFunction<'a>,
     nodes: FxHashMap<usize, NodeIndex>,
-    insert_between: FxHashMap<NodeIndex, (NodeIndex, Statement)>,
+    insert_between: FxHashMap<NodeIndex, (NodeIndex, Vec<Statement>)>,
     locals: FxHashMap<Register, RcLocal>,
+    registers: FxHashMap<RcLocal, Register>,
     constants: FxHashMap<usize, ast::Literal>,
     function: Function,
     upvalues: Vec<RcLocal>,
-    lifted_functions: &'b mut Vec<(Arc<Mutex<ast::Function>>, Function, Vec<RcLocal>)>,
+    lifted_functions: &'b mut Vec<LiftedFunction>,
 }
 
 impl<'a, 'b> Lifter<'a, 'b> {
     fn allocate_locals(&mut self) {
         self.upvalues
             .reserve(self.bytecode.number_of_upvalues as usize);
-        for _ in 0..self.bytecode.number_of_upvalues {
-            self.upvalues.push(RcLocal::default());
+        for i in 0..self.bytecode.number_of_upvalues as usize {
+            let name = self.bytecode.upvalues.get(i).map(|name| {
+                let name = name.strip_suffix(b"\0").unwrap_or(name);
+                String::from_utf8_lossy(name).into_owned()
+            });
+            self.upvalues.push(RcLocal::new(Local::new(name)));
         }
 
         self.locals
             .reserve(self.bytecode.maximum_stack_size as usize);
         for i in 0..self.bytecode.maximum_stack_size {
-            let local = RcLocal::default();
-            if i < self.bytecode.number_of_parameters {
+            let local = if i < self.bytecode.number_of_parameters {
+                let local = RcLocal::new(Local::new(self.local_name(Register(i), 0)));
                 self.function.parameters.push(local.clone());
-            }
+                local
+            } else {
+                RcLocal::default()
+            };
+            self.registers.insert(local.clone(), Register(i));
             self.locals.insert(Register(i), local);
         }
     }
 
+    // the name the debug info gives the local in `register` once `pc` is reached. locals
+    // become active after the instruction initializing them, so instructions that can't
+    // write the register, like the ones filling a table constructor, are skipped over.
+    fn local_name(&self, register: Register, mut pc: usize) -> Option<String> {
+        loop {
+            if let Some(local) = self
+                .bytecode
+                .locals
+                .iter()
+                .filter(|local| local.range.contains(&(pc as u32)))
+                .nth(register.0 as usize)
+            {
+                // internal locals like "(for index)" aren't valid names
+                return (!local.name.starts_with(b"("))
+                    .then(|| String::from_utf8_lossy(local.name).into_owned());
+            }
+            let instruction = self.bytecode.code.get(pc)?;
+            if Self::may_write(instruction, register) {
+                return None;
+            }
+            pc += 1;
+        }
+    }
+
+    // whether `instruction` might write `register` or leave straight line code
+    fn may_write(instruction: &Instruction, register: Register) -> bool {
+        let lowest = match instruction {
+            Instruction::SetGlobal { .. }
+            | Instruction::SetUpvalue { .. }
+            | Instruction::SetIndex { .. }
+            | Instruction::SetList { .. }
+            | Instruction::Data(_) => return false,
+            Instruction::Move { destination, .. }
+            | Instruction::LoadConstant { destination, .. }
+            | Instruction::LoadBoolean {
+                destination,
+                skip_next: false,
+                ..
+            }
+            | Instruction::GetUpvalue { destination, .. }
+            | Instruction::GetGlobal { destination, .. }
+            | Instruction::GetIndex { destination, .. }
+            | Instruction::NewTable { destination, .. }
+            | Instruction::PrepMethodCall { destination, .. }
+            | Instruction::Add { destination, .. }
+            | Instruction::Sub { destination, .. }
+            | Instruction::Mul { destination, .. }
+            | Instruction::Div { destination, .. }
+            | Instruction::Mod { destination, .. }
+            | Instruction::Pow { destination, .. }
+            | Instruction::Minus { destination, .. }
+            | Instruction::Not { destination, .. }
+            | Instruction::Length { destination, .. }
+            | Instruction::Concatenate { destination, .. }
+            | Instruction::VarArg(destination, _)
+            | Instruction::Call {
+                function: destination,
+                ..
+            } => destination,
+            Instruction::LoadNil(registers) => match registers.first() {
+                Some(first) => first,
+                None => return false,
+            },
+            _ => return true,
+        };
+        lowest.0 <= register.0
+    }
+
+    // writes values of named locals to a new local with the name first, which is then
+    // copied to the register. ssa construction propagates the copy, leaving the named local.
+    fn name_written_locals(&self, statements: &mut Vec<Statement>, start: usize, pc: usize) {
+        let mut index = start;
+        while index < statements.len() {
+            let mut copies = Vec::new();
+            if let Statement::Assign(assign) = &mut statements[index] {
+                for local in assign.left.iter_mut().filter_map(|l| l.as_local_mut()) {
+                    if let Some(&register) = self.registers.get(local)
+                        && let Some(name) = self.local_name(register, pc)
+                    {
+                        let named = RcLocal::new(Local::new(Some(name)));
+                        let register_local = std::mem::replace(local, named.clone());
+                        copies.push(
+                            ast::Assign::new(vec![register_local.into()], vec![named.into()])
+                                .into(),
+                        );
+                    }
+                }
+            }
+            index += 1;
+            let copied = copies.len();
+            statements.splice(index..index, copies);
+            index += copied;
+        }
+    }
+
     // TODO: support jumps to invalid destinations
     // including cases where there is usize::MAX instructions and the last instruction
     // skips forward, overflowing
@@ -55,12 +160,6 @@ impl<'a, 'b> Lifter<'a, 'b> {
         self.nodes.insert(0, self.function.new_block());
         for (insn_index, insn) in self.bytecode.code.iter().enumerate() {
             match *insn {
-                Instruction::SetList {
-                    block_number: 0, ..
-                } => {
-                    // TODO: skip next instruction
-                    todo!();
-                }
                 Instruction::LoadBoolean {
                     skip_next: true, ..
                 } => {
@@ -151,7 +250,12 @@ impl<'a, 'b> Lifter<'a, 'b> {
     }
 
     // TODO: rename to one of: lift_instructions, lift_range, lift_instruction_range, lift_block?
-    fn lift_instruction(&mut self, start: usize, end: usize, statements: &mut Vec<Statement>) {
+    fn lift_instruction(
+        &mut self,
+        start: usize,
+        end: usize,
+        statements: &mut Vec<Statement>,
+    ) -> Result<(), String> {
         if end > start {
             statements.reserve(end - start + 1);
         }
@@ -159,6 +263,7 @@ impl<'a, 'b> Lifter<'a, 'b> {
         // TODO: we should consume the instructions, reducing clones
         let mut iter = self.bytecode.code[start..=end].iter();
         while let Some(instruction) = iter.next() {
+            let statements_start = statements.len();
             match instruction {
                 Instruction::Move {
                     destination,
@@ -601,14 +706,18 @@ impl<'a, 'b> Lifter<'a, 'b> {
                                 destination: _,
                                 upvalue,
                             } => self.upvalues[upvalue.0 as usize].clone(),
-                            _ => panic!(),
+                            _ => {
+                                return Err(
+                                    "closure upvalue isn't captured by a MOVE or GETUPVAL".into()
+                                )
+                            }
                         };
                         upvalues_passed.push(local);
                     }
 
                     let ast_function = Arc::<Mutex<_>>::default();
 
-                    let (function, upvalues) = Lifter::lift(closure, self.lifted_functions);
+                    let (function, upvalues) = Lifter::lift(closure, self.lifted_functions)?;
                     self.lifted_functions
                         .push((ast_function.clone(), function, upvalues));
 
@@ -643,10 +752,15 @@ impl<'a, 'b> Lifter<'a, 'b> {
                 } => {
                     const FIELDS_PER_FLUSH: usize = 50;
 
+                    // a block number of 0 is only valid as the escape to the next word
+                    let Some(block) = block_number.checked_sub(1) else {
+                        return Err("SETLIST with a block number of 0".into());
+                    };
+                    let index = block as usize * FIELDS_PER_FLUSH + 1;
                     let setlist = if number_of_elements != 0 {
                         ast::SetList::new(
                             self.locals[&table].clone(),
-                            (block_number - 1) as usize * FIELDS_PER_FLUSH + 1,
+                            index,
                             (table.0 + 1..table.0 + 1 + number_of_elements)
                                 .map(|r| self.locals[&Register(r)].clone().into())
                                 .collect(),
@@ -656,7 +770,7 @@ impl<'a, 'b> Lifter<'a, 'b> {
                         let top = top.take().unwrap();
                         ast::SetList::new(
                             self.locals[&table].clone(),
-                            (block_number - 1) as usize * FIELDS_PER_FLUSH + 1,
+                            index,
                             (table.0 + 1..top.1)
                                 .map(|r| self.locals[&Register(r)].clone().into())
                                 .collect(),
@@ -708,26 +822,23 @@ impl<'a, 'b> Lifter<'a, 'b> {
                             .into(),
                     );
 
-                    let body_node = self.get_node(
-                        &((end + 1)
-                            .checked_add_signed(skip.try_into().unwrap())
-                            .unwrap()),
-                    );
+                    let body = (end + 1)
+                        .checked_add_signed(skip.try_into().unwrap())
+                        .unwrap();
+                    let body_node = self.get_node(&body);
+                    let mut counter = vec![ast::Assign::new(
+                        vec![external_counter.into()],
+                        vec![internal_counter.into()],
+                    )
+                    .into()];
+                    self.name_written_locals(&mut counter, 0, body);
                     assert!(self
                         .insert_between
-                        .insert(
-                            self.nodes[&start],
-                            (
-                                body_node,
-                                ast::Assign::new(
-                                    vec![external_counter.into()],
-                                    vec![internal_counter.into()],
-                                )
-                                .into()
-                            )
-                        )
+                        .insert(self.nodes[&start], (body_node, counter))
                         .is_none());
                 }
+                // the block number of the preceding `SetList`, not code
+                Instruction::Data(_) => {}
                 Instruction::IterateGenericForLoop {
                     generator,
                     state,
@@ -774,21 +885,32 @@ impl<'a, 'b> Lifter<'a, 'b> {
                             self.nodes[&start],
                             (
                                 body_node,
-                                ast::Assign::new(
+                                vec![ast::Assign::new(
                                     vec![internal_control.clone().into()],
                                     vec![control.clone().into()],
                                 )
-                                .into()
+                                .into()]
                             )
                         )
                         .is_none());
                 }
             }
 
+            // the instruction after this one and any it consumed, for generic for loops
+            // the body they jump back to after writing the variables
+            let next = match (instruction, self.bytecode.code.get(end + 1)) {
+                (Instruction::IterateGenericForLoop { .. }, Some(&Instruction::Jump(skip))) => {
+                    (end + 2).checked_add_signed(skip.try_into().unwrap()).unwrap()
+                }
+                _ => end + 1 - iter.as_slice().len(),
+            };
+            self.name_written_locals(statements, statements_start, next);
+
             if matches!(instruction, Instruction::Return { .. }) {
                 break;
             }
         }
+        Ok(())
     }
 
     // TODO: REFACTOR: this function doesnt need to exist
@@ -796,7 +918,7 @@ impl<'a, 'b> Lifter<'a, 'b> {
         self.nodes[index]
     }
 
-    fn lift_blocks(&mut self) {
+    fn lift_blocks(&mut self) -> Result<(), String> {
         let ranges = self.code_ranges();
         for (start, end) in ranges {
             // TODO: gotta be a better way
@@ -804,7 +926,7 @@ impl<'a, 'b> Lifter<'a, 'b> {
             // see: IterateNumericForLoop
             let mut statements =
                 std::mem::take(self.function.block_mut(self.nodes[&start]).unwrap());
-            self.lift_instruction(start, end, &mut statements);
+            self.lift_instruction(start, end, &mut statements)?;
             *self.function.block_mut(self.nodes[&start]).unwrap() = statements;
 
             match self.bytecode.code[end] {
@@ -872,26 +994,32 @@ impl<'a, 'b> Lifter<'a, 'b> {
                 }
             }
         }
+        Ok(())
     }
 
+    /// Lifts `bytecode` and the functions it defines, which are added to `lifted_functions`.
+    /// Fails on bytecode the compiler couldn't have produced.
     pub fn lift(
         bytecode: &'a BytecodeFunction,
-        lifted_functions: &'b mut Vec<(Arc<Mutex<ast::Function>>, Function, Vec<RcLocal>)>,
-    ) -> (Function, Vec<RcLocal>) {
+        lifted_functions: &'b mut Vec<LiftedFunction>,
+    ) -> Result<(Function, Vec<RcLocal>), String> {
         let mut context = Self {
             bytecode,
             nodes: FxHashMap::default(),
             insert_between: FxHashMap::default(),
             locals: FxHashMap::default(),
+            registers: FxHashMap::default(),
             constants: FxHashMap::default(),
             function: Function::new(0),
             upvalues: Vec::new(),
             lifted_functions,
         };
+        // `VARARG_ISVARARG`
+        context.function.is_variadic = bytecode.vararg_flag & 2 != 0;
 
         context.create_block_map();
         context.allocate_locals();
-        context.lift_blocks();
+        context.lift_blocks()?;
 
         // TODO: STYLE: instead of naming NodeIndex vars `{}_node`, we should name them
         // `{}_index`, or if it's the corresponding var for `block`, `block_index`
@@ -912,16 +1040,16 @@ impl<'a, 'b> Lifter<'a, 'b> {
         );
         context.function.set_entry(stack_init_node);
 
-        for (node, (successor, stat)) in context.insert_between {
+        for (node, (successor, stats)) in context.insert_between {
             if context.function.predecessor_blocks(successor).count() == 1 {
                 context
                     .function
                     .block_mut(successor)
                     .unwrap()
-                    .insert(0, stat);
+                    .splice(0..0, stats);
             } else {
                 let between_node = context.function.new_block();
-                context.function.block_mut(between_node).unwrap().push(stat);
+                context.function.block_mut(between_node).unwrap().extend(stats);
                 context.function.set_edges(
                     between_node,
                     vec![(successor, BlockEdge::new(BranchType::Unconditional))],
@@ -943,6 +1071,7 @@ impl<'a, 'b> Lifter<'a, 'b> {
             }
         }
 
-        (context.function, context.upvalues)
+        Ok((context.function, context.upvalues))
     }
 }
+
@@ -1,205 +1,158 @@
-#![feature(box_patterns)]
-#![feature(let_chains)]
-
-use ast::{
-    local_declarations::LocalDeclarer, name_locals::name_locals, replace_locals::replace_locals,
-    Traverse,
-};
-use by_address::ByAddress;
-use cfg::ssa::{
-    self,
-    structuring::{structure_conditionals, structure_jumps, structure_method_calls},
-};
-use indexmap::IndexMap;
-use lifter::Lifter;
-use parking_lot::Mutex;
-use petgraph::algo::dominators::simple_fast;
-use rayon::iter::ParallelIterator;
-use rayon::prelude::IntoParallelIterator;
-use rustc_hash::FxHashMap;
 use std::{
-    fs::File,
-    io::{Read, Write},
-    path::Path,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
     time::Instant,
 };
-use triomphe::Arc;
 
+use anyhow::{bail, Context};
+use ast::formatter::Dialect;
 use clap::Parser;
-
-use lua51_deserializer::chunk::Chunk;
-
-mod lifter;
+use walkdir::WalkDir;
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
+/// The extension of the bytecode files searched for in directories.
+const BYTECODE_EXTENSION: &str = "luac";
+const OUTPUT_EXTENSION: &str = "dec.51.lua";
+
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Args {
+    /// Files to decompile, directories are searched for `.luac` files
+    #[clap(required_unless_present = "file")]
+    paths: Vec<PathBuf>,
+    /// A file to decompile, like the paths before it
+    #[clap(short, long)]
+    file: Option<PathBuf>,
+    /// Search directories recursively
     #[clap(short, long)]
-    file: String,
+    recursive: bool,
+    /// Write the output to this file instead of stdout, for a single input
+    #[clap(short, long, conflicts_with = "out_dir")]
+    output: Option<PathBuf>,
+    /// Write the output of every input to `<name>.dec.51.lua` in this directory, keeping
+    /// the layout of searched directories
+    #[clap(long)]
+    out_dir: Option<PathBuf>,
+    /// Comment written before the output, `{file}` and `{time}` are replaced with the input
+    /// and how long it took to decompile
+    #[clap(long, default_value = "decompiled by Sentinel (took {time})")]
+    header: String,
+    /// Don't write a header comment
+    #[clap(long, conflicts_with = "header")]
+    no_header: bool,
+    /// Dialect of the output (luau, lua51, lua52, lua53, lua54)
+    #[clap(short, long, default_value_t = Dialect::Lua51)]
+    dialect: Dialect,
 }
 
-fn main() -> anyhow::Result<()> {
+// a file to decompile, and where it is relative to the directory it was found in
+struct Input {
+    path: PathBuf,
+    relative: PathBuf,
+}
+
+fn main() -> anyhow::Result<ExitCode> {
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
 
     let args = Args::parse();
-    let path = Path::new(&args.file);
-    let mut input = File::open(path)?;
-    let mut buffer = vec![0; input.metadata()?.len() as usize];
-    input.read_exact(&mut buffer)?;
-
-    let start = Instant::now();
-    let chunk = Chunk::parse(&buffer).unwrap().1;
-    let mut lifted = Vec::new();
-    let (function, upvalues) = Lifter::lift(&chunk.function, &mut lifted);
-    lifted.push((Arc::<Mutex<_>>::default(), function, upvalues));
-    lifted.reverse();
-
-    let (main, ..) = lifted.first().unwrap().clone();
-    let mut upvalues = lifted
-        .into_iter()
-        .map(|(ast_function, mut function, upvalues_in)| {
-            let (local_count, local_groups, upvalue_in_groups, upvalue_passed_groups) =
-                cfg::ssa::construct(&mut function, &upvalues_in);
-            let upvalue_to_group = upvalue_in_groups
-                .into_iter()
-                .chain(
-                    upvalue_passed_groups
-                        .into_iter()
-                        .map(|m| (ast::RcLocal::default(), m)),
-                )
-                .flat_map(|(i, g)| g.into_iter().map(move |u| (u, i.clone())))
-                .collect::<IndexMap<_, _>>();
-            // TODO: do we even need this?
-            let local_to_group = local_groups
-                .into_iter()
-                .enumerate()
-                .flat_map(|(i, g)| g.into_iter().map(move |l| (l, i)))
-                .collect::<FxHashMap<_, _>>();
-            // TODO: REFACTOR: some way to write a macro that states
-            // if cfg::ssa::inline results in change then structure_jumps, structure_compound_conditionals,
-            // structure_for_loops and remove_unnecessary_params must run again.
-            // if structure_compound_conditionals results in change then dominators and post dominators
-            // must be recalculated.
-            // etc.
-            // the macro could also maybe generate an optimal ordering?
-            let mut changed = true;
-            while changed {
-                changed = false;
-
-                let dominators = simple_fast(function.graph(), function.entry().unwrap());
-                changed |= structure_jumps(&mut function, &dominators);
-
-                ssa::inline::inline(&mut function, &local_to_group, &upvalue_to_group);
-
-                if structure_conditionals(&mut function)
-                // || {
-                //     let post_dominators = post_dominators(function.graph_mut());
-                //     structure_for_loops(&mut function, &dominators, &post_dominators)
-                // }
-                    || structure_method_calls(&mut function)
-                {
-                    changed = true;
-                }
-                let mut local_map = FxHashMap::default();
-                // TODO: loop until returns false?
-                if ssa::construct::remove_unnecessary_params(&mut function, &mut local_map) {
-                    changed = true;
-                }
-                ssa::construct::apply_local_map(&mut function, local_map);
-            }
-            ssa::Destructor::new(
-                &mut function,
-                upvalue_to_group,
-                upvalues_in.iter().cloned().collect(),
-                local_count,
-            )
-            .destruct();
+    let inputs = collect_inputs(&args)?;
+    if args.output.is_some() && inputs.len() != 1 {
+        bail!(
+            "--output takes a single input but {} were given, use --out-dir instead",
+            inputs.len()
+        );
+    }
 
-            let params = std::mem::take(&mut function.parameters);
-            let is_variadic = function.is_variadic;
-            let block = Arc::new(restructure::lift(function).into());
-            LocalDeclarer::default().declare_locals(
-                // TODO: why does block.clone() not work?
-                Arc::clone(&block),
-                &upvalues_in.iter().chain(params.iter()).cloned().collect(),
-            );
+    let mut failed = false;
+    for input in &inputs {
+        if let Err(err) = decompile(&args, input) {
+            eprintln!("{:#}", err);
+            failed = true;
+        }
+    }
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
 
+fn collect_inputs(args: &Args) -> anyhow::Result<Vec<Input>> {
+    let mut inputs = Vec::new();
+    for path in args.paths.iter().chain(&args.file) {
+        if !path.is_dir() {
+            inputs.push(Input {
+                path: path.clone(),
+                relative: PathBuf::from(path.file_name().context("input has no file name")?),
+            });
+            continue;
+        }
+        let walker = WalkDir::new(path)
+            .max_depth(if args.recursive { usize::MAX } else { 1 })
+            .sort_by_file_name();
+        for entry in walker {
+            let entry = entry?;
+            if entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| extension == BYTECODE_EXTENSION)
             {
-                let mut ast_function = ast_function.lock();
-                ast_function.body = Arc::try_unwrap(block).unwrap().into_inner();
-                ast_function.parameters = params;
-                ast_function.is_variadic = is_variadic;
+                inputs.push(Input {
+                    path: entry.path().to_path_buf(),
+                    relative: entry.path().strip_prefix(path)?.to_path_buf(),
+                });
             }
-            (ByAddress(ast_function), upvalues_in)
-        })
-        .collect::<FxHashMap<_, _>>();
+        }
+    }
+    Ok(inputs)
+}
 
-    let main = ByAddress(main);
-    upvalues.remove(&main);
-    let mut body = Arc::try_unwrap(main.0).unwrap().into_inner().body;
-    link_upvalues(&mut body, &mut upvalues);
-    name_locals(&mut body, true);
-    let res = body.to_string();
-    let duration = start.elapsed();
+fn decompile(args: &Args, input: &Input) -> anyhow::Result<()> {
+    let bytecode = fs::read(&input.path)
+        .with_context(|| format!("failed to read {}", input.path.display()))?;
 
-    // TODO: use BufWriter?
-    let mut out = File::create(path.with_extension("dec.51.lua").file_name().unwrap())?;
-    writeln!(out, "-- decompiled by Sentinel (took {:?})", duration)?;
-    writeln!(out, "{}", res)?;
+    let start = Instant::now();
+    let res = lua51_lifter::decompile_bytecode_with_dialect(&bytecode, args.dialect)
+        .map_err(anyhow::Error::msg)
+        .with_context(|| format!("failed to decompile {}", input.path.display()))?;
+    let duration = start.elapsed();
 
-    Ok(())
+    let mut output = String::new();
+    if !args.no_header {
+        let header = args
+            .header
+            .replace("{file}", &input.path.display().to_string())
+            .replace("{time}", &format!("{:?}", duration));
+        for line in header.lines() {
+            output.push_str("-- ");
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    output.push_str(&res);
+    output.push('\n');
+
+    let path = match (&args.output, &args.out_dir) {
+        (Some(path), _) => path.clone(),
+        (None, Some(out_dir)) => out_dir.join(input.relative.with_extension(OUTPUT_EXTENSION)),
+        (None, None) => {
+            io::stdout().lock().write_all(output.as_bytes())?;
+            return Ok(());
+        }
+    };
+    write_output(&path, &output)
 }
 
-fn link_upvalues(
-    body: &mut ast::Block,
-    upvalues: &mut FxHashMap<ByAddress<Arc<Mutex<ast::Function>>>, Vec<ast::RcLocal>>,
-) {
-    for stat in &mut body.0 {
-        stat.traverse_rvalues(&mut |rvalue| {
-            if let ast::RValue::Closure(closure) = rvalue {
-                let old_upvalues = upvalues.remove(&closure.function).unwrap();
-                let mut function = closure.function.lock();
-                // TODO: inefficient, try constructing a map of all up -> new up first
-                // and then call replace_locals on main body
-                let mut local_map =
-                    FxHashMap::with_capacity_and_hasher(old_upvalues.len(), Default::default());
-                for (old, new) in
-                    old_upvalues
-                        .iter()
-                        .zip(closure.upvalues.iter().map(|u| match u {
-                            ast::Upvalue::Copy(l) | ast::Upvalue::Ref(l) => l,
-                        }))
-                {
-                    // println!("{} -> {}", old, new);
-                    local_map.insert(old.clone(), new.clone());
-                }
-                link_upvalues(&mut function.body, upvalues);
-                replace_locals(&mut function.body, &local_map);
-            }
-        });
-        match stat {
-            ast::Statement::If(r#if) => {
-                link_upvalues(&mut r#if.then_block.lock(), upvalues);
-                link_upvalues(&mut r#if.else_block.lock(), upvalues);
-            }
-            ast::Statement::While(r#while) => {
-                link_upvalues(&mut r#while.block.lock(), upvalues);
-            }
-            ast::Statement::Repeat(repeat) => {
-                link_upvalues(&mut repeat.block.lock(), upvalues);
-            }
-            ast::Statement::NumericFor(numeric_for) => {
-                link_upvalues(&mut numeric_for.block.lock(), upvalues);
-            }
-            ast::Statement::GenericFor(generic_for) => {
-                link_upvalues(&mut generic_for.block.lock(), upvalues);
-            }
-            _ => {}
-        }
+fn write_output(path: &Path, output: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
     }
+    fs::write(path, output).with_context(|| format!("failed to write {}", path.display()))
 }
@@ -0,0 +1,115 @@
+//! Runs the `lua51-lifter` binary on the golden fixtures.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn run(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_lua51-lifter"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn expected(fixture: &str) -> String {
+    fs::read_to_string(fixtures().join(fixture).with_extension("lua")).unwrap() + "\n"
+}
+
+// an empty directory only this test writes to
+fn out_dir(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("lua51-lifter-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+#[test]
+fn stdout() {
+    let path = fixtures().join("loops.luac");
+    let output = run(&["--no-header", path.to_str().unwrap()]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected("loops"));
+
+    let output = run(&["--header", "{file}\nby the tests", path.to_str().unwrap()]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "-- {}\n-- by the tests\n{}",
+            path.display(),
+            expected("loops")
+        )
+    );
+}
+
+#[test]
+fn output() {
+    let out_dir = out_dir("output");
+    let output = out_dir.join("closures.lua");
+    let path = fixtures().join("closures.luac");
+    run(&[
+        "--no-header",
+        "--file",
+        path.to_str().unwrap(),
+        "--output",
+        output.to_str().unwrap(),
+    ]);
+    assert_eq!(fs::read_to_string(&output).unwrap(), expected("closures"));
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn out_dir_batch() {
+    let out_dir = out_dir("batch");
+    run(&[
+        "--no-header",
+        "--recursive",
+        fixtures().to_str().unwrap(),
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ]);
+    let written = fs::read_to_string(out_dir.join("setlist.dec.51.lua")).unwrap();
+    assert_eq!(written, expected("setlist"));
+    // searched directories keep their layout
+    assert!(out_dir
+        .join("platforms/closures.big_endian.dec.51.lua")
+        .is_file());
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn unparsable_input() {
+    let out_dir = out_dir("unparsable");
+    fs::create_dir_all(&out_dir).unwrap();
+    let garbage = out_dir.join("garbage.luac");
+    fs::write(&garbage, b"\x1bLua\x51\x00garbage").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_lua51-lifter"))
+        .args([
+            garbage.to_str().unwrap(),
+            fixtures().join("loops.luac").to_str().unwrap(),
+            "--out-dir",
+            out_dir.join("out").to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(&format!("failed to decompile {}: ", garbage.display())),
+        "{}",
+        stderr
+    );
+    // the other inputs are still decompiled
+    assert!(!out_dir.join("out/garbage.dec.51.lua").exists());
+    assert!(out_dir.join("out/loops.dec.51.lua").is_file());
+    fs::remove_dir_all(out_dir).unwrap();
+}
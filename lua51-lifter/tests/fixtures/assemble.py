@@ -0,0 +1,520 @@
+#!/usr/bin/env python3
+"""Assembles the Lua 5.1 bytecode fixtures used by `tests/golden.rs`.
+
+There is no `luac` in the build environment, so every fixture is written out by hand here,
+mirroring what `luac5.1` emits for the source in its docstring on a little-endian 32-bit
+build. Run `python3 assemble.py` from this directory to regenerate the `.luac` files, then
+`BLESS=1 cargo test -p lua51-lifter --test golden` to refresh the expected output.
+
+Every fixture is also dumped for the other platforms in `PLATFORMS` into `platforms/`,
+`tests/platforms.rs` checks those decompile the same as the fixture itself.
+"""
+
+import os
+import struct
+
+OPS = [
+    "MOVE", "LOADK", "LOADBOOL", "LOADNIL", "GETUPVAL", "GETGLOBAL", "GETTABLE", "SETGLOBAL",
+    "SETUPVAL", "SETTABLE", "NEWTABLE", "SELF", "ADD", "SUB", "MUL", "DIV", "MOD", "POW", "UNM",
+    "NOT", "LEN", "CONCAT", "JMP", "EQ", "LT", "LE", "TEST", "TESTSET", "CALL", "TAILCALL",
+    "RETURN", "FORLOOP", "FORPREP", "TFORLOOP", "SETLIST", "CLOSE", "CLOSURE", "VARARG",
+]
+OP = {name: index for index, name in enumerate(OPS)}
+
+MAXARG_SBX = ((1 << 18) - 1) >> 1
+VARARG_HASARG, VARARG_ISVARARG = 1, 2
+
+
+def rk(constant):
+    return constant | 256
+
+
+class Platform:
+    """The endianness and type widths `luac` was built with, as recorded in the header."""
+
+    def __init__(self, endian="<", int_width=4, size_t_width=4, number_width=8, integral=False):
+        self.endian = endian
+        self.int_width = int_width
+        self.size_t_width = size_t_width
+        self.number_width = number_width
+        self.integral = integral
+
+    def header(self):
+        return b"\x1bLua\x51\x00" + bytes([
+            self.endian == "<",
+            self.int_width,
+            self.size_t_width,
+            4,
+            self.number_width,
+            self.integral,
+        ])
+
+    def pack(self, format, value):
+        return struct.pack(self.endian + format, value)
+
+    def int(self, value):
+        return self.pack({4: "i", 8: "q"}[self.int_width], value)
+
+    def size_t(self, value):
+        return self.pack({4: "I", 8: "Q"}[self.size_t_width], value)
+
+    def instruction(self, value):
+        return self.pack("I", value)
+
+    def number(self, value):
+        if self.integral:
+            return self.pack({4: "i", 8: "q"}[self.number_width], int(value))
+        return self.pack({4: "f", 8: "d"}[self.number_width], value)
+
+    def string(self, value):
+        if value is None:
+            return self.size_t(0)
+        data = value.encode() + b"\0"
+        return self.size_t(len(data)) + data
+
+
+NATIVE = Platform()
+PLATFORMS = {
+    "big_endian": Platform(endian=">"),
+    "size_t8": Platform(size_t_width=8),
+    "int8": Platform(int_width=8, size_t_width=8),
+    "float": Platform(number_width=4),
+    "integral": Platform(number_width=4, integral=True),
+}
+
+
+class Proto:
+    def __init__(self, params=0, upvalues=(), vararg=0, line=0, last_line=0):
+        self.params = params
+        self.upvalues = list(upvalues)
+        self.vararg = vararg
+        self.line = line
+        self.last_line = last_line
+        self.max_stack = 2
+        self.code = []
+        self.labels = {}
+        self.constants = []
+        self.children = []
+        self.locals = []
+
+    def k(self, value):
+        if isinstance(value, int) and not isinstance(value, bool):
+            value = float(value)
+        entry = (type(value), value)
+        if entry not in self.constants:
+            self.constants.append(entry)
+        return self.constants.index(entry)
+
+    def child(self, proto):
+        self.children.append(proto)
+        return len(self.children) - 1
+
+    def local(self, name, start, end):
+        self.locals.append((name, start, end))
+
+    def label(self, name):
+        self.labels[name] = len(self.code)
+
+    def pc(self):
+        return len(self.code)
+
+    def abc(self, op, a=0, b=0, c=0):
+        self.max_stack = max(self.max_stack, a + 1)
+        self.code.append(OP[op] | a << 6 | c << 14 | b << 23)
+
+    def abx(self, op, a, bx):
+        self.max_stack = max(self.max_stack, a + 1)
+        self.code.append(OP[op] | a << 6 | bx << 14)
+
+    def asbx(self, op, a, target):
+        self.max_stack = max(self.max_stack, a + 1)
+        self.code.append((OP[op], a, target))
+
+    def resolve(self):
+        for pc, word in enumerate(self.code):
+            if isinstance(word, tuple):
+                op, a, label = word
+                sbx = self.labels[label] - (pc + 1)
+                self.code[pc] = op | a << 6 | (sbx + MAXARG_SBX) << 14
+
+    def serialize(self, source, platform):
+        self.resolve()
+        out = bytearray(platform.string(source))
+        out += platform.int(self.line) + platform.int(self.last_line)
+        out += bytes([len(self.upvalues), self.params, self.vararg, self.max_stack])
+        out += platform.int(len(self.code))
+        for word in self.code:
+            out += platform.instruction(word)
+        out += platform.int(len(self.constants))
+        for kind, value in self.constants:
+            if value is None:
+                out += bytes([0])
+            elif kind is bool:
+                out += bytes([1, int(value)])
+            elif kind is float:
+                out += bytes([3]) + platform.number(value)
+            else:
+                out += bytes([4]) + platform.string(value)
+        out += platform.int(len(self.children))
+        for child in self.children:
+            out += child.serialize(None, platform)
+        # line info: every instruction is attributed to the line the function starts on
+        out += platform.int(len(self.code))
+        out += platform.int(max(self.line, 1)) * len(self.code)
+        out += platform.int(len(self.locals))
+        for name, start, end in self.locals:
+            out += platform.string(name) + platform.int(start) + platform.int(end)
+        out += platform.int(len(self.upvalues))
+        for name in self.upvalues:
+            out += platform.string(name)
+        return bytes(out)
+
+
+def loops():
+    """
+    local sum = 0
+    for i = 1, 10 do
+        sum = sum + i
+    end
+    while sum > 0 do
+        sum = sum - 3
+    end
+    repeat
+        sum = sum + 1
+    until sum >= 5
+    for k, v in pairs(t) do
+        print(k, v)
+    end
+    return sum
+    """
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abx("LOADK", 0, main.k(0))
+    main.abx("LOADK", 1, main.k(1))
+    main.abx("LOADK", 2, main.k(10))
+    main.abx("LOADK", 3, main.k(1))
+    main.asbx("FORPREP", 1, "for_loop")
+    main.label("for_body")
+    main.abc("ADD", 0, 0, 4)
+    main.label("for_loop")
+    main.asbx("FORLOOP", 1, "for_body")
+    main.label("while")
+    main.abc("LT", 0, rk(main.k(0)), 0)
+    main.asbx("JMP", 0, "while_exit")
+    main.abc("SUB", 0, 0, rk(main.k(3)))
+    main.asbx("JMP", 0, "while")
+    main.label("while_exit")
+    main.label("repeat")
+    main.abc("ADD", 0, 0, rk(main.k(1)))
+    main.abc("LE", 0, rk(main.k(5)), 0)
+    main.asbx("JMP", 0, "repeat")
+    main.abx("GETGLOBAL", 1, main.k("pairs"))
+    main.abx("GETGLOBAL", 2, main.k("t"))
+    main.abc("CALL", 1, 2, 4)
+    main.asbx("JMP", 0, "tfor_loop")
+    main.label("tfor_body")
+    main.abx("GETGLOBAL", 6, main.k("print"))
+    main.abc("MOVE", 7, 4)
+    main.abc("MOVE", 8, 5)
+    main.abc("CALL", 6, 3, 1)
+    main.label("tfor_loop")
+    main.abc("TFORLOOP", 1, 0, 2)
+    main.asbx("JMP", 0, "tfor_body")
+    main.abc("RETURN", 0, 2)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 9
+    main.local("sum", 1, 26)
+    main.local("(for index)", 4, 7)
+    main.local("(for limit)", 4, 7)
+    main.local("(for step)", 4, 7)
+    main.local("i", 5, 6)
+    main.local("(for generator)", 17, 24)
+    main.local("(for state)", 17, 24)
+    main.local("(for control)", 17, 24)
+    main.local("k", 18, 22)
+    main.local("v", 18, 22)
+    return main
+
+
+def closures():
+    """
+    local count = 0
+    local function increment(n)
+        count = count + n
+        return count
+    end
+    local function make(x)
+        return function()
+            return x + count
+        end
+    end
+    increment(2)
+    return make(increment(1))
+    """
+    increment = Proto(params=1, upvalues=["count"], line=2, last_line=5)
+    increment.abc("GETUPVAL", 1, 0)
+    increment.abc("ADD", 1, 1, 0)
+    increment.abc("SETUPVAL", 1, 0)
+    increment.abc("GETUPVAL", 1, 0)
+    increment.abc("RETURN", 1, 2)
+    increment.abc("RETURN", 0, 1)
+    increment.local("n", 0, 6)
+
+    inner = Proto(upvalues=["x", "count"], line=7, last_line=9)
+    inner.abc("GETUPVAL", 0, 0)
+    inner.abc("GETUPVAL", 1, 1)
+    inner.abc("ADD", 0, 0, 1)
+    inner.abc("RETURN", 0, 2)
+    inner.abc("RETURN", 0, 1)
+
+    make = Proto(params=1, upvalues=["count"], line=6, last_line=10)
+    make.abx("CLOSURE", 1, make.child(inner))
+    make.abc("MOVE", 0, 0)
+    make.abc("GETUPVAL", 0, 0)
+    make.abc("RETURN", 1, 2)
+    make.abc("RETURN", 0, 1)
+    make.local("x", 0, 5)
+
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abx("LOADK", 0, main.k(0))
+    main.abx("CLOSURE", 1, main.child(increment))
+    main.abc("MOVE", 0, 0)
+    main.abx("CLOSURE", 2, main.child(make))
+    main.abc("MOVE", 0, 0)
+    main.abc("MOVE", 3, 1)
+    main.abx("LOADK", 4, main.k(2))
+    main.abc("CALL", 3, 2, 1)
+    main.abc("MOVE", 3, 2)
+    main.abc("MOVE", 4, 1)
+    main.abx("LOADK", 5, main.k(1))
+    main.abc("CALL", 4, 2, 0)
+    main.abc("TAILCALL", 3, 0, 0)
+    main.abc("RETURN", 3, 0)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 6
+    main.local("count", 1, 15)
+    main.local("increment", 1, 15)
+    main.local("make", 3, 15)
+    return main
+
+
+def varargs():
+    """
+    local function pack(...)
+        local first, second = ...
+        local count = select("#", ...)
+        return count, first, second, ...
+    end
+    return pack(1, 2, 3)
+    """
+    # with LUA_COMPAT_VARARG the implicit `arg` parameter still occupies register 0
+    pack = Proto(vararg=VARARG_HASARG | VARARG_ISVARARG, line=1, last_line=5)
+    pack.abc("VARARG", 1, 3)
+    pack.abx("GETGLOBAL", 3, pack.k("select"))
+    pack.abx("LOADK", 4, pack.k("#"))
+    pack.abc("VARARG", 5, 0)
+    pack.abc("CALL", 3, 0, 2)
+    pack.abc("MOVE", 4, 3)
+    pack.abc("MOVE", 5, 1)
+    pack.abc("MOVE", 6, 2)
+    pack.abc("VARARG", 7, 0)
+    pack.abc("RETURN", 4, 0)
+    pack.abc("RETURN", 0, 1)
+    pack.max_stack = 8
+    pack.local("arg", 0, 11)
+    pack.local("first", 1, 11)
+    pack.local("second", 1, 11)
+    pack.local("count", 5, 11)
+
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abx("CLOSURE", 0, main.child(pack))
+    main.abc("MOVE", 1, 0)
+    main.abx("LOADK", 2, main.k(1))
+    main.abx("LOADK", 3, main.k(2))
+    main.abx("LOADK", 4, main.k(3))
+    main.abc("TAILCALL", 1, 4, 0)
+    main.abc("RETURN", 1, 0)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 5
+    main.local("pack", 1, 8)
+    return main
+
+
+def method_calls():
+    """
+    local player = game.Players.LocalPlayer
+    local name = player.Name:upper()
+    player:Kick("bye " .. name)
+    workspace.Part:Destroy()
+    return name:sub(1, 3)
+    """
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abx("GETGLOBAL", 0, main.k("game"))
+    main.abc("GETTABLE", 0, 0, rk(main.k("Players")))
+    main.abc("GETTABLE", 0, 0, rk(main.k("LocalPlayer")))
+    main.abc("GETTABLE", 1, 0, rk(main.k("Name")))
+    main.abc("SELF", 1, 1, rk(main.k("upper")))
+    main.abc("CALL", 1, 2, 2)
+    main.abc("SELF", 2, 0, rk(main.k("Kick")))
+    main.abx("LOADK", 4, main.k("bye "))
+    main.abc("MOVE", 5, 1)
+    main.abc("CONCAT", 4, 4, 5)
+    main.abc("CALL", 2, 3, 1)
+    main.abx("GETGLOBAL", 2, main.k("workspace"))
+    main.abc("GETTABLE", 2, 2, rk(main.k("Part")))
+    main.abc("SELF", 2, 2, rk(main.k("Destroy")))
+    main.abc("CALL", 2, 2, 1)
+    main.abc("SELF", 2, 1, rk(main.k("sub")))
+    main.abx("LOADK", 4, main.k(1))
+    main.abx("LOADK", 5, main.k(3))
+    main.abc("TAILCALL", 2, 4, 0)
+    main.abc("RETURN", 2, 0)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 6
+    main.local("player", 3, 21)
+    main.local("name", 6, 21)
+    return main
+
+
+def setlist():
+    """
+    local function f()
+        return 4, 5
+    end
+    local list = {1, 2, 3, f()}
+    local map = {x = 1, y = list}
+    return list, map
+    """
+    f = Proto(line=1, last_line=3)
+    f.abx("LOADK", 0, f.k(4))
+    f.abx("LOADK", 1, f.k(5))
+    f.abc("RETURN", 0, 3)
+    f.abc("RETURN", 0, 1)
+
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abx("CLOSURE", 0, main.child(f))
+    main.abc("NEWTABLE", 1, 3, 0)
+    main.abx("LOADK", 2, main.k(1))
+    main.abx("LOADK", 3, main.k(2))
+    main.abx("LOADK", 4, main.k(3))
+    main.abc("MOVE", 5, 0)
+    main.abc("CALL", 5, 1, 0)
+    main.abc("SETLIST", 1, 0, 1)
+    main.abc("NEWTABLE", 2, 0, 2)
+    main.abc("SETTABLE", 2, rk(main.k("x")), rk(main.k(1)))
+    main.abc("SETTABLE", 2, rk(main.k("y")), 1)
+    main.abc("MOVE", 3, 1)
+    main.abc("MOVE", 4, 2)
+    main.abc("RETURN", 3, 3)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 6
+    main.local("f", 1, 15)
+    main.local("list", 8, 15)
+    main.local("map", 11, 15)
+    return main
+
+
+def conditionals():
+    """
+    local a, b, c = ...
+    if a and b or c then
+        print("first")
+    elseif a ~= nil and b == 5 then
+        print("second")
+    else
+        print("third")
+    end
+    return not a or b
+    """
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abc("VARARG", 0, 4)
+    main.abc("TEST", 0, 0, 0)
+    main.asbx("JMP", 0, "check_c")
+    main.abc("TEST", 1, 0, 1)
+    main.asbx("JMP", 0, "first")
+    main.label("check_c")
+    main.abc("TEST", 2, 0, 0)
+    main.asbx("JMP", 0, "elseif")
+    main.label("first")
+    main.abx("GETGLOBAL", 3, main.k("print"))
+    main.abx("LOADK", 4, main.k("first"))
+    main.abc("CALL", 3, 2, 1)
+    main.asbx("JMP", 0, "end")
+    main.label("elseif")
+    main.abc("EQ", 1, 0, rk(main.k(None)))
+    main.asbx("JMP", 0, "else")
+    main.abc("EQ", 0, 1, rk(main.k(5)))
+    main.asbx("JMP", 0, "else")
+    main.abx("GETGLOBAL", 3, main.k("print"))
+    main.abx("LOADK", 4, main.k("second"))
+    main.abc("CALL", 3, 2, 1)
+    main.asbx("JMP", 0, "end")
+    main.label("else")
+    main.abx("GETGLOBAL", 3, main.k("print"))
+    main.abx("LOADK", 4, main.k("third"))
+    main.abc("CALL", 3, 2, 1)
+    main.label("end")
+    main.abc("NOT", 3, 0)
+    main.abc("TEST", 3, 0, 1)
+    main.asbx("JMP", 0, "return")
+    main.abc("MOVE", 3, 1)
+    main.label("return")
+    main.abc("RETURN", 3, 2)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 5
+    main.local("a", 1, 28)
+    main.local("b", 1, 28)
+    main.local("c", 1, 28)
+    return main
+
+
+def shadowing():
+    """
+    local x = 1
+    do
+        local x = x + 1
+        print(x, x)
+    end
+    print(x)
+    """
+    main = Proto(vararg=VARARG_ISVARARG)
+    main.abx("LOADK", 0, main.k(1))
+    main.abc("ADD", 1, 0, rk(main.k(1)))
+    main.abx("GETGLOBAL", 2, main.k("print"))
+    main.abc("MOVE", 3, 1)
+    main.abc("MOVE", 4, 1)
+    main.abc("CALL", 2, 3, 1)
+    main.abx("GETGLOBAL", 1, main.k("print"))
+    main.abc("MOVE", 2, 0)
+    main.abc("CALL", 1, 2, 1)
+    main.abc("RETURN", 0, 1)
+    main.max_stack = 5
+    main.local("x", 1, 10)
+    main.local("x", 2, 6)
+    return main
+
+
+FIXTURES = {
+    "loops": loops,
+    "closures": closures,
+    "varargs": varargs,
+    "method_calls": method_calls,
+    "setlist": setlist,
+    "conditionals": conditionals,
+    "shadowing": shadowing,
+}
+
+
+def chunk(main, platform):
+    return platform.header() + main.serialize("@fixture.lua", platform)
+
+
+if __name__ == "__main__":
+    directory = os.path.dirname(os.path.abspath(__file__))
+    os.makedirs(os.path.join(directory, "platforms"), exist_ok=True)
+    for name, build in FIXTURES.items():
+        with open(os.path.join(directory, name + ".luac"), "wb") as file:
+            file.write(chunk(build(), NATIVE))
+        for platform_name, platform in PLATFORMS.items():
+            path = os.path.join(directory, "platforms", "{}.{}.luac".format(name, platform_name))
+            with open(path, "wb") as file:
+                file.write(chunk(build(), platform))
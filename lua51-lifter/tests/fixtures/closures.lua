@@ -0,0 +1,15 @@
+local count = 0
+local function increment(n)
+	-- upvalues: (ref) count
+	count = count + n
+	return count
+end
+local function make(x)
+	-- upvalues: (ref) count
+	return function()
+		-- upvalues: (ref) x, (ref) count
+		return x + count
+	end
+end
+increment(2)
+return make(increment(1))
\ No newline at end of file
@@ -0,0 +1,9 @@
+local a, b, c = ...
+if a and b or c then
+	print("first")
+elseif a == nil or b ~= 5 then
+	print("third")
+else
+	print("second")
+end
+return not a or b
\ No newline at end of file
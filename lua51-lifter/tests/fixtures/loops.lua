@@ -0,0 +1,20 @@
+local sum = 0
+for i = 1, 10 do
+	sum = sum + i
+end
+while sum > 0 do
+	sum = sum - 3
+end
+repeat
+	sum = sum + 1
+until sum >= 5
+local var, var_1, k = pairs(t)
+while true do
+	local v
+	k, v = var(var_1, k)
+	if k == nil then
+		break
+	end
+	print(k, v)
+end
+return sum
\ No newline at end of file
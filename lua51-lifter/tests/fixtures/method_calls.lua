@@ -0,0 +1,5 @@
+local player = game.Players.LocalPlayer
+local name = player.Name:upper()
+player:Kick("bye " .. name)
+workspace.Part:Destroy()
+return name:sub(1, 3)
\ No newline at end of file
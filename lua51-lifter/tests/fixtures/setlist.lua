@@ -0,0 +1,12 @@
+local list = {
+	1,
+	2,
+	3,
+	(function()
+		return 4, 5
+	end)()
+}
+return list, {
+	x = 1,
+	y = list
+}
\ No newline at end of file
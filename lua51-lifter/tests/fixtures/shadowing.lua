@@ -0,0 +1,4 @@
+local x = 1
+local x_1 = x + 1
+print(x_1, x_1)
+print(x)
\ No newline at end of file
@@ -0,0 +1,4 @@
+return (function(...)
+	local first, second = ...
+	return select("#", ...), first, second, ...
+end)(1, 2, 3)
\ No newline at end of file
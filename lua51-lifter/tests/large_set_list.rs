@@ -0,0 +1,103 @@
+//! A table constructor with more than 25550 array items flushes its last block with a C of
+//! 0 and stores the block number in the following word. Such a chunk is too large to keep
+//! as a fixture, so it is assembled here.
+
+use lua51_deserializer::chunk::Chunk;
+
+const LOADK: u32 = 1;
+const LOADNIL: u32 = 3;
+const NEWTABLE: u32 = 10;
+const JMP: u32 = 22;
+const TEST: u32 = 26;
+const RETURN: u32 = 30;
+const SETLIST: u32 = 34;
+const VARARG: u32 = 37;
+
+const MAXARG_C: u32 = (1 << 9) - 1;
+const MAXARG_SBX: i32 = ((1 << 18) - 1) >> 1;
+const FIELDS_PER_FLUSH: u32 = 50;
+
+fn abc(op: u32, a: u32, b: u32, c: u32) -> u32 {
+    op | a << 6 | c << 14 | b << 23
+}
+
+fn abx(op: u32, a: u32, bx: u32) -> u32 {
+    op | a << 6 | bx << 14
+}
+
+fn asbx(op: u32, a: u32, sbx: i32) -> u32 {
+    abx(op, a, (sbx + MAXARG_SBX) as u32)
+}
+
+// local flag = ...
+// local t = {1, 1, 1, ..., 1} -- 25551 items
+// if flag then
+//     t = nil
+// end
+// return t
+fn chunk(block_number: u32) -> Vec<u8> {
+    let mut code = vec![abc(VARARG, 0, 2, 0), abc(NEWTABLE, 1, 0, 0)];
+    for block in 1..=MAXARG_C {
+        code.extend((2..2 + FIELDS_PER_FLUSH).map(|register| abx(LOADK, register, 0)));
+        code.push(abc(SETLIST, 1, FIELDS_PER_FLUSH, block));
+    }
+    code.push(abx(LOADK, 2, 0));
+    code.push(abc(SETLIST, 1, 1, 0));
+    code.push(block_number);
+    code.extend([
+        abc(TEST, 0, 0, 0),
+        asbx(JMP, 0, 1),
+        abc(LOADNIL, 1, 1, 0),
+        abc(RETURN, 1, 2, 0),
+        abc(RETURN, 0, 1, 0),
+    ]);
+
+    // little endian, 4 byte int, 4 byte size_t, 4 byte instruction, 8 byte number, floating
+    let mut chunk = b"\x1bLua\x51\x00\x01\x04\x04\x04\x08\x00".to_vec();
+    // source name, line defined, last line defined
+    chunk.extend([0; 12]);
+    // upvalues, parameters, is_vararg, maximum stack size
+    chunk.extend([0, 0, 2, 2 + FIELDS_PER_FLUSH as u8]);
+    chunk.extend((code.len() as u32).to_le_bytes());
+    chunk.extend(code.iter().flat_map(|word| word.to_le_bytes()));
+    chunk.extend(1u32.to_le_bytes());
+    chunk.push(3);
+    chunk.extend(1f64.to_le_bytes());
+    // closures, line info, locals, upvalues
+    chunk.extend([0; 16]);
+    chunk
+}
+
+#[test]
+fn set_list_with_block_number_in_next_word() {
+    let output = lua51_lifter::decompile_bytecode(&chunk(MAXARG_C + 1)).unwrap();
+    let items = output
+        .lines()
+        .filter(|line| matches!(line.trim(), "1" | "1,"))
+        .count();
+    assert_eq!(
+        items,
+        (MAXARG_C * FIELDS_PER_FLUSH + 1) as usize,
+        "{}",
+        output
+    );
+    assert!(!output.contains("__set_list"), "{}", output);
+}
+
+#[test]
+fn set_list_with_block_number_0_in_next_word() {
+    assert_eq!(
+        lua51_lifter::decompile_bytecode(&chunk(0)),
+        Err("SETLIST with a block number of 0".into())
+    );
+}
+
+// the instruction count is read before any instructions, so it can't be trusted
+#[test]
+fn instruction_count_beyond_the_chunk() {
+    for count in [-1, i32::MAX] {
+        let mut chunk = chunk(MAXARG_C + 1);
+        chunk[28..32].copy_from_slice(&count.to_le_bytes());
+        assert!(Chunk::parse(&chunk).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! Every fixture in `tests/fixtures/platforms` is one of the fixtures as `assemble.py`
+//! dumps it with the header of another platform, `<fixture>.<platform>.luac`: big endian,
+//! 8 byte `int` or `size_t`, 4 byte floats or integral numbers. Decompiling it has to give
+//! the same output as the fixture itself.
+
+use std::{fs, path::Path};
+
+#[test]
+fn platforms() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut paths = fs::read_dir(fixtures.join("platforms"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "luac"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", fixtures.display());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let (fixture, _) = file_name.split_once('.').unwrap();
+        let native = fs::read(fixtures.join(fixture).with_extension("luac")).unwrap();
+        let expected = lua51_lifter::decompile_bytecode(&native).unwrap();
+        let output = lua51_lifter::decompile_bytecode(&fs::read(&path).unwrap()).unwrap();
+        if output != expected {
+            failures.push(format!(
+                "{} differs from {}.luac\n--- expected\n{}\n--- actual\n{}",
+                path.display(),
+                fixture,
+                expected,
+                output
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
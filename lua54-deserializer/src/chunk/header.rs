@@ -0,0 +1,133 @@
+use nom::{
+    bytes::complete::{tag, take},
+    error::{Error, ErrorKind, ParseError},
+    number::complete::le_u8,
+    Err, IResult,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+const LUAC_DATA: &[u8] = b"\x19\x93\r\n\x1a\n";
+const LUAC_INT: u64 = 0x5678;
+const LUAC_NUM: f64 = 370.5;
+
+/// The chunk header. Lua 5.4 doesn't store the endianness, it is found by reading the
+/// `LUAC_INT` check value, after which every fixed width field is read with it.
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub(crate) endianness: Endianness,
+    pub(crate) instruction_width: u8,
+    pub(crate) integer_width: u8,
+    pub(crate) number_width: u8,
+}
+
+fn failure<T>(input: &[u8], kind: ErrorKind) -> IResult<&[u8], T> {
+    Err(Err::Failure(Error::from_error_kind(input, kind)))
+}
+
+impl Header {
+    pub fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, _) = tag("\x1BLua")(input)?;
+        let (input, version_number) = le_u8(input)?;
+        // lua 5.3 has a different instruction set
+        if version_number != 0x54 {
+            return failure(input, ErrorKind::Verify);
+        }
+        // only the official format is supported
+        let (input, format) = le_u8(input)?;
+        if format != 0 {
+            return failure(input, ErrorKind::Switch);
+        }
+        let (input, _) = tag(LUAC_DATA)(input)?;
+        let (input, instruction_width) = le_u8(input)?;
+        let (input, integer_width) = le_u8(input)?;
+        let (input, number_width) = le_u8(input)?;
+
+        // instructions are 32 bits wide, integers and floating point numbers are either
+        // 32 or 64 bits wide
+        if instruction_width != 4
+            || !matches!(integer_width, 4 | 8)
+            || !matches!(number_width, 4 | 8)
+        {
+            return failure(input, ErrorKind::Verify);
+        }
+
+        let mut header = Self {
+            endianness: Endianness::Little,
+            instruction_width,
+            integer_width,
+            number_width,
+        };
+        let (_, check) = header.parse_unsigned(input, integer_width)?;
+        if check != LUAC_INT {
+            header.endianness = Endianness::Big;
+        }
+        let (input, check) = header.parse_unsigned(input, integer_width)?;
+        if check != LUAC_INT {
+            return failure(input, ErrorKind::Verify);
+        }
+        let (input, check) = header.parse_number(input)?;
+        if check != LUAC_NUM {
+            return failure(input, ErrorKind::Verify);
+        }
+
+        Ok((input, header))
+    }
+
+    fn parse_unsigned<'a>(&self, input: &'a [u8], width: u8) -> IResult<&'a [u8], u64> {
+        let (input, bytes) = take(width)(input)?;
+        let value = |acc: u64, &byte: &u8| acc << 8 | byte as u64;
+        let value = match self.endianness {
+            Endianness::Big => bytes.iter().fold(0, value),
+            Endianness::Little => bytes.iter().rev().fold(0, value),
+        };
+
+        Ok((input, value))
+    }
+
+    /// Parses a `lua_Integer`.
+    pub fn parse_integer<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], i64> {
+        let (input, value) = self.parse_unsigned(input, self.integer_width)?;
+        let unused = 64 - self.integer_width as u32 * 8;
+
+        Ok((input, ((value << unused) as i64) >> unused))
+    }
+
+    /// Parses a `lua_Number`.
+    pub fn parse_number<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], f64> {
+        let (input, bits) = self.parse_unsigned(input, self.number_width)?;
+        let value = match self.number_width {
+            4 => f32::from_bits(bits as u32) as f64,
+            _ => f64::from_bits(bits),
+        };
+
+        Ok((input, value))
+    }
+
+    pub fn parse_instruction<'a>(&self, input: &'a [u8]) -> IResult<&'a [u8], u32> {
+        let (input, instruction) = self.parse_unsigned(input, self.instruction_width)?;
+
+        Ok((input, instruction as u32))
+    }
+}
+
+/// Parses a size, count or line number. These are stored independently of the platform,
+/// most significant group first, in groups of 7 bits with the high bit set on the last one.
+pub fn parse_size(mut input: &[u8]) -> IResult<&[u8], u64> {
+    let mut value = 0u64;
+    loop {
+        let byte;
+        (input, byte) = le_u8(input)?;
+        if value > u64::MAX >> 7 {
+            return failure(input, ErrorKind::TooLarge);
+        }
+        value = value << 7 | (byte & 0x7F) as u64;
+        if byte & 0x80 != 0 {
+            return Ok((input, value));
+        }
+    }
+}
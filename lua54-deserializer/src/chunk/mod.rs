@@ -0,0 +1,30 @@
+use nom::{number::complete::le_u8, IResult};
+
+pub use header::Header;
+
+use crate::function::Function;
+
+pub mod header;
+
+#[derive(Debug)]
+pub struct Chunk<'a> {
+    /// The number of upvalues of the main function, one for `_ENV` in chunks from `luac`.
+    pub number_of_upvalues: u8,
+    pub function: Function<'a>,
+}
+
+impl<'a> Chunk<'a> {
+    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, header) = Header::parse(input)?;
+        let (input, number_of_upvalues) = le_u8(input)?;
+        let (input, function) = Function::parse(input, &header)?;
+
+        Ok((
+            input,
+            Self {
+                number_of_upvalues,
+                function,
+            },
+        ))
+    }
+}
@@ -0,0 +1,76 @@
+use nom::{multi::count, number::complete::le_u8, IResult};
+
+use crate::{
+    chunk::{header::parse_size, Header},
+    instruction::{position::Position, Instruction},
+    local::Local,
+    upvalue::Upvalue,
+    value::{parse_string, Value},
+};
+
+#[derive(Debug)]
+pub struct Function<'a> {
+    /// The source name, `None` when it is the same as the enclosing function's.
+    pub name: Option<&'a [u8]>,
+    pub line_defined: u32,
+    pub last_line_defined: u32,
+    pub number_of_parameters: u8,
+    pub is_vararg: bool,
+    pub maximum_stack_size: u8,
+    pub code: Vec<Instruction>,
+    pub constants: Vec<Value<'a>>,
+    pub upvalues: Vec<Upvalue<'a>>,
+    pub closures: Vec<Function<'a>>,
+    pub positions: Vec<Position>,
+    pub locals: Vec<Local<'a>>,
+}
+
+impl<'a> Function<'a> {
+    pub fn parse(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Self> {
+        let (input, name) = parse_string(input)?;
+        let (input, line_defined) = parse_size(input)?;
+        let (input, last_line_defined) = parse_size(input)?;
+        let (input, number_of_parameters) = le_u8(input)?;
+        let (input, is_vararg) = le_u8(input)?;
+        let (input, maximum_stack_size) = le_u8(input)?;
+        let (input, code) = Instruction::parse_list(input, header)?;
+        let (input, constants_length) = parse_size(input)?;
+        let (input, constants) = count(
+            |input| Value::parse(input, header),
+            constants_length as usize,
+        )(input)?;
+        let (input, upvalues_length) = parse_size(input)?;
+        let (input, mut upvalues) = count(Upvalue::parse, upvalues_length as usize)(input)?;
+        let (input, closures_length) = parse_size(input)?;
+        let (input, closures) = count(
+            |input| Self::parse(input, header),
+            closures_length as usize,
+        )(input)?;
+        let (input, positions) = Position::parse(input, line_defined as u32)?;
+        let (input, locals) = Local::parse_list(input)?;
+        // stripped chunks have no upvalue names
+        let (input, upvalue_names_length) = parse_size(input)?;
+        let (input, upvalue_names) = count(parse_string, upvalue_names_length as usize)(input)?;
+        for (upvalue, name) in upvalues.iter_mut().zip(upvalue_names) {
+            upvalue.name = name;
+        }
+
+        Ok((
+            input,
+            Self {
+                name,
+                line_defined: line_defined as u32,
+                last_line_defined: last_line_defined as u32,
+                number_of_parameters,
+                is_vararg: is_vararg != 0,
+                maximum_stack_size,
+                code,
+                constants,
+                upvalues,
+                closures,
+                positions,
+                locals,
+            },
+        ))
+    }
+}
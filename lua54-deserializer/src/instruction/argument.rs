@@ -0,0 +1,38 @@
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Register(pub u8);
+
+impl From<u8> for Register {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Constant(pub u32);
+
+#[derive(Debug, Copy, Clone)]
+pub struct Upvalue(pub u8);
+
+#[derive(Debug, Copy, Clone)]
+pub struct Function(pub u32);
+
+/// An operand of an arithmetic, comparison or table instruction. Small integers and the
+/// floats equal to them are encoded in the instruction itself.
+#[derive(Debug, Copy, Clone)]
+pub enum Operand {
+    Register(Register),
+    Constant(Constant),
+    Integer(i64),
+    Float(f64),
+}
+
+impl Operand {
+    // an `RK` argument, a constant when the `k` bit is set
+    pub(crate) fn register_or_constant(value: u32, is_constant: bool) -> Self {
+        if is_constant {
+            Self::Constant(Constant(value))
+        } else {
+            Self::Register(Register(value as u8))
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use nom::{
+    error::{Error, ErrorKind, ParseError},
+    multi::count,
+    Err, IResult,
+};
+
+use argument::{Constant, Function, Operand, Register, Upvalue};
+use operation_code::OperationCode;
+
+use crate::chunk::{header::parse_size, Header};
+
+pub mod argument;
+mod operation_code;
+pub mod position;
+
+const MAXIMUM_C: u32 = (1 << 8) - 1;
+const OFFSET_SB: i32 = ((1 << 17) - 1) >> 1;
+const OFFSET_SJ: i32 = ((1 << 25) - 1) >> 1;
+const OFFSET_SC: i32 = MAXIMUM_C as i32 >> 1;
+
+// the metamethod event of the first arithmetic operation
+const EVENT_ADD: u32 = 6;
+
+#[derive(Debug, Clone, Copy)]
+struct RawInstruction(u32);
+
+impl RawInstruction {
+    fn operation_code(self) -> Option<OperationCode> {
+        OperationCode::from_instruction(self.0)
+    }
+
+    fn a(self) -> u8 {
+        (self.0 >> 7) as u8
+    }
+
+    fn k(self) -> bool {
+        (self.0 >> 15) & 1 != 0
+    }
+
+    fn b(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    fn c(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    fn signed_b(self) -> i64 {
+        (self.b() as i32 - OFFSET_SC).into()
+    }
+
+    fn signed_c(self) -> i64 {
+        (self.c() as i32 - OFFSET_SC).into()
+    }
+
+    fn b_x(self) -> u32 {
+        self.0 >> 15
+    }
+
+    fn signed_b_x(self) -> i32 {
+        self.b_x() as i32 - OFFSET_SB
+    }
+
+    fn a_x(self) -> u32 {
+        self.0 >> 7
+    }
+
+    fn signed_j(self) -> i32 {
+        self.a_x() as i32 - OFFSET_SJ
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticOperation {
+    Add,
+    Sub,
+    Mul,
+    Mod,
+    Pow,
+    Div,
+    IDiv,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl ArithmeticOperation {
+    // in the same order as the operation codes and metamethod events
+    const ALL: [Self; 12] = [
+        Self::Add,
+        Self::Sub,
+        Self::Mul,
+        Self::Mod,
+        Self::Pow,
+        Self::Div,
+        Self::IDiv,
+        Self::BitAnd,
+        Self::BitOr,
+        Self::BitXor,
+        Self::ShiftLeft,
+        Self::ShiftRight,
+    ];
+
+    fn from_event(event: u32) -> Option<Self> {
+        Self::ALL
+            .get(event.checked_sub(EVENT_ADD)? as usize)
+            .copied()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Move {
+        destination: Register,
+        source: Register,
+    },
+    LoadConstant {
+        destination: Register,
+        source: Constant,
+    },
+    LoadInteger {
+        destination: Register,
+        value: i64,
+    },
+    LoadFloat {
+        destination: Register,
+        value: f64,
+    },
+    LoadBoolean {
+        destination: Register,
+        value: bool,
+        skip_next: bool,
+    },
+    LoadNil(Vec<Register>),
+    GetUpvalue {
+        destination: Register,
+        upvalue: Upvalue,
+    },
+    SetUpvalue {
+        destination: Upvalue,
+        source: Register,
+    },
+    GetIndex {
+        destination: Register,
+        object: Register,
+        key: Operand,
+    },
+    // indexing an upvalue, usually `_ENV` when accessing a global
+    GetUpvalueIndex {
+        destination: Register,
+        object: Upvalue,
+        key: Constant,
+    },
+    SetIndex {
+        object: Register,
+        key: Operand,
+        value: Operand,
+    },
+    SetUpvalueIndex {
+        object: Upvalue,
+        key: Constant,
+        value: Operand,
+    },
+    NewTable {
+        destination: Register,
+        array_size: u32,
+        hash_size: u32,
+    },
+    PrepMethodCall {
+        destination: Register,
+        self_arg: Register,
+        object: Register,
+        method: Operand,
+    },
+    Arithmetic {
+        operation: ArithmeticOperation,
+        destination: Register,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    Minus {
+        destination: Register,
+        operand: Register,
+    },
+    BitwiseNot {
+        destination: Register,
+        operand: Register,
+    },
+    Not {
+        destination: Register,
+        operand: Register,
+    },
+    Length {
+        destination: Register,
+        operand: Register,
+    },
+    Concatenate {
+        destination: Register,
+        operands: Vec<Register>,
+    },
+    Close(Register),
+    ToBeClosed(Register),
+    Jump(i32),
+    Equal {
+        lhs: Operand,
+        rhs: Operand,
+        invert: bool,
+    },
+    LessThan {
+        lhs: Operand,
+        rhs: Operand,
+        invert: bool,
+    },
+    LessThanOrEqual {
+        lhs: Operand,
+        rhs: Operand,
+        invert: bool,
+    },
+    GreaterThan {
+        lhs: Operand,
+        rhs: Operand,
+        invert: bool,
+    },
+    GreaterThanOrEqual {
+        lhs: Operand,
+        rhs: Operand,
+        invert: bool,
+    },
+    Test {
+        value: Register,
+        invert: bool,
+    },
+    TestSet {
+        destination: Register,
+        value: Register,
+        invert: bool,
+    },
+    Call {
+        function: Register,
+        arguments: u8,
+        return_values: u8,
+    },
+    TailCall {
+        function: Register,
+        arguments: u8,
+    },
+    Return(Register, u8),
+    IterateNumericForLoop {
+        // internal_counter, limit, step, external_counter
+        control: Vec<Register>,
+        // back to the body while the loop continues
+        skip: i32,
+    },
+    InitNumericForLoop {
+        // internal_counter, limit, step, external_counter
+        control: Vec<Register>,
+        // past the `IterateNumericForLoop` when the loop doesn't run at all
+        skip: i32,
+    },
+    // jumps to the `CallGenericForLoop` of the loop
+    InitGenericForLoop {
+        generator: Register,
+        state: Register,
+        internal_control: Register,
+        skip: i32,
+    },
+    CallGenericForLoop {
+        // ex. `next` in `for i, v in next, {}, 5`
+        generator: Register,
+        // ex. `{}` in `for i, v in next, {}, 5`
+        state: Register,
+        // internal control variable
+        // initial value ex. `5` in `for i, v in next, {}, 5`
+        internal_control: Register,
+        // variables returned by generator call, starting with the external control
+        vars: Vec<Register>,
+    },
+    // assigns the external control to the internal one and jumps back to the body
+    // while it isn't nil
+    IterateGenericForLoop {
+        internal_control: Register,
+        control: Register,
+        skip: i32,
+    },
+    SetList {
+        table: Register,
+        number_of_elements: u8,
+        // the number of elements set by previous `SetList`s
+        offset: u32,
+    },
+    Closure {
+        destination: Register,
+        function: Function,
+    },
+    VarArg(Register, u8),
+    PrepVarArg(u8),
+    // the metamethod fallback of the preceding arithmetic instruction, which is skipped
+    // when the arithmetic succeeds
+    Metamethod,
+    // the extra argument of the preceding instruction, never executed
+    Data(u32),
+}
+
+impl Instruction {
+    pub fn parse_list<'a>(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Vec<Self>> {
+        let (input, length) = parse_size(input)?;
+        let (input, words) = count(
+            |input| header.parse_instruction(input),
+            length as usize,
+        )(input)?;
+
+        let code = (0..words.len())
+            .map(|index| {
+                Self::decode(
+                    RawInstruction(words[index]),
+                    words.get(index + 1).copied().map(RawInstruction),
+                )
+                .ok_or_else(|| Err::Failure(Error::from_error_kind(input, ErrorKind::Switch)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok((input, code))
+    }
+
+    // decodes `instruction`, some instructions take an argument from the `next` one
+    fn decode(instruction: RawInstruction, next: Option<RawInstruction>) -> Option<Self> {
+        let extra_argument = || {
+            next.filter(|next| next.operation_code() == Some(OperationCode::ExtraArgument))
+                .map(RawInstruction::a_x)
+        };
+        let (a, b, c, k) = (
+            instruction.a(),
+            instruction.b(),
+            instruction.c(),
+            instruction.k(),
+        );
+        let register = |register: u8| Operand::Register(Register(register));
+        let constant = |constant: u8| Operand::Constant(Constant(constant.into()));
+        let immediate = |value: i64, is_float: bool| {
+            if is_float {
+                Operand::Float(value as f64)
+            } else {
+                Operand::Integer(value)
+            }
+        };
+
+        let instruction = match instruction.operation_code()? {
+            OperationCode::Move => Self::Move {
+                destination: Register(a),
+                source: Register(b),
+            },
+            OperationCode::LoadInteger => Self::LoadInteger {
+                destination: Register(a),
+                value: instruction.signed_b_x().into(),
+            },
+            OperationCode::LoadFloat => Self::LoadFloat {
+                destination: Register(a),
+                value: instruction.signed_b_x().into(),
+            },
+            OperationCode::LoadConstant => Self::LoadConstant {
+                destination: Register(a),
+                source: Constant(instruction.b_x()),
+            },
+            OperationCode::LoadConstantExtended => Self::LoadConstant {
+                destination: Register(a),
+                source: Constant(extra_argument()?),
+            },
+            OperationCode::LoadFalse | OperationCode::LoadFalseSkip | OperationCode::LoadTrue => {
+                Self::LoadBoolean {
+                    destination: Register(a),
+                    value: instruction.operation_code()? == OperationCode::LoadTrue,
+                    skip_next: instruction.operation_code()? == OperationCode::LoadFalseSkip,
+                }
+            }
+            OperationCode::LoadNil => Self::LoadNil((a..=a.checked_add(b)?).map(Register).collect()),
+            OperationCode::GetUpvalue => Self::GetUpvalue {
+                destination: Register(a),
+                upvalue: Upvalue(b),
+            },
+            OperationCode::SetUpvalue => Self::SetUpvalue {
+                destination: Upvalue(b),
+                source: Register(a),
+            },
+            OperationCode::GetUpvalueIndex => Self::GetUpvalueIndex {
+                destination: Register(a),
+                object: Upvalue(b),
+                key: Constant(c.into()),
+            },
+            OperationCode::GetIndex => Self::GetIndex {
+                destination: Register(a),
+                object: Register(b),
+                key: register(c),
+            },
+            OperationCode::GetIndexInteger => Self::GetIndex {
+                destination: Register(a),
+                object: Register(b),
+                key: Operand::Integer(c.into()),
+            },
+            OperationCode::GetField => Self::GetIndex {
+                destination: Register(a),
+                object: Register(b),
+                key: constant(c),
+            },
+            OperationCode::SetUpvalueIndex => Self::SetUpvalueIndex {
+                object: Upvalue(a),
+                key: Constant(b.into()),
+                value: Operand::register_or_constant(c.into(), k),
+            },
+            OperationCode::SetIndex => Self::SetIndex {
+                object: Register(a),
+                key: register(b),
+                value: Operand::register_or_constant(c.into(), k),
+            },
+            OperationCode::SetIndexInteger => Self::SetIndex {
+                object: Register(a),
+                key: Operand::Integer(b.into()),
+                value: Operand::register_or_constant(c.into(), k),
+            },
+            OperationCode::SetField => Self::SetIndex {
+                object: Register(a),
+                key: constant(b),
+                value: Operand::register_or_constant(c.into(), k),
+            },
+            OperationCode::NewTable => {
+                // the size of the array part doesn't fit in C when `k` is set
+                let array_size = if k {
+                    extra_argument()? * (MAXIMUM_C + 1) + c as u32
+                } else {
+                    c.into()
+                };
+                Self::NewTable {
+                    destination: Register(a),
+                    array_size,
+                    hash_size: if b == 0 { 0 } else { 1 << (b - 1) },
+                }
+            }
+            OperationCode::PrepMethodCall => Self::PrepMethodCall {
+                destination: Register(a),
+                self_arg: Register(a + 1),
+                object: Register(b),
+                method: Operand::register_or_constant(c.into(), k),
+            },
+            operation_code @ (OperationCode::AddInteger
+            | OperationCode::AddConstant
+            | OperationCode::SubtractConstant
+            | OperationCode::MultiplyConstant
+            | OperationCode::ModuloConstant
+            | OperationCode::PowerConstant
+            | OperationCode::DivideConstant
+            | OperationCode::IntegerDivideConstant
+            | OperationCode::BitwiseAndConstant
+            | OperationCode::BitwiseOrConstant
+            | OperationCode::BitwiseXorConstant
+            | OperationCode::ShiftRightInteger
+            | OperationCode::ShiftLeftInteger) => {
+                let (mut operation, mut lhs, mut rhs) = match operation_code {
+                    OperationCode::AddInteger => (
+                        ArithmeticOperation::Add,
+                        register(b),
+                        Operand::Integer(instruction.signed_c()),
+                    ),
+                    OperationCode::ShiftRightInteger => (
+                        ArithmeticOperation::ShiftRight,
+                        register(b),
+                        Operand::Integer(instruction.signed_c()),
+                    ),
+                    OperationCode::ShiftLeftInteger => (
+                        ArithmeticOperation::ShiftLeft,
+                        Operand::Integer(instruction.signed_c()),
+                        register(b),
+                    ),
+                    _ => (
+                        ArithmeticOperation::ALL[operation_code as usize
+                            - OperationCode::AddConstant as usize],
+                        register(b),
+                        constant(c),
+                    ),
+                };
+                // `x - 1` and `x << 1` are compiled to an addition and a right shift by the
+                // negated immediate, and constants on the left of commutative operations
+                // are moved to the right. the event and `k` of the metamethod fallback
+                // following the instruction tell the original operation. `1 << x` is
+                // flipped too, but already has the immediate on the left.
+                if let Some(metamethod) = next.filter(|next| {
+                    matches!(
+                        next.operation_code(),
+                        Some(
+                            OperationCode::MetamethodBinaryInteger
+                                | OperationCode::MetamethodBinaryConstant
+                        )
+                    )
+                }) {
+                    let event = ArithmeticOperation::from_event(metamethod.c().into())?;
+                    if event != operation
+                        && let Operand::Integer(value) = &mut rhs
+                    {
+                        *value = -*value;
+                    }
+                    operation = event;
+                    if metamethod.k() && operation_code != OperationCode::ShiftLeftInteger {
+                        std::mem::swap(&mut lhs, &mut rhs);
+                    }
+                }
+                Self::Arithmetic {
+                    operation,
+                    destination: Register(a),
+                    lhs,
+                    rhs,
+                }
+            }
+            operation_code @ (OperationCode::Add
+            | OperationCode::Subtract
+            | OperationCode::Multiply
+            | OperationCode::Modulo
+            | OperationCode::Power
+            | OperationCode::Divide
+            | OperationCode::IntegerDivide
+            | OperationCode::BitwiseAnd
+            | OperationCode::BitwiseOr
+            | OperationCode::BitwiseXor
+            | OperationCode::ShiftLeft
+            | OperationCode::ShiftRight) => Self::Arithmetic {
+                operation: ArithmeticOperation::ALL
+                    [operation_code as usize - OperationCode::Add as usize],
+                destination: Register(a),
+                lhs: register(b),
+                rhs: register(c),
+            },
+            OperationCode::MetamethodBinary
+            | OperationCode::MetamethodBinaryInteger
+            | OperationCode::MetamethodBinaryConstant => Self::Metamethod,
+            OperationCode::Minus => Self::Minus {
+                destination: Register(a),
+                operand: Register(b),
+            },
+            OperationCode::BitwiseNot => Self::BitwiseNot {
+                destination: Register(a),
+                operand: Register(b),
+            },
+            OperationCode::Not => Self::Not {
+                destination: Register(a),
+                operand: Register(b),
+            },
+            OperationCode::Length => Self::Length {
+                destination: Register(a),
+                operand: Register(b),
+            },
+            OperationCode::Concatenate => Self::Concatenate {
+                destination: Register(a),
+                operands: (a..a.checked_add(b)?).map(Register).collect(),
+            },
+            OperationCode::Close => Self::Close(Register(a)),
+            OperationCode::ToBeClosed => Self::ToBeClosed(Register(a)),
+            OperationCode::Jump => Self::Jump(instruction.signed_j()),
+            // the following jump is taken when the comparison is equal to `k`
+            OperationCode::Equal => Self::Equal {
+                lhs: register(a),
+                rhs: register(b),
+                invert: !k,
+            },
+            OperationCode::LessThan => Self::LessThan {
+                lhs: register(a),
+                rhs: register(b),
+                invert: !k,
+            },
+            OperationCode::LessThanOrEqual => Self::LessThanOrEqual {
+                lhs: register(a),
+                rhs: register(b),
+                invert: !k,
+            },
+            OperationCode::EqualConstant => Self::Equal {
+                lhs: register(a),
+                rhs: constant(b),
+                invert: !k,
+            },
+            // C is set when the immediate was written as a float
+            OperationCode::EqualInteger => Self::Equal {
+                lhs: register(a),
+                rhs: immediate(instruction.signed_b(), c != 0),
+                invert: !k,
+            },
+            OperationCode::LessThanInteger => Self::LessThan {
+                lhs: register(a),
+                rhs: immediate(instruction.signed_b(), c != 0),
+                invert: !k,
+            },
+            OperationCode::LessThanOrEqualInteger => Self::LessThanOrEqual {
+                lhs: register(a),
+                rhs: immediate(instruction.signed_b(), c != 0),
+                invert: !k,
+            },
+            OperationCode::GreaterThanInteger => Self::GreaterThan {
+                lhs: register(a),
+                rhs: immediate(instruction.signed_b(), c != 0),
+                invert: !k,
+            },
+            OperationCode::GreaterThanOrEqualInteger => Self::GreaterThanOrEqual {
+                lhs: register(a),
+                rhs: immediate(instruction.signed_b(), c != 0),
+                invert: !k,
+            },
+            OperationCode::Test => Self::Test {
+                value: Register(a),
+                invert: !k,
+            },
+            OperationCode::TestSet => Self::TestSet {
+                destination: Register(a),
+                value: Register(b),
+                invert: !k,
+            },
+            OperationCode::Call => Self::Call {
+                function: Register(a),
+                arguments: b,
+                return_values: c,
+            },
+            OperationCode::TailCall => Self::TailCall {
+                function: Register(a),
+                arguments: b,
+            },
+            OperationCode::Return => Self::Return(Register(a), b),
+            OperationCode::Return0 => Self::Return(Register(0), 1),
+            OperationCode::Return1 => Self::Return(Register(a), 2),
+            OperationCode::IterateNumericForLoop => Self::IterateNumericForLoop {
+                control: (a..=a.checked_add(3)?).map(Register).collect(),
+                skip: -(instruction.b_x() as i32),
+            },
+            OperationCode::InitNumericForLoop => Self::InitNumericForLoop {
+                control: (a..=a.checked_add(3)?).map(Register).collect(),
+                skip: instruction.b_x() as i32 + 1,
+            },
+            OperationCode::InitGenericForLoop => Self::InitGenericForLoop {
+                generator: Register(a),
+                state: Register(a.checked_add(1)?),
+                internal_control: Register(a.checked_add(2)?),
+                skip: instruction.b_x() as i32,
+            },
+            OperationCode::CallGenericForLoop => {
+                // the fourth control register holds the closing value
+                let vars = (a.checked_add(4)?..a.checked_add(4)?.checked_add(c)?)
+                    .map(Register)
+                    .collect::<Vec<_>>();
+                if vars.is_empty() {
+                    return None;
+                }
+                Self::CallGenericForLoop {
+                    generator: Register(a),
+                    state: Register(a + 1),
+                    internal_control: Register(a + 2),
+                    vars,
+                }
+            }
+            OperationCode::IterateGenericForLoop => Self::IterateGenericForLoop {
+                internal_control: Register(a.checked_add(2)?),
+                control: Register(a.checked_add(4)?),
+                skip: -(instruction.b_x() as i32),
+            },
+            OperationCode::SetList => Self::SetList {
+                table: Register(a),
+                number_of_elements: b,
+                offset: if k {
+                    extra_argument()? * (MAXIMUM_C + 1) + c as u32
+                } else {
+                    c.into()
+                },
+            },
+            OperationCode::Closure => Self::Closure {
+                destination: Register(a),
+                function: Function(instruction.b_x()),
+            },
+            OperationCode::VarArg => Self::VarArg(Register(a), c),
+            OperationCode::PrepVarArg => Self::PrepVarArg(a),
+            OperationCode::ExtraArgument => Self::Data(instruction.a_x()),
+        };
+
+        Some(instruction)
+    }
+}
@@ -0,0 +1,95 @@
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum OperationCode {
+    Move = 0,
+    LoadInteger,
+    LoadFloat,
+    LoadConstant,
+    LoadConstantExtended,
+    LoadFalse,
+    LoadFalseSkip,
+    LoadTrue,
+    LoadNil,
+    GetUpvalue,
+    SetUpvalue,
+    GetUpvalueIndex,
+    GetIndex,
+    GetIndexInteger,
+    GetField,
+    SetUpvalueIndex,
+    SetIndex,
+    SetIndexInteger,
+    SetField,
+    NewTable,
+    PrepMethodCall,
+    AddInteger,
+    AddConstant,
+    SubtractConstant,
+    MultiplyConstant,
+    ModuloConstant,
+    PowerConstant,
+    DivideConstant,
+    IntegerDivideConstant,
+    BitwiseAndConstant,
+    BitwiseOrConstant,
+    BitwiseXorConstant,
+    ShiftRightInteger,
+    ShiftLeftInteger,
+    Add,
+    Subtract,
+    Multiply,
+    Modulo,
+    Power,
+    Divide,
+    IntegerDivide,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    MetamethodBinary,
+    MetamethodBinaryInteger,
+    MetamethodBinaryConstant,
+    Minus,
+    BitwiseNot,
+    Not,
+    Length,
+    Concatenate,
+    Close,
+    ToBeClosed,
+    Jump,
+    Equal,
+    LessThan,
+    LessThanOrEqual,
+    EqualConstant,
+    EqualInteger,
+    LessThanInteger,
+    LessThanOrEqualInteger,
+    GreaterThanInteger,
+    GreaterThanOrEqualInteger,
+    Test,
+    TestSet,
+    Call,
+    TailCall,
+    Return,
+    Return0,
+    Return1,
+    IterateNumericForLoop,
+    InitNumericForLoop,
+    InitGenericForLoop,
+    CallGenericForLoop,
+    IterateGenericForLoop,
+    SetList,
+    Closure,
+    VarArg,
+    PrepVarArg,
+    ExtraArgument,
+}
+
+impl OperationCode {
+    pub fn from_instruction(instruction: u32) -> Option<Self> {
+        FromPrimitive::from_u32(instruction & 0x7F)
+    }
+}
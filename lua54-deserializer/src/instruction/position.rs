@@ -0,0 +1,51 @@
+use nom::{multi::count, number::complete::le_i8, IResult};
+
+use crate::chunk::header::parse_size;
+
+// marks an instruction whose line is stored in full instead of relative to the previous one
+const ABSOLUTE_LINE_INFO: i8 = -0x80;
+
+#[derive(Debug)]
+pub struct Position {
+    pub instruction: usize,
+    pub source: u32,
+}
+
+impl Position {
+    pub fn parse(input: &[u8], line_defined: u32) -> IResult<&[u8], Vec<Self>> {
+        let (input, line_info_length) = parse_size(input)?;
+        let (input, line_info) = count(le_i8, line_info_length as usize)(input)?;
+        let (input, absolute_line_info_length) = parse_size(input)?;
+        let (input, absolute_line_info) = count(
+            |input| {
+                let (input, instruction) = parse_size(input)?;
+                let (input, line) = parse_size(input)?;
+
+                Ok((input, (instruction as usize, line as u32)))
+            },
+            absolute_line_info_length as usize,
+        )(input)?;
+
+        let mut line = line_defined;
+        let positions = line_info
+            .into_iter()
+            .enumerate()
+            .map(|(instruction, difference)| {
+                line = if difference == ABSOLUTE_LINE_INFO {
+                    absolute_line_info
+                        .iter()
+                        .find(|&&(absolute, _)| absolute == instruction)
+                        .map_or(line, |&(_, line)| line)
+                } else {
+                    line.wrapping_add_signed(difference.into())
+                };
+                Self {
+                    instruction,
+                    source: line,
+                }
+            })
+            .collect();
+
+        Ok((input, positions))
+    }
+}
@@ -0,0 +1,33 @@
+use std::ops::Range;
+
+use nom::{multi::count, IResult};
+
+use crate::{chunk::header::parse_size, value::parse_string};
+
+#[derive(Debug)]
+pub struct Local<'a> {
+    pub name: &'a [u8],
+    pub range: Range<u32>,
+}
+
+impl<'a> Local<'a> {
+    pub fn parse_list(input: &'a [u8]) -> IResult<&'a [u8], Vec<Self>> {
+        let (input, length) = parse_size(input)?;
+
+        count(Self::parse, length as usize)(input)
+    }
+
+    fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, name) = parse_string(input)?;
+        let (input, start) = parse_size(input)?;
+        let (input, end) = parse_size(input)?;
+
+        Ok((
+            input,
+            Self {
+                name: name.unwrap_or_default(),
+                range: (start as u32..end as u32),
+            },
+        ))
+    }
+}
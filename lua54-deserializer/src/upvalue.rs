@@ -0,0 +1,33 @@
+use nom::{number::complete::le_u8, IResult};
+
+/// Where a closure gets an upvalue from when it is created.
+#[derive(Debug)]
+pub struct Upvalue<'a> {
+    /// The name from the debug info, `None` in stripped chunks.
+    pub name: Option<&'a [u8]>,
+    /// Whether the upvalue is a register of the enclosing function rather than one of its
+    /// upvalues.
+    pub in_stack: bool,
+    pub index: u8,
+    /// The kind of the captured local: regular, `<const>`, `<close>` or a compile time
+    /// constant.
+    pub kind: u8,
+}
+
+impl<'a> Upvalue<'a> {
+    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, in_stack) = le_u8(input)?;
+        let (input, index) = le_u8(input)?;
+        let (input, kind) = le_u8(input)?;
+
+        Ok((
+            input,
+            Self {
+                name: None,
+                in_stack: in_stack != 0,
+                index,
+                kind,
+            },
+        ))
+    }
+}
@@ -0,0 +1,65 @@
+use enum_as_inner::EnumAsInner;
+use nom::{
+    bytes::complete::take,
+    error::{Error, ErrorKind, ParseError},
+    number::complete::le_u8,
+    Err, IResult,
+};
+
+use crate::chunk::{header::parse_size, Header};
+
+#[derive(Debug, EnumAsInner)]
+pub enum Value<'a> {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(&'a [u8]),
+}
+
+impl<'a> Value<'a> {
+    pub fn parse(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Self> {
+        let (input, kind) = le_u8(input)?;
+
+        // the low nibble is the type, the high one the variant
+        match kind {
+            0x00 => Ok((input, Self::Nil)),
+            0x01 => Ok((input, Self::Boolean(false))),
+            0x11 => Ok((input, Self::Boolean(true))),
+            0x03 => {
+                let (input, value) = header.parse_integer(input)?;
+
+                Ok((input, Self::Integer(value)))
+            }
+            0x13 => {
+                let (input, value) = header.parse_number(input)?;
+
+                Ok((input, Self::Float(value)))
+            }
+            // short and long strings
+            0x04 | 0x14 => match parse_string(input)? {
+                (input, Some(value)) => Ok((input, Self::String(value))),
+                (input, None) => Err(Err::Failure(Error::from_error_kind(
+                    input,
+                    ErrorKind::Verify,
+                ))),
+            },
+            _ => Err(Err::Failure(Error::from_error_kind(
+                input,
+                ErrorKind::Switch,
+            ))),
+        }
+    }
+}
+
+/// Parses a string, which is `None` for a missing one like the source of a stripped chunk.
+/// The size includes a null terminator that isn't stored.
+pub fn parse_string(input: &[u8]) -> IResult<&[u8], Option<&[u8]>> {
+    let (input, size) = parse_size(input)?;
+    if size == 0 {
+        return Ok((input, None));
+    }
+    let (input, string) = take(size - 1)(input)?;
+
+    Ok((input, Some(string)))
+}
@@ -0,0 +1,40 @@
+//! The Lua 5.4 frontend of the decompiler. To decompile a file, run the `decompiler` binary,
+//! which detects the format of its input.
+
+mod lifter;
+use ast::formatter::Dialect;
+use decompiler_core::{Frontend, LiftedFunction};
+use lifter::Lifter;
+use lua54_deserializer::chunk::Chunk;
+use parking_lot::Mutex;
+use triomphe::Arc;
+
+/// Lua 5.4 bytecode. Lua 5.3 encodes its instructions differently and isn't supported,
+/// though the output can target it with [`Dialect::Lua53`].
+pub struct Lua54;
+
+impl Frontend for Lua54 {
+    fn lift(&self, bytecode: &[u8]) -> Result<Vec<LiftedFunction>, String> {
+        let (_, chunk) = Chunk::parse(bytecode).map_err(|error| {
+            format!("failed to parse bytecode: {}", error.map(|error| error.code))
+        })?;
+        let mut lifted = Vec::new();
+        let (function, upvalues) = Lifter::lift(&chunk.function, None, &mut lifted);
+        lifted.push((Arc::<Mutex<_>>::default(), function, upvalues));
+        lifted.reverse();
+        Ok(lifted)
+    }
+}
+
+pub fn decompile_bytecode(bytecode: &[u8]) -> Result<String, String> {
+    decompile_bytecode_with_dialect(bytecode, Dialect::Lua54)
+}
+
+/// Decompiles Lua 5.4 `bytecode` into source for `dialect`. Integer division and bitwise
+/// operators have no equivalent before Lua 5.3, so they are lowered to calls.
+pub fn decompile_bytecode_with_dialect(
+    bytecode: &[u8],
+    dialect: Dialect,
+) -> Result<String, String> {
+    decompiler_core::decompile_bytecode(&Lua54, bytecode, dialect)
+}
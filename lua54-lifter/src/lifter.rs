@@ -0,0 +1,933 @@
+use by_address::ByAddress;
+use cfg::block::{BlockEdge, BranchType};
+
+use itertools::Itertools;
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+
+use ast::{Local, LocalRw, RcLocal, Statement};
+use cfg::function::Function;
+use decompiler_core::LiftedFunction;
+
+use lua54_deserializer::{
+    argument::{Constant, Operand, Register},
+    instruction::ArithmeticOperation,
+    Function as BytecodeFunction, Instruction, Value,
+};
+
+use petgraph::{stable_graph::NodeIndex, visit::EdgeRef, Direction};
+
+use triomphe::Arc;
+
+pub struct Lifter<'a, 'b> {
+    bytecode: &'a BytecodeFunction<'a>,
+    // whether each upvalue is `_ENV`, the fields of which are globals
+    environment: Vec<bool>,
+    nodes: FxHashMap<usize, NodeIndex>,
+    insert_between: FxHashMap<NodeIndex, (NodeIndex, Vec<Statement>)>,
+    locals: FxHashMap<Register, RcLocal>,
+    registers: FxHashMap<RcLocal, Register>,
+    constants: FxHashMap<usize, ast::Literal>,
+    function: Function,
+    upvalues: Vec<RcLocal>,
+    lifted_functions: &'b mut Vec<LiftedFunction>,
+}
+
+impl<'a, 'b> Lifter<'a, 'b> {
+    fn allocate_locals(&mut self) {
+        self.upvalues.reserve(self.bytecode.upvalues.len());
+        for upvalue in &self.bytecode.upvalues {
+            let name = upvalue
+                .name
+                .map(|name| String::from_utf8_lossy(name).into_owned());
+            self.upvalues.push(RcLocal::new(Local::new(name)));
+        }
+
+        self.locals
+            .reserve(self.bytecode.maximum_stack_size as usize);
+        for i in 0..self.bytecode.maximum_stack_size {
+            let local = if i < self.bytecode.number_of_parameters {
+                let local = RcLocal::new(Local::new(self.local_name(Register(i), 0)));
+                self.function.parameters.push(local.clone());
+                local
+            } else {
+                RcLocal::default()
+            };
+            self.registers.insert(local.clone(), Register(i));
+            self.locals.insert(Register(i), local);
+        }
+    }
+
+    // the name the debug info gives the local in `register` once `pc` is reached. locals
+    // become active after the instruction initializing them, so instructions that can't
+    // write the register, like metamethod fallbacks, are skipped over.
+    fn local_name(&self, register: Register, mut pc: usize) -> Option<String> {
+        loop {
+            if let Some(local) = self
+                .bytecode
+                .locals
+                .iter()
+                .filter(|local| local.range.contains(&(pc as u32)))
+                .nth(register.0 as usize)
+            {
+                // internal locals like "(for state)" aren't valid names
+                return (!local.name.starts_with(b"("))
+                    .then(|| String::from_utf8_lossy(local.name).into_owned());
+            }
+            let instruction = self.bytecode.code.get(pc)?;
+            if Self::may_write(instruction, register) {
+                return None;
+            }
+            pc += 1;
+        }
+    }
+
+    // whether `instruction` might write `register` or leave straight line code
+    fn may_write(instruction: &Instruction, register: Register) -> bool {
+        let lowest = match instruction {
+            Instruction::SetUpvalue { .. }
+            | Instruction::SetIndex { .. }
+            | Instruction::SetUpvalueIndex { .. }
+            | Instruction::SetList { .. }
+            | Instruction::ToBeClosed(_)
+            | Instruction::Metamethod
+            | Instruction::PrepVarArg(_)
+            | Instruction::Data(_) => return false,
+            Instruction::Move { destination, .. }
+            | Instruction::LoadConstant { destination, .. }
+            | Instruction::LoadInteger { destination, .. }
+            | Instruction::LoadFloat { destination, .. }
+            | Instruction::LoadBoolean {
+                destination,
+                skip_next: false,
+                ..
+            }
+            | Instruction::GetUpvalue { destination, .. }
+            | Instruction::GetIndex { destination, .. }
+            | Instruction::GetUpvalueIndex { destination, .. }
+            | Instruction::NewTable { destination, .. }
+            | Instruction::PrepMethodCall { destination, .. }
+            | Instruction::Arithmetic { destination, .. }
+            | Instruction::Minus { destination, .. }
+            | Instruction::BitwiseNot { destination, .. }
+            | Instruction::Not { destination, .. }
+            | Instruction::Length { destination, .. }
+            | Instruction::Concatenate { destination, .. }
+            | Instruction::Closure { destination, .. }
+            | Instruction::VarArg(destination, _)
+            | Instruction::Call {
+                function: destination,
+                ..
+            } => destination,
+            Instruction::LoadNil(registers) => match registers.first() {
+                Some(first) => first,
+                None => return false,
+            },
+            _ => return true,
+        };
+        lowest.0 <= register.0
+    }
+
+    // writes values of named locals to a new local with the name first, which is then
+    // copied to the register. ssa construction propagates the copy, leaving the named local.
+    fn name_written_locals(&self, statements: &mut Vec<Statement>, start: usize, pc: usize) {
+        let mut index = start;
+        while index < statements.len() {
+            let mut copies = Vec::new();
+            if let Statement::Assign(assign) = &mut statements[index] {
+                for local in assign.left.iter_mut().filter_map(|l| l.as_local_mut()) {
+                    if let Some(&register) = self.registers.get(local)
+                        && let Some(name) = self.local_name(register, pc)
+                    {
+                        let named = RcLocal::new(Local::new(Some(name)));
+                        let register_local = std::mem::replace(local, named.clone());
+                        copies.push(
+                            ast::Assign::new(vec![register_local.into()], vec![named.into()])
+                                .into(),
+                        );
+                    }
+                }
+            }
+            index += 1;
+            let copied = copies.len();
+            statements.splice(index..index, copies);
+            index += copied;
+        }
+    }
+
+    // the instruction `skip` instructions after the one following `pc`
+    fn destination(pc: usize, skip: i32) -> usize {
+        (pc + 1).checked_add_signed(skip.try_into().unwrap()).unwrap()
+    }
+
+    fn create_block_map(&mut self) {
+        self.nodes.insert(0, self.function.new_block());
+        for (insn_index, insn) in self.bytecode.code.iter().enumerate() {
+            let successors = match *insn {
+                Instruction::LoadBoolean {
+                    skip_next: true, ..
+                }
+                | Instruction::Equal { .. }
+                | Instruction::LessThan { .. }
+                | Instruction::LessThanOrEqual { .. }
+                | Instruction::GreaterThan { .. }
+                | Instruction::GreaterThanOrEqual { .. }
+                | Instruction::Test { .. }
+                | Instruction::TestSet { .. } => vec![insn_index + 1, insn_index + 2],
+                Instruction::Jump(skip)
+                | Instruction::IterateNumericForLoop { skip, .. }
+                | Instruction::InitGenericForLoop { skip, .. }
+                | Instruction::IterateGenericForLoop { skip, .. } => {
+                    vec![Self::destination(insn_index, skip), insn_index + 1]
+                }
+                // the loop is entered through the `IterateNumericForLoop` like in lua 5.1,
+                // which checks the counter again
+                Instruction::InitNumericForLoop { skip, .. } => {
+                    vec![Self::destination(insn_index, skip - 1), insn_index + 1]
+                }
+                Instruction::Return(..) => vec![insn_index + 1],
+                _ => continue,
+            };
+            for successor in successors {
+                self.nodes
+                    .entry(successor)
+                    .or_insert_with(|| self.function.new_block());
+            }
+        }
+    }
+
+    fn code_ranges(&self) -> Vec<(usize, usize)> {
+        let mut nodes = self.nodes.keys().cloned().collect::<Vec<_>>();
+        nodes.sort_unstable();
+        let ends = nodes
+            .iter()
+            .skip(1)
+            .map(|&s| s - 1)
+            .chain(std::iter::once(self.bytecode.code.len() - 1));
+        nodes.iter().cloned().zip(ends).collect()
+    }
+
+    fn constant(&mut self, constant: Constant) -> ast::Literal {
+        self.constants
+            .entry(constant.0 as usize)
+            .or_insert_with(
+                || match self.bytecode.constants.get(constant.0 as usize).unwrap() {
+                    Value::Nil => ast::Literal::Nil,
+                    Value::Boolean(v) => ast::Literal::Boolean(*v),
+                    Value::Integer(v) => ast::Literal::Integer(*v),
+                    Value::Float(v) => ast::Literal::Number(*v),
+                    Value::String(v) => ast::Literal::String(v.to_vec()),
+                },
+            )
+            .clone()
+    }
+
+    fn operand(&mut self, operand: Operand) -> ast::RValue {
+        match operand {
+            Operand::Register(register) => self.locals[&register].clone().into(),
+            Operand::Constant(constant) => self.constant(constant).into(),
+            Operand::Integer(value) => ast::Literal::Integer(value).into(),
+            Operand::Float(value) => ast::Literal::Number(value).into(),
+        }
+    }
+
+    fn assign(&self, destination: Register, value: ast::RValue) -> Statement {
+        ast::Assign::new(vec![self.locals[&destination].clone().into()], vec![value]).into()
+    }
+
+    fn condition(value: ast::RValue, invert: bool) -> Statement {
+        let condition = if invert {
+            ast::Unary::new(value, ast::UnaryOperation::Not).into()
+        } else {
+            value
+        };
+        ast::If::new(condition, ast::Block::default(), ast::Block::default()).into()
+    }
+
+    // marks the local the value in `register` was last assigned from as to-be-closed. a
+    // local of its own is made when there is none, or when it is a register.
+    fn close_local(&self, register: Register, pc: usize, statements: &mut Vec<Statement>) {
+        let local = self.locals[&register].clone();
+        if let Some(Statement::Assign(assign)) = statements
+            .iter()
+            .rev()
+            .find(|statement| statement.values_written().contains(&&local))
+            && let [ast::LValue::Local(to)] = &assign.left[..]
+            && let [ast::RValue::Local(from)] = &assign.right[..]
+            && *to == local
+            && !self.registers.contains_key(from)
+        {
+            from.0.lock().1 = Some(ast::Attribute::Close);
+            return;
+        }
+        let closed = RcLocal::new(Local(
+            self.local_name(register, pc),
+            Some(ast::Attribute::Close),
+        ));
+        statements.push(ast::Assign::new(vec![closed.clone().into()], vec![local.clone().into()]).into());
+        statements.push(ast::Assign::new(vec![local.into()], vec![closed.into()]).into());
+    }
+
+    fn lift_instructions(&mut self, start: usize, end: usize, statements: &mut Vec<Statement>) {
+        if end > start {
+            statements.reserve(end - start + 1);
+        }
+        let bytecode = self.bytecode;
+        let mut top: Option<(ast::RValue, u8)> = None;
+        for pc in start..=end {
+            let instruction = &bytecode.code[pc];
+            let statements_start = statements.len();
+            match instruction {
+                &Instruction::Move {
+                    destination,
+                    source,
+                } => {
+                    statements.push(self.assign(destination, self.locals[&source].clone().into()));
+                }
+                &Instruction::LoadConstant {
+                    destination,
+                    source,
+                } => {
+                    let constant = self.constant(source);
+                    statements.push(self.assign(destination, constant.into()));
+                }
+                &Instruction::LoadInteger { destination, value } => {
+                    statements.push(self.assign(destination, ast::Literal::Integer(value).into()));
+                }
+                &Instruction::LoadFloat { destination, value } => {
+                    statements.push(self.assign(destination, ast::Literal::Number(value).into()));
+                }
+                &Instruction::LoadBoolean {
+                    destination, value, ..
+                } => {
+                    statements.push(self.assign(destination, ast::Literal::Boolean(value).into()));
+                }
+                Instruction::LoadNil(registers) => {
+                    for &register in registers {
+                        statements.push(self.assign(register, ast::Literal::Nil.into()));
+                    }
+                }
+                &Instruction::GetUpvalue {
+                    destination,
+                    upvalue,
+                } => {
+                    statements.push(
+                        self.assign(destination, self.upvalues[upvalue.0 as usize].clone().into()),
+                    );
+                }
+                &Instruction::SetUpvalue {
+                    destination,
+                    source,
+                } => {
+                    statements.push(
+                        ast::Assign::new(
+                            vec![self.upvalues[destination.0 as usize].clone().into()],
+                            vec![self.locals[&source].clone().into()],
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::GetIndex {
+                    destination,
+                    object,
+                    key,
+                } => {
+                    let key = self.operand(key);
+                    statements.push(self.assign(
+                        destination,
+                        ast::Index::new(self.locals[&object].clone().into(), key).into(),
+                    ));
+                }
+                &Instruction::GetUpvalueIndex {
+                    destination,
+                    object,
+                    key,
+                } => {
+                    let value = self.upvalue_index(object.0, key);
+                    statements.push(self.assign(destination, value));
+                }
+                &Instruction::SetIndex { object, key, value } => {
+                    let key = self.operand(key);
+                    let value = self.operand(value);
+                    statements.push(
+                        ast::Assign::new(
+                            vec![ast::Index::new(self.locals[&object].clone().into(), key).into()],
+                            vec![value],
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::SetUpvalueIndex { object, key, value } => {
+                    let target: ast::LValue = self.upvalue_index(object.0, key);
+                    let value = self.operand(value);
+                    statements.push(ast::Assign::new(vec![target], vec![value]).into());
+                }
+                &Instruction::NewTable { destination, .. } => {
+                    statements.push(self.assign(destination, ast::Table::default().into()));
+                }
+                &Instruction::PrepMethodCall {
+                    destination,
+                    self_arg,
+                    object,
+                    method,
+                } => {
+                    let object = self.locals[&object].clone();
+                    statements.push(self.assign(self_arg, object.clone().into()));
+                    let method = self.operand(method);
+                    statements
+                        .push(self.assign(destination, ast::Index::new(object.into(), method).into()));
+                }
+                &Instruction::Arithmetic {
+                    operation,
+                    destination,
+                    lhs,
+                    rhs,
+                } => {
+                    let lhs = self.operand(lhs);
+                    let rhs = self.operand(rhs);
+                    let operation = match operation {
+                        ArithmeticOperation::Add => ast::BinaryOperation::Add,
+                        ArithmeticOperation::Sub => ast::BinaryOperation::Sub,
+                        ArithmeticOperation::Mul => ast::BinaryOperation::Mul,
+                        ArithmeticOperation::Mod => ast::BinaryOperation::Mod,
+                        ArithmeticOperation::Pow => ast::BinaryOperation::Pow,
+                        ArithmeticOperation::Div => ast::BinaryOperation::Div,
+                        ArithmeticOperation::IDiv => ast::BinaryOperation::IDiv,
+                        ArithmeticOperation::BitAnd => ast::BinaryOperation::BitAnd,
+                        ArithmeticOperation::BitOr => ast::BinaryOperation::BitOr,
+                        ArithmeticOperation::BitXor => ast::BinaryOperation::BitXor,
+                        ArithmeticOperation::ShiftLeft => ast::BinaryOperation::ShiftLeft,
+                        ArithmeticOperation::ShiftRight => ast::BinaryOperation::ShiftRight,
+                    };
+                    statements
+                        .push(self.assign(destination, ast::Binary::new(lhs, rhs, operation).into()));
+                }
+                &Instruction::Minus {
+                    destination,
+                    operand,
+                }
+                | &Instruction::BitwiseNot {
+                    destination,
+                    operand,
+                }
+                | &Instruction::Not {
+                    destination,
+                    operand,
+                }
+                | &Instruction::Length {
+                    destination,
+                    operand,
+                } => {
+                    let operation = match instruction {
+                        Instruction::Minus { .. } => ast::UnaryOperation::Negate,
+                        Instruction::BitwiseNot { .. } => ast::UnaryOperation::BitNot,
+                        Instruction::Not { .. } => ast::UnaryOperation::Not,
+                        Instruction::Length { .. } => ast::UnaryOperation::Length,
+                        _ => unreachable!(),
+                    };
+                    statements.push(self.assign(
+                        destination,
+                        ast::Unary::new(self.locals[&operand].clone().into(), operation).into(),
+                    ));
+                }
+                Instruction::Concatenate {
+                    destination,
+                    operands,
+                } => {
+                    assert!(operands.len() >= 2);
+                    let mut operands = operands.iter().rev();
+
+                    let right = operands.next().unwrap();
+                    let left = operands.next().unwrap();
+                    let mut concat = ast::Binary::new(
+                        self.locals[left].clone().into(),
+                        self.locals[right].clone().into(),
+                        ast::BinaryOperation::Concat,
+                    );
+                    for r in operands {
+                        concat = ast::Binary::new(
+                            self.locals[r].clone().into(),
+                            concat.into(),
+                            ast::BinaryOperation::Concat,
+                        );
+                    }
+                    statements.push(self.assign(*destination, concat.into()));
+                }
+                &Instruction::Close(start) => {
+                    let locals = (start.0..self.bytecode.maximum_stack_size)
+                        .map(|i| self.locals[&Register(i)].clone())
+                        .collect();
+                    statements.push(ast::Close { locals }.into());
+                }
+                &Instruction::ToBeClosed(register) => {
+                    self.close_local(register, pc, statements);
+                }
+                &Instruction::Equal { lhs, rhs, invert }
+                | &Instruction::LessThan { lhs, rhs, invert }
+                | &Instruction::LessThanOrEqual { lhs, rhs, invert }
+                | &Instruction::GreaterThan { lhs, rhs, invert }
+                | &Instruction::GreaterThanOrEqual { lhs, rhs, invert } => {
+                    let operation = match instruction {
+                        Instruction::Equal { .. } => ast::BinaryOperation::Equal,
+                        Instruction::LessThan { .. } => ast::BinaryOperation::LessThan,
+                        Instruction::LessThanOrEqual { .. } => {
+                            ast::BinaryOperation::LessThanOrEqual
+                        }
+                        Instruction::GreaterThan { .. } => ast::BinaryOperation::GreaterThan,
+                        Instruction::GreaterThanOrEqual { .. } => {
+                            ast::BinaryOperation::GreaterThanOrEqual
+                        }
+                        _ => unreachable!(),
+                    };
+                    let lhs = self.operand(lhs);
+                    let rhs = self.operand(rhs);
+                    statements.push(Self::condition(
+                        ast::Binary::new(lhs, rhs, operation).into(),
+                        invert,
+                    ));
+                }
+                &Instruction::Test { value, invert } => {
+                    statements.push(Self::condition(self.locals[&value].clone().into(), invert));
+                }
+                &Instruction::TestSet {
+                    destination,
+                    value,
+                    invert,
+                } => {
+                    let value: ast::RValue = self.locals[&value].clone().into();
+                    statements.push(Self::condition(value.clone(), invert));
+
+                    // the value is only assigned when the following jump is taken
+                    let assign = self.assign(destination, value);
+                    self.function
+                        .block_mut(self.nodes[&(end + 1)])
+                        .unwrap()
+                        .push(assign);
+                }
+                &Instruction::TailCall {
+                    function,
+                    arguments,
+                }
+                | &Instruction::Call {
+                    function,
+                    arguments,
+                    ..
+                } => {
+                    let arguments = if arguments != 0 {
+                        (function.0 + 1..function.0 + arguments)
+                            .map(|r| self.locals[&Register(r)].clone().into())
+                            .collect()
+                    } else {
+                        let top = top.take().unwrap();
+                        (function.0 + 1..top.1)
+                            .map(|r| self.locals[&Register(r)].clone().into())
+                            .chain(std::iter::once(top.0))
+                            .collect()
+                    };
+
+                    let call = ast::Call::new(self.locals[&function].clone().into(), arguments);
+
+                    if let &Instruction::Call { return_values, .. } = instruction
+                        && return_values != 0
+                    {
+                        if return_values == 1 {
+                            statements.push(call.into());
+                        } else {
+                            statements.push(
+                                ast::Assign::new(
+                                    (function.0..function.0 + return_values - 1)
+                                        .map(|r| self.locals[&Register(r)].clone().into())
+                                        .collect_vec(),
+                                    vec![ast::RValue::Select(call.into())],
+                                )
+                                .into(),
+                            );
+                        }
+                    } else {
+                        top = Some((call.into(), function.0));
+                    }
+                }
+                &Instruction::Return(values, b) => {
+                    let values = if b != 0 {
+                        (values.0..values.0 + (b - 1))
+                            .map(|r| self.locals[&Register(r)].clone().into())
+                            .collect()
+                    } else {
+                        let (tail, end) = top.take().unwrap();
+                        (values.0..end)
+                            .map(|r| self.locals[&Register(r)].clone().into())
+                            .chain(std::iter::once(tail))
+                            .collect()
+                    };
+                    statements.push(ast::Return::new(values).into());
+                }
+                Instruction::InitNumericForLoop { control, .. } => {
+                    statements.push(
+                        ast::NumForInit::new(
+                            self.locals[&control[0]].clone(),
+                            self.locals[&control[1]].clone(),
+                            self.locals[&control[2]].clone(),
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::IterateNumericForLoop { ref control, skip } => {
+                    let (internal_counter, limit, step, external_counter) = (
+                        self.locals[&control[0]].clone(),
+                        self.locals[&control[1]].clone(),
+                        self.locals[&control[2]].clone(),
+                        self.locals[&control[3]].clone(),
+                    );
+                    statements.push(
+                        ast::NumForNext::new(internal_counter.clone(), limit.into(), step.into())
+                            .into(),
+                    );
+
+                    let body = Self::destination(pc, skip);
+                    let mut counter = vec![ast::Assign::new(
+                        vec![external_counter.into()],
+                        vec![internal_counter.into()],
+                    )
+                    .into()];
+                    self.name_written_locals(&mut counter, 0, body);
+                    assert!(self
+                        .insert_between
+                        .insert(self.nodes[&start], (self.nodes[&body], counter))
+                        .is_none());
+                }
+                &Instruction::InitGenericForLoop {
+                    generator,
+                    state,
+                    internal_control,
+                    ..
+                } => {
+                    // the closing value after the control is implied by the for statement
+                    let closing = self.locals[&Register(internal_control.0 + 1)].clone();
+                    if let Some(Statement::Assign(assign)) = statements.last_mut()
+                        && assign.left.last().and_then(|l| l.as_local()) == Some(&closing)
+                    {
+                        match &assign.right[..] {
+                            [ast::RValue::Select(_)] if assign.left.len() > 1 => {
+                                assign.left.pop();
+                            }
+                            [ast::RValue::Literal(ast::Literal::Nil)] if assign.left.len() == 1 => {
+                                statements.pop();
+                            }
+                            _ => {}
+                        }
+                    }
+                    statements.push(
+                        ast::GenericForInit::new(
+                            self.locals[&generator].clone(),
+                            self.locals[&state].clone(),
+                            self.locals[&internal_control].clone(),
+                        )
+                        .into(),
+                    );
+                }
+                Instruction::CallGenericForLoop {
+                    generator,
+                    state,
+                    vars,
+                    ..
+                } => {
+                    // the loop variables are named in the body the following
+                    // `IterateGenericForLoop` jumps back to
+                    let Some(&Instruction::IterateGenericForLoop { skip, .. }) =
+                        bytecode.code.get(pc + 1)
+                    else {
+                        panic!("generic for loop call without an iterate instruction");
+                    };
+                    let body = Self::destination(pc + 1, skip);
+                    let mut copies = Vec::new();
+                    let vars = vars
+                        .iter()
+                        .map(|&var| match self.local_name(var, body) {
+                            Some(name) => {
+                                let named = RcLocal::new(Local::new(Some(name)));
+                                copies.push(
+                                    ast::Assign::new(
+                                        vec![self.locals[&var].clone().into()],
+                                        vec![named.clone().into()],
+                                    )
+                                    .into(),
+                                );
+                                named
+                            }
+                            None => self.locals[&var].clone(),
+                        })
+                        .collect::<Vec<_>>();
+                    statements.push(
+                        ast::GenericForNext::new(
+                            vars,
+                            self.locals[generator].clone().into(),
+                            self.locals[state].clone(),
+                        )
+                        .into(),
+                    );
+                    if !copies.is_empty() {
+                        assert!(self
+                            .insert_between
+                            .insert(self.nodes[&start], (self.nodes[&body], copies))
+                            .is_none());
+                    }
+                }
+                &Instruction::SetList {
+                    table,
+                    number_of_elements,
+                    offset,
+                } => {
+                    let setlist = if number_of_elements != 0 {
+                        ast::SetList::new(
+                            self.locals[&table].clone(),
+                            offset as usize + 1,
+                            (table.0 + 1..table.0 + 1 + number_of_elements)
+                                .map(|r| self.locals[&Register(r)].clone().into())
+                                .collect(),
+                            None,
+                        )
+                    } else {
+                        let top = top.take().unwrap();
+                        ast::SetList::new(
+                            self.locals[&table].clone(),
+                            offset as usize + 1,
+                            (table.0 + 1..top.1)
+                                .map(|r| self.locals[&Register(r)].clone().into())
+                                .collect(),
+                            Some(top.0),
+                        )
+                    };
+                    statements.push(setlist.into());
+                }
+                &Instruction::Closure {
+                    destination,
+                    function,
+                } => {
+                    let closure = &bytecode.closures[function.0 as usize];
+                    let upvalues_passed = closure
+                        .upvalues
+                        .iter()
+                        .map(|upvalue| {
+                            if upvalue.in_stack {
+                                self.locals[&Register(upvalue.index)].clone()
+                            } else {
+                                self.upvalues[upvalue.index as usize].clone()
+                            }
+                        })
+                        .collect::<Vec<_>>();
+
+                    let ast_function = Arc::<Mutex<_>>::default();
+
+                    let (function, upvalues) =
+                        Lifter::lift(closure, Some(&self.environment), self.lifted_functions);
+                    self.lifted_functions
+                        .push((ast_function.clone(), function, upvalues));
+
+                    statements.push(
+                        self.assign(
+                            destination,
+                            ast::Closure {
+                                function: ByAddress(ast_function),
+                                upvalues: upvalues_passed
+                                    .into_iter()
+                                    .map(ast::Upvalue::Ref)
+                                    .collect(),
+                            }
+                            .into(),
+                        ),
+                    );
+                }
+                &Instruction::VarArg(destination, c) => {
+                    let vararg = ast::VarArg {};
+                    if c != 0 {
+                        statements.push(
+                            ast::Assign::new(
+                                (destination.0..destination.0 + c - 1)
+                                    .map(|r| self.locals[&Register(r)].clone().into())
+                                    .collect(),
+                                vec![ast::RValue::Select(vararg.into())],
+                            )
+                            .into(),
+                        );
+                    } else {
+                        top = Some((vararg.into(), destination.0));
+                    }
+                }
+                // the loop variables are written by the `CallGenericForLoop` before
+                Instruction::IterateGenericForLoop { .. }
+                | Instruction::Jump(_)
+                | Instruction::PrepVarArg(_)
+                | Instruction::Metamethod
+                | Instruction::Data(_) => {}
+            }
+
+            self.name_written_locals(statements, statements_start, pc + 1);
+
+            if matches!(instruction, Instruction::Return { .. }) {
+                break;
+            }
+        }
+    }
+
+    // a field of an upvalue, a global when the upvalue is `_ENV`
+    fn upvalue_index<T: From<ast::Global> + From<ast::Index>>(
+        &mut self,
+        upvalue: u8,
+        key: Constant,
+    ) -> T {
+        let key = self.constant(key);
+        if self.environment[upvalue as usize]
+            && let ast::Literal::String(name) = key
+        {
+            ast::Global::new(name).into()
+        } else {
+            ast::Index::new(self.upvalues[upvalue as usize].clone().into(), key.into()).into()
+        }
+    }
+
+    fn lift_blocks(&mut self) {
+        let ranges = self.code_ranges();
+        for (start, end) in ranges {
+            // a block might already have statements, see `Instruction::TestSet`
+            let mut statements =
+                std::mem::take(self.function.block_mut(self.nodes[&start]).unwrap());
+            self.lift_instructions(start, end, &mut statements);
+            *self.function.block_mut(self.nodes[&start]).unwrap() = statements;
+
+            let edges = match self.bytecode.code[end] {
+                Instruction::Equal { .. }
+                | Instruction::LessThan { .. }
+                | Instruction::LessThanOrEqual { .. }
+                | Instruction::GreaterThan { .. }
+                | Instruction::GreaterThanOrEqual { .. }
+                | Instruction::Test { .. }
+                | Instruction::TestSet { .. } => vec![
+                    (self.nodes[&(end + 1)], BlockEdge::new(BranchType::Then)),
+                    (self.nodes[&(end + 2)], BlockEdge::new(BranchType::Else)),
+                ],
+                Instruction::IterateNumericForLoop { skip, .. }
+                | Instruction::IterateGenericForLoop { skip, .. } => vec![
+                    (
+                        self.nodes[&Self::destination(end, skip)],
+                        BlockEdge::new(BranchType::Then),
+                    ),
+                    (self.nodes[&(end + 1)], BlockEdge::new(BranchType::Else)),
+                ],
+                Instruction::Jump(skip) | Instruction::InitGenericForLoop { skip, .. } => vec![(
+                    self.nodes[&Self::destination(end, skip)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                Instruction::InitNumericForLoop { skip, .. } => vec![(
+                    self.nodes[&Self::destination(end, skip - 1)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                Instruction::Return { .. } => Vec::new(),
+                Instruction::LoadBoolean { skip_next, .. } => vec![(
+                    self.nodes[&(end + 1 + skip_next as usize)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                _ if end + 1 != self.bytecode.code.len() => vec![(
+                    self.nodes[&(end + 1)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                _ => Vec::new(),
+            };
+            self.function.set_edges(self.nodes[&start], edges);
+        }
+    }
+
+    /// Lifts `bytecode` and the functions it defines, which are added to `lifted_functions`.
+    /// `environment` tells which upvalues of the enclosing function are `_ENV`, it is `None`
+    /// for the main function.
+    pub fn lift(
+        bytecode: &'a BytecodeFunction,
+        environment: Option<&[bool]>,
+        lifted_functions: &'b mut Vec<LiftedFunction>,
+    ) -> (Function, Vec<RcLocal>) {
+        let environment = bytecode
+            .upvalues
+            .iter()
+            .map(|upvalue| match (upvalue.name, environment) {
+                (Some(name), _) => name == b"_ENV",
+                // stripped chunks have no upvalue names, but the only upvalue of the main
+                // function is `_ENV` and closures capture it from there
+                (None, None) => true,
+                (None, Some(environment)) => {
+                    !upvalue.in_stack
+                        && environment
+                            .get(upvalue.index as usize)
+                            .copied()
+                            .unwrap_or(false)
+                }
+            })
+            .collect();
+        let mut context = Self {
+            bytecode,
+            environment,
+            nodes: FxHashMap::default(),
+            insert_between: FxHashMap::default(),
+            locals: FxHashMap::default(),
+            registers: FxHashMap::default(),
+            constants: FxHashMap::default(),
+            function: Function::new(0),
+            upvalues: Vec::new(),
+            lifted_functions,
+        };
+        context.function.is_variadic = bytecode.is_vararg;
+
+        context.create_block_map();
+        context.allocate_locals();
+        context.lift_blocks();
+
+        let stack_init_node = context.function.new_block();
+        let stack_init_block = context.function.block_mut(stack_init_node).unwrap();
+        stack_init_block.reserve(context.locals.len());
+        for (_, local) in context.locals {
+            if !context.function.parameters.contains(&local) {
+                let stack_init_block = context.function.block_mut(stack_init_node).unwrap();
+                stack_init_block.push(
+                    ast::Assign::new(vec![local.into()], vec![ast::Literal::Nil.into()]).into(),
+                )
+            }
+        }
+        context.function.set_edges(
+            stack_init_node,
+            vec![(context.nodes[&0], BlockEdge::new(BranchType::Unconditional))],
+        );
+        context.function.set_entry(stack_init_node);
+
+        for (node, (successor, stats)) in context.insert_between {
+            if context.function.predecessor_blocks(successor).count() == 1 {
+                context
+                    .function
+                    .block_mut(successor)
+                    .unwrap()
+                    .splice(0..0, stats);
+            } else {
+                let between_node = context.function.new_block();
+                context.function.block_mut(between_node).unwrap().extend(stats);
+                context.function.set_edges(
+                    between_node,
+                    vec![(successor, BlockEdge::new(BranchType::Unconditional))],
+                );
+                for edge in context
+                    .function
+                    .graph()
+                    .edges_directed(node, Direction::Outgoing)
+                    .filter(|e| e.target() == successor)
+                    .map(|e| e.id())
+                    .collect::<Vec<_>>()
+                {
+                    let edge = context.function.graph_mut().remove_edge(edge).unwrap();
+                    context
+                        .function
+                        .graph_mut()
+                        .add_edge(node, between_node, edge);
+                }
+            }
+        }
+
+        (context.function, context.upvalues)
+    }
+}
+
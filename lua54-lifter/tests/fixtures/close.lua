@@ -0,0 +1,8 @@
+local function open(name)
+	-- upvalues: (ref) _ENV
+	print("closing " .. name)
+	return setmetatable({}, mt)
+end
+local f <close> = open("a")
+f:write(#f)
+return open
\ No newline at end of file
@@ -0,0 +1,7 @@
+local n, s = ...
+if n >= 0 and n < 10 then
+	s = "small"
+elseif n == -1 then
+	s = nil
+end
+return s, n > 100, 3.25
\ No newline at end of file
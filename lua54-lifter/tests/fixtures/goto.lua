@@ -0,0 +1,5 @@
+for i = 1, 3 do
+	if i ~= 2 then
+		print(i)
+	end
+end
\ No newline at end of file
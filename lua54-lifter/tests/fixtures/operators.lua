@@ -0,0 +1,4 @@
+local a, b = ...
+x = a // 3 + b
+y = (a & 15 | a << 2) ~ ~a
+return a - 1, 2 * b, 1 << a, 2.0, 9007199254740993
\ No newline at end of file
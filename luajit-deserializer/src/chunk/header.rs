@@ -0,0 +1,152 @@
+use nom::{
+    bytes::complete::{tag, take},
+    error::{Error, ErrorKind, ParseError},
+    number::complete::{be_u16, be_u32, le_u16, le_u32, le_u8},
+    Err, IResult,
+};
+
+const FLAG_BIG_ENDIAN: u64 = 0x01;
+const FLAG_STRIPPED: u64 = 0x02;
+const FLAG_FFI: u64 = 0x04;
+const FLAG_TWO_SLOT_FRAMES: u64 = 0x08;
+// set by `-b -d`, which doesn't change the format
+const FLAG_DETERMINISTIC: u64 = 0x8000_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// LuaJIT 2.0
+    V2_0,
+    /// LuaJIT 2.1, which added instructions and frames that take two slots on 64 bit
+    /// targets.
+    V2_1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+pub(crate) fn failure<T>(input: &[u8], kind: ErrorKind) -> IResult<&[u8], T> {
+    Err(Err::Failure(Error::from_error_kind(input, kind)))
+}
+
+/// The chunk header. Every multi-byte field that isn't a ULEB128 is stored with the
+/// endianness of the platform the chunk was dumped on, which is recorded in the flags.
+#[derive(Debug, Clone, Copy)]
+pub struct Header<'a> {
+    pub version: Version,
+    pub endianness: Endianness,
+    /// Whether the chunk was dumped without debug info.
+    pub stripped: bool,
+    /// Whether the chunk uses FFI constants like `1LL`.
+    pub ffi: bool,
+    /// Whether calls take two slots for the function, in which case arguments start at
+    /// the second slot after it.
+    pub two_slot_frames: bool,
+    /// The chunk name, `None` in stripped chunks.
+    pub name: Option<&'a [u8]>,
+}
+
+impl<'a> Header<'a> {
+    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, _) = tag("\x1BLJ")(input)?;
+        let (input, version) = le_u8(input)?;
+        let version = match version {
+            1 => Version::V2_0,
+            2 => Version::V2_1,
+            _ => return failure(input, ErrorKind::Verify),
+        };
+        let (input, flags) = parse_uleb128(input)?;
+        let known_flags = FLAG_BIG_ENDIAN
+            | FLAG_STRIPPED
+            | FLAG_FFI
+            | FLAG_DETERMINISTIC
+            | if version == Version::V2_1 {
+                FLAG_TWO_SLOT_FRAMES
+            } else {
+                0
+            };
+        if flags & !known_flags != 0 {
+            return failure(input, ErrorKind::Verify);
+        }
+
+        let stripped = flags & FLAG_STRIPPED != 0;
+        let (input, name) = if stripped {
+            (input, None)
+        } else {
+            let (input, length) = parse_uleb128(input)?;
+            let (input, name) = take(length)(input)?;
+            (input, Some(name))
+        };
+
+        Ok((
+            input,
+            Self {
+                version,
+                endianness: if flags & FLAG_BIG_ENDIAN != 0 {
+                    Endianness::Big
+                } else {
+                    Endianness::Little
+                },
+                stripped,
+                ffi: flags & FLAG_FFI != 0,
+                two_slot_frames: flags & FLAG_TWO_SLOT_FRAMES != 0,
+                name,
+            },
+        ))
+    }
+
+    pub fn parse_u16<'b>(&self, input: &'b [u8]) -> IResult<&'b [u8], u16> {
+        match self.endianness {
+            Endianness::Big => be_u16(input),
+            Endianness::Little => le_u16(input),
+        }
+    }
+
+    pub fn parse_u32<'b>(&self, input: &'b [u8]) -> IResult<&'b [u8], u32> {
+        match self.endianness {
+            Endianness::Big => be_u32(input),
+            Endianness::Little => le_u32(input),
+        }
+    }
+}
+
+/// Parses a ULEB128, least significant group of 7 bits first, with the high bit set on
+/// every byte but the last one.
+pub fn parse_uleb128(mut input: &[u8]) -> IResult<&[u8], u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte;
+        (input, byte) = le_u8(input)?;
+        if shift >= u64::BITS {
+            return failure(input, ErrorKind::TooLarge);
+        }
+        value |= ((byte & 0x7F) as u64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok((input, value));
+        }
+    }
+}
+
+/// Parses the 33 bit ULEB128 number constants start with, the lowest bit of the first
+/// byte tells whether the constant is a float.
+pub(crate) fn parse_uleb128_33(input: &[u8]) -> IResult<&[u8], (u32, bool)> {
+    let (mut input, first) = le_u8(input)?;
+    let is_float = first & 1 != 0;
+    let mut value = (first >> 1) as u32 & 0x3F;
+    let mut byte = first;
+    let mut shift = 6;
+    while byte & 0x80 != 0 {
+        (input, byte) = le_u8(input)?;
+        if shift >= u32::BITS {
+            return failure(input, ErrorKind::TooLarge);
+        }
+        value |= ((byte & 0x7F) as u32) << shift;
+        shift += 7;
+    }
+
+    Ok((input, (value, is_float)))
+}
@@ -0,0 +1,41 @@
+use nom::{bytes::complete::take, error::ErrorKind, IResult};
+
+pub use header::Header;
+
+use crate::function::Function;
+use header::{failure, parse_uleb128};
+
+pub mod header;
+
+#[derive(Debug)]
+pub struct Chunk<'a> {
+    pub header: Header<'a>,
+    pub function: Function<'a>,
+}
+
+impl<'a> Chunk<'a> {
+    pub fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (mut input, header) = Header::parse(input)?;
+
+        // functions are dumped after the functions they define, which are taken off the
+        // stack again by the constant referring to them. the main function is last.
+        let mut functions = Vec::new();
+        loop {
+            let length;
+            (input, length) = parse_uleb128(input)?;
+            if length == 0 {
+                break;
+            }
+            let function;
+            (input, function) = take(length)(input)?;
+            let (_, function) = Function::parse(function, &header, &mut functions)?;
+            functions.push(function);
+        }
+        let function = match (functions.pop(), functions.is_empty()) {
+            (Some(function), true) => function,
+            _ => return failure(input, ErrorKind::Verify),
+        };
+
+        Ok((input, Self { header, function }))
+    }
+}
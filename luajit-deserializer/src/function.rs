@@ -0,0 +1,126 @@
+use nom::{
+    bytes::complete::{take, take_until},
+    multi::count,
+    number::complete::le_u8,
+    IResult,
+};
+
+use crate::{
+    chunk::{header::parse_uleb128, Header},
+    instruction::Instruction,
+    local::Local,
+    upvalue::Upvalue,
+    value::{Number, Value},
+};
+
+const FLAG_VARARG: u8 = 0x02;
+
+#[derive(Debug)]
+pub struct Function<'a> {
+    pub line_defined: u32,
+    pub number_of_lines: u32,
+    pub number_of_parameters: u8,
+    pub is_vararg: bool,
+    pub frame_size: u8,
+    /// The instructions after the function header, which isn't dumped.
+    pub code: Vec<Instruction>,
+    pub upvalues: Vec<Upvalue<'a>>,
+    /// The garbage collected constants, in the order instructions refer to them.
+    pub constants: Vec<Value<'a>>,
+    pub numbers: Vec<Number>,
+    /// The line of each instruction, empty in stripped chunks.
+    pub lines: Vec<u32>,
+    pub locals: Vec<Local<'a>>,
+}
+
+impl<'a> Function<'a> {
+    /// Parses a function, the functions it defines are taken from the end of `functions`.
+    pub fn parse(
+        input: &'a [u8],
+        header: &Header,
+        functions: &mut Vec<Function<'a>>,
+    ) -> IResult<&'a [u8], Self> {
+        let (input, flags) = le_u8(input)?;
+        let (input, number_of_parameters) = le_u8(input)?;
+        let (input, frame_size) = le_u8(input)?;
+        let (input, upvalues_length) = le_u8(input)?;
+        let (input, constants_length) = parse_uleb128(input)?;
+        let (input, numbers_length) = parse_uleb128(input)?;
+        let (input, code_length) = parse_uleb128(input)?;
+        let (input, debug_length) = if header.stripped {
+            (input, 0)
+        } else {
+            parse_uleb128(input)?
+        };
+        let (input, (line_defined, number_of_lines)) = if debug_length != 0 {
+            let (input, line_defined) = parse_uleb128(input)?;
+            let (input, number_of_lines) = parse_uleb128(input)?;
+            (input, (line_defined as u32, number_of_lines as u32))
+        } else {
+            (input, (0, 0))
+        };
+        let (input, code) = Instruction::parse_list(input, header, code_length as usize)?;
+        let (input, mut upvalues) = count(
+            |input| Upvalue::parse(input, header),
+            upvalues_length as usize,
+        )(input)?;
+        let (input, mut constants) = count(
+            |input| Value::parse(input, functions),
+            constants_length as usize,
+        )(input)?;
+        // the constant referred to as 0 is dumped last
+        constants.reverse();
+        let (input, numbers) = count(Number::parse, numbers_length as usize)(input)?;
+
+        let (input, lines, locals) = if debug_length != 0 {
+            // lines take as many bytes as are needed for the line count of the function
+            let (input, lines) = count(
+                |input| match number_of_lines {
+                    0..=0xFF => le_u8(input).map(|(input, line)| (input, line.into())),
+                    0x100..=0xFFFF => header
+                        .parse_u16(input)
+                        .map(|(input, line)| (input, line.into())),
+                    _ => header.parse_u32(input),
+                },
+                code_length as usize,
+            )(input)?;
+            let (input, upvalue_names) = count(
+                |input| {
+                    let (input, name) = take_until(&b"\0"[..])(input)?;
+                    let (input, _) = take(1usize)(input)?;
+
+                    Ok((input, name))
+                },
+                upvalues.len(),
+            )(input)?;
+            for (upvalue, name) in upvalues.iter_mut().zip(upvalue_names) {
+                upvalue.name = Some(name);
+            }
+            let (input, locals) = Local::parse_list(input)?;
+            let lines = lines
+                .into_iter()
+                .map(|line: u32| line_defined + line)
+                .collect();
+            (input, lines, locals)
+        } else {
+            (input, Vec::new(), Vec::new())
+        };
+
+        Ok((
+            input,
+            Self {
+                line_defined,
+                number_of_lines,
+                number_of_parameters,
+                is_vararg: flags & FLAG_VARARG != 0,
+                frame_size,
+                code,
+                upvalues,
+                constants,
+                numbers,
+                lines,
+                locals,
+            },
+        ))
+    }
+}
@@ -0,0 +1,47 @@
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Register(pub u8);
+
+impl From<u8> for Register {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+/// A garbage collected constant: a string, a table template, a function or an FFI number.
+#[derive(Debug, Copy, Clone)]
+pub struct Constant(pub u32);
+
+#[derive(Debug, Copy, Clone)]
+pub struct NumberConstant(pub u32);
+
+#[derive(Debug, Copy, Clone)]
+pub struct Upvalue(pub u8);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Primitive {
+    Nil,
+    False,
+    True,
+}
+
+impl Primitive {
+    pub(crate) fn new(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Nil),
+            1 => Some(Self::False),
+            2 => Some(Self::True),
+            _ => None,
+        }
+    }
+}
+
+/// An operand of a load, comparison, upvalue or table instruction.
+#[derive(Debug, Copy, Clone)]
+pub enum Operand {
+    Register(Register),
+    Constant(Constant),
+    Number(NumberConstant),
+    Primitive(Primitive),
+    /// A signed 16 bit number or an unsigned byte encoded in the instruction itself.
+    Integer(i32),
+}
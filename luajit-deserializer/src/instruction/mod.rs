@@ -0,0 +1,575 @@
+use nom::{
+    error::{Error, ErrorKind, ParseError},
+    multi::count,
+    Err, IResult,
+};
+
+use argument::{Constant, NumberConstant, Operand, Primitive, Register, Upvalue};
+use operation_code::OperationCode;
+
+use crate::chunk::Header;
+
+pub mod argument;
+mod operation_code;
+
+const OFFSET_J: i32 = 0x8000;
+
+#[derive(Debug, Clone, Copy)]
+struct RawInstruction(u32);
+
+impl RawInstruction {
+    fn a(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    fn c(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    fn b(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    fn d(self) -> u32 {
+        self.0 >> 16
+    }
+
+    fn j(self) -> i32 {
+        self.d() as i32 - OFFSET_J
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticOperation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+impl ArithmeticOperation {
+    // in the same order as the operation codes
+    const ALL: [Self; 5] = [Self::Add, Self::Sub, Self::Mul, Self::Div, Self::Mod];
+}
+
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Move {
+        destination: Register,
+        source: Register,
+    },
+    Load {
+        destination: Register,
+        value: Operand,
+    },
+    LoadNil(Vec<Register>),
+    GetUpvalue {
+        destination: Register,
+        upvalue: Upvalue,
+    },
+    SetUpvalue {
+        destination: Upvalue,
+        value: Operand,
+    },
+    GetGlobal {
+        destination: Register,
+        name: Constant,
+    },
+    SetGlobal {
+        name: Constant,
+        value: Register,
+    },
+    GetIndex {
+        destination: Register,
+        object: Register,
+        key: Operand,
+    },
+    SetIndex {
+        object: Register,
+        key: Operand,
+        value: Register,
+    },
+    NewTable {
+        destination: Register,
+        array_size: u32,
+        hash_size: u32,
+    },
+    // copies a table template, a constructor with constant fields
+    DuplicateTable {
+        destination: Register,
+        template: Constant,
+    },
+    Arithmetic {
+        operation: ArithmeticOperation,
+        destination: Register,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    Minus {
+        destination: Register,
+        operand: Register,
+    },
+    Not {
+        destination: Register,
+        operand: Register,
+    },
+    Length {
+        destination: Register,
+        operand: Register,
+    },
+    Concatenate {
+        destination: Register,
+        operands: Vec<Register>,
+    },
+    // closes the upvalues of the slots from `start` on and jumps
+    Close {
+        start: Register,
+        skip: i32,
+    },
+    Jump(i32),
+    // a loop hint for the jit compiler, `while` and `repeat` loops start with it
+    Loop,
+    // the following jump is taken when the comparison holds, unless it is inverted.
+    // `ISGE` and `ISGT` are inverted `ISLT` and `ISLE`, which is how they treat NaN.
+    Equal {
+        lhs: Register,
+        rhs: Operand,
+        invert: bool,
+    },
+    LessThan {
+        lhs: Register,
+        rhs: Register,
+        invert: bool,
+    },
+    LessThanOrEqual {
+        lhs: Register,
+        rhs: Register,
+        invert: bool,
+    },
+    Test {
+        value: Register,
+        invert: bool,
+    },
+    TestSet {
+        destination: Register,
+        value: Register,
+        invert: bool,
+    },
+    Call {
+        function: Register,
+        // the fixed arguments, which are followed by the values of the preceding
+        // instruction with multiple results when `variadic` is set
+        arguments: Vec<Register>,
+        variadic: bool,
+        return_values: u8,
+    },
+    TailCall {
+        function: Register,
+        arguments: Vec<Register>,
+        variadic: bool,
+    },
+    Return(Register, u8),
+    IterateNumericForLoop {
+        // internal_counter, limit, step, external_counter
+        control: Vec<Register>,
+        // back to the body while the loop continues
+        skip: i32,
+    },
+    InitNumericForLoop {
+        // internal_counter, limit, step, external_counter
+        control: Vec<Register>,
+        // past the `IterateNumericForLoop` when the loop doesn't run at all
+        skip: i32,
+    },
+    // jumps to the `CallGenericForLoop` of the loop, either an `ISNEXT` or a `JMP`
+    InitGenericForLoop {
+        generator: Register,
+        state: Register,
+        internal_control: Register,
+        skip: i32,
+    },
+    // `ITERC`, or `ITERN` when the generator is likely `next`
+    CallGenericForLoop {
+        // ex. `next` in `for i, v in next, {}, 5`
+        generator: Register,
+        // ex. `{}` in `for i, v in next, {}, 5`
+        state: Register,
+        // internal control variable
+        // initial value ex. `5` in `for i, v in next, {}, 5`
+        internal_control: Register,
+        // variables returned by generator call, starting with the external control
+        vars: Vec<Register>,
+    },
+    // assigns the external control to the internal one and jumps back to the body
+    // while it isn't nil
+    IterateGenericForLoop {
+        internal_control: Register,
+        control: Register,
+        skip: i32,
+    },
+    // sets the values of the preceding instruction with multiple results starting at
+    // the index in the low 32 bits of the number
+    SetList {
+        table: Register,
+        values: Register,
+        index: NumberConstant,
+    },
+    Closure {
+        destination: Register,
+        function: Constant,
+    },
+    VarArg(Register, u8),
+}
+
+impl Instruction {
+    pub fn parse_list<'a>(
+        input: &'a [u8],
+        header: &Header,
+        length: usize,
+    ) -> IResult<&'a [u8], Vec<Self>> {
+        let (input, words) = count(|input| header.parse_u32(input), length)(input)?;
+
+        let mut code = words
+            .into_iter()
+            .map(|word| {
+                Self::decode(RawInstruction(word), header)
+                    .ok_or_else(|| Err::Failure(Error::from_error_kind(input, ErrorKind::Switch)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // a generic for loop without `ISNEXT` starts with a jump to the `ITERC` right
+        // after its body, which jumps back to the instruction following the jump
+        for pc in 0..code.len() {
+            if let Self::Jump(skip) = code[pc]
+                && let Some(target) = (pc + 1).checked_add_signed(skip as isize)
+                && let Some(&Self::CallGenericForLoop {
+                    generator,
+                    state,
+                    internal_control,
+                    ..
+                }) = code.get(target)
+                && let Some(&Self::IterateGenericForLoop { skip: back, .. }) =
+                    code.get(target + 1)
+                && (target + 2).checked_add_signed(back as isize) == Some(pc + 1)
+            {
+                code[pc] = Self::InitGenericForLoop {
+                    generator,
+                    state,
+                    internal_control,
+                    skip,
+                };
+            }
+        }
+
+        Ok((input, code))
+    }
+
+    fn decode(instruction: RawInstruction, header: &Header) -> Option<Self> {
+        let (a, b, c, d) = (
+            instruction.a(),
+            instruction.b(),
+            instruction.c(),
+            instruction.d(),
+        );
+        let register = |register: u32| -> Option<Register> { Some(Register(register.try_into().ok()?)) };
+        let registers = |start: u8, length: u32| -> Option<Vec<Register>> {
+            let end = u8::try_from(start as u32 + length).ok()?;
+            Some((start..end).map(Register).collect())
+        };
+        // the arguments of a call start after the function, and the frame link with
+        // two slot frames
+        let arguments = |length: u32| {
+            registers(a.checked_add(1 + header.two_slot_frames as u8)?, length)
+        };
+
+        let operation_code = OperationCode::from_instruction(instruction.0, header.version)?;
+        let instruction = match operation_code {
+            OperationCode::IsLessThan | OperationCode::IsGreaterThanOrEqual => Self::LessThan {
+                lhs: Register(a),
+                rhs: register(d)?,
+                invert: operation_code == OperationCode::IsGreaterThanOrEqual,
+            },
+            OperationCode::IsLessThanOrEqual | OperationCode::IsGreaterThan => {
+                Self::LessThanOrEqual {
+                    lhs: Register(a),
+                    rhs: register(d)?,
+                    invert: operation_code == OperationCode::IsGreaterThan,
+                }
+            }
+            OperationCode::IsEqualVariable
+            | OperationCode::IsNotEqualVariable
+            | OperationCode::IsEqualString
+            | OperationCode::IsNotEqualString
+            | OperationCode::IsEqualNumber
+            | OperationCode::IsNotEqualNumber
+            | OperationCode::IsEqualPrimitive
+            | OperationCode::IsNotEqualPrimitive => Self::Equal {
+                lhs: Register(a),
+                rhs: match operation_code {
+                    OperationCode::IsEqualVariable | OperationCode::IsNotEqualVariable => {
+                        Operand::Register(register(d)?)
+                    }
+                    OperationCode::IsEqualString | OperationCode::IsNotEqualString => {
+                        Operand::Constant(Constant(d))
+                    }
+                    OperationCode::IsEqualNumber | OperationCode::IsNotEqualNumber => {
+                        Operand::Number(NumberConstant(d))
+                    }
+                    _ => Operand::Primitive(Primitive::new(d)?),
+                },
+                invert: matches!(
+                    operation_code,
+                    OperationCode::IsNotEqualVariable
+                        | OperationCode::IsNotEqualString
+                        | OperationCode::IsNotEqualNumber
+                        | OperationCode::IsNotEqualPrimitive
+                ),
+            },
+            OperationCode::IsTrueCopy | OperationCode::IsFalseCopy => Self::TestSet {
+                destination: Register(a),
+                value: register(d)?,
+                invert: operation_code == OperationCode::IsFalseCopy,
+            },
+            OperationCode::IsTrue | OperationCode::IsFalse => Self::Test {
+                value: register(d)?,
+                invert: operation_code == OperationCode::IsFalse,
+            },
+            OperationCode::Move => Self::Move {
+                destination: Register(a),
+                source: register(d)?,
+            },
+            OperationCode::Not => Self::Not {
+                destination: Register(a),
+                operand: register(d)?,
+            },
+            OperationCode::Minus => Self::Minus {
+                destination: Register(a),
+                operand: register(d)?,
+            },
+            OperationCode::Length => Self::Length {
+                destination: Register(a),
+                operand: register(d)?,
+            },
+            OperationCode::AddVariableNumber
+            | OperationCode::SubtractVariableNumber
+            | OperationCode::MultiplyVariableNumber
+            | OperationCode::DivideVariableNumber
+            | OperationCode::ModuloVariableNumber => Self::Arithmetic {
+                operation: ArithmeticOperation::ALL
+                    [operation_code as usize - OperationCode::AddVariableNumber as usize],
+                destination: Register(a),
+                lhs: Operand::Register(Register(b)),
+                rhs: Operand::Number(NumberConstant(c.into())),
+            },
+            OperationCode::AddNumberVariable
+            | OperationCode::SubtractNumberVariable
+            | OperationCode::MultiplyNumberVariable
+            | OperationCode::DivideNumberVariable
+            | OperationCode::ModuloNumberVariable => Self::Arithmetic {
+                operation: ArithmeticOperation::ALL
+                    [operation_code as usize - OperationCode::AddNumberVariable as usize],
+                destination: Register(a),
+                lhs: Operand::Number(NumberConstant(c.into())),
+                rhs: Operand::Register(Register(b)),
+            },
+            OperationCode::AddVariableVariable
+            | OperationCode::SubtractVariableVariable
+            | OperationCode::MultiplyVariableVariable
+            | OperationCode::DivideVariableVariable
+            | OperationCode::ModuloVariableVariable
+            | OperationCode::Power => Self::Arithmetic {
+                operation: match operation_code {
+                    OperationCode::Power => ArithmeticOperation::Pow,
+                    _ => ArithmeticOperation::ALL
+                        [operation_code as usize - OperationCode::AddVariableVariable as usize],
+                },
+                destination: Register(a),
+                lhs: Operand::Register(Register(b)),
+                rhs: Operand::Register(Register(c)),
+            },
+            OperationCode::Concatenate => Self::Concatenate {
+                destination: Register(a),
+                operands: (b..=c).map(Register).collect(),
+            },
+            OperationCode::LoadString | OperationCode::LoadCData => Self::Load {
+                destination: Register(a),
+                value: Operand::Constant(Constant(d)),
+            },
+            OperationCode::LoadShort => Self::Load {
+                destination: Register(a),
+                value: Operand::Integer(d as u16 as i16 as i32),
+            },
+            OperationCode::LoadNumber => Self::Load {
+                destination: Register(a),
+                value: Operand::Number(NumberConstant(d)),
+            },
+            OperationCode::LoadPrimitive => Self::Load {
+                destination: Register(a),
+                value: Operand::Primitive(Primitive::new(d)?),
+            },
+            OperationCode::LoadNil => Self::LoadNil(registers(a, (d + 1).checked_sub(a.into())?)?),
+            OperationCode::GetUpvalue => Self::GetUpvalue {
+                destination: Register(a),
+                upvalue: Upvalue(d.try_into().ok()?),
+            },
+            OperationCode::SetUpvalueVariable
+            | OperationCode::SetUpvalueString
+            | OperationCode::SetUpvalueNumber
+            | OperationCode::SetUpvaluePrimitive => Self::SetUpvalue {
+                destination: Upvalue(a),
+                value: match operation_code {
+                    OperationCode::SetUpvalueVariable => Operand::Register(register(d)?),
+                    OperationCode::SetUpvalueString => Operand::Constant(Constant(d)),
+                    OperationCode::SetUpvalueNumber => Operand::Number(NumberConstant(d)),
+                    _ => Operand::Primitive(Primitive::new(d)?),
+                },
+            },
+            OperationCode::CloseUpvalues => Self::Close {
+                start: Register(a),
+                skip: instruction.j(),
+            },
+            OperationCode::NewClosure => Self::Closure {
+                destination: Register(a),
+                function: Constant(d),
+            },
+            // the low 11 bits are the size of the array part, the rest the logarithm of
+            // the size of the hash part
+            OperationCode::NewTable => Self::NewTable {
+                destination: Register(a),
+                array_size: d & 0x7FF,
+                hash_size: match d >> 11 {
+                    0 => 0,
+                    bits => 1 << bits,
+                },
+            },
+            OperationCode::DuplicateTable => Self::DuplicateTable {
+                destination: Register(a),
+                template: Constant(d),
+            },
+            OperationCode::GetGlobal => Self::GetGlobal {
+                destination: Register(a),
+                name: Constant(d),
+            },
+            OperationCode::SetGlobal => Self::SetGlobal {
+                name: Constant(d),
+                value: Register(a),
+            },
+            OperationCode::GetTableVariable
+            | OperationCode::GetTableString
+            | OperationCode::GetTableByte
+            | OperationCode::GetTableRaw => Self::GetIndex {
+                destination: Register(a),
+                object: Register(b),
+                key: match operation_code {
+                    OperationCode::GetTableString => Operand::Constant(Constant(c.into())),
+                    OperationCode::GetTableByte => Operand::Integer(c.into()),
+                    _ => Operand::Register(Register(c)),
+                },
+            },
+            OperationCode::SetTableVariable
+            | OperationCode::SetTableString
+            | OperationCode::SetTableByte
+            | OperationCode::SetTableRaw => Self::SetIndex {
+                object: Register(b),
+                key: match operation_code {
+                    OperationCode::SetTableString => Operand::Constant(Constant(c.into())),
+                    OperationCode::SetTableByte => Operand::Integer(c.into()),
+                    _ => Operand::Register(Register(c)),
+                },
+                value: Register(a),
+            },
+            OperationCode::SetTableMultiple => Self::SetList {
+                table: Register(a.checked_sub(1)?),
+                values: Register(a),
+                index: NumberConstant(d),
+            },
+            OperationCode::CallMultiple => Self::Call {
+                function: Register(a),
+                arguments: arguments(c.into())?,
+                variadic: true,
+                return_values: b,
+            },
+            OperationCode::Call => Self::Call {
+                function: Register(a),
+                arguments: arguments((c as u32).checked_sub(1)?)?,
+                variadic: false,
+                return_values: b,
+            },
+            OperationCode::TailCallMultiple => Self::TailCall {
+                function: Register(a),
+                arguments: arguments(d)?,
+                variadic: true,
+            },
+            OperationCode::TailCall => Self::TailCall {
+                function: Register(a),
+                arguments: arguments(d.checked_sub(1)?)?,
+                variadic: false,
+            },
+            OperationCode::IteratorCall | OperationCode::IteratorNext => {
+                let vars = registers(a, (b as u32).checked_sub(1)?)?;
+                if vars.is_empty() {
+                    return None;
+                }
+                Self::CallGenericForLoop {
+                    generator: Register(a.checked_sub(3)?),
+                    state: Register(a - 2),
+                    internal_control: Register(a - 1),
+                    vars,
+                }
+            }
+            OperationCode::VarArg => Self::VarArg(Register(a), b),
+            // like `ITERN`, `A` is the first loop variable
+            OperationCode::IsNext => Self::InitGenericForLoop {
+                generator: Register(a.checked_sub(3)?),
+                state: Register(a - 2),
+                internal_control: Register(a - 1),
+                skip: instruction.j(),
+            },
+            // the number of values plus one, zero when the values of the preceding
+            // instruction with multiple results follow
+            OperationCode::ReturnMultiple => Self::Return(Register(a), 0),
+            OperationCode::Return => Self::Return(Register(a), d.try_into().ok()?),
+            OperationCode::Return0 => Self::Return(Register(a), 1),
+            OperationCode::Return1 => Self::Return(Register(a), 2),
+            OperationCode::ForInit => Self::InitNumericForLoop {
+                control: registers(a, 4)?,
+                skip: instruction.j(),
+            },
+            OperationCode::ForLoop | OperationCode::InterpreterForLoop => {
+                Self::IterateNumericForLoop {
+                    control: registers(a, 4)?,
+                    skip: instruction.j(),
+                }
+            }
+            OperationCode::IteratorLoop | OperationCode::InterpreterIteratorLoop => {
+                Self::IterateGenericForLoop {
+                    internal_control: Register(a.checked_sub(1)?),
+                    control: Register(a),
+                    skip: instruction.j(),
+                }
+            }
+            OperationCode::Loop | OperationCode::InterpreterLoop => Self::Loop,
+            OperationCode::Jump => Self::Jump(instruction.j()),
+            // type checks and instructions referring to compiled traces or native code
+            // are never dumped
+            OperationCode::IsType
+            | OperationCode::IsNumber
+            | OperationCode::JitForInit
+            | OperationCode::JitForLoop
+            | OperationCode::JitIteratorLoop
+            | OperationCode::JitLoop
+            | OperationCode::Function
+            | OperationCode::InterpreterFunction
+            | OperationCode::JitFunction
+            | OperationCode::VarArgFunction
+            | OperationCode::InterpreterVarArgFunction
+            | OperationCode::JitVarArgFunction
+            | OperationCode::CFunction
+            | OperationCode::WrappedCFunction => return None,
+        };
+
+        Some(instruction)
+    }
+}
@@ -0,0 +1,126 @@
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+use crate::chunk::header::Version;
+
+// numbered like LuaJIT 2.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum OperationCode {
+    IsLessThan = 0,
+    IsGreaterThanOrEqual,
+    IsLessThanOrEqual,
+    IsGreaterThan,
+    IsEqualVariable,
+    IsNotEqualVariable,
+    IsEqualString,
+    IsNotEqualString,
+    IsEqualNumber,
+    IsNotEqualNumber,
+    IsEqualPrimitive,
+    IsNotEqualPrimitive,
+    IsTrueCopy,
+    IsFalseCopy,
+    IsTrue,
+    IsFalse,
+    IsType,
+    IsNumber,
+    Move,
+    Not,
+    Minus,
+    Length,
+    AddVariableNumber,
+    SubtractVariableNumber,
+    MultiplyVariableNumber,
+    DivideVariableNumber,
+    ModuloVariableNumber,
+    AddNumberVariable,
+    SubtractNumberVariable,
+    MultiplyNumberVariable,
+    DivideNumberVariable,
+    ModuloNumberVariable,
+    AddVariableVariable,
+    SubtractVariableVariable,
+    MultiplyVariableVariable,
+    DivideVariableVariable,
+    ModuloVariableVariable,
+    Power,
+    Concatenate,
+    LoadString,
+    LoadCData,
+    LoadShort,
+    LoadNumber,
+    LoadPrimitive,
+    LoadNil,
+    GetUpvalue,
+    SetUpvalueVariable,
+    SetUpvalueString,
+    SetUpvalueNumber,
+    SetUpvaluePrimitive,
+    CloseUpvalues,
+    NewClosure,
+    NewTable,
+    DuplicateTable,
+    GetGlobal,
+    SetGlobal,
+    GetTableVariable,
+    GetTableString,
+    GetTableByte,
+    GetTableRaw,
+    SetTableVariable,
+    SetTableString,
+    SetTableByte,
+    SetTableMultiple,
+    SetTableRaw,
+    CallMultiple,
+    Call,
+    TailCallMultiple,
+    TailCall,
+    IteratorCall,
+    IteratorNext,
+    VarArg,
+    IsNext,
+    ReturnMultiple,
+    Return,
+    Return0,
+    Return1,
+    ForInit,
+    JitForInit,
+    ForLoop,
+    InterpreterForLoop,
+    JitForLoop,
+    IteratorLoop,
+    InterpreterIteratorLoop,
+    JitIteratorLoop,
+    Loop,
+    InterpreterLoop,
+    JitLoop,
+    Jump,
+    Function,
+    InterpreterFunction,
+    JitFunction,
+    VarArgFunction,
+    InterpreterVarArgFunction,
+    JitVarArgFunction,
+    CFunction,
+    WrappedCFunction,
+}
+
+impl OperationCode {
+    pub fn from_instruction(instruction: u32, version: Version) -> Option<Self> {
+        let mut operation_code = instruction & 0xFF;
+        // LuaJIT 2.0 lacks `ISTYPE` and `ISNUM` after `ISF`, `TGETR` after `TGETB` and
+        // `TSETR` after `TSETM`
+        if version == Version::V2_0 {
+            if operation_code > Self::IsFalse as u32 {
+                operation_code += 2;
+            }
+            if operation_code > Self::GetTableByte as u32 {
+                operation_code += 1;
+            }
+            if operation_code > Self::SetTableMultiple as u32 {
+                operation_code += 1;
+            }
+        }
+        FromPrimitive::from_u32(operation_code)
+    }
+}
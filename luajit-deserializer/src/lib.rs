@@ -0,0 +1,10 @@
+pub use function::Function;
+pub use instruction::{argument, Instruction};
+pub use value::Value;
+
+pub mod chunk;
+pub mod function;
+pub mod instruction;
+pub mod local;
+pub mod upvalue;
+pub mod value;
@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+use nom::{
+    bytes::complete::{take, take_until},
+    number::complete::le_u8,
+    IResult,
+};
+
+use crate::chunk::header::parse_uleb128;
+
+// the names of the hidden locals of for loops, which are stored as a single byte
+const INTERNAL_NAMES: [&[u8]; 6] = [
+    b"(for index)",
+    b"(for limit)",
+    b"(for step)",
+    b"(for generator)",
+    b"(for state)",
+    b"(for control)",
+];
+
+#[derive(Debug)]
+pub struct Local<'a> {
+    pub name: &'a [u8],
+    /// The instructions the local is active for, not counting the function header.
+    pub range: Range<u32>,
+}
+
+impl<'a> Local<'a> {
+    /// Parses the locals up to the terminating zero byte. Ranges are stored relative to
+    /// the start of the previous local.
+    pub fn parse_list(mut input: &'a [u8]) -> IResult<&'a [u8], Vec<Self>> {
+        let mut locals = Vec::new();
+        let mut last_start = 0;
+        loop {
+            let (rest, kind) = le_u8(input)?;
+            let name = match kind {
+                0 => return Ok((rest, locals)),
+                1..=6 => {
+                    input = rest;
+                    INTERNAL_NAMES[kind as usize - 1]
+                }
+                _ => {
+                    let name;
+                    (input, name) = take_until(&b"\0"[..])(input)?;
+                    (input, _) = take(1usize)(input)?;
+                    name
+                }
+            };
+            let (start, length);
+            (input, start) = parse_uleb128(input)?;
+            (input, length) = parse_uleb128(input)?;
+            let start = last_start + start as u32;
+            last_start = start;
+            // the function header at pc 0 isn't dumped
+            locals.push(Self {
+                name,
+                range: start.saturating_sub(1)..(start + length as u32).saturating_sub(1),
+            });
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use nom::IResult;
+
+use crate::chunk::Header;
+
+const LOCAL: u16 = 0x8000;
+const IMMUTABLE: u16 = 0x4000;
+
+/// Where a closure gets an upvalue from when it is created.
+#[derive(Debug)]
+pub struct Upvalue<'a> {
+    /// The name from the debug info, `None` in stripped chunks.
+    pub name: Option<&'a [u8]>,
+    /// Whether the upvalue is a slot of the enclosing function rather than one of its
+    /// upvalues.
+    pub in_stack: bool,
+    pub index: u16,
+    /// Whether the captured local is never assigned to after its declaration.
+    pub immutable: bool,
+}
+
+impl<'a> Upvalue<'a> {
+    pub fn parse(input: &'a [u8], header: &Header) -> IResult<&'a [u8], Self> {
+        let (input, descriptor) = header.parse_u16(input)?;
+        let in_stack = descriptor & LOCAL != 0;
+
+        Ok((
+            input,
+            Self {
+                name: None,
+                in_stack,
+                index: if in_stack {
+                    descriptor & !(LOCAL | IMMUTABLE)
+                } else {
+                    descriptor
+                },
+                immutable: descriptor & IMMUTABLE != 0,
+            },
+        ))
+    }
+}
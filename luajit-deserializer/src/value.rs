@@ -0,0 +1,177 @@
+use enum_as_inner::EnumAsInner;
+use nom::{bytes::complete::take, error::ErrorKind, multi::count, IResult};
+
+use crate::{
+    chunk::header::{failure, parse_uleb128, parse_uleb128_33},
+    function::Function,
+};
+
+const KGC_CHILD: u64 = 0;
+const KGC_TABLE: u64 = 1;
+const KGC_I64: u64 = 2;
+const KGC_U64: u64 = 3;
+const KGC_COMPLEX: u64 = 4;
+const KGC_STRING: u64 = 5;
+
+const KTAB_NIL: u64 = 0;
+const KTAB_FALSE: u64 = 1;
+const KTAB_TRUE: u64 = 2;
+const KTAB_INTEGER: u64 = 3;
+const KTAB_NUMBER: u64 = 4;
+const KTAB_STRING: u64 = 5;
+
+/// A garbage collected constant: a string, a table template, a function or an FFI
+/// number.
+#[derive(Debug, EnumAsInner)]
+pub enum Value<'a> {
+    String(&'a [u8]),
+    Table(Table<'a>),
+    Function(Function<'a>),
+    /// An `int64_t` literal like `1LL`.
+    Integer64(i64),
+    /// A `uint64_t` literal like `1ULL`.
+    Unsigned64(u64),
+    /// An imaginary literal like `1i`, the real and imaginary parts.
+    Complex(f64, f64),
+}
+
+impl<'a> Value<'a> {
+    pub fn parse(input: &'a [u8], functions: &mut Vec<Function<'a>>) -> IResult<&'a [u8], Self> {
+        let (input, kind) = parse_uleb128(input)?;
+        match kind {
+            KGC_CHILD => match functions.pop() {
+                Some(function) => Ok((input, Self::Function(function))),
+                None => failure(input, ErrorKind::Verify),
+            },
+            KGC_TABLE => {
+                let (input, table) = Table::parse(input)?;
+
+                Ok((input, Self::Table(table)))
+            }
+            KGC_I64 => {
+                let (input, value) = parse_u64(input)?;
+
+                Ok((input, Self::Integer64(value as i64)))
+            }
+            KGC_U64 => {
+                let (input, value) = parse_u64(input)?;
+
+                Ok((input, Self::Unsigned64(value)))
+            }
+            KGC_COMPLEX => {
+                let (input, real) = parse_u64(input)?;
+                let (input, imaginary) = parse_u64(input)?;
+
+                Ok((
+                    input,
+                    Self::Complex(f64::from_bits(real), f64::from_bits(imaginary)),
+                ))
+            }
+            _ => {
+                let (input, value) = take(kind - KGC_STRING)(input)?;
+
+                Ok((input, Self::String(value)))
+            }
+        }
+    }
+}
+
+// a 64 bit value stored as its low and high halves
+fn parse_u64(input: &[u8]) -> IResult<&[u8], u64> {
+    let (input, low) = parse_uleb128(input)?;
+    let (input, high) = parse_uleb128(input)?;
+
+    Ok((input, (high as u32 as u64) << 32 | low as u32 as u64))
+}
+
+/// A number constant. Integral numbers that fit in 32 bits are stored as integers.
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    Integer(i32),
+    Float(f64),
+}
+
+impl Number {
+    pub fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, (low, is_float)) = parse_uleb128_33(input)?;
+        if is_float {
+            let (input, high) = parse_uleb128(input)?;
+
+            Ok((
+                input,
+                Self::Float(f64::from_bits((high as u32 as u64) << 32 | low as u64)),
+            ))
+        } else {
+            Ok((input, Self::Integer(low as i32)))
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Self::Integer(value) => value.into(),
+            Self::Float(value) => value,
+        }
+    }
+}
+
+/// A constant key or value of a table template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableValue<'a> {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(&'a [u8]),
+}
+
+impl<'a> TableValue<'a> {
+    fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, kind) = parse_uleb128(input)?;
+        match kind {
+            KTAB_NIL => Ok((input, Self::Nil)),
+            KTAB_FALSE => Ok((input, Self::Boolean(false))),
+            KTAB_TRUE => Ok((input, Self::Boolean(true))),
+            KTAB_INTEGER => {
+                let (input, value) = parse_uleb128(input)?;
+
+                Ok((input, Self::Number((value as u32 as i32).into())))
+            }
+            KTAB_NUMBER => {
+                let (input, value) = parse_u64(input)?;
+
+                Ok((input, Self::Number(f64::from_bits(value))))
+            }
+            _ => {
+                let (input, value) = take(kind - KTAB_STRING)(input)?;
+
+                Ok((input, Self::String(value)))
+            }
+        }
+    }
+}
+
+/// The template a table constructor with constant fields is copied from.
+#[derive(Debug)]
+pub struct Table<'a> {
+    /// The array part, starting at index 0.
+    pub array: Vec<TableValue<'a>>,
+    pub hash: Vec<(TableValue<'a>, TableValue<'a>)>,
+}
+
+impl<'a> Table<'a> {
+    fn parse(input: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (input, array_length) = parse_uleb128(input)?;
+        let (input, hash_length) = parse_uleb128(input)?;
+        let (input, array) = count(TableValue::parse, array_length as usize)(input)?;
+        let (input, hash) = count(
+            |input| {
+                let (input, key) = TableValue::parse(input)?;
+                let (input, value) = TableValue::parse(input)?;
+
+                Ok((input, (key, value)))
+            },
+            hash_length as usize,
+        )(input)?;
+
+        Ok((input, Self { array, hash }))
+    }
+}
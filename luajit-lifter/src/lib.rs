@@ -0,0 +1,39 @@
+//! The LuaJIT frontend of the decompiler. To decompile a file, run the `decompiler` binary,
+//! which detects the format of its input.
+
+mod lifter;
+use ast::formatter::Dialect;
+use decompiler_core::{Frontend, LiftedFunction};
+use lifter::Lifter;
+use luajit_deserializer::chunk::Chunk;
+use parking_lot::Mutex;
+use triomphe::Arc;
+
+/// LuaJIT 2.0 or 2.1 bytecode.
+pub struct LuaJit;
+
+impl Frontend for LuaJit {
+    fn lift(&self, bytecode: &[u8]) -> Result<Vec<LiftedFunction>, String> {
+        let (_, chunk) = Chunk::parse(bytecode).map_err(|error| {
+            format!("failed to parse bytecode: {}", error.map(|error| error.code))
+        })?;
+        let mut lifted = Vec::new();
+        let (function, upvalues) = Lifter::lift(&chunk.function, &mut lifted)?;
+        lifted.push((Arc::<Mutex<_>>::default(), function, upvalues));
+        lifted.reverse();
+        Ok(lifted)
+    }
+}
+
+pub fn decompile_bytecode(bytecode: &[u8]) -> Result<String, String> {
+    decompile_bytecode_with_dialect(bytecode, Dialect::Lua52)
+}
+
+/// Decompiles LuaJIT 2.0 or 2.1 `bytecode` into source for `dialect`. LuaJIT reads Lua 5.1
+/// with `goto`, which is what the Lua 5.2 dialect is printed as.
+pub fn decompile_bytecode_with_dialect(
+    bytecode: &[u8],
+    dialect: Dialect,
+) -> Result<String, String> {
+    decompiler_core::decompile_bytecode(&LuaJit, bytecode, dialect)
+}
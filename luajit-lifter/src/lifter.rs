@@ -0,0 +1,866 @@
+use by_address::ByAddress;
+use cfg::block::{BlockEdge, BranchType};
+
+use itertools::Itertools;
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+
+use ast::{Local, RcLocal, Statement};
+use cfg::function::Function;
+use decompiler_core::LiftedFunction;
+
+use luajit_deserializer::{
+    argument::{Constant, NumberConstant, Operand, Primitive, Register},
+    instruction::ArithmeticOperation,
+    value::{Number, TableValue},
+    Function as BytecodeFunction, Instruction, Value,
+};
+
+use petgraph::{stable_graph::NodeIndex, visit::EdgeRef, Direction};
+
+use triomphe::Arc;
+
+pub struct Lifter<'a, 'b> {
+    bytecode: &'a BytecodeFunction<'a>,
+    nodes: FxHashMap<usize, NodeIndex>,
+    insert_between: FxHashMap<NodeIndex, (NodeIndex, Vec<Statement>)>,
+    locals: FxHashMap<Register, RcLocal>,
+    registers: FxHashMap<RcLocal, Register>,
+    constants: FxHashMap<usize, ast::Literal>,
+    function: Function,
+    upvalues: Vec<RcLocal>,
+    lifted_functions: &'b mut Vec<LiftedFunction>,
+}
+
+impl<'a, 'b> Lifter<'a, 'b> {
+    fn allocate_locals(&mut self) {
+        self.upvalues.reserve(self.bytecode.upvalues.len());
+        for upvalue in &self.bytecode.upvalues {
+            let name = upvalue
+                .name
+                .map(|name| String::from_utf8_lossy(name).into_owned());
+            self.upvalues.push(RcLocal::new(Local::new(name)));
+        }
+
+        self.locals.reserve(self.bytecode.frame_size as usize);
+        for i in 0..self.bytecode.frame_size {
+            let local = if i < self.bytecode.number_of_parameters {
+                let local = RcLocal::new(Local::new(self.local_name(Register(i), 0)));
+                self.function.parameters.push(local.clone());
+                local
+            } else {
+                RcLocal::default()
+            };
+            self.registers.insert(local.clone(), Register(i));
+            self.locals.insert(Register(i), local);
+        }
+    }
+
+    // the name the debug info gives the local in `register` once `pc` is reached. locals
+    // become active after the instruction initializing them, so loop hints, which can't
+    // write the register, are skipped over.
+    fn local_name(&self, register: Register, mut pc: usize) -> Option<String> {
+        loop {
+            if let Some(local) = self
+                .bytecode
+                .locals
+                .iter()
+                .filter(|local| local.range.contains(&(pc as u32)))
+                .nth(register.0 as usize)
+            {
+                // internal locals like "(for index)" aren't valid names
+                return (!local.name.starts_with(b"("))
+                    .then(|| String::from_utf8_lossy(local.name).into_owned());
+            }
+            let instruction = self.bytecode.code.get(pc)?;
+            if Self::may_write(instruction, register) {
+                return None;
+            }
+            pc += 1;
+        }
+    }
+
+    // whether `instruction` might write `register` or leave straight line code
+    fn may_write(instruction: &Instruction, register: Register) -> bool {
+        let lowest = match instruction {
+            Instruction::SetUpvalue { .. }
+            | Instruction::SetGlobal { .. }
+            | Instruction::SetIndex { .. }
+            | Instruction::SetList { .. }
+            | Instruction::Loop => return false,
+            Instruction::Move { destination, .. }
+            | Instruction::Load { destination, .. }
+            | Instruction::GetUpvalue { destination, .. }
+            | Instruction::GetGlobal { destination, .. }
+            | Instruction::GetIndex { destination, .. }
+            | Instruction::NewTable { destination, .. }
+            | Instruction::DuplicateTable { destination, .. }
+            | Instruction::Arithmetic { destination, .. }
+            | Instruction::Minus { destination, .. }
+            | Instruction::Not { destination, .. }
+            | Instruction::Length { destination, .. }
+            | Instruction::Concatenate { destination, .. }
+            | Instruction::Closure { destination, .. }
+            | Instruction::VarArg(destination, _)
+            | Instruction::Call {
+                function: destination,
+                ..
+            } => destination,
+            Instruction::LoadNil(registers) => match registers.first() {
+                Some(first) => first,
+                None => return false,
+            },
+            _ => return true,
+        };
+        lowest.0 <= register.0
+    }
+
+    // writes values of named locals to a new local with the name first, which is then
+    // copied to the register. ssa construction propagates the copy, leaving the named local.
+    fn name_written_locals(&self, statements: &mut Vec<Statement>, start: usize, pc: usize) {
+        let mut index = start;
+        while index < statements.len() {
+            let mut copies = Vec::new();
+            if let Statement::Assign(assign) = &mut statements[index] {
+                for local in assign.left.iter_mut().filter_map(|l| l.as_local_mut()) {
+                    if let Some(&register) = self.registers.get(local)
+                        && let Some(name) = self.local_name(register, pc)
+                    {
+                        let named = RcLocal::new(Local::new(Some(name)));
+                        let register_local = std::mem::replace(local, named.clone());
+                        copies.push(
+                            ast::Assign::new(vec![register_local.into()], vec![named.into()])
+                                .into(),
+                        );
+                    }
+                }
+            }
+            index += 1;
+            let copied = copies.len();
+            statements.splice(index..index, copies);
+            index += copied;
+        }
+    }
+
+    // the instruction `skip` instructions after the one following `pc`
+    fn destination(pc: usize, skip: i32) -> usize {
+        (pc + 1).checked_add_signed(skip.try_into().unwrap()).unwrap()
+    }
+
+    fn create_block_map(&mut self) {
+        self.nodes.insert(0, self.function.new_block());
+        for (insn_index, insn) in self.bytecode.code.iter().enumerate() {
+            let successors = match *insn {
+                Instruction::Equal { .. }
+                | Instruction::LessThan { .. }
+                | Instruction::LessThanOrEqual { .. }
+                | Instruction::Test { .. }
+                | Instruction::TestSet { .. } => vec![insn_index + 1, insn_index + 2],
+                Instruction::Jump(skip)
+                | Instruction::Close { skip, .. }
+                | Instruction::IterateNumericForLoop { skip, .. }
+                | Instruction::InitGenericForLoop { skip, .. }
+                | Instruction::IterateGenericForLoop { skip, .. } => {
+                    vec![Self::destination(insn_index, skip), insn_index + 1]
+                }
+                // the loop is entered through the `IterateNumericForLoop` like in lua 5.1,
+                // which checks the counter again
+                Instruction::InitNumericForLoop { skip, .. } => {
+                    vec![Self::destination(insn_index, skip - 1), insn_index + 1]
+                }
+                Instruction::Return(..) | Instruction::TailCall { .. } => vec![insn_index + 1],
+                _ => continue,
+            };
+            for successor in successors {
+                self.nodes
+                    .entry(successor)
+                    .or_insert_with(|| self.function.new_block());
+            }
+        }
+    }
+
+    fn code_ranges(&self) -> Vec<(usize, usize)> {
+        let mut nodes = self.nodes.keys().cloned().collect::<Vec<_>>();
+        nodes.sort_unstable();
+        let ends = nodes
+            .iter()
+            .skip(1)
+            .map(|&s| s - 1)
+            .chain(std::iter::once(self.bytecode.code.len() - 1));
+        nodes.iter().cloned().zip(ends).collect()
+    }
+
+    fn constant(&mut self, constant: Constant) -> Result<ast::Literal, String> {
+        if let Some(literal) = self.constants.get(&(constant.0 as usize)) {
+            return Ok(literal.clone());
+        }
+        let literal = match self.bytecode.constants.get(constant.0 as usize) {
+            Some(Value::String(v)) => ast::Literal::String(v.to_vec()),
+            // cdata can only be written as ffi literals, which the ast has no equivalent of
+            Some(Value::Integer64(_) | Value::Unsigned64(_) | Value::Complex(..)) => {
+                return Err("ffi cdata constants (`1LL`, `1ULL`, `1i`) are not supported".into());
+            }
+            Some(value) => return Err(format!("constant {:?} isn't a string", value)),
+            None => return Err(format!("constant {} doesn't exist", constant.0)),
+        };
+        self.constants.insert(constant.0 as usize, literal.clone());
+        Ok(literal)
+    }
+
+    fn number(&self, number: NumberConstant) -> ast::Literal {
+        ast::Literal::Number(self.bytecode.numbers[number.0 as usize].as_f64())
+    }
+
+    fn operand(&mut self, operand: Operand) -> Result<ast::RValue, String> {
+        Ok(match operand {
+            Operand::Register(register) => self.locals[&register].clone().into(),
+            Operand::Constant(constant) => self.constant(constant)?.into(),
+            Operand::Number(number) => self.number(number).into(),
+            Operand::Primitive(Primitive::Nil) => ast::Literal::Nil.into(),
+            Operand::Primitive(Primitive::False) => ast::Literal::Boolean(false).into(),
+            Operand::Primitive(Primitive::True) => ast::Literal::Boolean(true).into(),
+            Operand::Integer(value) => ast::Literal::Number(value.into()).into(),
+        })
+    }
+
+    // a table constructor with the fields of a template
+    fn table(&self, template: Constant) -> Result<ast::Table, String> {
+        let Some(Value::Table(template)) = self.bytecode.constants.get(template.0 as usize) else {
+            return Err(format!("table template {} isn't a table", template.0));
+        };
+        let literal = |value: &TableValue| -> ast::RValue {
+            match *value {
+                TableValue::Nil => ast::Literal::Nil,
+                TableValue::Boolean(value) => ast::Literal::Boolean(value),
+                TableValue::Number(value) => ast::Literal::Number(value),
+                TableValue::String(value) => ast::Literal::String(value.to_vec()),
+            }
+            .into()
+        };
+        // the array part starts at 0, which is only set with an explicit key. fields
+        // after a hole need one too.
+        let mut fields = Vec::with_capacity(template.array.len() + template.hash.len());
+        let mut position = 1;
+        for (index, value) in template.array.iter().enumerate() {
+            if *value == TableValue::Nil {
+                continue;
+            }
+            let key = if index == position {
+                position += 1;
+                None
+            } else {
+                Some(ast::Literal::Number(index as f64).into())
+            };
+            fields.push((key, literal(value)));
+        }
+        for (key, value) in &template.hash {
+            fields.push((Some(literal(key)), literal(value)));
+        }
+        Ok(ast::Table(fields))
+    }
+
+    fn assign(&self, destination: Register, value: ast::RValue) -> Statement {
+        ast::Assign::new(vec![self.locals[&destination].clone().into()], vec![value]).into()
+    }
+
+    fn condition(value: ast::RValue, invert: bool) -> Statement {
+        let condition = if invert {
+            ast::Unary::new(value, ast::UnaryOperation::Not).into()
+        } else {
+            value
+        };
+        ast::If::new(condition, ast::Block::default(), ast::Block::default()).into()
+    }
+
+    fn lift_instructions(
+        &mut self,
+        start: usize,
+        end: usize,
+        statements: &mut Vec<Statement>,
+    ) -> Result<(), String> {
+        if end > start {
+            statements.reserve(end - start + 1);
+        }
+        let bytecode = self.bytecode;
+        let mut top: Option<(ast::RValue, u8)> = None;
+        for pc in start..=end {
+            let instruction = &bytecode.code[pc];
+            let statements_start = statements.len();
+            match instruction {
+                &Instruction::Move {
+                    destination,
+                    source,
+                } => {
+                    statements.push(self.assign(destination, self.locals[&source].clone().into()));
+                }
+                &Instruction::Load { destination, value } => {
+                    let value = self.operand(value)?;
+                    statements.push(self.assign(destination, value));
+                }
+                Instruction::LoadNil(registers) => {
+                    for &register in registers {
+                        statements.push(self.assign(register, ast::Literal::Nil.into()));
+                    }
+                }
+                &Instruction::GetUpvalue {
+                    destination,
+                    upvalue,
+                } => {
+                    statements.push(
+                        self.assign(destination, self.upvalues[upvalue.0 as usize].clone().into()),
+                    );
+                }
+                &Instruction::SetUpvalue { destination, value } => {
+                    let value = self.operand(value)?;
+                    statements.push(
+                        ast::Assign::new(
+                            vec![self.upvalues[destination.0 as usize].clone().into()],
+                            vec![value],
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::GetGlobal { destination, name } => {
+                    let ast::Literal::String(name) = self.constant(name)? else {
+                        unreachable!();
+                    };
+                    statements.push(self.assign(destination, ast::Global::new(name).into()));
+                }
+                &Instruction::SetGlobal { name, value } => {
+                    let ast::Literal::String(name) = self.constant(name)? else {
+                        unreachable!();
+                    };
+                    statements.push(
+                        ast::Assign::new(
+                            vec![ast::Global::new(name).into()],
+                            vec![self.locals[&value].clone().into()],
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::GetIndex {
+                    destination,
+                    object,
+                    key,
+                } => {
+                    let key = self.operand(key)?;
+                    statements.push(self.assign(
+                        destination,
+                        ast::Index::new(self.locals[&object].clone().into(), key).into(),
+                    ));
+                }
+                &Instruction::SetIndex { object, key, value } => {
+                    let key = self.operand(key)?;
+                    statements.push(
+                        ast::Assign::new(
+                            vec![ast::Index::new(self.locals[&object].clone().into(), key).into()],
+                            vec![self.locals[&value].clone().into()],
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::NewTable { destination, .. } => {
+                    statements.push(self.assign(destination, ast::Table::default().into()));
+                }
+                &Instruction::DuplicateTable {
+                    destination,
+                    template,
+                } => {
+                    statements.push(self.assign(destination, self.table(template)?.into()));
+                }
+                &Instruction::Arithmetic {
+                    operation,
+                    destination,
+                    lhs,
+                    rhs,
+                } => {
+                    let lhs = self.operand(lhs)?;
+                    let rhs = self.operand(rhs)?;
+                    let operation = match operation {
+                        ArithmeticOperation::Add => ast::BinaryOperation::Add,
+                        ArithmeticOperation::Sub => ast::BinaryOperation::Sub,
+                        ArithmeticOperation::Mul => ast::BinaryOperation::Mul,
+                        ArithmeticOperation::Div => ast::BinaryOperation::Div,
+                        ArithmeticOperation::Mod => ast::BinaryOperation::Mod,
+                        ArithmeticOperation::Pow => ast::BinaryOperation::Pow,
+                    };
+                    statements
+                        .push(self.assign(destination, ast::Binary::new(lhs, rhs, operation).into()));
+                }
+                &Instruction::Minus {
+                    destination,
+                    operand,
+                }
+                | &Instruction::Not {
+                    destination,
+                    operand,
+                }
+                | &Instruction::Length {
+                    destination,
+                    operand,
+                } => {
+                    let operation = match instruction {
+                        Instruction::Minus { .. } => ast::UnaryOperation::Negate,
+                        Instruction::Not { .. } => ast::UnaryOperation::Not,
+                        Instruction::Length { .. } => ast::UnaryOperation::Length,
+                        _ => unreachable!(),
+                    };
+                    statements.push(self.assign(
+                        destination,
+                        ast::Unary::new(self.locals[&operand].clone().into(), operation).into(),
+                    ));
+                }
+                Instruction::Concatenate {
+                    destination,
+                    operands,
+                } => {
+                    assert!(operands.len() >= 2);
+                    let mut operands = operands.iter().rev();
+
+                    let right = operands.next().unwrap();
+                    let left = operands.next().unwrap();
+                    let mut concat = ast::Binary::new(
+                        self.locals[left].clone().into(),
+                        self.locals[right].clone().into(),
+                        ast::BinaryOperation::Concat,
+                    );
+                    for r in operands {
+                        concat = ast::Binary::new(
+                            self.locals[r].clone().into(),
+                            concat.into(),
+                            ast::BinaryOperation::Concat,
+                        );
+                    }
+                    statements.push(self.assign(*destination, concat.into()));
+                }
+                &Instruction::Close { start, .. } => {
+                    let locals = (start.0..self.bytecode.frame_size)
+                        .map(|i| self.locals[&Register(i)].clone())
+                        .collect();
+                    statements.push(ast::Close { locals }.into());
+                }
+                &Instruction::Equal { lhs, rhs, invert } => {
+                    let rhs = self.operand(rhs)?;
+                    statements.push(Self::condition(
+                        ast::Binary::new(
+                            self.locals[&lhs].clone().into(),
+                            rhs,
+                            ast::BinaryOperation::Equal,
+                        )
+                        .into(),
+                        invert,
+                    ));
+                }
+                &Instruction::LessThan { lhs, rhs, invert }
+                | &Instruction::LessThanOrEqual { lhs, rhs, invert } => {
+                    let operation = match instruction {
+                        Instruction::LessThan { .. } => ast::BinaryOperation::LessThan,
+                        Instruction::LessThanOrEqual { .. } => {
+                            ast::BinaryOperation::LessThanOrEqual
+                        }
+                        _ => unreachable!(),
+                    };
+                    statements.push(Self::condition(
+                        ast::Binary::new(
+                            self.locals[&lhs].clone().into(),
+                            self.locals[&rhs].clone().into(),
+                            operation,
+                        )
+                        .into(),
+                        invert,
+                    ));
+                }
+                &Instruction::Test { value, invert } => {
+                    statements.push(Self::condition(self.locals[&value].clone().into(), invert));
+                }
+                &Instruction::TestSet {
+                    destination,
+                    value,
+                    invert,
+                } => {
+                    let value: ast::RValue = self.locals[&value].clone().into();
+                    statements.push(Self::condition(value.clone(), invert));
+
+                    // the value is only assigned when the following jump is taken
+                    let assign = self.assign(destination, value);
+                    self.function
+                        .block_mut(self.nodes[&(end + 1)])
+                        .unwrap()
+                        .push(assign);
+                }
+                Instruction::TailCall {
+                    function,
+                    arguments,
+                    variadic,
+                }
+                | Instruction::Call {
+                    function,
+                    arguments,
+                    variadic,
+                    ..
+                } => {
+                    let mut arguments = arguments
+                        .iter()
+                        .map(|r| self.locals[r].clone().into())
+                        .collect::<Vec<_>>();
+                    if *variadic {
+                        arguments.push(top.take().unwrap().0);
+                    }
+
+                    let call = ast::Call::new(self.locals[function].clone().into(), arguments);
+
+                    match *instruction {
+                        Instruction::Call { return_values, .. } if return_values != 0 => {
+                            if return_values == 1 {
+                                statements.push(call.into());
+                            } else {
+                                statements.push(
+                                    ast::Assign::new(
+                                        (function.0..function.0 + return_values - 1)
+                                            .map(|r| self.locals[&Register(r)].clone().into())
+                                            .collect_vec(),
+                                        vec![ast::RValue::Select(call.into())],
+                                    )
+                                    .into(),
+                                );
+                            }
+                        }
+                        Instruction::Call { .. } => {
+                            top = Some((call.into(), function.0));
+                        }
+                        _ => {
+                            statements.push(ast::Return::new(vec![call.into()]).into());
+                        }
+                    }
+                }
+                &Instruction::Return(values, b) => {
+                    let values = if b != 0 {
+                        (values.0..values.0 + (b - 1))
+                            .map(|r| self.locals[&Register(r)].clone().into())
+                            .collect()
+                    } else {
+                        let (tail, end) = top.take().unwrap();
+                        (values.0..end)
+                            .map(|r| self.locals[&Register(r)].clone().into())
+                            .chain(std::iter::once(tail))
+                            .collect()
+                    };
+                    statements.push(ast::Return::new(values).into());
+                }
+                Instruction::InitNumericForLoop { control, .. } => {
+                    statements.push(
+                        ast::NumForInit::new(
+                            self.locals[&control[0]].clone(),
+                            self.locals[&control[1]].clone(),
+                            self.locals[&control[2]].clone(),
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::IterateNumericForLoop { ref control, skip } => {
+                    let (internal_counter, limit, step, external_counter) = (
+                        self.locals[&control[0]].clone(),
+                        self.locals[&control[1]].clone(),
+                        self.locals[&control[2]].clone(),
+                        self.locals[&control[3]].clone(),
+                    );
+                    statements.push(
+                        ast::NumForNext::new(internal_counter.clone(), limit.into(), step.into())
+                            .into(),
+                    );
+
+                    let body = Self::destination(pc, skip);
+                    let mut counter = vec![ast::Assign::new(
+                        vec![external_counter.into()],
+                        vec![internal_counter.into()],
+                    )
+                    .into()];
+                    self.name_written_locals(&mut counter, 0, body);
+                    assert!(self
+                        .insert_between
+                        .insert(self.nodes[&start], (self.nodes[&body], counter))
+                        .is_none());
+                }
+                &Instruction::InitGenericForLoop {
+                    generator,
+                    state,
+                    internal_control,
+                    ..
+                } => {
+                    statements.push(
+                        ast::GenericForInit::new(
+                            self.locals[&generator].clone(),
+                            self.locals[&state].clone(),
+                            self.locals[&internal_control].clone(),
+                        )
+                        .into(),
+                    );
+                }
+                Instruction::CallGenericForLoop {
+                    generator,
+                    state,
+                    vars,
+                    ..
+                } => {
+                    // the loop variables are named in the body the following
+                    // `IterateGenericForLoop` jumps back to
+                    let Some(&Instruction::IterateGenericForLoop { skip, .. }) =
+                        bytecode.code.get(pc + 1)
+                    else {
+                        return Err("generic for loop call without an iterate instruction".into());
+                    };
+                    let body = Self::destination(pc + 1, skip);
+                    let mut copies = Vec::new();
+                    let vars = vars
+                        .iter()
+                        .map(|&var| match self.local_name(var, body) {
+                            Some(name) => {
+                                let named = RcLocal::new(Local::new(Some(name)));
+                                copies.push(
+                                    ast::Assign::new(
+                                        vec![self.locals[&var].clone().into()],
+                                        vec![named.clone().into()],
+                                    )
+                                    .into(),
+                                );
+                                named
+                            }
+                            None => self.locals[&var].clone(),
+                        })
+                        .collect::<Vec<_>>();
+                    statements.push(
+                        ast::GenericForNext::new(
+                            vars,
+                            self.locals[generator].clone().into(),
+                            self.locals[state].clone(),
+                        )
+                        .into(),
+                    );
+                    if !copies.is_empty() {
+                        assert!(self
+                            .insert_between
+                            .insert(self.nodes[&start], (self.nodes[&body], copies))
+                            .is_none());
+                    }
+                }
+                &Instruction::SetList {
+                    table,
+                    values,
+                    index,
+                } => {
+                    // the index is in the low bits of a float biased to avoid denormals
+                    let index = match self.bytecode.numbers[index.0 as usize] {
+                        Number::Integer(index) => index as u32,
+                        Number::Float(index) => index.to_bits() as u32,
+                    };
+                    let top = top.take().unwrap();
+                    statements.push(
+                        ast::SetList::new(
+                            self.locals[&table].clone(),
+                            index as usize,
+                            (values.0..top.1)
+                                .map(|r| self.locals[&Register(r)].clone().into())
+                                .collect(),
+                            Some(top.0),
+                        )
+                        .into(),
+                    );
+                }
+                &Instruction::Closure {
+                    destination,
+                    function,
+                } => {
+                    let Some(Value::Function(closure)) = bytecode.constants.get(function.0 as usize)
+                    else {
+                        return Err(format!("closure constant {} isn't a function", function.0));
+                    };
+                    let upvalues_passed = closure
+                        .upvalues
+                        .iter()
+                        .map(|upvalue| {
+                            if upvalue.in_stack {
+                                self.locals[&Register(upvalue.index as u8)].clone()
+                            } else {
+                                self.upvalues[upvalue.index as usize].clone()
+                            }
+                        })
+                        .collect::<Vec<_>>();
+
+                    let ast_function = Arc::<Mutex<_>>::default();
+
+                    let (function, upvalues) = Lifter::lift(closure, self.lifted_functions)?;
+                    self.lifted_functions
+                        .push((ast_function.clone(), function, upvalues));
+
+                    statements.push(
+                        self.assign(
+                            destination,
+                            ast::Closure {
+                                function: ByAddress(ast_function),
+                                upvalues: upvalues_passed
+                                    .into_iter()
+                                    .map(ast::Upvalue::Ref)
+                                    .collect(),
+                            }
+                            .into(),
+                        ),
+                    );
+                }
+                &Instruction::VarArg(destination, b) => {
+                    let vararg = ast::VarArg {};
+                    if b != 0 {
+                        statements.push(
+                            ast::Assign::new(
+                                (destination.0..destination.0 + b - 1)
+                                    .map(|r| self.locals[&Register(r)].clone().into())
+                                    .collect(),
+                                vec![ast::RValue::Select(vararg.into())],
+                            )
+                            .into(),
+                        );
+                    } else {
+                        top = Some((vararg.into(), destination.0));
+                    }
+                }
+                // the loop variables are written by the `CallGenericForLoop` before
+                Instruction::IterateGenericForLoop { .. }
+                | Instruction::Jump(_)
+                | Instruction::Loop => {}
+            }
+
+            self.name_written_locals(statements, statements_start, pc + 1);
+
+            if matches!(
+                instruction,
+                Instruction::Return { .. } | Instruction::TailCall { .. }
+            ) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn lift_blocks(&mut self) -> Result<(), String> {
+        let ranges = self.code_ranges();
+        for (start, end) in ranges {
+            // a block might already have statements, see `Instruction::TestSet`
+            let mut statements =
+                std::mem::take(self.function.block_mut(self.nodes[&start]).unwrap());
+            self.lift_instructions(start, end, &mut statements)?;
+            *self.function.block_mut(self.nodes[&start]).unwrap() = statements;
+
+            let edges = match self.bytecode.code[end] {
+                Instruction::Equal { .. }
+                | Instruction::LessThan { .. }
+                | Instruction::LessThanOrEqual { .. }
+                | Instruction::Test { .. }
+                | Instruction::TestSet { .. } => vec![
+                    (self.nodes[&(end + 1)], BlockEdge::new(BranchType::Then)),
+                    (self.nodes[&(end + 2)], BlockEdge::new(BranchType::Else)),
+                ],
+                Instruction::IterateNumericForLoop { skip, .. }
+                | Instruction::IterateGenericForLoop { skip, .. } => vec![
+                    (
+                        self.nodes[&Self::destination(end, skip)],
+                        BlockEdge::new(BranchType::Then),
+                    ),
+                    (self.nodes[&(end + 1)], BlockEdge::new(BranchType::Else)),
+                ],
+                Instruction::Jump(skip)
+                | Instruction::Close { skip, .. }
+                | Instruction::InitGenericForLoop { skip, .. } => vec![(
+                    self.nodes[&Self::destination(end, skip)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                Instruction::InitNumericForLoop { skip, .. } => vec![(
+                    self.nodes[&Self::destination(end, skip - 1)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                Instruction::Return(..) | Instruction::TailCall { .. } => Vec::new(),
+                _ if end + 1 != self.bytecode.code.len() => vec![(
+                    self.nodes[&(end + 1)],
+                    BlockEdge::new(BranchType::Unconditional),
+                )],
+                _ => Vec::new(),
+            };
+            self.function.set_edges(self.nodes[&start], edges);
+        }
+        Ok(())
+    }
+
+    /// Lifts `bytecode` and the functions it defines, which are added to `lifted_functions`.
+    /// Fails on constants that can't be lifted or that the instructions using them don't
+    /// expect.
+    pub fn lift(
+        bytecode: &'a BytecodeFunction,
+        lifted_functions: &'b mut Vec<LiftedFunction>,
+    ) -> Result<(Function, Vec<RcLocal>), String> {
+        let mut context = Self {
+            bytecode,
+            nodes: FxHashMap::default(),
+            insert_between: FxHashMap::default(),
+            locals: FxHashMap::default(),
+            registers: FxHashMap::default(),
+            constants: FxHashMap::default(),
+            function: Function::new(0),
+            upvalues: Vec::new(),
+            lifted_functions,
+        };
+        context.function.is_variadic = bytecode.is_vararg;
+
+        context.create_block_map();
+        context.allocate_locals();
+        context.lift_blocks()?;
+
+        let stack_init_node = context.function.new_block();
+        let stack_init_block = context.function.block_mut(stack_init_node).unwrap();
+        stack_init_block.reserve(context.locals.len());
+        for (_, local) in context.locals {
+            if !context.function.parameters.contains(&local) {
+                let stack_init_block = context.function.block_mut(stack_init_node).unwrap();
+                stack_init_block.push(
+                    ast::Assign::new(vec![local.into()], vec![ast::Literal::Nil.into()]).into(),
+                )
+            }
+        }
+        context.function.set_edges(
+            stack_init_node,
+            vec![(context.nodes[&0], BlockEdge::new(BranchType::Unconditional))],
+        );
+        context.function.set_entry(stack_init_node);
+
+        for (node, (successor, stats)) in context.insert_between {
+            if context.function.predecessor_blocks(successor).count() == 1 {
+                context
+                    .function
+                    .block_mut(successor)
+                    .unwrap()
+                    .splice(0..0, stats);
+            } else {
+                let between_node = context.function.new_block();
+                context.function.block_mut(between_node).unwrap().extend(stats);
+                context.function.set_edges(
+                    between_node,
+                    vec![(successor, BlockEdge::new(BranchType::Unconditional))],
+                );
+                for edge in context
+                    .function
+                    .graph()
+                    .edges_directed(node, Direction::Outgoing)
+                    .filter(|e| e.target() == successor)
+                    .map(|e| e.id())
+                    .collect::<Vec<_>>()
+                {
+                    let edge = context.function.graph_mut().remove_edge(edge).unwrap();
+                    context
+                        .function
+                        .graph_mut()
+                        .add_edge(node, between_node, edge);
+                }
+            }
+        }
+
+        Ok((context.function, context.upvalues))
+    }
+}
+
@@ -0,0 +1,443 @@
+#!/usr/bin/env python3
+"""Assembles the LuaJIT bytecode fixtures used by `tests/golden.rs`.
+
+There is no `luajit` in the build environment, so every fixture is written out by hand here,
+mirroring what `luajit -b` emits for the source in its docstring. Fixtures are LuaJIT 2.1
+dumps from a 64-bit build with two slot frames, except for `legacy`, a stripped LuaJIT 2.0
+dump. Run `python3 assemble.py` from this directory to regenerate the `.ljbc` files, then
+`BLESS=1 cargo test -p luajit-lifter --test golden` to refresh the expected output.
+"""
+
+import os
+import struct
+
+OPS_2_1 = [
+    "ISLT", "ISGE", "ISLE", "ISGT", "ISEQV", "ISNEV", "ISEQS", "ISNES", "ISEQN", "ISNEN",
+    "ISEQP", "ISNEP", "ISTC", "ISFC", "IST", "ISF", "ISTYPE", "ISNUM", "MOV", "NOT", "UNM",
+    "LEN", "ADDVN", "SUBVN", "MULVN", "DIVVN", "MODVN", "ADDNV", "SUBNV", "MULNV", "DIVNV",
+    "MODNV", "ADDVV", "SUBVV", "MULVV", "DIVVV", "MODVV", "POW", "CAT", "KSTR", "KCDATA",
+    "KSHORT", "KNUM", "KPRI", "KNIL", "UGET", "USETV", "USETS", "USETN", "USETP", "UCLO",
+    "FNEW", "TNEW", "TDUP", "GGET", "GSET", "TGETV", "TGETS", "TGETB", "TGETR", "TSETV",
+    "TSETS", "TSETB", "TSETM", "TSETR", "CALLM", "CALL", "CALLMT", "CALLT", "ITERC", "ITERN",
+    "VARG", "ISNEXT", "RETM", "RET", "RET0", "RET1", "FORI", "JFORI", "FORL", "IFORL",
+    "JFORL", "ITERL", "IITERL", "JITERL", "LOOP", "ILOOP", "JLOOP", "JMP",
+]
+# LuaJIT 2.0 lacks the type checks and raw table accesses
+OPS_2_0 = [op for op in OPS_2_1 if op not in ("ISTYPE", "ISNUM", "TGETR", "TSETR")]
+
+FLAG_STRIPPED = 0x02
+FLAG_TWO_SLOT_FRAMES = 0x08
+
+PROTO_CHILD = 0x01
+PROTO_VARARG = 0x02
+
+UV_LOCAL = 0x8000
+
+JUMP_OPS = ("JMP", "UCLO", "ISNEXT", "FORI", "FORL", "ITERL", "LOOP")
+
+# the hidden locals of for loops, stored as a single byte
+INTERNAL_NAMES = {
+    "(for index)": 1,
+    "(for limit)": 2,
+    "(for step)": 3,
+    "(for generator)": 4,
+    "(for state)": 5,
+    "(for control)": 6,
+}
+
+NIL, FALSE, TRUE = 0, 1, 2
+
+
+def uleb(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def number(value):
+    """A number constant, integral ones that fit in 32 bits are stored as integers."""
+    if isinstance(value, int) or (value == int(value) and -2**31 <= value < 2**31):
+        return uleb((int(value) & 0xFFFFFFFF) << 1)
+    bits = struct.unpack("<Q", struct.pack("<d", value))[0]
+    return uleb((bits & 0xFFFFFFFF) << 1 | 1) + uleb(bits >> 32)
+
+
+def table_value(value):
+    if value is None:
+        return uleb(0)
+    if value is False:
+        return uleb(1)
+    if value is True:
+        return uleb(2)
+    if isinstance(value, (int, float)):
+        if value == int(value):
+            return uleb(3) + uleb(int(value) & 0xFFFFFFFF)
+        bits = struct.unpack("<Q", struct.pack("<d", value))[0]
+        return uleb(4) + uleb(bits & 0xFFFFFFFF) + uleb(bits >> 32)
+    data = value.encode()
+    return uleb(5 + len(data)) + data
+
+
+class Table:
+    def __init__(self, array, hash):
+        # the array part starts at index 0
+        self.array = array
+        self.hash = hash
+
+
+class Proto:
+    def __init__(self, params=0, upvalues=(), vararg=False, line=0, lines=1):
+        self.params = params
+        # (name, in_stack, index)
+        self.upvalues = list(upvalues)
+        self.vararg = vararg
+        self.line = line
+        self.lines = lines
+        self.frame_size = 2
+        self.code = []
+        self.labels = {}
+        self.constants = []
+        self.numbers = []
+        self.locals = []
+
+    def k(self, value):
+        """A garbage collected constant: a string, a `Table` or a child `Proto`."""
+        for index, constant in enumerate(self.constants):
+            if constant is value or (isinstance(value, str) and constant == value):
+                return index
+        self.constants.append(value)
+        return len(self.constants) - 1
+
+    def n(self, value):
+        entry = (type(value), value)
+        if entry not in self.numbers:
+            self.numbers.append(entry)
+        return self.numbers.index(entry)
+
+    def local(self, name, start, end):
+        """A local active from instruction `start` up to `end`, not counting the header."""
+        self.locals.append((name, start, end))
+
+    def label(self, name):
+        self.labels[name] = len(self.code)
+
+    def ad(self, op, a=0, d=0):
+        self.frame_size = max(self.frame_size, a + 1)
+        self.code.append((op, a, d))
+
+    def abc(self, op, a=0, b=0, c=0):
+        self.ad(op, a, c | b << 8)
+
+    def jump(self, op, a, label):
+        """A jump to `label`, its argument is resolved once the code is complete."""
+        self.code.append((op, a, label))
+
+    def words(self, ops):
+        out = []
+        for pc, (op, a, d) in enumerate(self.code):
+            if op in JUMP_OPS and isinstance(d, str):
+                d = self.labels[d] - (pc + 1) + 0x8000
+            out.append(ops.index(op) | a << 8 | d << 16)
+        return out
+
+    def serialize(self, ops, stripped):
+        children = [k for k in self.constants if isinstance(k, Proto)]
+        flags = (PROTO_CHILD if children else 0) | (PROTO_VARARG if self.vararg else 0)
+        out = bytearray([flags, self.params, self.frame_size, len(self.upvalues)])
+        out += uleb(len(self.constants)) + uleb(len(self.numbers)) + uleb(len(self.code))
+
+        debug = bytearray()
+        if not stripped:
+            # every instruction is attributed to the line the function starts on
+            debug += bytes(len(self.code))
+            for name, _, _ in self.upvalues:
+                debug += name.encode() + b"\0"
+            last_start = 0
+            for name, start, end in self.locals:
+                # ranges count the function header
+                start, end = start + 1, end + 1
+                if name in INTERNAL_NAMES:
+                    debug.append(INTERNAL_NAMES[name])
+                else:
+                    debug += name.encode() + b"\0"
+                debug += uleb(start - last_start) + uleb(end - start)
+                last_start = start
+            debug.append(0)
+            out += uleb(len(debug))
+            out += uleb(self.line) + uleb(self.lines)
+
+        for word in self.words(ops):
+            out += struct.pack("<I", word)
+        for _, in_stack, index in self.upvalues:
+            out += struct.pack("<H", index | (UV_LOCAL if in_stack else 0))
+        # the constant referred to as 0 is dumped last
+        for constant in reversed(self.constants):
+            if isinstance(constant, Proto):
+                out += uleb(0)
+            elif isinstance(constant, Table):
+                out += uleb(1) + uleb(len(constant.array)) + uleb(len(constant.hash))
+                for value in constant.array:
+                    out += table_value(value)
+                for key, value in constant.hash:
+                    out += table_value(key) + table_value(value)
+            else:
+                data = constant.encode()
+                out += uleb(5 + len(data)) + data
+        for _, value in self.numbers:
+            out += number(value)
+        out += debug
+        return bytes(out)
+
+    def dump(self, ops, stripped):
+        """The functions this one defines, followed by itself."""
+        out = bytearray()
+        for constant in self.constants:
+            if isinstance(constant, Proto):
+                out += constant.dump(ops, stripped)
+        body = self.serialize(ops, stripped)
+        return bytes(out) + uleb(len(body)) + body
+
+
+def main_proto():
+    return Proto(vararg=True)
+
+
+def operators():
+    """
+    local a, b = ...
+    x = a % 3 + b
+    y = -a .. "!" .. #b
+    return 2 ^ a, 1.5 * b, a - 1, 2 - a, not a
+    """
+    main = main_proto()
+    main.abc("VARG", 0, 3, 0)
+    main.abc("MODVN", 2, 0, main.n(3))
+    main.abc("ADDVV", 2, 2, 1)
+    main.ad("GSET", 2, main.k("x"))
+    main.ad("UNM", 2, 0)
+    main.ad("KSTR", 3, main.k("!"))
+    main.ad("LEN", 4, 1)
+    main.abc("CAT", 2, 2, 4)
+    main.ad("GSET", 2, main.k("y"))
+    main.ad("KSHORT", 2, 2)
+    main.abc("POW", 2, 2, 0)
+    main.abc("MULNV", 3, 1, main.n(1.5))
+    main.abc("SUBVN", 4, 0, main.n(1))
+    main.abc("SUBNV", 5, 0, main.n(2))
+    main.ad("NOT", 6, 0)
+    main.ad("RET", 2, 6)
+    main.local("a", 1, 16)
+    main.local("b", 1, 16)
+    return main
+
+
+def loops():
+    """
+    local t = {}
+    for i = 1, 10 do
+        t[i] = i * 2
+    end
+    for k, v in pairs(t) do
+        print(k, v)
+    end
+    return t
+    """
+    main = main_proto()
+    main.ad("TNEW", 0, 0)
+    main.ad("KSHORT", 1, 1)
+    main.ad("KSHORT", 2, 10)
+    main.ad("KSHORT", 3, 1)
+    main.jump("FORI", 1, "for_end")
+    main.label("for_body")
+    main.abc("MULVN", 5, 4, main.n(2))
+    main.abc("TSETV", 5, 0, 4)
+    main.jump("FORL", 1, "for_body")
+    main.label("for_end")
+    main.ad("GGET", 1, main.k("pairs"))
+    main.ad("MOV", 3, 0)
+    main.abc("CALL", 1, 4, 2)
+    main.jump("ISNEXT", 4, "iterate")
+    main.label("iterate_body")
+    main.ad("GGET", 6, main.k("print"))
+    main.ad("MOV", 8, 4)
+    main.ad("MOV", 9, 5)
+    main.abc("CALL", 6, 1, 3)
+    main.label("iterate")
+    main.abc("ITERN", 4, 3, 3)
+    main.jump("ITERL", 4, "iterate_body")
+    main.ad("RET1", 0, 2)
+    main.frame_size = 10
+    main.local("t", 1, 19)
+    main.local("(for index)", 4, 8)
+    main.local("(for limit)", 4, 8)
+    main.local("(for step)", 4, 8)
+    main.local("i", 5, 7)
+    main.local("(for generator)", 11, 18)
+    main.local("(for state)", 11, 18)
+    main.local("(for control)", 11, 18)
+    main.local("k", 12, 16)
+    main.local("v", 12, 16)
+    return main
+
+
+def conditionals():
+    """
+    local n, s = ...
+    if n >= 0 and n < 10 then
+        s = "small"
+    elseif n == -1 then
+        s = nil
+    end
+    return s, n > 100, s or "none"
+    """
+    main = main_proto()
+    main.abc("VARG", 0, 3, 0)
+    main.ad("KSHORT", 2, 0)
+    main.ad("ISLT", 0, 2)
+    main.jump("JMP", 2, "elseif")
+    main.ad("KSHORT", 2, 10)
+    main.ad("ISGE", 0, 2)
+    main.jump("JMP", 2, "elseif")
+    main.ad("KSTR", 1, main.k("small"))
+    main.jump("JMP", 2, "end")
+    main.label("elseif")
+    main.ad("ISNEN", 0, main.n(-1))
+    main.jump("JMP", 2, "end")
+    main.ad("KPRI", 1, NIL)
+    main.label("end")
+    main.ad("MOV", 2, 1)
+    main.ad("KSHORT", 3, 100)
+    main.ad("ISLT", 3, 0)
+    main.jump("JMP", 3, "true")
+    main.ad("KPRI", 3, FALSE)
+    main.jump("JMP", 4, "or")
+    main.label("true")
+    main.ad("KPRI", 3, TRUE)
+    main.label("or")
+    main.ad("ISTC", 4, 1)
+    main.jump("JMP", 5, "return")
+    main.ad("KSTR", 4, main.k("none"))
+    main.label("return")
+    main.ad("RET", 2, 4)
+    main.frame_size = 5
+    main.local("n", 1, 23)
+    main.local("s", 1, 23)
+    return main
+
+
+def closures():
+    """
+    local function counter(count)
+        return function(step)
+            count = count + step
+            return count
+        end
+    end
+    local t = {1, 2, "three", x = true, ...}
+    t.n = select("#", ...)
+    print(counter, t.n)
+    return counter(t[1])
+    """
+    step = Proto(params=1, upvalues=[("count", True, 0)], line=2, lines=3)
+    step.ad("UGET", 1, 0)
+    step.abc("ADDVV", 1, 1, 0)
+    step.ad("USETV", 0, 1)
+    step.ad("UGET", 1, 0)
+    step.ad("RET1", 1, 2)
+    step.local("step", 0, 5)
+
+    counter = Proto(params=1, line=1, lines=6)
+    counter.ad("FNEW", 1, counter.k(step))
+    counter.jump("UCLO", 0, "return")
+    counter.label("return")
+    counter.ad("RET1", 1, 2)
+    counter.local("count", 0, 3)
+
+    main = main_proto()
+    main.ad("FNEW", 0, main.k(counter))
+    main.ad("TDUP", 1, main.k(Table([None, 1, 2, "three"], [("x", True)])))
+    main.abc("VARG", 2, 0, 0)
+    main.ad("TSETM", 2, main.n(2.0**52 + 4))
+    main.ad("GGET", 2, main.k("select"))
+    main.ad("KSTR", 4, main.k("#"))
+    main.abc("VARG", 5, 0, 0)
+    main.abc("CALLM", 2, 2, 1)
+    main.abc("TSETS", 2, 1, main.k("n"))
+    main.ad("GGET", 2, main.k("print"))
+    main.ad("MOV", 4, 0)
+    main.abc("TGETS", 5, 1, main.k("n"))
+    main.abc("CALL", 2, 1, 3)
+    main.ad("MOV", 2, 0)
+    main.abc("TGETB", 4, 1, 1)
+    main.ad("CALLT", 2, 2)
+    main.frame_size = 6
+    main.local("counter", 1, 16)
+    main.local("t", 2, 16)
+    return main
+
+
+def legacy():
+    """
+    local n = ...
+    local total = 0
+    while n > 0 do
+        total = total + n * 0.5
+        n = n - 1
+    end
+    print(total)
+    """
+    main = main_proto()
+    main.abc("VARG", 0, 2, 0)
+    main.ad("KSHORT", 1, 0)
+    main.label("while")
+    main.ad("KSHORT", 2, 0)
+    main.ad("ISGE", 2, 0)
+    main.jump("JMP", 2, "end")
+    main.jump("LOOP", 2, "end")
+    main.abc("MULVN", 2, 0, main.n(0.5))
+    main.abc("ADDVV", 1, 1, 2)
+    main.abc("SUBVN", 0, 0, main.n(1))
+    main.jump("JMP", 2, "while")
+    main.label("end")
+    main.ad("GGET", 2, main.k("print"))
+    main.ad("MOV", 3, 1)
+    main.abc("CALL", 2, 1, 2)
+    main.ad("RET0", 0, 1)
+    main.frame_size = 4
+    return main
+
+
+def chunk(main, version=2, stripped=False):
+    ops = OPS_2_1 if version == 2 else OPS_2_0
+    flags = FLAG_TWO_SLOT_FRAMES if version == 2 else 0
+    out = bytearray(b"\x1bLJ") + bytes([version])
+    if stripped:
+        out += uleb(flags | FLAG_STRIPPED)
+    else:
+        name = b"@fixture.lua"
+        out += uleb(flags) + uleb(len(name)) + name
+    out += main.dump(ops, stripped)
+    out += b"\0"
+    return bytes(out)
+
+
+FIXTURES = {
+    "operators": lambda: chunk(operators()),
+    "loops": lambda: chunk(loops()),
+    "conditionals": lambda: chunk(conditionals()),
+    "closures": lambda: chunk(closures()),
+    "legacy": lambda: chunk(legacy(), version=1, stripped=True),
+}
+
+
+if __name__ == "__main__":
+    directory = os.path.dirname(os.path.abspath(__file__))
+    for name, build in FIXTURES.items():
+        with open(os.path.join(directory, name + ".ljbc"), "wb") as file:
+            file.write(build())
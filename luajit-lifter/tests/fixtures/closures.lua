@@ -0,0 +1,17 @@
+local function counter(count)
+	return function(step)
+		-- upvalues: (ref) count
+		count = count + step
+		return count
+	end
+end
+local t = {
+	1,
+	2,
+	"three",
+	x = true,
+	...,
+	n = select("#", ...)
+}
+print(counter, t.n)
+return counter(t[1])
\ No newline at end of file
@@ -0,0 +1,9 @@
+local n, s = ...
+if n < 0 or n >= 10 then
+	if n == -1 then
+		s = nil
+	end
+else
+	s = "small"
+end
+return s, n > 100, s or "none"
\ No newline at end of file
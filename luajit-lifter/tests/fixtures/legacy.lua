@@ -0,0 +1,7 @@
+local var = ...
+local num = 0
+while var > 0 do
+	num = num + var * 0.5
+	var = var - 1
+end
+print(num)
\ No newline at end of file
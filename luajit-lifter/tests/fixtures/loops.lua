@@ -0,0 +1,8 @@
+local t = {}
+for i = 1, 10 do
+	t[i] = i * 2
+end
+for k, v in pairs(t) do
+	print(k, v)
+end
+return t
\ No newline at end of file
@@ -0,0 +1,4 @@
+local a, b = ...
+x = a % 3 + b
+y = -a .. "!" .. #b
+return 2 ^ a, 1.5 * b, a - 1, 2 - a, not a
\ No newline at end of file
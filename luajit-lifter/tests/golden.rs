@@ -0,0 +1,17 @@
+//! Decompiles every `.ljbc` file in `tests/fixtures` and compares the output, which must
+//! parse, against the `.lua` file next to it. Run with `BLESS=1` to rewrite the expected
+//! output instead.
+
+use std::path::Path;
+
+use ast::formatter::Dialect;
+
+#[test]
+fn golden() {
+    decompiler_core::golden::check(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+        "ljbc",
+        Dialect::Lua52,
+        luajit_lifter::decompile_bytecode,
+    );
+}
@@ -0,0 +1,1103 @@
+use ast::{
+    Binary, BinaryOperation, Block, Call, Closure, GenericFor, If, Index, LValue, Literal,
+    MethodCall, NumericFor, RValue, RcLocal, Repeat, Return, Select, Statement, Table, Unary,
+    UnaryOperation, Upvalue, While,
+};
+use luau_lifter::{
+    deserializer::{chunk::Chunk, constant::Constant, function::Function},
+    instruction::Instruction,
+    op_code::OpCode,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::Error;
+
+// the number of values SETLIST stores at once, like Luau's compiler
+const SETLIST_BATCH: usize = 16;
+
+const CAPTURE_VALUE: u8 = 0;
+const CAPTURE_REFERENCE: u8 = 1;
+const CAPTURE_UPVALUE: u8 = 2;
+
+pub(crate) fn compile(block: &Block) -> Result<Chunk, Error> {
+    let mut chunk = ChunkBuilder::default();
+    let main = compile_function(&mut chunk, None, &[], true, &[], block)?;
+    Ok(Chunk {
+        string_table: chunk.string_table,
+        functions: chunk.functions,
+        main,
+    })
+}
+
+#[derive(Default)]
+struct ChunkBuilder {
+    string_table: Vec<Vec<u8>>,
+    strings: FxHashMap<Vec<u8>, usize>,
+    functions: Vec<Function>,
+}
+
+impl ChunkBuilder {
+    // string indices are 1-based, 0 means no string
+    fn string(&mut self, string: &[u8]) -> usize {
+        if let Some(&index) = self.strings.get(string) {
+            return index;
+        }
+        self.string_table.push(string.to_vec());
+        let index = self.string_table.len();
+        self.strings.insert(string.to_vec(), index);
+        index
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    // by bits, so that -0 and 0 are different constants
+    Number(u64),
+    String(usize),
+    Vector([u32; 3]),
+}
+
+struct Scope {
+    start: usize,
+    locals: Vec<RcLocal>,
+}
+
+struct Loop {
+    // the index of the scope of the loop body
+    scope: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// where an assignment stores its value
+enum Place {
+    Register(u8),
+    Upvalue(u8),
+    Global(u32),
+    Field(u8, u32),
+    Element(u8, u8),
+    Index(u8, u8),
+}
+
+enum MultipleValue<'a> {
+    Call(&'a Call),
+    MethodCall(&'a MethodCall),
+    VarArg,
+}
+
+// whether `value` gives all of its values when it is last in a list. a select is a single
+// value everywhere but on the right of an assignment
+fn multiple_value(value: &RValue, in_assign: bool) -> Option<MultipleValue<'_>> {
+    match value {
+        RValue::Call(call) => Some(MultipleValue::Call(call)),
+        RValue::MethodCall(method_call) => Some(MultipleValue::MethodCall(method_call)),
+        RValue::VarArg(_) => Some(MultipleValue::VarArg),
+        RValue::Select(select) if in_assign => Some(select_value(select)),
+        _ => None,
+    }
+}
+
+fn select_value(select: &Select) -> MultipleValue<'_> {
+    match select {
+        Select::Call(call) => MultipleValue::Call(call),
+        Select::MethodCall(method_call) => MultipleValue::MethodCall(method_call),
+        Select::VarArg(_) => MultipleValue::VarArg,
+    }
+}
+
+fn number_literal(value: &RValue) -> Option<f64> {
+    match *value {
+        RValue::Literal(Literal::Number(n)) => Some(n),
+        RValue::Literal(Literal::Integer(i)) => Some(i as f64),
+        _ => None,
+    }
+}
+
+// GETTABLEN and SETTABLEN hold keys from 1 to 256
+fn element_index(key: &RValue) -> Option<u8> {
+    number_literal(key)
+        .filter(|n| n.fract() == 0.0 && (1.0..=256.0).contains(n))
+        .map(|n| (n - 1.0) as u8)
+}
+
+fn register(index: usize) -> Result<u8, Error> {
+    u8::try_from(index).map_err(|_| Error::TooManyRegisters)
+}
+
+fn compile_function(
+    chunk: &mut ChunkBuilder,
+    name: Option<&str>,
+    parameters: &[RcLocal],
+    is_variadic: bool,
+    upvalues: &[Upvalue],
+    body: &Block,
+) -> Result<usize, Error> {
+    let num_upvalues = u8::try_from(upvalues.len()).map_err(|_| Error::TooManyUpvalues)?;
+    let num_parameters = register(parameters.len())?;
+    let mut compiler = FunctionCompiler {
+        chunk,
+        instructions: Vec::new(),
+        constants: Vec::new(),
+        constant_indices: FxHashMap::default(),
+        functions: Vec::new(),
+        registers: FxHashMap::default(),
+        upvalues: FxHashMap::default(),
+        captured: FxHashSet::default(),
+        scopes: vec![Scope {
+            start: 0,
+            locals: Vec::new(),
+        }],
+        loops: Vec::new(),
+        top: 0,
+        max_stack_size: 0,
+    };
+    for (index, upvalue) in upvalues.iter().enumerate() {
+        let (Upvalue::Copy(local) | Upvalue::Ref(local)) = upvalue;
+        compiler.upvalues.insert(local.clone(), index as u8);
+    }
+    compiler.allocate(parameters.len())?;
+    for (register, parameter) in (0..).zip(parameters) {
+        compiler.bind(parameter, register);
+    }
+    if is_variadic {
+        compiler.abc(OpCode::LOP_PREPVARARGS, num_parameters, 0, 0);
+    }
+    compiler.block(body)?;
+    if !matches!(body.last(), Some(Statement::Return(_))) {
+        compiler.abc(OpCode::LOP_RETURN, 0, 1, 0);
+    }
+
+    let function_name = name.map_or(0, |name| compiler.chunk.string(name.as_bytes()));
+    let function = Function {
+        max_stack_size: compiler.max_stack_size,
+        num_parameters,
+        num_upvalues,
+        is_vararg: is_variadic,
+        instructions: compiler.instructions,
+        constants: compiler.constants,
+        functions: compiler.functions,
+        line_defined: 0,
+        function_name,
+        line_gap_log2: None,
+        line_info_delta: None,
+        abs_line_info_delta: None,
+    };
+    chunk.functions.push(function);
+    Ok(chunk.functions.len() - 1)
+}
+
+struct FunctionCompiler<'a> {
+    chunk: &'a mut ChunkBuilder,
+    instructions: Vec<Instruction>,
+    constants: Vec<Constant>,
+    constant_indices: FxHashMap<ConstantKey, usize>,
+    functions: Vec<usize>,
+    registers: FxHashMap<RcLocal, u8>,
+    upvalues: FxHashMap<RcLocal, u8>,
+    // locals captured by reference, their registers are closed when they go out of scope
+    captured: FxHashSet<RcLocal>,
+    scopes: Vec<Scope>,
+    loops: Vec<Loop>,
+    // the first free register
+    top: usize,
+    max_stack_size: u8,
+}
+
+impl FunctionCompiler<'_> {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+
+    fn abc(&mut self, op_code: OpCode, a: u8, b: u8, c: u8) -> usize {
+        self.abc_aux(op_code, a, b, c, None)
+    }
+
+    fn ad(&mut self, op_code: OpCode, a: u8, d: i16) -> usize {
+        self.ad_aux(op_code, a, d, None)
+    }
+
+    // like the deserializer, the aux word is kept on its instruction and its slot is a NOP
+    fn abc_aux(&mut self, op_code: OpCode, a: u8, b: u8, c: u8, aux: Option<u32>) -> usize {
+        let pc = self.emit(Instruction::BC {
+            op_code,
+            a,
+            b,
+            c,
+            aux: aux.unwrap_or(0),
+        });
+        if aux.is_some() {
+            self.abc(OpCode::LOP_NOP, 0, 0, 0);
+        }
+        pc
+    }
+
+    fn ad_aux(&mut self, op_code: OpCode, a: u8, d: i16, aux: Option<u32>) -> usize {
+        let pc = self.emit(Instruction::AD {
+            op_code,
+            a,
+            d,
+            aux: aux.unwrap_or(0),
+        });
+        if aux.is_some() {
+            self.abc(OpCode::LOP_NOP, 0, 0, 0);
+        }
+        pc
+    }
+
+    // jumps are relative to the instruction after them, backward jumps are JUMPBACKs
+    fn patch(&mut self, pc: usize, target: usize) -> Result<(), Error> {
+        let offset =
+            i16::try_from(target as isize - (pc as isize + 1)).map_err(|_| Error::JumpTooFar)?;
+        match &mut self.instructions[pc] {
+            Instruction::AD { op_code, d, .. } => {
+                if matches!(op_code, OpCode::LOP_JUMP | OpCode::LOP_JUMPBACK) {
+                    *op_code = if offset < 0 {
+                        OpCode::LOP_JUMPBACK
+                    } else {
+                        OpCode::LOP_JUMP
+                    };
+                }
+                *d = offset;
+            }
+            _ => unreachable!("only AD instructions jump"),
+        }
+        Ok(())
+    }
+
+    fn patch_here(&mut self, jumps: Vec<usize>) -> Result<(), Error> {
+        let target = self.instructions.len();
+        jumps.into_iter().try_for_each(|pc| self.patch(pc, target))
+    }
+
+    fn jump(&mut self) -> usize {
+        self.ad(OpCode::LOP_JUMP, 0, 0)
+    }
+
+    fn jump_to(&mut self, target: usize) -> Result<(), Error> {
+        let pc = self.jump();
+        self.patch(pc, target)
+    }
+
+    fn reserve(&mut self, end: usize) -> Result<(), Error> {
+        self.max_stack_size = self.max_stack_size.max(register(end)?);
+        Ok(())
+    }
+
+    fn allocate(&mut self, count: usize) -> Result<u8, Error> {
+        let base = register(self.top)?;
+        self.reserve(self.top + count)?;
+        self.top += count;
+        Ok(base)
+    }
+
+    fn bind(&mut self, local: &RcLocal, register: u8) {
+        self.registers.insert(local.clone(), register);
+        self.scopes.last_mut().unwrap().locals.push(local.clone());
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Scope {
+            start: self.top,
+            locals: Vec::new(),
+        });
+    }
+
+    fn captures(&self, scopes: &[Scope]) -> bool {
+        scopes
+            .iter()
+            .flat_map(|scope| &scope.locals)
+            .any(|local| self.captured.contains(local))
+    }
+
+    fn close_upvalues(&mut self, start: usize) -> Result<(), Error> {
+        self.abc(OpCode::LOP_CLOSEUPVALS, register(start)?, 0, 0);
+        Ok(())
+    }
+
+    fn pop_scope(&mut self, close: bool) -> Result<(), Error> {
+        let scope = self.scopes.pop().unwrap();
+        if close && self.captures(std::slice::from_ref(&scope)) {
+            self.close_upvalues(scope.start)?;
+        }
+        for local in &scope.locals {
+            self.registers.remove(local);
+        }
+        self.top = scope.start;
+        Ok(())
+    }
+
+    fn constant(&mut self, key: ConstantKey, constant: Constant) -> usize {
+        if let Some(&index) = self.constant_indices.get(&key) {
+            return index;
+        }
+        self.constants.push(constant);
+        self.constant_indices.insert(key, self.constants.len() - 1);
+        self.constants.len() - 1
+    }
+
+    fn string_constant(&mut self, string: &[u8]) -> u32 {
+        let string = self.chunk.string(string);
+        self.constant(ConstantKey::String(string), Constant::String(string)) as u32
+    }
+
+    fn number_constant(&mut self, n: f64) -> usize {
+        self.constant(ConstantKey::Number(n.to_bits()), Constant::Number(n))
+    }
+
+    fn load_constant(&mut self, index: usize, target: u8) {
+        match i16::try_from(index) {
+            Ok(d) => self.ad(OpCode::LOP_LOADK, target, d),
+            Err(_) => self.abc_aux(OpCode::LOP_LOADKX, target, 0, 0, Some(index as u32)),
+        };
+    }
+
+    fn block(&mut self, block: &Block) -> Result<(), Error> {
+        for statement in block.iter() {
+            let top = self.top;
+            self.statement(statement)?;
+            // declarations keep their registers, temporaries are freed
+            if !matches!(statement, Statement::Assign(assign) if assign.prefix) {
+                self.top = top;
+            }
+        }
+        Ok(())
+    }
+
+    fn scoped_block(&mut self, block: &Block) -> Result<(), Error> {
+        self.push_scope();
+        self.block(block)?;
+        self.pop_scope(true)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), Error> {
+        match statement {
+            Statement::Empty(_) | Statement::Comment(_) => Ok(()),
+            Statement::Call(call) => self.call(call, register(self.top)?, Some(0)),
+            Statement::MethodCall(method_call) => {
+                self.method_call(method_call, register(self.top)?, Some(0))
+            }
+            Statement::Assign(assign) if assign.prefix => {
+                let base = self.allocate(assign.left.len())?;
+                // the locals are bound first so that a local function can capture itself
+                for (register, lvalue) in (base..).zip(&assign.left) {
+                    let local = lvalue
+                        .as_local()
+                        .ok_or(Error::Unsupported("declaring a non-local"))?;
+                    if local.0.lock().is_to_be_closed() {
+                        return Err(Error::Unsupported("to-be-closed variables"));
+                    }
+                    self.bind(local, register);
+                }
+                self.values(&assign.right, base, assign.left.len())
+            }
+            Statement::Assign(assign) => self.assign(&assign.left, &assign.right),
+            Statement::If(r#if) => self.r#if(r#if),
+            Statement::While(r#while) => self.r#while(r#while),
+            Statement::Repeat(repeat) => self.repeat(repeat),
+            Statement::NumericFor(numeric_for) => self.numeric_for(numeric_for),
+            Statement::GenericFor(generic_for) => self.generic_for(generic_for),
+            Statement::Return(r#return) => self.r#return(r#return),
+            Statement::Break(_) => {
+                let pc = self.loop_exit("`break` outside of a loop")?;
+                self.loops.last_mut().unwrap().breaks.push(pc);
+                Ok(())
+            }
+            Statement::Continue(_) => {
+                let pc = self.loop_exit("`continue` outside of a loop")?;
+                self.loops.last_mut().unwrap().continues.push(pc);
+                Ok(())
+            }
+            Statement::Goto(_) => Err(Error::Unsupported("`goto`")),
+            Statement::Label(_) => Err(Error::Unsupported("labels")),
+            Statement::NumForInit(_)
+            | Statement::NumForNext(_)
+            | Statement::GenericForInit(_)
+            | Statement::GenericForNext(_) => Err(Error::Unsupported("unstructured `for` loops")),
+            Statement::Close(_) => Err(Error::Unsupported("to-be-closed variables")),
+            Statement::SetList(_) => Err(Error::Unsupported("unstructured table constructors")),
+        }
+    }
+
+    // closes the upvalues of the loop body and jumps to a location patched later
+    fn loop_exit(&mut self, outside: &'static str) -> Result<usize, Error> {
+        let scope = self.loops.last().ok_or(Error::Unsupported(outside))?.scope;
+        if self.captures(&self.scopes[scope..]) {
+            self.close_upvalues(self.scopes[scope].start)?;
+        }
+        Ok(self.jump())
+    }
+
+    fn push_loop(&mut self) {
+        self.push_scope();
+        self.loops.push(Loop {
+            scope: self.scopes.len() - 1,
+            breaks: Vec::new(),
+            continues: Vec::new(),
+        });
+    }
+
+    fn place(&mut self, lvalue: &LValue) -> Result<Place, Error> {
+        Ok(match lvalue {
+            LValue::Local(local) => {
+                if let Some(&register) = self.registers.get(local) {
+                    Place::Register(register)
+                } else if let Some(&upvalue) = self.upvalues.get(local) {
+                    Place::Upvalue(upvalue)
+                } else {
+                    return Err(Error::UndeclaredLocal(local.to_string()));
+                }
+            }
+            LValue::Global(global) => Place::Global(self.string_constant(&global.0)),
+            LValue::Index(index) => {
+                let object = self.expression_register(&index.left)?;
+                if let RValue::Literal(Literal::String(key)) = &*index.right {
+                    Place::Field(object, self.string_constant(key))
+                } else if let Some(element) = element_index(&index.right) {
+                    Place::Element(object, element)
+                } else {
+                    Place::Index(object, self.expression_register(&index.right)?)
+                }
+            }
+        })
+    }
+
+    fn store(&mut self, place: &Place, source: u8) {
+        match *place {
+            Place::Register(register) => {
+                if register != source {
+                    self.abc(OpCode::LOP_MOVE, register, source, 0);
+                }
+            }
+            Place::Upvalue(upvalue) => {
+                self.abc(OpCode::LOP_SETUPVAL, source, upvalue, 0);
+            }
+            Place::Global(key) => {
+                self.abc_aux(OpCode::LOP_SETGLOBAL, source, 0, 0, Some(key));
+            }
+            Place::Field(object, key) => {
+                self.abc_aux(OpCode::LOP_SETTABLEKS, source, object, 0, Some(key));
+            }
+            Place::Element(object, element) => {
+                self.abc(OpCode::LOP_SETTABLEN, source, object, element);
+            }
+            Place::Index(object, key) => {
+                self.abc(OpCode::LOP_SETTABLE, source, object, key);
+            }
+        }
+    }
+
+    fn assign(&mut self, left: &[LValue], right: &[RValue]) -> Result<(), Error> {
+        // a single local is assigned in place, unless the value reads the local after
+        // writing to its register
+        if let ([LValue::Local(local)], [value]) = (left, right)
+            && let Some(&register) = self.registers.get(local)
+        {
+            let reads_after_write = matches!(
+                value,
+                RValue::Table(_)
+                    | RValue::Binary(Binary {
+                        operation: BinaryOperation::And | BinaryOperation::Or,
+                        ..
+                    })
+            );
+            if !reads_after_write {
+                return self.expression(value, register);
+            }
+        }
+        let places = left
+            .iter()
+            .map(|lvalue| self.place(lvalue))
+            .collect::<Result<Vec<_>, _>>()?;
+        let base = self.allocate(left.len())?;
+        self.values(right, base, left.len())?;
+        for (source, place) in (base..).zip(&places) {
+            self.store(place, source);
+        }
+        Ok(())
+    }
+
+    fn r#if(&mut self, r#if: &If) -> Result<(), Error> {
+        let jumps = self.condition(&r#if.condition, false)?;
+        self.scoped_block(&r#if.then_block.lock())?;
+        let else_block = r#if.else_block.lock();
+        if else_block.is_empty() {
+            self.patch_here(jumps)
+        } else {
+            let skip = self.jump();
+            self.patch_here(jumps)?;
+            self.scoped_block(&else_block)?;
+            self.patch_here(vec![skip])
+        }
+    }
+
+    fn r#while(&mut self, r#while: &While) -> Result<(), Error> {
+        let start = self.instructions.len();
+        let exits = self.condition(&r#while.condition, false)?;
+        self.push_loop();
+        self.block(&r#while.block.lock())?;
+        self.pop_scope(true)?;
+        let r#loop = self.loops.pop().unwrap();
+        for pc in r#loop.continues {
+            self.patch(pc, start)?;
+        }
+        self.jump_to(start)?;
+        self.patch_here(exits)?;
+        self.patch_here(r#loop.breaks)
+    }
+
+    fn repeat(&mut self, repeat: &Repeat) -> Result<(), Error> {
+        let start = self.instructions.len();
+        self.push_loop();
+        self.block(&repeat.block.lock())?;
+        let continues = std::mem::take(&mut self.loops.last_mut().unwrap().continues);
+        self.patch_here(continues)?;
+        // the condition can use the locals of the body, so they are closed after it
+        let exits = self.condition(&repeat.condition, true)?;
+        let scope_start = self.scopes.last().unwrap().start;
+        let close = self.captures(&self.scopes[self.scopes.len() - 1..]);
+        if close {
+            self.close_upvalues(scope_start)?;
+        }
+        self.jump_to(start)?;
+        self.patch_here(exits)?;
+        if close {
+            self.close_upvalues(scope_start)?;
+        }
+        self.pop_scope(false)?;
+        let r#loop = self.loops.pop().unwrap();
+        self.patch_here(r#loop.breaks)
+    }
+
+    fn numeric_for(&mut self, numeric_for: &NumericFor) -> Result<(), Error> {
+        // FORNPREP and FORNLOOP use the limit, the step and the index in this order
+        let base = self.allocate(3)?;
+        self.expression(&numeric_for.initial, base + 2)?;
+        self.expression(&numeric_for.limit, base)?;
+        self.expression(&numeric_for.step, base + 1)?;
+        let prepare = self.ad(OpCode::LOP_FORNPREP, base, 0);
+        let body = self.instructions.len();
+        self.push_loop();
+        // the body gets a copy of the index, so assigning to the counter doesn't change
+        // the iteration
+        let counter = self.allocate(1)?;
+        self.abc(OpCode::LOP_MOVE, counter, base + 2, 0);
+        self.bind(&numeric_for.counter, counter);
+        self.block(&numeric_for.block.lock())?;
+        self.pop_scope(true)?;
+        let r#loop = self.loops.pop().unwrap();
+        self.patch_here(r#loop.continues)?;
+        let next = self.ad(OpCode::LOP_FORNLOOP, base, 0);
+        self.patch(next, body)?;
+        self.patch_here(vec![prepare])?;
+        self.patch_here(r#loop.breaks)
+    }
+
+    fn generic_for(&mut self, generic_for: &GenericFor) -> Result<(), Error> {
+        // the generator, the state and the control, followed by the variables
+        let base = self.allocate(3)?;
+        self.values(&generic_for.right, base, 3)?;
+        let prepare = self.ad(OpCode::LOP_FORGPREP, base, 0);
+        let body = self.instructions.len();
+        self.push_loop();
+        let variables = self.allocate(generic_for.res_locals.len())?;
+        for (register, local) in (variables..).zip(&generic_for.res_locals) {
+            self.bind(local, register);
+        }
+        self.block(&generic_for.block.lock())?;
+        self.pop_scope(true)?;
+        let r#loop = self.loops.pop().unwrap();
+        self.patch_here(r#loop.continues)?;
+        self.patch_here(vec![prepare])?;
+        let next = self.ad_aux(
+            OpCode::LOP_FORGLOOP,
+            base,
+            0,
+            Some(generic_for.res_locals.len() as u32),
+        );
+        self.patch(next, body)?;
+        self.patch_here(r#loop.breaks)
+    }
+
+    fn r#return(&mut self, r#return: &Return) -> Result<(), Error> {
+        if let [RValue::Local(local)] = &r#return.values[..]
+            && let Some(&register) = self.registers.get(local)
+        {
+            self.abc(OpCode::LOP_RETURN, register, 2, 0);
+            return Ok(());
+        }
+        let base = register(self.top)?;
+        let count = self.list(&r#return.values, base)?;
+        self.abc(
+            OpCode::LOP_RETURN,
+            base,
+            count.map_or(0, |count| count + 1),
+            0,
+        );
+        Ok(())
+    }
+
+    // puts `count` values in the registers from `base`, which are above every live
+    // register. like on the right of an assignment, the last value is adjusted
+    fn values(&mut self, values: &[RValue], base: u8, count: usize) -> Result<(), Error> {
+        let end = base as usize + count;
+        for (index, value) in values.iter().enumerate() {
+            let target = register(base as usize + index)?;
+            self.top = target as usize;
+            if index + 1 == values.len()
+                && let Some(multiple) = multiple_value(value, true)
+            {
+                let remaining = register(count.saturating_sub(index))?;
+                self.multiple(multiple, target, Some(remaining))?;
+                self.top = end;
+                return Ok(());
+            }
+            self.allocate(1)?;
+            self.expression(value, target)?;
+        }
+        for target in base as usize + values.len()..end {
+            self.abc(OpCode::LOP_LOADNIL, register(target)?, 0, 0);
+        }
+        self.top = end;
+        Ok(())
+    }
+
+    // puts `values` in the registers from `base` and returns how many there are, or `None`
+    // if the last value gives all of its values and the count is only known when running
+    fn list(&mut self, values: &[RValue], base: u8) -> Result<Option<u8>, Error> {
+        for (index, value) in values.iter().enumerate() {
+            let target = register(base as usize + index)?;
+            self.top = target as usize;
+            if index + 1 == values.len()
+                && let Some(multiple) = multiple_value(value, false)
+            {
+                self.multiple(multiple, target, None)?;
+                return Ok(None);
+            }
+            self.allocate(1)?;
+            self.expression(value, target)?;
+        }
+        Ok(Some(register(values.len())?))
+    }
+
+    // `base` is the highest live register or above, the values can overwrite everything
+    // after it
+    fn multiple(&mut self, value: MultipleValue, base: u8, count: Option<u8>) -> Result<(), Error> {
+        match value {
+            MultipleValue::Call(call) => self.call(call, base, count),
+            MultipleValue::MethodCall(method_call) => self.method_call(method_call, base, count),
+            MultipleValue::VarArg => {
+                self.top = base as usize;
+                self.allocate(count.unwrap_or(0) as usize)?;
+                self.abc(
+                    OpCode::LOP_GETVARARGS,
+                    base,
+                    count.map_or(0, |count| count + 1),
+                    0,
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn call(&mut self, call: &Call, base: u8, results: Option<u8>) -> Result<(), Error> {
+        self.top = base as usize;
+        self.allocate(1)?;
+        self.expression(&call.value, base)?;
+        let arguments = self.list(&call.arguments, base + 1)?;
+        self.finish_call(base, arguments.map(|count| count + 1), results)
+    }
+
+    fn method_call(
+        &mut self,
+        method_call: &MethodCall,
+        base: u8,
+        results: Option<u8>,
+    ) -> Result<(), Error> {
+        self.top = base as usize;
+        self.allocate(2)?;
+        self.expression(&method_call.value, base)?;
+        let method = self.string_constant(method_call.method.as_bytes());
+        self.abc_aux(OpCode::LOP_NAMECALL, base, base, 0, Some(method));
+        // the object is the first argument
+        let arguments = self.list(&method_call.arguments, base + 2)?;
+        self.finish_call(base, arguments.map(|count| count + 2), results)
+    }
+
+    fn finish_call(
+        &mut self,
+        base: u8,
+        arguments: Option<u8>,
+        results: Option<u8>,
+    ) -> Result<(), Error> {
+        self.abc(
+            OpCode::LOP_CALL,
+            base,
+            arguments.unwrap_or(0),
+            results.map_or(0, |results| results + 1),
+        );
+        self.top = base as usize;
+        self.allocate(results.unwrap_or(0) as usize)?;
+        Ok(())
+    }
+
+    fn expression_register(&mut self, value: &RValue) -> Result<u8, Error> {
+        if let RValue::Local(local) = value
+            && let Some(&register) = self.registers.get(local)
+        {
+            return Ok(register);
+        }
+        let register = self.allocate(1)?;
+        self.expression(value, register)?;
+        Ok(register)
+    }
+
+    fn expression(&mut self, value: &RValue, target: u8) -> Result<(), Error> {
+        match value {
+            RValue::Local(local) => {
+                if let Some(&register) = self.registers.get(local) {
+                    if register != target {
+                        self.abc(OpCode::LOP_MOVE, target, register, 0);
+                    }
+                } else if let Some(&upvalue) = self.upvalues.get(local) {
+                    self.abc(OpCode::LOP_GETUPVAL, target, upvalue, 0);
+                } else {
+                    return Err(Error::UndeclaredLocal(local.to_string()));
+                }
+            }
+            RValue::Global(global) => {
+                let key = self.string_constant(&global.0);
+                self.abc_aux(OpCode::LOP_GETGLOBAL, target, 0, 0, Some(key));
+            }
+            RValue::Call(_) | RValue::MethodCall(_) | RValue::VarArg(_) => {
+                self.single(multiple_value(value, false).unwrap(), target)?
+            }
+            RValue::Select(select) => self.single(select_value(select), target)?,
+            RValue::Table(table) => self.table(table, target)?,
+            RValue::Literal(literal) => self.literal(literal, target),
+            RValue::Index(index) => self.index(index, target)?,
+            RValue::Unary(unary) => self.unary(unary, target)?,
+            RValue::Binary(binary) => self.binary(binary, target)?,
+            RValue::Closure(closure) => self.closure(closure, target)?,
+        }
+        Ok(())
+    }
+
+    // the first value of a call or `...`
+    fn single(&mut self, value: MultipleValue, target: u8) -> Result<(), Error> {
+        if target as usize + 1 >= self.top {
+            self.multiple(value, target, Some(1))
+        } else {
+            // the registers after the target are live, so the call can't put its
+            // arguments there
+            let temporary = self.allocate(1)?;
+            self.multiple(value, temporary, Some(1))?;
+            self.abc(OpCode::LOP_MOVE, target, temporary, 0);
+            Ok(())
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal, target: u8) {
+        match *literal {
+            Literal::Nil => {
+                self.abc(OpCode::LOP_LOADNIL, target, 0, 0);
+            }
+            Literal::Boolean(b) => {
+                self.abc(OpCode::LOP_LOADB, target, b as u8, 0);
+            }
+            Literal::Number(n) => self.number(n, target),
+            Literal::Integer(i) => self.number(i as f64, target),
+            Literal::String(ref string) => {
+                let constant = self.string_constant(string);
+                self.load_constant(constant as usize, target);
+            }
+            Literal::Vector(x, y, z) => {
+                let key = ConstantKey::Vector([x.to_bits(), y.to_bits(), z.to_bits()]);
+                let constant = self.constant(key, Constant::Vector(x, y, z, 0.0));
+                self.load_constant(constant, target);
+            }
+        }
+    }
+
+    fn number(&mut self, n: f64, target: u8) {
+        let is_small_integer = n.fract() == 0.0
+            && n >= i16::MIN as f64
+            && n <= i16::MAX as f64
+            && !(n == 0.0 && n.is_sign_negative());
+        if is_small_integer {
+            self.ad(OpCode::LOP_LOADN, target, n as i16);
+        } else {
+            let constant = self.number_constant(n);
+            self.load_constant(constant, target);
+        }
+    }
+
+    fn index(&mut self, index: &Index, target: u8) -> Result<(), Error> {
+        let object = self.expression_register(&index.left)?;
+        if let RValue::Literal(Literal::String(key)) = &*index.right {
+            let key = self.string_constant(key);
+            self.abc_aux(OpCode::LOP_GETTABLEKS, target, object, 0, Some(key));
+        } else if let Some(element) = element_index(&index.right) {
+            self.abc(OpCode::LOP_GETTABLEN, target, object, element);
+        } else {
+            let key = self.expression_register(&index.right)?;
+            self.abc(OpCode::LOP_GETTABLE, target, object, key);
+        }
+        Ok(())
+    }
+
+    fn unary(&mut self, unary: &Unary, target: u8) -> Result<(), Error> {
+        let op_code = match unary.operation {
+            UnaryOperation::Not => OpCode::LOP_NOT,
+            UnaryOperation::Negate => OpCode::LOP_MINUS,
+            UnaryOperation::Length => OpCode::LOP_LENGTH,
+            UnaryOperation::BitNot => return Err(Error::Unsupported("bitwise operators")),
+        };
+        let value = self.expression_register(&unary.value)?;
+        self.abc(op_code, target, value, 0);
+        Ok(())
+    }
+
+    fn binary(&mut self, binary: &Binary, target: u8) -> Result<(), Error> {
+        let (op_code, constant_op_code) = match binary.operation {
+            BinaryOperation::And | BinaryOperation::Or => {
+                self.expression(&binary.left, target)?;
+                let op_code = if binary.operation == BinaryOperation::And {
+                    OpCode::LOP_JUMPIFNOT
+                } else {
+                    OpCode::LOP_JUMPIF
+                };
+                let skip = self.ad(op_code, target, 0);
+                self.expression(&binary.right, target)?;
+                return self.patch_here(vec![skip]);
+            }
+            operation if operation.is_comparator() => {
+                let jump = self.comparison(binary, true)?;
+                self.abc(OpCode::LOP_LOADB, target, 0, 1);
+                self.patch_here(vec![jump])?;
+                self.abc(OpCode::LOP_LOADB, target, 1, 0);
+                return Ok(());
+            }
+            BinaryOperation::Concat => return self.concat(binary, target),
+            BinaryOperation::Add => (OpCode::LOP_ADD, OpCode::LOP_ADDK),
+            BinaryOperation::Sub => (OpCode::LOP_SUB, OpCode::LOP_SUBK),
+            BinaryOperation::Mul => (OpCode::LOP_MUL, OpCode::LOP_MULK),
+            BinaryOperation::Div => (OpCode::LOP_DIV, OpCode::LOP_DIVK),
+            BinaryOperation::Mod => (OpCode::LOP_MOD, OpCode::LOP_MODK),
+            BinaryOperation::Pow => (OpCode::LOP_POW, OpCode::LOP_POWK),
+            BinaryOperation::IDiv => (OpCode::LOP_IDIV, OpCode::LOP_IDIVK),
+            _ => return Err(Error::Unsupported("bitwise operators")),
+        };
+        let left = self.expression_register(&binary.left)?;
+        // the K variants take the constant index in C
+        if let Some(n) = number_literal(&binary.right)
+            && let Ok(constant) = u8::try_from(self.number_constant(n))
+        {
+            self.abc(constant_op_code, target, left, constant);
+            return Ok(());
+        }
+        let right = self.expression_register(&binary.right)?;
+        self.abc(op_code, target, left, right);
+        Ok(())
+    }
+
+    // a chain of concatenations is a single CONCAT of consecutive registers
+    fn concat(&mut self, binary: &Binary, target: u8) -> Result<(), Error> {
+        fn flatten<'a>(value: &'a RValue, operands: &mut Vec<&'a RValue>) {
+            match value {
+                RValue::Binary(Binary {
+                    left,
+                    right,
+                    operation: BinaryOperation::Concat,
+                }) => {
+                    flatten(left, operands);
+                    flatten(right, operands);
+                }
+                _ => operands.push(value),
+            }
+        }
+        let mut operands = Vec::new();
+        flatten(&binary.left, &mut operands);
+        flatten(&binary.right, &mut operands);
+        let base = register(self.top)?;
+        for operand in operands {
+            let register = self.allocate(1)?;
+            self.expression(operand, register)?;
+        }
+        self.abc(OpCode::LOP_CONCAT, target, base, register(self.top - 1)?);
+        Ok(())
+    }
+
+    fn table(&mut self, table: &Table, target: u8) -> Result<(), Error> {
+        let array_size = table.0.iter().filter(|(key, _)| key.is_none()).count();
+        self.abc_aux(OpCode::LOP_NEWTABLE, target, 0, 0, Some(array_size as u32));
+        // positional values are buffered in the registers from `base` and stored in batches
+        let base = self.top;
+        let mut pending = 0;
+        let mut index = 1;
+        for (position, (key, value)) in table.0.iter().enumerate() {
+            self.top = base + pending;
+            match key {
+                Some(key) => {
+                    let key = if let RValue::Literal(Literal::String(key)) = key {
+                        Place::Field(target, self.string_constant(key))
+                    } else if let Some(element) = element_index(key) {
+                        Place::Element(target, element)
+                    } else {
+                        Place::Index(target, self.expression_register(key)?)
+                    };
+                    let value = self.expression_register(value)?;
+                    self.store(&key, value);
+                }
+                None => {
+                    let target_register = register(base + pending)?;
+                    if position + 1 == table.0.len()
+                        && let Some(multiple) = multiple_value(value, false)
+                    {
+                        self.multiple(multiple, target_register, None)?;
+                        self.abc_aux(OpCode::LOP_SETLIST, target, register(base)?, 0, Some(index));
+                        pending = 0;
+                        break;
+                    }
+                    self.allocate(1)?;
+                    self.expression(value, target_register)?;
+                    pending += 1;
+                    if pending == SETLIST_BATCH {
+                        self.set_list(target, base, pending, index)?;
+                        index += pending as u32;
+                        pending = 0;
+                    }
+                }
+            }
+        }
+        if pending != 0 {
+            self.set_list(target, base, pending, index)?;
+        }
+        self.top = base;
+        Ok(())
+    }
+
+    fn set_list(&mut self, table: u8, base: usize, count: usize, index: u32) -> Result<(), Error> {
+        self.abc_aux(
+            OpCode::LOP_SETLIST,
+            table,
+            register(base)?,
+            register(count + 1)?,
+            Some(index),
+        );
+        Ok(())
+    }
+
+    fn closure(&mut self, closure: &Closure, target: u8) -> Result<(), Error> {
+        let mut captures = Vec::with_capacity(closure.upvalues.len());
+        for upvalue in &closure.upvalues {
+            let (Upvalue::Copy(local) | Upvalue::Ref(local)) = upvalue;
+            captures.push(if let Some(&register) = self.registers.get(local) {
+                if matches!(upvalue, Upvalue::Ref(_)) {
+                    self.captured.insert(local.clone());
+                    (CAPTURE_REFERENCE, register)
+                } else {
+                    (CAPTURE_VALUE, register)
+                }
+            } else if let Some(&upvalue) = self.upvalues.get(local) {
+                (CAPTURE_UPVALUE, upvalue)
+            } else {
+                return Err(Error::UndeclaredLocal(local.to_string()));
+            });
+        }
+        let function = closure.function.lock();
+        let proto = compile_function(
+            self.chunk,
+            function.name.as_deref(),
+            &function.parameters,
+            function.is_variadic,
+            &closure.upvalues,
+            &function.body,
+        )?;
+        let child = i16::try_from(self.functions.len())
+            .map_err(|_| Error::Unsupported("more than 32767 closures in a function"))?;
+        self.functions.push(proto);
+        self.ad(OpCode::LOP_NEWCLOSURE, target, child);
+        for (kind, source) in captures {
+            self.abc(OpCode::LOP_CAPTURE, kind, source, 0);
+        }
+        Ok(())
+    }
+
+    // returns the jumps taken when the truthiness of `condition` is `jump_if`, the code
+    // falls through otherwise
+    fn condition(&mut self, condition: &RValue, jump_if: bool) -> Result<Vec<usize>, Error> {
+        let top = self.top;
+        let jumps = match condition {
+            RValue::Unary(Unary {
+                operation: UnaryOperation::Not,
+                value,
+            }) => self.condition(value, !jump_if)?,
+            RValue::Binary(Binary {
+                left,
+                right,
+                operation: operation @ (BinaryOperation::And | BinaryOperation::Or),
+            }) => {
+                if (*operation == BinaryOperation::Or) == jump_if {
+                    // either side decides
+                    let mut jumps = self.condition(left, jump_if)?;
+                    jumps.extend(self.condition(right, jump_if)?);
+                    jumps
+                } else {
+                    let skip = self.condition(left, !jump_if)?;
+                    let jumps = self.condition(right, jump_if)?;
+                    self.patch_here(skip)?;
+                    jumps
+                }
+            }
+            RValue::Binary(binary) if binary.operation.is_comparator() => {
+                vec![self.comparison(binary, jump_if)?]
+            }
+            RValue::Literal(literal) => {
+                let truthy = !matches!(literal, Literal::Nil | Literal::Boolean(false));
+                if truthy == jump_if {
+                    vec![self.jump()]
+                } else {
+                    Vec::new()
+                }
+            }
+            _ => {
+                let register = self.expression_register(condition)?;
+                let op_code = if jump_if {
+                    OpCode::LOP_JUMPIF
+                } else {
+                    OpCode::LOP_JUMPIFNOT
+                };
+                vec![self.ad(op_code, register, 0)]
+            }
+        };
+        self.top = top;
+        Ok(jumps)
+    }
+
+    fn comparison(&mut self, binary: &Binary, jump_if: bool) -> Result<usize, Error> {
+        let (op_code, swap) = match binary.operation {
+            BinaryOperation::Equal => (OpCode::LOP_JUMPIFEQ, false),
+            BinaryOperation::NotEqual => (OpCode::LOP_JUMPIFNOTEQ, false),
+            BinaryOperation::LessThan => (OpCode::LOP_JUMPIFLT, false),
+            BinaryOperation::LessThanOrEqual => (OpCode::LOP_JUMPIFLE, false),
+            BinaryOperation::GreaterThan => (OpCode::LOP_JUMPIFLT, true),
+            BinaryOperation::GreaterThanOrEqual => (OpCode::LOP_JUMPIFLE, true),
+            _ => unreachable!("not a comparison"),
+        };
+        // `a < b` being false doesn't mean `a >= b` with NaNs, so the negated jumps are used
+        let op_code = match (op_code, jump_if) {
+            (op_code, true) => op_code,
+            (OpCode::LOP_JUMPIFEQ, false) => OpCode::LOP_JUMPIFNOTEQ,
+            (OpCode::LOP_JUMPIFNOTEQ, false) => OpCode::LOP_JUMPIFEQ,
+            (OpCode::LOP_JUMPIFLT, false) => OpCode::LOP_JUMPIFNOTLT,
+            (_, false) => OpCode::LOP_JUMPIFNOTLE,
+        };
+        let top = self.top;
+        let left = self.expression_register(&binary.left)?;
+        let right = self.expression_register(&binary.right)?;
+        self.top = top;
+        let (left, right) = if swap { (right, left) } else { (left, right) };
+        Ok(self.ad_aux(op_code, left, 0, Some(right as u32)))
+    }
+}
@@ -0,0 +1,174 @@
+use luau_lifter::{
+    deserializer::{chunk::Chunk, constant::Constant, function::Function},
+    instruction::Instruction,
+    op_code::OpCode,
+};
+
+// the oldest version `luau_lifter::deserializer` reads, it has no type information
+const VERSION: u8 = 3;
+
+/// Serializes `chunk` as Luau bytecode, with opcodes that aren't encoded (an encode key
+/// of 1).
+pub fn encode(chunk: &Chunk) -> Vec<u8> {
+    let mut output = vec![VERSION];
+    write_uleb128(&mut output, chunk.string_table.len());
+    for string in &chunk.string_table {
+        write_uleb128(&mut output, string.len());
+        output.extend_from_slice(string);
+    }
+    write_uleb128(&mut output, chunk.functions.len());
+    for function in &chunk.functions {
+        write_function(&mut output, function);
+    }
+    write_uleb128(&mut output, chunk.main);
+    output
+}
+
+fn write_uleb128(output: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            output.push(byte);
+            break;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+// the same list as the deserializer's
+fn has_aux(op_code: OpCode) -> bool {
+    matches!(
+        op_code,
+        OpCode::LOP_GETGLOBAL
+            | OpCode::LOP_SETGLOBAL
+            | OpCode::LOP_GETIMPORT
+            | OpCode::LOP_GETTABLEKS
+            | OpCode::LOP_SETTABLEKS
+            | OpCode::LOP_NAMECALL
+            | OpCode::LOP_JUMPIFEQ
+            | OpCode::LOP_JUMPIFLE
+            | OpCode::LOP_JUMPIFLT
+            | OpCode::LOP_JUMPIFNOTEQ
+            | OpCode::LOP_JUMPIFNOTLE
+            | OpCode::LOP_JUMPIFNOTLT
+            | OpCode::LOP_NEWTABLE
+            | OpCode::LOP_SETLIST
+            | OpCode::LOP_FORGLOOP
+            | OpCode::LOP_LOADKX
+            | OpCode::LOP_FASTCALL2
+            | OpCode::LOP_FASTCALL2K
+            | OpCode::LOP_FASTCALL3
+            | OpCode::LOP_JUMPXEQKNIL
+            | OpCode::LOP_JUMPXEQKB
+            | OpCode::LOP_JUMPXEQKN
+            | OpCode::LOP_JUMPXEQKS
+    )
+}
+
+// returns the instruction's word and its aux word if it has one
+fn encode_instruction(instruction: &Instruction) -> (u32, Option<u32>) {
+    let (op_code, word, aux) = match *instruction {
+        Instruction::BC {
+            op_code,
+            a,
+            b,
+            c,
+            aux,
+        } => (
+            op_code,
+            (a as u32) << 8 | (b as u32) << 16 | (c as u32) << 24,
+            aux,
+        ),
+        Instruction::AD { op_code, a, d, aux } => {
+            (op_code, (a as u32) << 8 | (d as u16 as u32) << 16, aux)
+        }
+        Instruction::E { op_code, e } => (op_code, (e as u32) << 8, 0),
+    };
+    (word | op_code as u32, has_aux(op_code).then_some(aux))
+}
+
+fn write_function(output: &mut Vec<u8>, function: &Function) {
+    output.extend([
+        function.max_stack_size,
+        function.num_parameters,
+        function.num_upvalues,
+        function.is_vararg as u8,
+    ]);
+
+    // the aux word takes the place of the NOP the deserializer puts after its instruction
+    write_uleb128(output, function.instructions.len());
+    let mut instructions = function.instructions.iter();
+    while let Some(instruction) = instructions.next() {
+        let (word, aux) = encode_instruction(instruction);
+        output.extend(word.to_le_bytes());
+        if let Some(aux) = aux {
+            output.extend(aux.to_le_bytes());
+            instructions.next();
+        }
+    }
+
+    write_uleb128(output, function.constants.len());
+    for constant in &function.constants {
+        write_constant(output, constant);
+    }
+    write_uleb128(output, function.functions.len());
+    for &child in &function.functions {
+        write_uleb128(output, child);
+    }
+    write_uleb128(output, function.line_defined);
+    write_uleb128(output, function.function_name);
+
+    match (
+        function.line_gap_log2,
+        &function.line_info_delta,
+        &function.abs_line_info_delta,
+    ) {
+        (Some(line_gap_log2), Some(line_info_delta), Some(abs_line_info_delta)) => {
+            output.extend([1, line_gap_log2]);
+            output.extend(line_info_delta);
+            for delta in abs_line_info_delta {
+                output.extend(delta.to_le_bytes());
+            }
+        }
+        _ => output.push(0),
+    }
+    // no debug info
+    output.push(0);
+}
+
+fn write_constant(output: &mut Vec<u8>, constant: &Constant) {
+    match *constant {
+        Constant::Nil => output.push(0),
+        Constant::Boolean(b) => output.extend([1, b as u8]),
+        Constant::Number(n) => {
+            output.push(2);
+            output.extend(n.to_le_bytes());
+        }
+        Constant::String(index) => {
+            output.push(3);
+            write_uleb128(output, index);
+        }
+        Constant::Import(import) => {
+            output.push(4);
+            output.extend((import as u32).to_le_bytes());
+        }
+        Constant::Table(ref keys) => {
+            output.push(5);
+            write_uleb128(output, keys.len());
+            for &key in keys {
+                write_uleb128(output, key);
+            }
+        }
+        Constant::Closure(function) => {
+            output.push(6);
+            write_uleb128(output, function);
+        }
+        Constant::Vector(x, y, z, w) => {
+            output.push(7);
+            for component in [x, y, z, w] {
+                output.extend(component.to_le_bytes());
+            }
+        }
+    }
+}
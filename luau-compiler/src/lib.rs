@@ -0,0 +1,37 @@
+//! Compiles the ast back into Luau bytecode, as the structures `luau_lifter::deserializer`
+//! reads, so that a decompiled chunk can be recompiled and run next to the original.
+//!
+//! The code generator is simple next to Luau's own compiler: there is no constant folding,
+//! no fast calls and no imports, but registers, constants, upvalue captures and jumps are
+//! laid out the way the Luau VM expects. Statements only the lifters use before
+//! structuring, `goto` and Lua 5.4 features can't be compiled.
+
+#![feature(let_chains)]
+
+mod compiler;
+mod encode;
+
+use ast::Block;
+use luau_lifter::deserializer::chunk::Chunk;
+use thiserror::Error;
+
+pub use encode::encode;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0} can't be compiled to Luau")]
+    Unsupported(&'static str),
+    #[error("local {0} is used outside of its scope")]
+    UndeclaredLocal(String),
+    #[error("function needs more than 255 registers")]
+    TooManyRegisters,
+    #[error("function has more than 255 upvalues")]
+    TooManyUpvalues,
+    #[error("jump is too far to encode")]
+    JumpTooFar,
+}
+
+/// Compiles `block` as the main function of a chunk.
+pub fn compile(block: &Block) -> Result<Chunk, Error> {
+    compiler::compile(block)
+}
@@ -0,0 +1,197 @@
+//! Recompiles decompiled Luau and checks that it behaves like the original bytecode.
+
+use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+use ast::{formatter::Dialect, parser::parse};
+use luau_lifter::deserializer::{self, bytecode::Bytecode, chunk::Chunk};
+use luau_vm::{Table, Value, Vm};
+
+// the same globals as the VM's differential test
+fn install_environment(vm: &mut Vm) {
+    let table = |entries: Vec<(Value, Value)>| {
+        let mut table = Table::default();
+        for (key, value) in entries {
+            table.set(key, value).unwrap();
+        }
+        Value::Table(Rc::new(RefCell::new(table)))
+    };
+    let mut globals = vm.globals.borrow_mut();
+    let method = globals.get(&"type".into());
+    let player = table(vec![
+        ("Name".into(), "player".into()),
+        ("Kick".into(), method.clone()),
+    ]);
+    let players = table(vec![("LocalPlayer".into(), player)]);
+    let part = table(vec![("Destroy".into(), method)]);
+    let t = table(vec![(1.0.into(), "a".into()), ("key".into(), 2.0.into())]);
+    for (name, value) in [
+        ("game", table(vec![("Players".into(), players)])),
+        ("workspace", table(vec![("Part".into(), part)])),
+        ("t", t),
+    ] {
+        globals.set(name.into(), value).unwrap();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    results: Result<Vec<String>, String>,
+    output: Vec<String>,
+}
+
+fn execute(chunk: &Chunk) -> Outcome {
+    let mut vm = Vm::new(chunk);
+    install_environment(&mut vm);
+    let results = vm.execute(Vec::new());
+    Outcome {
+        results: results
+            .map(|values| values.iter().map(Value::dump).collect())
+            .map_err(|error| error.to_string()),
+        output: vm.output,
+    }
+}
+
+fn deserialize(bytecode: &[u8]) -> Chunk {
+    match deserializer::deserialize(bytecode, 1).unwrap() {
+        Bytecode::Chunk(chunk) => chunk,
+        Bytecode::Error(error) => panic!("bytecode holds an error: {}", error),
+    }
+}
+
+// compiles `source`, checks that encoding the chunk and reading it back gives the same
+// chunk, and runs it
+fn run(source: &str) -> Outcome {
+    let chunk = luau_compiler::compile(&parse(source, Dialect::Luau).unwrap()).unwrap();
+    let deserialized = deserialize(&luau_compiler::encode(&chunk));
+    assert_eq!(format!("{:?}", deserialized), format!("{:?}", chunk));
+    execute(&chunk)
+}
+
+fn results(source: &str) -> Vec<String> {
+    run(source).results.unwrap()
+}
+
+#[test]
+fn recompiled_fixtures_behave_like_bytecode() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../luau-lifter/tests/fixtures");
+    let mut paths = fs::read_dir(&fixtures)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "luauc"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", fixtures.display());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let bytecode = fs::read(&path).unwrap();
+        let expected = execute(&deserialize(&bytecode));
+        let block = luau_lifter::decompile_to_ast(&bytecode, 1).unwrap();
+        let actual = match luau_compiler::compile(&block) {
+            Ok(chunk) => execute(&deserialize(&luau_compiler::encode(&chunk))),
+            Err(error) => {
+                failures.push(format!("{}: {}", path.display(), error));
+                continue;
+            }
+        };
+        if expected != actual {
+            failures.push(format!(
+                "{}:\n  bytecode:     {:?}\n  recompiled:   {:?}",
+                path.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn locals_and_upvalues() {
+    assert_eq!(
+        results(
+            "local function fib(n)
+                if n < 2 then return n end
+                return fib(n - 1) + fib(n - 2)
+            end
+            local count = 0
+            local function increment() count = count + 1 return count end
+            increment()
+            return fib(10), increment(), count"
+        ),
+        ["55", "2", "2"]
+    );
+    // every iteration has its own local, captured by reference
+    assert_eq!(
+        results(
+            "local functions = {}
+            for i = 1, 3 do
+                local j = i
+                functions[i] = function() j = j * 10 return j end
+            end
+            local k = 0
+            while k < 2 do
+                k = k + 1
+                local captured = k
+                functions[#functions + 1] = function() return captured end
+            end
+            return functions[1](), functions[2](), functions[3](), functions[1](), functions[4](), functions[5]()"
+        ),
+        ["10", "20", "30", "100", "1", "2"]
+    );
+}
+
+#[test]
+fn control_flow() {
+    assert_eq!(
+        results(
+            "local total = 0
+            for i = 10, 1, -2 do
+                if i == 4 then continue end
+                total = total + i
+            end
+            for _, v in {5, 6, 7} do
+                if v > 6 then break end
+                total = total + v
+            end
+            repeat
+                local done = total > 50
+                total = total + 1
+            until done
+            return total, 1 < 2, 2 <= 1, not (1 == 1), nil or 'default', false and 1"
+        ),
+        ["52", "true", "false", "false", "\"default\"", "false"]
+    );
+}
+
+#[test]
+fn multiple_values() {
+    assert_eq!(
+        results(
+            "local function values(...) return ... end
+            local a, b, c = values(1, 2)
+            local packed = {values(3, 4, 5)}
+            local first = (values(6, 7))
+            local t = {x = 1, [2] = 'two', ['key with spaces'] = true, 10}
+            return a, b, c, #packed, first, t.x .. t[2] .. 3, t[1], select('#', values(nil, nil))"
+        ),
+        ["1", "2", "nil", "3", "6", "\"1two3\"", "10", "2"]
+    );
+}
+
+#[test]
+fn errors() {
+    let error = |source| {
+        luau_compiler::compile(&parse(source, Dialect::Lua54).unwrap())
+            .unwrap_err()
+            .to_string()
+    };
+    assert_eq!(
+        error("return 1 & 2"),
+        "bitwise operators can't be compiled to Luau"
+    );
+    assert_eq!(
+        error("goto done ::done::"),
+        "`goto` can't be compiled to Luau"
+    );
+}
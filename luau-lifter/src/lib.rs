@@ -1,277 +1,94 @@
-mod deserializer;
-mod instruction;
+pub mod deserializer;
+pub mod instruction;
 mod lifter;
-mod op_code;
+pub mod op_code;
 
-use ast::{
-    local_declarations::LocalDeclarer, name_locals::name_locals, replace_locals::replace_locals,
-    Traverse,
-};
-
-use by_address::ByAddress;
-use cfg::{
-    function::Function,
-    ssa::{
-        self,
-        structuring::{structure_conditionals, structure_jumps},
-    },
-};
-use indexmap::IndexMap;
-
-use lifter::Lifter;
-
-//use cfg_ir::{dot, function::Function, ssa};
-use clap::Parser;
+use ast::formatter::Dialect;
+use decompiler_core::{Frontend, LiftedFunction};
 use parking_lot::Mutex;
-use petgraph::algo::dominators::simple_fast;
-use rayon::prelude::*;
-
-use anyhow::anyhow;
-use rustc_hash::FxHashMap;
 use triomphe::Arc;
-use walkdir::WalkDir;
 
-use std::{
-    fs::File,
-    io::{Read, Write},
-    path::Path,
-    time::Instant,
-};
+use lifter::Lifter;
 
 use deserializer::bytecode::Bytecode;
-use ast::replace_locals::fail_on_goto;
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
-#[derive(Parser, Debug)]
-#[clap(about, version, author)]
-struct Args {
-    paths: Vec<String>,
-    /// Number of threads to use (0 = automatic)
-    #[clap(short, long, default_value_t = 0)]
-    threads: usize,
-    /// op = op * key % 256
-    /// For Roblox client bytecode, use 203
-    #[clap(short, long, default_value_t = 1)]
-    key: u8,
-    #[clap(short, long)]
-    recursive: bool,
-    #[clap(short, long)]
-    verbose: bool,
+/// Luau bytecode, with opcodes encoded as `op * encode_key % 256`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Luau {
+    encode_key: u8,
 }
 
-pub fn decompile_bytecode(bytecode: &[u8], encode_key: u8) -> String {
-    let chunk = deserializer::deserialize(bytecode, encode_key).unwrap();
-    match chunk {
-        Bytecode::Error(msg) => msg,
-        Bytecode::Chunk(chunk) => {
-            let mut lifted = Vec::new();
-            let mut stack = vec![(Arc::<Mutex<ast::Function>>::default(), chunk.main)];
-            while let Some((ast_func, func_id)) = stack.pop() {
-                let (function, upvalues, child_functions) =
-                    Lifter::lift(&chunk.functions, &chunk.string_table, func_id);
-                lifted.push((ast_func, function, upvalues));
-                stack.extend(child_functions.into_iter().map(|(a, f)| (a.0, f)));
-            }
-
-            let (main, ..) = lifted.first().unwrap().clone();
-            let mut upvalues = lifted
-                .into_iter()
-                .map(|(ast_function, function, upvalues_in)| {
-                    use std::{backtrace::Backtrace, cell::RefCell, fmt::Write, panic};
-
-                    thread_local! {
-                        static BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
-                    }
-
-                    let function_id = function.id;
-                    let mut args = std::panic::AssertUnwindSafe(Some((
-                        ast_function.clone(),
-                        function,
-                        upvalues_in,
-                    )));
-
-                    let prev_hook = panic::take_hook();
-                    panic::set_hook(Box::new(|_| {
-                        let trace = Backtrace::capture();
-                        BACKTRACE.with(move |b| b.borrow_mut().replace(trace));
-                    }));
-                    let result = panic::catch_unwind(move || {
-                        let (ast_function, function, upvalues_in) = args.take().unwrap();
-                        decompile_function(ast_function, function, upvalues_in)
-                    });
-                    panic::set_hook(prev_hook);
-
-                    match result {
-                        Ok(r) => r,
-                        Err(e) => {
-                            let panic_information = match e.downcast::<String>() {
-                                Ok(v) => *v,
-                                Err(e) => match e.downcast::<&str>() {
-                                    Ok(v) => v.to_string(),
-                                    _ => "Unknown Source of Error".to_owned(),
-                                },
-                            };
-
-                            let mut message = String::new();
-                            writeln!(message, "failed to decompile").unwrap();
-                            // writeln!(message, "function {} panicked at '{}'", function_id, panic_information).unwrap();
-                            // if let Some(backtrace) = BACKTRACE.with(|b| b.borrow_mut().take()) {
-                            //     write!(message, "stack backtrace:\n{}", backtrace).unwrap();
-                            // }
+impl Luau {
+    /// Reads bytecode whose opcodes are encoded with `encode_key`, which is 203 for Roblox
+    /// client bytecode.
+    pub const fn new(encode_key: u8) -> Self {
+        Self { encode_key }
+    }
+}
 
-                            ast_function.lock().body.extend(
-                                message
-                                    .trim_end()
-                                    .split('\n')
-                                    .map(|s| ast::Comment::new(s.to_string()).into()),
-                            );
-                            (ByAddress(ast_function), Vec::new())
-                        }
-                    }
-                })
-                .collect::<FxHashMap<_, _>>();
+// bytecode from `luau-compile` isn't encoded
+impl Default for Luau {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
 
-            let main = ByAddress(main);
-            upvalues.remove(&main);
-            let mut body = Arc::try_unwrap(main.0).unwrap().into_inner().body;
-            fail_on_goto(&body);
-            link_upvalues(&mut body, &mut upvalues);
-            name_locals(&mut body, true);
-            body.to_string()
+impl Frontend for Luau {
+    fn lift(&self, bytecode: &[u8]) -> Result<Vec<LiftedFunction>, String> {
+        let chunk = deserializer::deserialize(bytecode, self.encode_key)?;
+        match chunk {
+            Bytecode::Error(msg) => Err(msg),
+            Bytecode::Chunk(chunk) => {
+                let mut lifted = Vec::new();
+                let mut stack = vec![(Arc::<Mutex<ast::Function>>::default(), chunk.main)];
+                while let Some((ast_func, func_id)) = stack.pop() {
+                    let (function, upvalues, child_functions) =
+                        Lifter::lift(&chunk.functions, &chunk.string_table, func_id);
+                    lifted.push((ast_func, function, upvalues));
+                    stack.extend(child_functions.into_iter().map(|(a, f)| (a.0, f)));
+                }
+                Ok(lifted)
+            }
         }
     }
-}
 
-fn decompile_function(
-    ast_function: Arc<Mutex<ast::Function>>,
-    mut function: Function,
-    upvalues_in: Vec<ast::RcLocal>,
-) -> (ByAddress<Arc<Mutex<ast::Function>>>, Vec<ast::RcLocal>) {
-    let (local_count, local_groups, upvalue_in_groups, upvalue_passed_groups) =
-        cfg::ssa::construct(&mut function, &upvalues_in);
-    let upvalue_to_group = upvalue_in_groups
-        .into_iter()
-        .chain(
-            upvalue_passed_groups
-                .into_iter()
-                .map(|m| (ast::RcLocal::default(), m)),
-        )
-        .flat_map(|(i, g)| g.into_iter().map(move |u| (u, i.clone())))
-        .collect::<IndexMap<_, _>>();
-    // TODO: do we even need this?
-    let local_to_group = local_groups
-        .into_iter()
-        .enumerate()
-        .flat_map(|(i, g)| g.into_iter().map(move |l| (l, i)))
-        .collect::<FxHashMap<_, _>>();
-    // TODO: REFACTOR: some way to write a macro that states
-    // if cfg::ssa::inline results in change then structure_jumps, structure_compound_conditionals,
-    // structure_for_loops and remove_unnecessary_params must run again.
-    // if structure_compound_conditionals results in change then dominators and post dominators
-    // must be recalculated.
-    // etc.
-    // the macro could also maybe generate an optimal ordering?
-    let mut changed = true;
-    while changed {
-        changed = false;
+    // we can't structure method calls like this because of __namecall
+    fn structure_method_calls(&self) -> bool {
+        false
+    }
+}
 
-        let dominators = simple_fast(function.graph(), function.entry().unwrap());
-        changed |= structure_jumps(&mut function, &dominators);
+pub fn decompile_bytecode(bytecode: &[u8], encode_key: u8) -> Result<String, String> {
+    decompile_bytecode_with_dialect(bytecode, encode_key, Dialect::Luau)
+}
 
-        ssa::inline::inline(&mut function, &local_to_group, &upvalue_to_group);
+/// Decompiles `bytecode` into source for `dialect`. Goto-capable dialects keep
+/// unstructurable control flow as `goto`s instead of failing.
+pub fn decompile_bytecode_with_dialect(
+    bytecode: &[u8],
+    encode_key: u8,
+    dialect: Dialect,
+) -> Result<String, String> {
+    decompiler_core::decompile_bytecode(&Luau::new(encode_key), bytecode, dialect)
+}
 
-        if structure_conditionals(&mut function)
-        // || {
-        //     let post_dominators = post_dominators(function.graph_mut());
-        //     structure_for_loops(&mut function, &dominators, &post_dominators)
-        // }
-        // we can't structure method calls like this because of __namecall
-        // || structure_method_calls(&mut function)
-        {
-            changed = true;
-        }
-        let mut local_map = FxHashMap::default();
-        // TODO: loop until returns false?
-        if ssa::construct::remove_unnecessary_params(&mut function, &mut local_map) {
-            changed = true;
-        }
-        ssa::construct::apply_local_map(&mut function, local_map);
-    }
-    // cfg::dot::render_to(&function, &mut std::io::stdout()).unwrap();
-    ssa::Destructor::new(
-        &mut function,
-        upvalue_to_group,
-        upvalues_in.iter().cloned().collect(),
-        local_count,
+/// Decompiles `bytecode` into the ast that would be formatted as Luau, or the compiler's
+/// error message if the bytecode holds one.
+pub fn decompile_to_ast(bytecode: &[u8], encode_key: u8) -> Result<ast::Block, String> {
+    decompiler_core::decompile_to_ast(
+        &Luau::new(encode_key),
+        bytecode,
+        &decompiler_core::Options::new(Dialect::Luau),
     )
-    .destruct();
-
-    let params = std::mem::take(&mut function.parameters);
-    let is_variadic = function.is_variadic;
-    let block = Arc::new(restructure::lift(function).into());
-    LocalDeclarer::default().declare_locals(
-        // TODO: why does block.clone() not work?
-        Arc::clone(&block),
-        &upvalues_in.iter().chain(params.iter()).cloned().collect(),
-    );
-
-    {
-        let mut ast_function = ast_function.lock();
-        ast_function.body = Arc::try_unwrap(block).unwrap().into_inner();
-        ast_function.parameters = params;
-        ast_function.is_variadic = is_variadic;
-    }
-    (ByAddress(ast_function), upvalues_in)
 }
 
-fn link_upvalues(
-    body: &mut ast::Block,
-    upvalues: &mut FxHashMap<ByAddress<Arc<Mutex<ast::Function>>>, Vec<ast::RcLocal>>,
-) {
-    for stat in &mut body.0 {
-        stat.traverse_rvalues(&mut |rvalue| {
-            if let ast::RValue::Closure(closure) = rvalue {
-                let old_upvalues = &upvalues[&closure.function];
-                let mut function = closure.function.lock();
-                // TODO: inefficient, try constructing a map of all up -> new up first
-                // and then call replace_locals on main body
-                let mut local_map =
-                    FxHashMap::with_capacity_and_hasher(old_upvalues.len(), Default::default());
-                for (old, new) in
-                    old_upvalues
-                        .iter()
-                        .zip(closure.upvalues.iter().map(|u| match u {
-                            ast::Upvalue::Copy(l) | ast::Upvalue::Ref(l) => l,
-                        }))
-                {
-                    // println!("{} -> {}", old, new);
-                    local_map.insert(old.clone(), new.clone());
-                }
-                link_upvalues(&mut function.body, upvalues);
-                replace_locals(&mut function.body, &local_map);
-            }
-        });
-        match stat {
-            ast::Statement::If(r#if) => {
-                link_upvalues(&mut r#if.then_block.lock(), upvalues);
-                link_upvalues(&mut r#if.else_block.lock(), upvalues);
-            }
-            ast::Statement::While(r#while) => {
-                link_upvalues(&mut r#while.block.lock(), upvalues);
-            }
-            ast::Statement::Repeat(repeat) => {
-                link_upvalues(&mut repeat.block.lock(), upvalues);
-            }
-            ast::Statement::NumericFor(numeric_for) => {
-                link_upvalues(&mut numeric_for.block.lock(), upvalues);
-            }
-            ast::Statement::GenericFor(generic_for) => {
-                link_upvalues(&mut generic_for.block.lock(), upvalues);
-            }
-            _ => {}
-        }
-    }
+/// Lifts `bytecode` into the body of its main function, before locals are named and
+/// before any dialect specific lowering. Returns the compiler's error message if the
+/// bytecode holds one instead of a chunk.
+pub fn lift_bytecode(bytecode: &[u8], encode_key: u8) -> Result<ast::Block, String> {
+    decompiler_core::lift_bytecode(&Luau::new(encode_key), bytecode)
 }
@@ -2,6 +2,7 @@ use anyhow::Result;
 
 use by_address::ByAddress;
 
+use indexmap::IndexMap;
 use itertools::Itertools;
 use parking_lot::Mutex;
 use petgraph::stable_graph::NodeIndex;
@@ -27,7 +28,7 @@ pub struct Lifter<'a> {
     string_table: &'a Vec<Vec<u8>>,
     blocks: FxHashMap<usize, NodeIndex>,
     function: Function,
-    child_functions: FxHashMap<ByAddress<Arc<Mutex<ast::Function>>>, usize>,
+    child_functions: IndexMap<ByAddress<Arc<Mutex<ast::Function>>>, usize>,
     register_map: FxHashMap<usize, ast::RcLocal>,
     constant_map: FxHashMap<usize, ast::Literal>,
     current_node: Option<NodeIndex>,
@@ -42,14 +43,14 @@ impl<'a> Lifter<'a> {
     ) -> (
         Function,
         Vec<ast::RcLocal>,
-        FxHashMap<ByAddress<Arc<Mutex<ast::Function>>>, usize>,
+        IndexMap<ByAddress<Arc<Mutex<ast::Function>>>, usize>,
     ) {
         let mut context = Self {
             function_list: f_list,
             string_table: str_list,
             blocks: FxHashMap::default(),
             function: Function::new(function_id),
-            child_functions: FxHashMap::default(),
+            child_functions: IndexMap::new(),
             register_map: FxHashMap::default(),
             constant_map: FxHashMap::default(),
             current_node: None,
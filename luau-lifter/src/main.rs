@@ -1,4 +1,6 @@
-fn main() {
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
     let file_name = std::env::args().nth(1).expect("expected exactly one file");
     let key = std::env::args()
         .nth(2)
@@ -6,5 +8,14 @@ fn main() {
         .map(|s| if s == "-e" { 203 } else { panic!() })
         .unwrap_or(1);
     let bytecode = std::fs::read(file_name).expect("failed to read file");
-    println!("{}", luau_lifter::decompile_bytecode(&bytecode, key));
+    match luau_lifter::decompile_bytecode(&bytecode, key) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
 }
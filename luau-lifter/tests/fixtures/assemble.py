@@ -0,0 +1,531 @@
+#!/usr/bin/env python3
+"""Assembles the Luau bytecode fixtures used by `tests/golden.rs`.
+
+There is no Luau compiler in the build environment, so every fixture is written out by hand
+here, mirroring what `luau-compile -O1 -g1` emits for the source in its docstring.
+Run `python3 assemble.py` from this directory to regenerate the `.luauc` files, then
+`BLESS=1 cargo test -p luau-lifter --test golden` to refresh the expected output.
+
+The fixtures in `ENCODED` are also written to `encoded/` with their opcodes encoded the way
+Roblox client bytecode is, for the decompiler's `--luau-encode-key 203`.
+"""
+
+import os
+import struct
+
+OPS = [
+    "NOP", "BREAK", "LOADNIL", "LOADB", "LOADN", "LOADK", "MOVE", "GETGLOBAL", "SETGLOBAL",
+    "GETUPVAL", "SETUPVAL", "CLOSEUPVALS", "GETIMPORT", "GETTABLE", "SETTABLE", "GETTABLEKS",
+    "SETTABLEKS", "GETTABLEN", "SETTABLEN", "NEWCLOSURE", "NAMECALL", "CALL", "RETURN", "JUMP",
+    "JUMPBACK", "JUMPIF", "JUMPIFNOT", "JUMPIFEQ", "JUMPIFLE", "JUMPIFLT", "JUMPIFNOTEQ",
+    "JUMPIFNOTLE", "JUMPIFNOTLT", "ADD", "SUB", "MUL", "DIV", "MOD", "POW", "ADDK", "SUBK",
+    "MULK", "DIVK", "MODK", "POWK", "AND", "OR", "ANDK", "ORK", "CONCAT", "NOT", "MINUS",
+    "LENGTH", "NEWTABLE", "DUPTABLE", "SETLIST", "FORNPREP", "FORNLOOP", "FORGLOOP",
+    "FORGPREP_INEXT", "FASTCALL3", "FORGPREP_NEXT", "NATIVECALL", "GETVARARGS", "DUPCLOSURE",
+    "PREPVARARGS", "LOADKX", "JUMPX", "FASTCALL", "COVERAGE", "CAPTURE", "SUBRK", "DIVRK",
+    "FASTCALL1", "FASTCALL2", "FASTCALL2K", "FORGPREP", "JUMPXEQKNIL", "JUMPXEQKB",
+    "JUMPXEQKN", "JUMPXEQKS", "IDIV", "IDIVK",
+]
+OP = {name: index for index, name in enumerate(OPS)}
+
+CAPTURE_VAL, CAPTURE_REF, CAPTURE_UPVAL = 0, 1, 2
+NOT_FLAG = 1 << 31
+
+
+def leb128(value):
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def import_id(*constants):
+    id = len(constants) << 30
+    for index, constant in enumerate(constants):
+        id |= constant << (20 - 10 * index)
+    return id
+
+
+class Proto:
+    def __init__(self, module, params=0, upvalues=0, vararg=False, name=None, line=1):
+        self.module = module
+        self.params = params
+        self.upvalues = upvalues
+        self.vararg = vararg
+        self.name = name
+        self.line = line
+        self.max_stack = 0
+        self.code = []
+        # indices of the words in `code` that are aux words rather than instructions
+        self.aux = set()
+        self.labels = {}
+        self.constants = []
+        self.children = []
+
+    # constants
+
+    def k(self, kind, value):
+        entry = (kind, value)
+        if entry not in self.constants:
+            self.constants.append(entry)
+        return self.constants.index(entry)
+
+    def knum(self, value):
+        return self.k("number", float(value))
+
+    def kstr(self, value):
+        return self.k("string", self.module.string(value))
+
+    def kimport(self, path):
+        parts = [self.kstr(part) for part in path.split(".")]
+        return self.k("import", import_id(*parts)), import_id(*parts)
+
+    def ktable(self, *keys):
+        return self.k("table", tuple(self.kstr(key) for key in keys))
+
+    def kclosure(self, proto):
+        return self.k("closure", self.module.protos.index(proto))
+
+    def child(self, proto):
+        self.children.append(self.module.protos.index(proto))
+        return len(self.children) - 1
+
+    # instructions
+
+    def label(self, name):
+        self.labels[name] = len(self.code)
+
+    def emit(self, word, aux=None):
+        self.code.append(word)
+        if aux is not None:
+            self.aux.add(len(self.code))
+            self.code.append(aux)
+
+    def abc(self, op, a=0, b=0, c=0, aux=None):
+        for register in (a, b, c):
+            self.max_stack = max(self.max_stack, register + 1)
+        self.emit(OP[op] | a << 8 | b << 16 | c << 24, aux)
+
+    def ad(self, op, a=0, d=0, aux=None):
+        self.max_stack = max(self.max_stack, a + 1)
+        if isinstance(d, str):
+            self.code.append((OP[op], a, d))
+            if aux is not None:
+                self.aux.add(len(self.code))
+                self.code.append(aux)
+        else:
+            self.emit(OP[op] | a << 8 | (d & 0xFFFF) << 16, aux)
+
+    def getimport(self, a, path):
+        index, id = self.kimport(path)
+        self.ad("GETIMPORT", a, index, id)
+
+    def resolve(self):
+        for pc, word in enumerate(self.code):
+            if isinstance(word, tuple):
+                op, a, label = word
+                d = self.labels[label] - (pc + 1)
+                self.code[pc] = op | a << 8 | (d & 0xFFFF) << 16
+
+    def serialize(self, version, encode_key):
+        self.resolve()
+        # the deserializer multiplies each opcode by the key, so they are stored multiplied by
+        # its inverse
+        inverse = pow(encode_key, -1, 256)
+        out = bytearray()
+        out += bytes([self.max_stack, self.params, self.upvalues, int(self.vararg)])
+        if version >= 4:
+            # flags, empty type info
+            out += bytes([0]) + leb128(0)
+        out += leb128(len(self.code))
+        for pc, word in enumerate(self.code):
+            if pc not in self.aux:
+                word = word & ~0xFF | (word & 0xFF) * inverse & 0xFF
+            out += struct.pack("<I", word)
+        out += leb128(len(self.constants))
+        for kind, value in self.constants:
+            if kind == "number":
+                out += bytes([2]) + struct.pack("<d", value)
+            elif kind == "string":
+                out += bytes([3]) + leb128(value)
+            elif kind == "import":
+                out += bytes([4]) + struct.pack("<I", value)
+            elif kind == "table":
+                out += bytes([5]) + leb128(len(value))
+                for key in value:
+                    out += leb128(key)
+            elif kind == "closure":
+                out += bytes([6]) + leb128(value)
+        out += leb128(len(self.children))
+        for child in self.children:
+            out += leb128(child)
+        out += leb128(self.line)
+        out += leb128(self.module.string(self.name) if self.name else 0)
+        # line info: one absolute line for the whole function, no per-instruction deltas
+        out += bytes([1, 24])
+        out += bytes(len(self.code))
+        out += struct.pack("<I", self.line)
+        # no debug info
+        out += bytes([0])
+        return bytes(out)
+
+
+class Module:
+    def __init__(self, version):
+        self.version = version
+        self.encode_key = 1
+        self.strings = []
+        self.protos = []
+
+    def string(self, value):
+        if value not in self.strings:
+            self.strings.append(value)
+        return self.strings.index(value) + 1
+
+    def proto(self, **kwargs):
+        proto = Proto(self, **kwargs)
+        self.protos.append(proto)
+        return proto
+
+    def serialize(self):
+        functions = [proto.serialize(self.version, self.encode_key) for proto in self.protos]
+        out = bytearray([self.version])
+        if self.version >= 4:
+            types_version = 3 if self.version >= 6 else 1
+            out.append(types_version)
+        out += leb128(len(self.strings))
+        for string in self.strings:
+            data = string.encode()
+            out += leb128(len(data)) + data
+        if self.version >= 6:
+            # no userdata type remapping
+            out.append(0)
+        out += leb128(len(functions))
+        for function in functions:
+            out += function
+        out += leb128(len(self.protos) - 1)
+        return bytes(out)
+
+
+def loops():
+    """
+    local sum = 0
+    for i = 1, 10 do
+        sum += i
+    end
+    while sum > 0 do
+        sum -= 3
+    end
+    repeat
+        sum += 1
+    until sum >= 5
+    for k, v in pairs(t) do
+        print(k, v)
+    end
+    return sum
+    """
+    module = Module(3)
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.ad("LOADN", 0, 0)
+    main.ad("LOADN", 3, 1)
+    main.ad("LOADN", 1, 10)
+    main.ad("LOADN", 2, 1)
+    main.ad("FORNPREP", 1, "for_exit")
+    main.label("for_body")
+    main.abc("ADD", 0, 0, 3)
+    main.ad("FORNLOOP", 1, "for_body")
+    main.label("for_exit")
+    main.label("while")
+    main.ad("LOADN", 1, 0)
+    main.ad("JUMPIFNOTLT", 1, "while_exit", 0)
+    main.abc("SUBK", 0, 0, main.knum(3))
+    main.ad("JUMPBACK", 0, "while")
+    main.label("while_exit")
+    main.label("repeat")
+    main.abc("ADDK", 0, 0, main.knum(1))
+    main.ad("LOADN", 1, 5)
+    main.ad("JUMPIFNOTLE", 1, "repeat", 0)
+    main.getimport(1, "pairs")
+    main.getimport(2, "t")
+    main.abc("CALL", 1, 2, 4)
+    main.ad("FORGPREP_NEXT", 1, "forg_loop")
+    main.label("forg_body")
+    main.getimport(6, "print")
+    main.abc("MOVE", 7, 4)
+    main.abc("MOVE", 8, 5)
+    main.abc("CALL", 6, 3, 1)
+    main.label("forg_loop")
+    main.ad("FORGLOOP", 1, "forg_body", 2)
+    main.abc("RETURN", 0, 2)
+    return module
+
+
+def closures():
+    """
+    local count = 0
+    local function increment(n)
+        count += n
+        return count
+    end
+    local function make(x)
+        return function()
+            return x + count
+        end
+    end
+    increment(2)
+    return make(increment(1))
+    """
+    module = Module(4)
+    increment = module.proto(params=1, upvalues=1, name="increment", line=2)
+    increment.abc("GETUPVAL", 1, 0)
+    increment.abc("ADD", 1, 1, 0)
+    increment.abc("SETUPVAL", 1, 0)
+    increment.abc("GETUPVAL", 1, 0)
+    increment.abc("RETURN", 1, 2)
+
+    inner = module.proto(upvalues=2, line=7)
+    inner.abc("GETUPVAL", 1, 0)
+    inner.abc("GETUPVAL", 2, 1)
+    inner.abc("ADD", 0, 1, 2)
+    inner.abc("RETURN", 0, 2)
+
+    make = module.proto(params=1, upvalues=1, name="make", line=6)
+    make.ad("NEWCLOSURE", 1, make.child(inner))
+    make.abc("CAPTURE", CAPTURE_VAL, 0)
+    make.abc("CAPTURE", CAPTURE_UPVAL, 0)
+    make.abc("RETURN", 1, 2)
+
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.ad("LOADN", 0, 0)
+    main.ad("NEWCLOSURE", 1, main.child(increment))
+    main.abc("CAPTURE", CAPTURE_REF, 0)
+    main.ad("NEWCLOSURE", 2, main.child(make))
+    main.abc("CAPTURE", CAPTURE_REF, 0)
+    main.abc("MOVE", 3, 1)
+    main.ad("LOADN", 4, 2)
+    main.abc("CALL", 3, 2, 1)
+    main.abc("MOVE", 3, 2)
+    main.abc("MOVE", 4, 1)
+    main.ad("LOADN", 5, 1)
+    main.abc("CALL", 4, 2, 0)
+    main.abc("CALL", 3, 0, 0)
+    main.abc("RETURN", 3, 0)
+    return module
+
+
+def varargs():
+    """
+    local function pack(...)
+        local first, second = ...
+        local count = select("#", ...)
+        return count, first, second, ...
+    end
+    return pack(1, 2, 3)
+    """
+    module = Module(5)
+    pack = module.proto(vararg=True, name="pack", line=1)
+    pack.abc("PREPVARARGS", 0)
+    pack.abc("GETVARARGS", 0, 3)
+    pack.getimport(2, "select")
+    pack.ad("LOADK", 3, pack.kstr("#"))
+    pack.abc("GETVARARGS", 4, 0)
+    pack.abc("CALL", 2, 0, 2)
+    pack.abc("MOVE", 3, 2)
+    pack.abc("MOVE", 4, 0)
+    pack.abc("MOVE", 5, 1)
+    pack.abc("GETVARARGS", 6, 0)
+    pack.abc("RETURN", 3, 0)
+
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.ad("DUPCLOSURE", 0, main.kclosure(pack))
+    main.abc("MOVE", 1, 0)
+    main.ad("LOADN", 2, 1)
+    main.ad("LOADN", 3, 2)
+    main.ad("LOADN", 4, 3)
+    main.abc("CALL", 1, 4, 0)
+    main.abc("RETURN", 1, 0)
+    return module
+
+
+def method_calls():
+    """
+    local player = game.Players.LocalPlayer
+    local name = player.Name:upper()
+    player:Kick("bye " .. name)
+    workspace.Part:Destroy()
+    return name:sub(1, 3)
+    """
+    module = Module(6)
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.getimport(0, "game.Players.LocalPlayer")
+    main.abc("GETTABLEKS", 2, 0, 0, main.kstr("Name"))
+    main.abc("NAMECALL", 1, 2, 0, main.kstr("upper"))
+    main.abc("CALL", 1, 2, 2)
+    main.ad("LOADK", 5, main.kstr("bye "))
+    main.abc("MOVE", 6, 1)
+    main.abc("CONCAT", 4, 5, 6)
+    main.abc("NAMECALL", 2, 0, 0, main.kstr("Kick"))
+    main.abc("CALL", 2, 3, 1)
+    main.getimport(2, "workspace.Part")
+    main.abc("NAMECALL", 2, 2, 0, main.kstr("Destroy"))
+    main.abc("CALL", 2, 2, 1)
+    main.ad("LOADN", 4, 1)
+    main.ad("LOADN", 5, 3)
+    main.abc("NAMECALL", 2, 1, 0, main.kstr("sub"))
+    main.abc("CALL", 2, 4, 0)
+    main.abc("RETURN", 2, 0)
+    return module
+
+
+def setlist():
+    """
+    local function f()
+        return 4, 5
+    end
+    local list = {1, 2, 3, f()}
+    local map = {x = 1, y = list}
+    return list, map
+    """
+    module = Module(5)
+    f = module.proto(name="f", line=1)
+    f.ad("LOADN", 0, 4)
+    f.ad("LOADN", 1, 5)
+    f.abc("RETURN", 0, 3)
+
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.ad("DUPCLOSURE", 0, main.kclosure(f))
+    main.abc("NEWTABLE", 1, 0, 0, 3)
+    main.ad("LOADN", 2, 1)
+    main.ad("LOADN", 3, 2)
+    main.ad("LOADN", 4, 3)
+    main.abc("MOVE", 5, 0)
+    main.abc("CALL", 5, 1, 0)
+    main.abc("SETLIST", 1, 2, 0, 1)
+    main.ad("DUPTABLE", 2, main.ktable("x", "y"))
+    main.ad("LOADN", 3, 1)
+    main.abc("SETTABLEKS", 3, 2, 0, main.kstr("x"))
+    main.abc("SETTABLEKS", 1, 2, 0, main.kstr("y"))
+    main.abc("RETURN", 1, 3)
+    return module
+
+
+def conditionals():
+    """
+    local a, b, c = ...
+    if a and b or c then
+        print("first")
+    elseif a ~= nil and b == 5 then
+        print("second")
+    else
+        print("third")
+    end
+    return not a or b
+    """
+    module = Module(6)
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.abc("GETVARARGS", 0, 4)
+    main.ad("JUMPIFNOT", 0, "check_c")
+    main.ad("JUMPIF", 1, "first")
+    main.label("check_c")
+    main.ad("JUMPIFNOT", 2, "elseif")
+    main.label("first")
+    main.getimport(3, "print")
+    main.ad("LOADK", 4, main.kstr("first"))
+    main.abc("CALL", 3, 2, 1)
+    main.ad("JUMP", 0, "end")
+    main.label("elseif")
+    main.ad("JUMPXEQKNIL", 0, "else", 0)
+    main.ad("JUMPXEQKN", 1, "else", main.knum(5) | NOT_FLAG)
+    main.getimport(3, "print")
+    main.ad("LOADK", 4, main.kstr("second"))
+    main.abc("CALL", 3, 2, 1)
+    main.ad("JUMP", 0, "end")
+    main.label("else")
+    main.getimport(3, "print")
+    main.ad("LOADK", 4, main.kstr("third"))
+    main.abc("CALL", 3, 2, 1)
+    main.label("end")
+    main.abc("NOT", 3, 0)
+    main.ad("JUMPIF", 3, "return")
+    main.abc("MOVE", 3, 1)
+    main.label("return")
+    main.abc("RETURN", 3, 2)
+    return module
+
+
+def services():
+    """
+    local Players = game:GetService("Players")
+    local ReplicatedStorage = game:GetService("ReplicatedStorage")
+    local Knit = require(ReplicatedStorage.Packages.Knit)
+    local gui = Players.LocalPlayer:WaitForChild("PlayerGui")
+    gui.Enabled = true
+    Knit.Start(gui, Players)
+    return Knit, ReplicatedStorage
+    """
+    module = Module(6)
+    main = module.proto(vararg=True)
+    main.abc("PREPVARARGS", 0)
+    main.getimport(1, "game")
+    main.ad("LOADK", 2, main.kstr("Players"))
+    main.abc("NAMECALL", 0, 1, 0, main.kstr("GetService"))
+    main.abc("CALL", 0, 3, 2)
+    main.getimport(2, "game")
+    main.ad("LOADK", 3, main.kstr("ReplicatedStorage"))
+    main.abc("NAMECALL", 1, 2, 0, main.kstr("GetService"))
+    main.abc("CALL", 1, 3, 2)
+    main.getimport(2, "require")
+    main.abc("GETTABLEKS", 3, 1, 0, main.kstr("Packages"))
+    main.abc("GETTABLEKS", 3, 3, 0, main.kstr("Knit"))
+    main.abc("CALL", 2, 2, 2)
+    main.abc("GETTABLEKS", 4, 0, 0, main.kstr("LocalPlayer"))
+    main.ad("LOADK", 5, main.kstr("PlayerGui"))
+    main.abc("NAMECALL", 3, 4, 0, main.kstr("WaitForChild"))
+    main.abc("CALL", 3, 3, 2)
+    main.abc("LOADB", 4, 1)
+    main.abc("SETTABLEKS", 4, 3, 0, main.kstr("Enabled"))
+    main.abc("GETTABLEKS", 4, 2, 0, main.kstr("Start"))
+    main.abc("MOVE", 5, 3)
+    main.abc("MOVE", 6, 0)
+    main.abc("CALL", 4, 3, 1)
+    main.abc("MOVE", 4, 2)
+    main.abc("MOVE", 5, 1)
+    main.abc("RETURN", 4, 3)
+    return module
+
+
+FIXTURES = {
+    "loops_v3": loops,
+    "closures_v4": closures,
+    "varargs_v5": varargs,
+    "method_calls_v6": method_calls,
+    "setlist_v5": setlist,
+    "conditionals_v6": conditionals,
+    "services_v6": services,
+}
+
+ENCODED = ["closures_v4"]
+ENCODE_KEY = 203
+
+if __name__ == "__main__":
+    directory = os.path.dirname(os.path.abspath(__file__))
+    for name, build in FIXTURES.items():
+        with open(os.path.join(directory, name + ".luauc"), "wb") as file:
+            file.write(build().serialize())
+    os.makedirs(os.path.join(directory, "encoded"), exist_ok=True)
+    for name in ENCODED:
+        module = FIXTURES[name]()
+        module.encode_key = ENCODE_KEY
+        with open(os.path.join(directory, "encoded", name + ".luauc"), "wb") as file:
+            file.write(module.serialize())
@@ -0,0 +1,15 @@
+local num = 0
+local function func(arg)
+	-- upvalues: (ref) num
+	num = num + arg
+	return num
+end
+local function func_1(arg_1)
+	-- upvalues: (ref) num
+	return function()
+		-- upvalues: (copy) arg_1, (ref) num
+		return arg_1 + num
+	end
+end
+func(2)
+return func_1(func(1))
\ No newline at end of file
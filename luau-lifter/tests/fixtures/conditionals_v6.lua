@@ -0,0 +1,9 @@
+local var, var_1, var_2 = ...
+if var and var_1 or var_2 then
+	print("first")
+elseif var == nil or var_1 ~= 5 then
+	print("third")
+else
+	print("second")
+end
+return not var or var_1
\ No newline at end of file
@@ -0,0 +1,14 @@
+local num = 0
+for i = 1, 10 do
+	num = num + i
+end
+while num > 0 do
+	num = num - 3
+end
+repeat
+	num = num + 1
+until num >= 5
+for k, v in pairs(t) do
+	print(k, v)
+end
+return num
\ No newline at end of file
@@ -0,0 +1,5 @@
+local LocalPlayer = game.Players.LocalPlayer
+local var = LocalPlayer.Name:upper()
+LocalPlayer:Kick("bye " .. var)
+workspace.Part:Destroy()
+return var:sub(1, 3)
\ No newline at end of file
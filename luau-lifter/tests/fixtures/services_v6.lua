@@ -0,0 +1,7 @@
+local Players = game:GetService("Players")
+local ReplicatedStorage = game:GetService("ReplicatedStorage")
+local Knit = require(ReplicatedStorage.Packages.Knit)
+local PlayerGui = Players.LocalPlayer:WaitForChild("PlayerGui")
+PlayerGui.Enabled = true
+Knit.Start(PlayerGui, Players)
+return Knit, ReplicatedStorage
\ No newline at end of file
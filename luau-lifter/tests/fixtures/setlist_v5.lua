@@ -0,0 +1,12 @@
+local tbl = {
+	1,
+	2,
+	3,
+	(function()
+		return 4, 5
+	end)()
+}
+return tbl, {
+	x = 1,
+	y = tbl
+}
\ No newline at end of file
@@ -0,0 +1,4 @@
+return (function(...)
+	local var, var_1 = ...
+	return select("#", ...), var, var_1, ...
+end)(1, 2, 3)
\ No newline at end of file
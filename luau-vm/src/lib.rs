@@ -0,0 +1,12 @@
+//! Executes deserialized Luau chunks directly, without lifting them.
+//!
+//! Running a chunk here and running the decompiled source with `cfg::interpreter` should
+//! give the same results, which checks the whole Luau pipeline end to end without the
+//! real Luau runtime. The VM shares that interpreter's values, tables and builtins, and
+//! like it only models the core language: there are no metatables and the globals are
+//! whatever the embedder installs, usually `builtins::install`.
+
+mod vm;
+
+pub use cfg::interpreter::{builtins, Cell, Error, NativeFunction, PrototypeClosure, Table, Value};
+pub use vm::Vm;
@@ -0,0 +1,591 @@
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use cfg::interpreter::{builtins, type_error, Cell, Error, Host, PrototypeClosure, Table, Value};
+use luau_lifter::{
+    deserializer::{chunk::Chunk, constant::Constant, function::Function},
+    instruction::Instruction,
+    op_code::OpCode,
+};
+
+const MAX_DEPTH: usize = 200;
+
+#[derive(Default)]
+struct Frame {
+    // registers are cells so that closures can capture them by reference, closing an
+    // upvalue gives the register a new cell
+    registers: Vec<Cell>,
+    varargs: Vec<Value>,
+    // one past the last value of the last variable length list, e.g. call results
+    top: usize,
+}
+
+impl Frame {
+    fn get(&self, register: usize) -> Value {
+        self.registers
+            .get(register)
+            .map(|cell| cell.borrow().clone())
+            .unwrap_or(Value::Nil)
+    }
+
+    fn cell(&mut self, register: usize) -> Cell {
+        if register >= self.registers.len() {
+            self.registers.resize_with(register + 1, Default::default);
+        }
+        self.registers[register].clone()
+    }
+
+    fn set(&mut self, register: usize, value: Value) {
+        *self.cell(register).borrow_mut() = value;
+    }
+
+    // `None` means up to `top`
+    fn range(&self, start: usize, count: Option<usize>) -> Vec<Value> {
+        let end = count.map_or(self.top, |count| start + count);
+        (start..end).map(|register| self.get(register)).collect()
+    }
+
+    // `None` stores all of `values` and sets `top`
+    fn store(&mut self, start: usize, mut values: Vec<Value>, count: Option<usize>) {
+        match count {
+            Some(count) => values.resize(count, Value::Nil),
+            None => self.top = start + values.len(),
+        }
+        for (register, value) in (start..).zip(values) {
+            self.set(register, value);
+        }
+    }
+
+    fn close_upvalues(&mut self, start: usize) {
+        for cell in self.registers.iter_mut().skip(start) {
+            let value = cell.borrow().clone();
+            *cell = Rc::new(RefCell::new(value));
+        }
+    }
+}
+
+pub struct Vm<'a> {
+    chunk: &'a Chunk,
+    pub globals: Rc<RefCell<Table>>,
+    /// Lines written by `print`.
+    pub output: Vec<String>,
+    steps: usize,
+    step_limit: usize,
+    depth: usize,
+}
+
+impl Host for Vm<'_> {
+    fn print(&mut self, line: String) {
+        self.output.push(line);
+    }
+}
+
+impl<'a> Vm<'a> {
+    /// Creates a VM for `chunk` whose globals are the ones `builtins::install` provides.
+    pub fn new(chunk: &'a Chunk) -> Self {
+        let mut globals = Table::default();
+        builtins::install(&mut globals).unwrap();
+        Self::with_globals(chunk, Rc::new(globals.into()))
+    }
+
+    /// Creates a VM for `chunk` that resolves globals in `globals`.
+    pub fn with_globals(chunk: &'a Chunk, globals: Rc<RefCell<Table>>) -> Self {
+        Self {
+            chunk,
+            globals,
+            output: Vec::new(),
+            steps: 0,
+            step_limit: 10_000_000,
+            depth: 0,
+        }
+    }
+
+    /// Limits the number of instructions executed, so that a chunk that never terminates
+    /// makes the run fail instead of hang.
+    pub fn with_step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = step_limit;
+        self
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().set(name.into(), value).unwrap();
+    }
+
+    /// Runs the main function of the chunk with `arguments` as its varargs.
+    pub fn execute(&mut self, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+        let main = PrototypeClosure {
+            proto: self.chunk.main,
+            upvalues: Vec::new(),
+        };
+        self.call(&Value::Prototype(Rc::new(main)), arguments)
+    }
+
+    pub fn call(&mut self, function: &Value, arguments: Vec<Value>) -> Result<Vec<Value>, Error> {
+        match function {
+            Value::Native(_, native) => native(self, arguments),
+            Value::Prototype(closure) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(Error::StackOverflow);
+                }
+                self.depth += 1;
+                let result = self.execute_closure(closure, arguments);
+                self.depth -= 1;
+                result
+            }
+            Value::Function(_) => Err(Error::Unsupported("closure of an ast function")),
+            other => Err(type_error("call", other)),
+        }
+    }
+
+    fn step(&mut self) -> Result<(), Error> {
+        self.steps += 1;
+        if self.steps > self.step_limit {
+            Err(Error::StepLimit)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn constant(&self, proto: &Function, index: usize) -> Result<Value, Error> {
+        Ok(
+            match proto
+                .constants
+                .get(index)
+                .ok_or(Error::Malformed("constant index out of bounds"))?
+            {
+                Constant::Nil => Value::Nil,
+                &Constant::Boolean(b) => Value::Boolean(b),
+                &Constant::Number(n) => Value::Number(n),
+                &Constant::String(index) => Value::String(
+                    index
+                        .checked_sub(1)
+                        .and_then(|index| self.chunk.string_table.get(index))
+                        .ok_or(Error::Malformed("string index out of bounds"))?
+                        .clone(),
+                ),
+                &Constant::Vector(x, y, z, _) => Value::Vector(x, y, z),
+                Constant::Import(_) | Constant::Table(_) | Constant::Closure(_) => {
+                    return Err(Error::Malformed("constant cannot be loaded"))
+                }
+            },
+        )
+    }
+
+    // the path is made of up to three 10 bit constant indices, the length is in the top
+    // two bits
+    fn import(&self, proto: &Function, aux: u32) -> Result<Value, Error> {
+        let len = (aux >> 30) as usize;
+        let mut value = Value::Nil;
+        for (i, shift) in [20, 10, 0].into_iter().take(len).enumerate() {
+            let key = self.constant(proto, ((aux >> shift) & 1023) as usize)?;
+            value = if i == 0 {
+                self.globals.borrow().get(&key)
+            } else {
+                self.index(&value, &key)?
+            };
+        }
+        Ok(value)
+    }
+
+    fn index(&self, object: &Value, key: &Value) -> Result<Value, Error> {
+        match object {
+            Value::Table(table) => Ok(table.borrow().get(key)),
+            Value::String(_) => match self.globals.borrow().get(&"string".into()) {
+                Value::Table(string) => Ok(string.borrow().get(key)),
+                _ => Err(type_error("index", object)),
+            },
+            other => Err(type_error("index", other)),
+        }
+    }
+
+    fn set_index(&self, object: &Value, key: Value, value: Value) -> Result<(), Error> {
+        match object {
+            Value::Table(table) => table.borrow_mut().set(key, value),
+            other => Err(type_error("index", other)),
+        }
+    }
+
+    fn execute_closure(
+        &mut self,
+        closure: &PrototypeClosure,
+        mut arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, Error> {
+        let chunk = self.chunk;
+        let proto = chunk
+            .functions
+            .get(closure.proto)
+            .ok_or(Error::Malformed("function index out of bounds"))?;
+        let upvalue = |index: usize| {
+            closure
+                .upvalues
+                .get(index)
+                .ok_or(Error::Malformed("upvalue index out of bounds"))
+        };
+
+        let mut frame = Frame::default();
+        frame
+            .registers
+            .resize_with(proto.max_stack_size as usize, Default::default);
+        let parameters = proto.num_parameters as usize;
+        if arguments.len() < parameters {
+            arguments.resize(parameters, Value::Nil);
+        }
+        let varargs = arguments.split_off(parameters);
+        frame.store(0, arguments, Some(parameters));
+        if proto.is_vararg {
+            frame.varargs = varargs;
+        }
+
+        let mut pc = 0;
+        loop {
+            self.step()?;
+            let instruction = *proto
+                .instructions
+                .get(pc)
+                .ok_or(Error::Malformed("execution ran past the last instruction"))?;
+            let (op_code, a, b, c, d, aux) = match instruction {
+                Instruction::BC {
+                    op_code,
+                    a,
+                    b,
+                    c,
+                    aux,
+                } => (op_code, a as usize, b as usize, c as usize, 0, aux),
+                Instruction::AD { op_code, a, d, aux } => (op_code, a as usize, 0, 0, d as i32, aux),
+                Instruction::E { op_code, e } => (op_code, 0, 0, 0, e, 0),
+            };
+            // jumps are relative to the next instruction, for instructions with an aux word
+            // that is the word itself
+            let jump = |offset: i32| {
+                (pc + 1)
+                    .checked_add_signed(offset as isize)
+                    .ok_or(Error::Malformed("jump out of bounds"))
+            };
+            let mut next = pc + 1;
+            match op_code {
+                OpCode::LOP_NOP
+                | OpCode::LOP_BREAK
+                | OpCode::LOP_PREPVARARGS
+                | OpCode::LOP_COVERAGE
+                | OpCode::LOP_NATIVECALL
+                | OpCode::LOP_CAPTURE
+                // fast calls are an optimization, the call that follows does the same
+                | OpCode::LOP_FASTCALL
+                | OpCode::LOP_FASTCALL1
+                | OpCode::LOP_FASTCALL2
+                | OpCode::LOP_FASTCALL2K
+                | OpCode::LOP_FASTCALL3 => {}
+                OpCode::LOP_LOADNIL => frame.set(a, Value::Nil),
+                OpCode::LOP_LOADB => {
+                    frame.set(a, Value::Boolean(b != 0));
+                    next = jump(c as i32)?;
+                }
+                OpCode::LOP_LOADN => frame.set(a, Value::Number(d as f64)),
+                OpCode::LOP_LOADK => frame.set(a, self.constant(proto, d as usize)?),
+                OpCode::LOP_LOADKX => frame.set(a, self.constant(proto, aux as usize)?),
+                OpCode::LOP_MOVE => frame.set(a, frame.get(b)),
+                OpCode::LOP_GETGLOBAL => {
+                    let key = self.constant(proto, aux as usize)?;
+                    let value = self.globals.borrow().get(&key);
+                    frame.set(a, value);
+                }
+                OpCode::LOP_SETGLOBAL => {
+                    let key = self.constant(proto, aux as usize)?;
+                    self.globals.borrow_mut().set(key, frame.get(a))?;
+                }
+                OpCode::LOP_GETUPVAL => frame.set(a, upvalue(b)?.borrow().clone()),
+                OpCode::LOP_SETUPVAL => *upvalue(b)?.borrow_mut() = frame.get(a),
+                OpCode::LOP_CLOSEUPVALS => frame.close_upvalues(a),
+                OpCode::LOP_GETIMPORT => frame.set(a, self.import(proto, aux)?),
+                OpCode::LOP_GETTABLE => frame.set(a, self.index(&frame.get(b), &frame.get(c))?),
+                OpCode::LOP_SETTABLE => self.set_index(&frame.get(b), frame.get(c), frame.get(a))?,
+                OpCode::LOP_GETTABLEKS => {
+                    let key = self.constant(proto, aux as usize)?;
+                    frame.set(a, self.index(&frame.get(b), &key)?);
+                }
+                OpCode::LOP_SETTABLEKS => {
+                    let key = self.constant(proto, aux as usize)?;
+                    self.set_index(&frame.get(b), key, frame.get(a))?;
+                }
+                OpCode::LOP_GETTABLEN => {
+                    let key = Value::Number((c + 1) as f64);
+                    frame.set(a, self.index(&frame.get(b), &key)?);
+                }
+                OpCode::LOP_SETTABLEN => {
+                    let key = Value::Number((c + 1) as f64);
+                    self.set_index(&frame.get(b), key, frame.get(a))?;
+                }
+                OpCode::LOP_NEWCLOSURE | OpCode::LOP_DUPCLOSURE => {
+                    let child = if op_code == OpCode::LOP_NEWCLOSURE {
+                        proto.functions.get(d as usize).copied()
+                    } else {
+                        match proto.constants.get(d as usize) {
+                            Some(&Constant::Closure(child)) => Some(child),
+                            _ => None,
+                        }
+                    }
+                    .ok_or(Error::Malformed("closure of an unknown function"))?;
+                    let mut upvalues = Vec::new();
+                    // like in Luau the closure is in its register before the captures, so
+                    // a local function can capture itself by value. the cycle this makes is
+                    // never freed, which is fine for the VM's short runs.
+                    let mut captures_itself = Vec::new();
+                    while let Some(&Instruction::BC {
+                        op_code: OpCode::LOP_CAPTURE,
+                        a: kind,
+                        b: source,
+                        ..
+                    }) = proto.instructions.get(next)
+                    {
+                        let source = source as usize;
+                        upvalues.push(match kind {
+                            0 if source == a => {
+                                let cell = Cell::default();
+                                captures_itself.push(cell.clone());
+                                cell
+                            }
+                            0 => Rc::new(RefCell::new(frame.get(source))),
+                            1 => frame.cell(source),
+                            2 => upvalue(source)?.clone(),
+                            _ => return Err(Error::Malformed("unknown capture type")),
+                        });
+                        next += 1;
+                    }
+                    let child = Value::Prototype(Rc::new(PrototypeClosure {
+                        proto: child,
+                        upvalues,
+                    }));
+                    for cell in captures_itself {
+                        *cell.borrow_mut() = child.clone();
+                    }
+                    frame.set(a, child);
+                }
+                OpCode::LOP_NAMECALL => {
+                    let object = frame.get(b);
+                    let method = self.index(&object, &self.constant(proto, aux as usize)?)?;
+                    frame.set(a + 1, object);
+                    frame.set(a, method);
+                }
+                OpCode::LOP_CALL => {
+                    let function = frame.get(a);
+                    let arguments = frame.range(a + 1, b.checked_sub(1));
+                    let results = self.call(&function, arguments)?;
+                    frame.store(a, results, c.checked_sub(1));
+                }
+                OpCode::LOP_RETURN => return Ok(frame.range(a, b.checked_sub(1))),
+                OpCode::LOP_JUMP | OpCode::LOP_JUMPBACK | OpCode::LOP_JUMPX => next = jump(d)?,
+                OpCode::LOP_JUMPIF => {
+                    if frame.get(a).is_truthy() {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_JUMPIFNOT => {
+                    if !frame.get(a).is_truthy() {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_JUMPIFEQ | OpCode::LOP_JUMPIFNOTEQ => {
+                    let equal = frame.get(a) == frame.get(aux as usize);
+                    if equal == (op_code == OpCode::LOP_JUMPIFEQ) {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_JUMPIFLE
+                | OpCode::LOP_JUMPIFLT
+                | OpCode::LOP_JUMPIFNOTLE
+                | OpCode::LOP_JUMPIFNOTLT => {
+                    // every ordered comparison with NaN is false
+                    let ordering = compare(&frame.get(a), &frame.get(aux as usize))?;
+                    let (holds, negated) = match op_code {
+                        OpCode::LOP_JUMPIFLE => (ordering.is_some_and(Ordering::is_le), false),
+                        OpCode::LOP_JUMPIFLT => (ordering.is_some_and(Ordering::is_lt), false),
+                        OpCode::LOP_JUMPIFNOTLE => (ordering.is_some_and(Ordering::is_le), true),
+                        _ => (ordering.is_some_and(Ordering::is_lt), true),
+                    };
+                    if holds != negated {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_JUMPXEQKNIL
+                | OpCode::LOP_JUMPXEQKB
+                | OpCode::LOP_JUMPXEQKN
+                | OpCode::LOP_JUMPXEQKS => {
+                    let constant = match op_code {
+                        OpCode::LOP_JUMPXEQKNIL => Value::Nil,
+                        OpCode::LOP_JUMPXEQKB => Value::Boolean(aux & 1 != 0),
+                        _ => self.constant(proto, (aux & ((1 << 24) - 1)) as usize)?,
+                    };
+                    // the top bit of aux negates the comparison
+                    if (frame.get(a) == constant) != (aux & (1 << 31) != 0) {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_ADD
+                | OpCode::LOP_SUB
+                | OpCode::LOP_MUL
+                | OpCode::LOP_DIV
+                | OpCode::LOP_MOD
+                | OpCode::LOP_POW
+                | OpCode::LOP_IDIV => {
+                    frame.set(a, arithmetic(op_code, &frame.get(b), &frame.get(c))?)
+                }
+                OpCode::LOP_ADDK
+                | OpCode::LOP_SUBK
+                | OpCode::LOP_MULK
+                | OpCode::LOP_DIVK
+                | OpCode::LOP_MODK
+                | OpCode::LOP_POWK
+                | OpCode::LOP_IDIVK => {
+                    let constant = self.constant(proto, c)?;
+                    frame.set(a, arithmetic(op_code, &frame.get(b), &constant)?);
+                }
+                OpCode::LOP_SUBRK | OpCode::LOP_DIVRK => {
+                    let constant = self.constant(proto, b)?;
+                    frame.set(a, arithmetic(op_code, &constant, &frame.get(c))?);
+                }
+                OpCode::LOP_AND | OpCode::LOP_ANDK | OpCode::LOP_OR | OpCode::LOP_ORK => {
+                    let left = frame.get(b);
+                    let right = match op_code {
+                        OpCode::LOP_AND | OpCode::LOP_OR => frame.get(c),
+                        _ => self.constant(proto, c)?,
+                    };
+                    let is_and = matches!(op_code, OpCode::LOP_AND | OpCode::LOP_ANDK);
+                    frame.set(a, if left.is_truthy() == is_and { right } else { left });
+                }
+                OpCode::LOP_CONCAT => {
+                    let mut string = Vec::new();
+                    for register in b..=c {
+                        string.extend(concat_operand(&frame.get(register))?);
+                    }
+                    frame.set(a, Value::String(string));
+                }
+                OpCode::LOP_NOT => frame.set(a, Value::Boolean(!frame.get(b).is_truthy())),
+                OpCode::LOP_MINUS => {
+                    let value = frame.get(b);
+                    let n = value
+                        .to_number()
+                        .ok_or_else(|| type_error("perform arithmetic on", &value))?;
+                    frame.set(a, Value::Number(-n));
+                }
+                OpCode::LOP_LENGTH => {
+                    let len = match frame.get(b) {
+                        Value::String(string) => string.len(),
+                        Value::Table(table) => table.borrow().len(),
+                        other => return Err(type_error("get length of", &other)),
+                    };
+                    frame.set(a, Value::Number(len as f64));
+                }
+                // a duplicated table's template only holds keys, their values are all nil
+                OpCode::LOP_NEWTABLE | OpCode::LOP_DUPTABLE => frame.set(a, Value::new_table()),
+                OpCode::LOP_SETLIST => {
+                    let table = frame.get(a);
+                    let values = frame.range(b, c.checked_sub(1));
+                    for (index, value) in (aux as usize..).zip(values) {
+                        self.set_index(&table, Value::Number(index as f64), value)?;
+                    }
+                }
+                OpCode::LOP_FORNPREP => {
+                    let limit = for_number(&frame.get(a), "limit")?;
+                    let step = for_number(&frame.get(a + 1), "step")?;
+                    let index = for_number(&frame.get(a + 2), "initial")?;
+                    frame.store(
+                        a,
+                        vec![limit.into(), step.into(), index.into()],
+                        Some(3),
+                    );
+                    if !in_range(index, limit, step) {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_FORNLOOP => {
+                    let limit = for_number(&frame.get(a), "limit")?;
+                    let step = for_number(&frame.get(a + 1), "step")?;
+                    let index = for_number(&frame.get(a + 2), "initial")? + step;
+                    frame.set(a + 2, Value::Number(index));
+                    if in_range(index, limit, step) {
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_FORGPREP | OpCode::LOP_FORGPREP_NEXT | OpCode::LOP_FORGPREP_INEXT => {
+                    next = jump(d)?;
+                }
+                OpCode::LOP_FORGLOOP => {
+                    let generator = frame.get(a);
+                    let control = frame.get(a + 2);
+                    let mut values = match &generator {
+                        // generalized iteration over a table without a metatable
+                        Value::Table(table) => match table.borrow().next(&control)? {
+                            Some((key, value)) => vec![key, value],
+                            None => vec![Value::Nil],
+                        },
+                        generator => self.call(generator, vec![frame.get(a + 1), control])?,
+                    };
+                    let variables = (aux & 0xff) as usize;
+                    let control = values.first().cloned().unwrap_or(Value::Nil);
+                    values.resize(variables, Value::Nil);
+                    frame.store(a + 3, values, Some(variables));
+                    if control != Value::Nil {
+                        frame.set(a + 2, control);
+                        next = jump(d)?;
+                    }
+                }
+                OpCode::LOP_GETVARARGS => {
+                    let varargs = frame.varargs.clone();
+                    frame.store(a, varargs, b.checked_sub(1));
+                }
+                OpCode::LOP__COUNT => return Err(Error::Malformed("unknown instruction")),
+            }
+            pc = next;
+        }
+    }
+}
+
+fn for_number(value: &Value, name: &str) -> Result<f64, Error> {
+    value
+        .to_number()
+        .ok_or_else(|| Error::Runtime(format!("'for' {} value must be a number", name)))
+}
+
+fn in_range(index: f64, limit: f64, step: f64) -> bool {
+    if step > 0.0 {
+        index <= limit
+    } else {
+        index >= limit
+    }
+}
+
+fn arithmetic(op_code: OpCode, left: &Value, right: &Value) -> Result<Value, Error> {
+    let (a, b) = match (left.to_number(), right.to_number()) {
+        (Some(left), Some(right)) => (left, right),
+        (None, _) => return Err(type_error("perform arithmetic on", left)),
+        (_, None) => return Err(type_error("perform arithmetic on", right)),
+    };
+    Ok(Value::Number(match op_code {
+        OpCode::LOP_ADD | OpCode::LOP_ADDK => a + b,
+        OpCode::LOP_SUB | OpCode::LOP_SUBK | OpCode::LOP_SUBRK => a - b,
+        OpCode::LOP_MUL | OpCode::LOP_MULK => a * b,
+        OpCode::LOP_DIV | OpCode::LOP_DIVK | OpCode::LOP_DIVRK => a / b,
+        OpCode::LOP_MOD | OpCode::LOP_MODK => a - (a / b).floor() * b,
+        OpCode::LOP_POW | OpCode::LOP_POWK => a.powf(b),
+        OpCode::LOP_IDIV | OpCode::LOP_IDIVK => (a / b).floor(),
+        _ => unreachable!(),
+    }))
+}
+
+// `None` when either number is NaN, which is unordered
+fn compare(left: &Value, right: &Value) -> Result<Option<Ordering>, Error> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => Ok(a.partial_cmp(b)),
+        (Value::String(a), Value::String(b)) => Ok(Some(a.cmp(b))),
+        (Value::Number(_) | Value::String(_), other) | (other, _) => {
+            Err(type_error("compare", other))
+        }
+    }
+}
+
+fn concat_operand(value: &Value) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::String(string) => Ok(string.clone()),
+        Value::Number(_) => Ok(value.to_string().into_bytes()),
+        _ => Err(type_error("concatenate", value)),
+    }
+}
@@ -0,0 +1,70 @@
+//! Runs closures compiled by `luau-compiler` that capture their upvalues in each way.
+
+use ast::{formatter::Dialect, parser::parse};
+use luau_lifter::{deserializer::chunk::Chunk, instruction::Instruction, op_code::OpCode};
+use luau_vm::{Value, Vm};
+
+fn compile(source: &str) -> Chunk {
+    luau_compiler::compile(&parse(source, Dialect::Luau).unwrap()).unwrap()
+}
+
+fn execute(chunk: &Chunk) -> Vec<String> {
+    let mut vm = Vm::new(chunk);
+    let results = vm.execute(Vec::new()).unwrap();
+    results.iter().map(Value::dump).collect()
+}
+
+#[test]
+fn capture_itself_by_value() {
+    let chunk = compile(
+        "local function fib(n)\n\
+         \tif n < 2 then\n\
+         \t\treturn n\n\
+         \tend\n\
+         \treturn fib(n - 1) + fib(n - 2)\n\
+         end\n\
+         return fib(10)",
+    );
+    // fib is never reassigned, so it captures the register it is being created in
+    let main = &chunk.functions[chunk.main];
+    let closure = main
+        .instructions
+        .iter()
+        .position(|instruction| {
+            matches!(
+                instruction,
+                Instruction::AD {
+                    op_code: OpCode::LOP_NEWCLOSURE,
+                    ..
+                }
+            )
+        })
+        .unwrap();
+    let &Instruction::AD { a: register, .. } = &main.instructions[closure] else {
+        unreachable!()
+    };
+    assert!(matches!(
+        main.instructions[closure + 1],
+        Instruction::BC {
+            op_code: OpCode::LOP_CAPTURE,
+            a: 0,
+            b,
+            ..
+        } if b == register
+    ));
+    assert_eq!(execute(&chunk), ["55"]);
+}
+
+#[test]
+fn capture_by_reference() {
+    let chunk = compile(
+        "local count = 0\n\
+         local function increment()\n\
+         \tcount = count + 1\n\
+         end\n\
+         increment()\n\
+         increment()\n\
+         return count",
+    );
+    assert_eq!(execute(&chunk), ["2"]);
+}
@@ -0,0 +1,50 @@
+//! Runs ordered comparisons compiled by `luau-compiler`, which are false whenever NaN is
+//! compared.
+
+use ast::{formatter::Dialect, parser::parse};
+use luau_vm::{Value, Vm};
+
+fn execute(source: &str, arguments: Vec<Value>) -> Vec<String> {
+    let chunk = luau_compiler::compile(&parse(source, Dialect::Luau).unwrap()).unwrap();
+    let mut vm = Vm::new(&chunk);
+    let results = vm.execute(arguments).unwrap();
+    results.iter().map(Value::dump).collect()
+}
+
+#[test]
+fn nan() {
+    let source = "local nan = ...\n\
+                  return nan < 1, nan <= 1, nan > 1, nan >= 1, 1 < nan, nan < nan, \
+                  not (nan < 1), not (nan <= 1)";
+    assert_eq!(
+        execute(source, vec![f64::NAN.into()]),
+        ["false", "false", "false", "false", "false", "false", "true", "true"]
+    );
+}
+
+#[test]
+fn nan_in_conditions() {
+    let source = "local nan = ...\n\
+                  local results = {}\n\
+                  if nan < 1 then results[1] = 'lt' else results[1] = 'not lt' end\n\
+                  if not (nan <= 1) then results[2] = 'not le' end\n\
+                  while nan < 1 do results[3] = 'loop' break end\n\
+                  return results[1], results[2], results[3]";
+    assert_eq!(
+        execute(source, vec![f64::NAN.into()]),
+        ["\"not lt\"", "\"not le\"", "nil"]
+    );
+}
+
+#[test]
+fn ordered() {
+    let source = "local a, b = ...\nreturn a < b, a <= b, a > b, a >= b";
+    assert_eq!(
+        execute(source, vec![1.0.into(), 2.0.into()]),
+        ["true", "true", "false", "false"]
+    );
+    assert_eq!(
+        execute(source, vec!["b".into(), "a".into()]),
+        ["false", "false", "true", "true"]
+    );
+}
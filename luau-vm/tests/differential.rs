@@ -0,0 +1,97 @@
+//! Runs every Luau fixture of `luau-lifter` both directly in the VM and by interpreting its
+//! decompiled AST, and checks that the two agree on the results and everything printed.
+
+use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+use cfg::interpreter::Interpreter;
+use luau_lifter::deserializer::{self, bytecode::Bytecode};
+use luau_vm::{Table, Value, Vm};
+
+// the globals the fixtures expect
+fn install_environment(globals: &RefCell<Table>) {
+    let table = |entries: Vec<(Value, Value)>| {
+        let mut table = Table::default();
+        for (key, value) in entries {
+            table.set(key, value).unwrap();
+        }
+        Value::Table(Rc::new(RefCell::new(table)))
+    };
+    let mut globals = globals.borrow_mut();
+    let method = globals.get(&"type".into());
+    let player = table(vec![
+        ("Name".into(), "player".into()),
+        ("Kick".into(), method.clone()),
+    ]);
+    let players = table(vec![("LocalPlayer".into(), player)]);
+    let part = table(vec![("Destroy".into(), method)]);
+    let t = table(vec![(1.0.into(), "a".into()), ("key".into(), 2.0.into())]);
+    for (name, value) in [
+        ("game", table(vec![("Players".into(), players)])),
+        ("workspace", table(vec![("Part".into(), part)])),
+        ("t", t),
+    ] {
+        globals.set(name.into(), value).unwrap();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    results: Result<Vec<String>, String>,
+    output: Vec<String>,
+}
+
+fn execute_bytecode(bytecode: &[u8]) -> Outcome {
+    let Bytecode::Chunk(chunk) = deserializer::deserialize(bytecode, 1).unwrap() else {
+        panic!("fixture failed to compile");
+    };
+    let mut vm = Vm::new(&chunk);
+    install_environment(&vm.globals);
+    let results = vm.execute(Vec::new());
+    Outcome {
+        results: results
+            .map(|values| values.iter().map(Value::dump).collect())
+            .map_err(|error| error.to_string()),
+        output: vm.output,
+    }
+}
+
+fn execute_decompiled(bytecode: &[u8]) -> Outcome {
+    let body = luau_lifter::lift_bytecode(bytecode, 1).unwrap();
+    let mut interpreter = Interpreter::new();
+    install_environment(&interpreter.globals);
+    let results = interpreter.execute_chunk(&body, Vec::new());
+    Outcome {
+        results: results
+            .map(|values| values.iter().map(Value::dump).collect())
+            .map_err(|error| error.to_string()),
+        output: interpreter.output,
+    }
+}
+
+#[test]
+fn decompiled_fixtures_behave_like_bytecode() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../luau-lifter/tests/fixtures");
+    let mut paths = fs::read_dir(&fixtures)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "luauc"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", fixtures.display());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let bytecode = fs::read(&path).unwrap();
+        let expected = execute_bytecode(&bytecode);
+        let actual = execute_decompiled(&bytecode);
+        if expected != actual {
+            failures.push(format!(
+                "{}:\n  bytecode:   {:?}\n  decompiled: {:?}",
+                path.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -14,10 +14,14 @@ struct DecompileMessage {
     encoded_bytecode: String,
 }
 
+/// Has either the decompilation or the error decompiling failed with.
 #[derive(Serialize)]
 struct DecompileResponse {
     id: String,
-    decompilation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decompilation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
 #[event(fetch, respond_with_errors)]
@@ -43,9 +47,11 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
                         let bytecode = BASE64_STANDARD
                             .decode(msg.encoded_bytecode)
                             .expect("bytecode must be base64 encoded");
+                        let result = decompile_bytecode(&bytecode, 1);
                         let resp = DecompileResponse {
                             id: msg.id,
-                            decompilation: decompile_bytecode(&bytecode, 1),
+                            decompilation: result.as_ref().ok().cloned(),
+                            error: result.err(),
                         };
                         server
                             .send_with_str(serde_json::to_string(&resp).unwrap())
@@ -69,7 +75,10 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
 
             let encoded_bytecode = req.bytes().await?;
             match BASE64_STANDARD.decode(encoded_bytecode) {
-                Ok(bytecode) => Response::ok(decompile_bytecode(&bytecode, 203)),
+                Ok(bytecode) => match decompile_bytecode(&bytecode, 203) {
+                    Ok(decompilation) => Response::ok(decompilation),
+                    Err(error) => Response::error(error, 422),
+                },
                 Err(_) => Response::error("invalid bytecode", 400),
             }
         })
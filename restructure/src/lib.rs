@@ -315,7 +315,8 @@ impl GraphStructurer {
                 let block = self.function.remove_block(node).unwrap();
                 let mut goto_destinations = FxHashSet::default();
                 collect_gotos(&block, &mut goto_destinations);
-                for label in goto_destinations {
+                // visit targets in a fixed order so block placement doesn't depend on hashing
+                for label in goto_destinations.into_iter().sorted() {
                     let target_node = self.label_to_node[&label];
                     if self.function.has_block(target_node) {
                         stack.push(target_node);
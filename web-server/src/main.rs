@@ -2,8 +2,9 @@
 
 use std::io;
 
-use axum::{body::{Body, Bytes}, http::StatusCode, response::{IntoResponse, Response}, routing::post, Router};
+use axum::{body::{Body, Bytes}, extract::Query, http::{header, StatusCode}, response::{IntoResponse, Response}, routing::post, Router};
 use base64::prelude::*;
+use serde::Deserialize;
 use tokio::net::TcpListener;
 use tracing::info;
 
@@ -15,12 +16,18 @@ enum Error {
     Io(#[from] io::Error),
     #[error("invalid base64 data recieved: {0}")]
     Base64(#[from] base64::DecodeError),
+    #[error("failed to decompile: {0}")]
+    Decompile(String),
+    #[error("failed to serialize the ast: {0}")]
+    Json(#[from] serde_json::Error),
 }
 impl Error {
     fn status_code(&self) -> StatusCode {
         match self {
             Error::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Base64(_) => StatusCode::BAD_REQUEST,
+            Error::Decompile(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -55,10 +62,36 @@ async fn main() -> Result<(), io::Error> {
     axum::serve(listener, app).await
 }
 
-async fn decompile(body: Bytes) -> Result<String, Error> {
+/// What `/decompile` responds with, from the `emit` query parameter.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Emit {
+    /// Luau source
+    #[default]
+    Lua,
+    /// The decompiled ast as JSON
+    AstJson,
+}
+
+#[derive(Debug, Deserialize)]
+struct DecompileQuery {
+    #[serde(default)]
+    emit: Emit,
+}
+
+async fn decompile(Query(query): Query<DecompileQuery>, body: Bytes) -> Result<Response, Error> {
     let mut bytecode = Vec::new();
     BASE64_STANDARD.decode_vec(body, &mut bytecode)?;
-    let decompiled = luau_lifter::decompile_bytecode(&bytecode, 203);
+    let response = match query.emit {
+        Emit::Lua => luau_lifter::decompile_bytecode(&bytecode, 203)
+            .map_err(Error::Decompile)?
+            .into_response(),
+        Emit::AstJson => {
+            let ast = luau_lifter::decompile_to_ast(&bytecode, 203).map_err(Error::Decompile)?;
+            let json = ast::serialize::to_json(&ast)?;
+            ([(header::CONTENT_TYPE, "application/json")], json).into_response()
+        }
+    };
     info!("Successfully decompiled bytecode.");
-    Ok(decompiled)
+    Ok(response)
 }